@@ -0,0 +1,319 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use rayon::prelude::*;
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+use walkdir::WalkDir;
+use anyhow::Result;
+use tracing::{debug, error, info, warn};
+
+use crate::config::{Config, PORTABLE_FLAG};
+use crate::error::PenglerError;
+use crate::models::is_media_file;
+use crate::utils::{run_in_worker_pool, Workload};
+use crate::commands::backup::run_backup_internal;
+use crate::commands::cache::{init_database, save_media_files_internal};
+use crate::commands::cache_janitor::enforce_cache_budget;
+use crate::commands::copy_media::{copy_file, relative_dir, CopyLayout};
+use crate::commands::duplicates::find_library_duplicates_internal;
+use crate::commands::ingest::{process_file, IngestContext};
+use crate::commands::scanner::scan_folder_internal;
+use crate::commands::similar::DEFAULT_SIMILARITY_THRESHOLD;
+use crate::commands::thumbnail::generate_thumbnail_internal;
+
+const USAGE: &str = "\
+Usage: pengler [--portable] <operation>
+
+Runs one library operation without opening a window, prints its result as JSON and
+exits. Without an operation the app starts as usual.
+
+Operations:
+  --scan [FOLDER]            Catalog FOLDER, or every library folder
+  --import SOURCE --dest DIR Copy photos and videos not yet in the library from SOURCE
+                             (e.g. a memory card) into date folders in DIR
+  --thumbnails               Make missing thumbnails
+  --dedupe [--threshold N]   Find duplicates for review in the app
+  --backup                   Run the backup set up in settings
+  --help                     Show this help";
+
+/// Exit code for a failed operation
+const EXIT_FAILURE: i32 = 1;
+/// Exit code for arguments that make no sense
+const EXIT_USAGE: i32 = 2;
+
+#[derive(Debug, PartialEq)]
+enum Operation {
+    Scan(Option<PathBuf>),
+    Import { source: PathBuf, dest: PathBuf },
+    Thumbnails,
+    Dedupe { threshold: u32 },
+    Backup,
+    Help,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScanSummary {
+    folders: usize,
+    files: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportSummary {
+    imported: usize,
+    /// Already in the library, or a second copy on the source
+    skipped: usize,
+    failed: usize,
+    bytes_copied: u64,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ThumbnailSummary {
+    files: usize,
+    failed: usize,
+}
+
+/// Run the operation named on the command line, if any. Returns the process exit code,
+/// or `None` to start the app.
+pub fn run_from_args(owns_library: bool) -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).filter(|arg| arg != PORTABLE_FLAG).collect();
+    let operation = match parse_args(&args) {
+        Ok(Some(operation)) => operation,
+        Ok(None) => return None,
+        Err(e) => {
+            attach_console();
+            eprintln!("{}\n\n{}", e, USAGE);
+            return Some(EXIT_USAGE);
+        }
+    };
+
+    attach_console();
+    if operation == Operation::Help {
+        println!("{}", USAGE);
+        return Some(0);
+    }
+    if !owns_library {
+        eprintln!("Pengler is open; close it before running library operations from the command line");
+        return Some(EXIT_FAILURE);
+    }
+
+    match run(operation) {
+        Ok(json) => {
+            println!("{}", json);
+            Some(0)
+        }
+        Err(e) => {
+            eprintln!("{}", PenglerError::from_error("Failed", e));
+            Some(EXIT_FAILURE)
+        }
+    }
+}
+
+/// `None` unless the arguments name an operation, so anything the OS passes to a GUI
+/// launch still starts the app
+fn parse_args(args: &[String]) -> Result<Option<Operation>> {
+    let mut operation = None;
+    let mut dest = None;
+    let mut threshold = None;
+
+    let mut args = args.iter().peekable();
+    while let Some(arg) = args.next() {
+        // Optional values are whatever follows that isn't another flag
+        let mut value = || args.next_if(|next| !next.starts_with("--")).map(PathBuf::from);
+        let parsed = match arg.as_str() {
+            "--scan" => Operation::Scan(value()),
+            "--import" => Operation::Import {
+                source: value().ok_or_else(|| anyhow::anyhow!("--import needs a source folder"))?,
+                dest: PathBuf::new(),
+            },
+            "--thumbnails" => Operation::Thumbnails,
+            "--dedupe" => Operation::Dedupe { threshold: DEFAULT_SIMILARITY_THRESHOLD },
+            "--backup" => Operation::Backup,
+            "--help" | "-h" => Operation::Help,
+            "--dest" => {
+                dest = Some(value().ok_or_else(|| anyhow::anyhow!("--dest needs a folder"))?);
+                continue;
+            }
+            "--threshold" => {
+                let number = value().ok_or_else(|| anyhow::anyhow!("--threshold needs a number"))?;
+                let number = number.to_string_lossy().parse::<u32>();
+                threshold = Some(number.map_err(|_| anyhow::anyhow!("--threshold needs a number"))?);
+                continue;
+            }
+            other if operation.is_none() && !other.starts_with("--") => return Ok(None),
+            other => return Err(anyhow::anyhow!("Unknown argument: {}", other)),
+        };
+        if operation.replace(parsed).is_some() {
+            return Err(anyhow::anyhow!("Only one operation can run at a time"));
+        }
+    }
+
+    let operation = match operation {
+        None if dest.is_none() && threshold.is_none() => return Ok(None),
+        None => return Err(anyhow::anyhow!("No operation given")),
+        Some(Operation::Import { source, .. }) => {
+            let dest = dest.ok_or_else(|| anyhow::anyhow!("--import needs --dest"))?;
+            Operation::Import { source, dest }
+        }
+        Some(Operation::Dedupe { threshold: default }) => Operation::Dedupe { threshold: threshold.unwrap_or(default) },
+        Some(_) if dest.is_some() => return Err(anyhow::anyhow!("--dest only goes with --import")),
+        Some(_) if threshold.is_some() => return Err(anyhow::anyhow!("--threshold only goes with --dedupe")),
+        Some(operation) => operation,
+    };
+    Ok(Some(operation))
+}
+
+fn run(operation: Operation) -> Result<String> {
+    let json = match operation {
+        Operation::Scan(folder) => serde_json::to_string_pretty(&scan(folder)?)?,
+        Operation::Import { source, dest } => serde_json::to_string_pretty(&import(&source, &dest)?)?,
+        Operation::Thumbnails => serde_json::to_string_pretty(&make_thumbnails()?)?,
+        Operation::Dedupe { threshold } => {
+            let summary = find_library_duplicates_internal(
+                &|phase, processed, total| debug!("Finding duplicates: {} {}/{}", phase, processed, total),
+                threshold,
+            )?;
+            serde_json::to_string_pretty(&summary)?
+        }
+        Operation::Backup => {
+            let run = run_backup_internal(&|progress| debug!("Backing up {}/{}", progress.processed, progress.total))?;
+            serde_json::to_string_pretty(&run)?
+        }
+        Operation::Help => USAGE.to_string(),
+    };
+
+    // No janitor runs in command line mode; new thumbnails may have filled the cache
+    let (files, bytes) = enforce_cache_budget(&init_database()?)?;
+    if files > 0 {
+        info!("Evicted {} cached thumbnails ({} bytes) to stay within the cache budget", files, bytes);
+    }
+    Ok(json)
+}
+
+fn scan(folder: Option<PathBuf>) -> Result<ScanSummary> {
+    let folders = match folder {
+        Some(folder) => vec![folder],
+        None => Config::load()?.library_folders().into_iter().map(PathBuf::from).collect(),
+    };
+
+    let mut summary = ScanSummary::default();
+    for folder in folders {
+        if !folder.is_dir() {
+            warn!("Skipping unavailable folder {}", folder.display());
+            continue;
+        }
+        info!("Scanning folder: {}", folder.display());
+        let files = scan_folder_internal(&folder)?;
+        summary.folders += 1;
+        summary.files += files.len();
+        save_media_files_internal(files)?;
+    }
+    Ok(summary)
+}
+
+/// Offload a memory card or phone folder: files whose content is already cataloged are
+/// skipped, so the same card can be imported again after more shots
+fn import(source: &Path, dest: &Path) -> Result<ImportSummary> {
+    if !source.is_dir() {
+        return Err(PenglerError::NotFound(format!("Import source not found: {}", source.display())).into());
+    }
+    let dest = std::path::absolute(dest)?;
+    if !Config::load()?.library_folders().iter().any(|folder| dest.starts_with(folder)) {
+        warn!("{} isn't in a library folder; add it in settings to see new files after rescans", dest.display());
+    }
+
+    let conn = init_database()?;
+    // Hashes of the source files aren't kept; the card is gone after the import
+    let source_ctx = IngestContext::local(&conn)?;
+    let mut library_ctx = IngestContext::local(&conn)?;
+    let mut cataloged = conn.prepare("SELECT 1 FROM media_files WHERE file_hash = ?1 LIMIT 1")?;
+
+    let mut summary = ImportSummary::default();
+    let mut seen = HashSet::new();
+    let mut imported = Vec::new();
+    let sources = WalkDir::new(source)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| entry.path().to_str().and_then(is_media_file).is_some());
+    for entry in sources {
+        let path = entry.path();
+        let mut file = match process_file(path, &source_ctx) {
+            Ok(file) => file,
+            Err(e) => {
+                error!("Failed to read {}: {}", path.display(), e);
+                summary.failed += 1;
+                continue;
+            }
+        };
+        let known = cataloged.query_row([&file.file_hash], |_| Ok(())).optional()?.is_some();
+        if known || !seen.insert(file.file_hash.clone()) {
+            summary.skipped += 1;
+            continue;
+        }
+
+        match copy_file(path, &dest.join(relative_dir(&file, CopyLayout::DateFolders, &[]))) {
+            Ok((target, bytes)) => {
+                library_ctx.record_hash(&target, &file.file_hash);
+                file.file_path = target.to_string_lossy().to_string();
+                imported.push(file);
+                summary.imported += 1;
+                summary.bytes_copied += bytes;
+            }
+            Err(e) => {
+                error!("Failed to copy {}: {}", path.display(), e);
+                summary.failed += 1;
+            }
+        }
+    }
+
+    save_media_files_internal(imported)?;
+    library_ctx.save(&conn)?;
+    info!(
+        "Imported {} files from {} into {}; {} already in the library, {} failed",
+        summary.imported,
+        source.display(),
+        dest.display(),
+        summary.skipped,
+        summary.failed
+    );
+    Ok(summary)
+}
+
+/// Private files are left out; their thumbnails need the passphrase
+fn make_thumbnails() -> Result<ThumbnailSummary> {
+    let conn = init_database()?;
+    let mut stmt = conn.prepare("SELECT file_path, file_hash FROM media_files WHERE online_only = 0 AND NOT private")?;
+    let files: Vec<(String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let failed = run_in_worker_pool(Workload::Thumbnail, || {
+        files
+            .par_iter()
+            .filter(|(file_path, file_hash)| match generate_thumbnail_internal(file_path, file_hash, false) {
+                Ok(_) => false,
+                Err(e) => {
+                    error!("Failed to make a thumbnail of {}: {}", file_path, e);
+                    true
+                }
+            })
+            .count()
+    })?;
+    Ok(ThumbnailSummary { files: files.len(), failed })
+}
+
+/// Release builds have no console of their own on Windows; borrow the one the command
+/// was typed in so output shows up there
+#[cfg(target_os = "windows")]
+fn attach_console() {
+    use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn attach_console() {}
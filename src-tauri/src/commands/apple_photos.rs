@@ -0,0 +1,449 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+use tauri::AppHandle;
+use walkdir::WalkDir;
+use anyhow::Result;
+use tracing::{error, info};
+
+use crate::error::PenglerError;
+use crate::models::{is_media_file, MediaFile, MediaType};
+use crate::utils::{ensure_free_space, run_in_worker_pool, wall_clock, write_atomically, DuplicateScreen, OperationTimer, Workload};
+use crate::commands::cache::{init_database, save_media_files_internal};
+use crate::commands::ingest::{process_file, IngestContext};
+use crate::commands::smart_albums::notify_smart_albums_changed;
+use crate::commands::tags::{ensure_tag, TAG_PATH_SEPARATOR};
+use crate::commands::takeout::{copy_and_hash, emit_import_progress, unique_destination, ImportProgress};
+
+/// Parent tag for imported albums, e.g. "Albums/Summer 2021"
+const ALBUM_TAG: &str = "Albums";
+
+/// Live Photo clips are about three seconds; anything much longer is a real video
+const MAX_LIVE_PHOTO_SECONDS: f64 = 5.0;
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplePhotosImportResult {
+    pub imported: usize,
+    pub duplicates_skipped: usize,
+    /// Albums recreated as tags under "Albums/"
+    pub albums: usize,
+    pub live_photos: usize,
+    pub failed: usize,
+}
+
+/// What iCloud's "Photo Details.csv" says about one file
+struct PhotoDetails {
+    taken_at: Option<DateTime<Utc>>,
+    favorite: bool,
+}
+
+/// A media file imported in this run, by index into the imported list
+struct ImportedFile {
+    index: usize,
+    source: PathBuf,
+}
+
+/// Import an extracted iCloud Photos download or a folder exported from Apple Photos.
+/// Album membership comes from iCloud's album CSVs or, for exports, from the album
+/// folders; albums become tags under "Albums/". Photos and their Live Photo clips are
+/// linked, and original dates are restored. Files already in the library are skipped
+/// but still gain the album tags.
+#[tauri::command]
+pub async fn import_apple_photos(
+    app: AppHandle,
+    folder: String,
+    destination: String,
+) -> Result<ApplePhotosImportResult, PenglerError> {
+    let handle = app.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        run_in_worker_pool(Workload::Import, || import_apple_photos_internal(&handle, Path::new(&folder), Path::new(&destination)))
+            .and_then(|result| result)
+    })
+    .await
+    .map_err(|e| PenglerError::report("Apple Photos import stopped", e))?
+    .map_err(|e| PenglerError::report("Failed to import from Apple Photos", e))?;
+    notify_smart_albums_changed(&app);
+    Ok(result)
+}
+
+fn import_apple_photos_internal(app: &AppHandle, source: &Path, destination: &Path) -> Result<ApplePhotosImportResult> {
+    if !source.is_dir() {
+        return Err(anyhow::anyhow!("Export folder not found: {}", source.display()));
+    }
+    info!("Importing Apple Photos export: {}", source.display());
+    let timer = OperationTimer::start("import");
+
+    let files: Vec<PathBuf> = WalkDir::new(source)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect();
+
+    // iCloud downloads describe albums and dates in CSV files next to the photos
+    let mut details: HashMap<String, PhotoDetails> = HashMap::new();
+    let mut csv_albums: HashMap<String, BTreeSet<String>> = HashMap::new();
+    for path in files.iter().filter(|p| p.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))) {
+        let name = path.file_stem().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let in_albums_folder = path
+            .parent()
+            .and_then(|p| p.file_name())
+            .is_some_and(|n| n.eq_ignore_ascii_case("Albums"));
+
+        let result = if name.starts_with("Photo Details") {
+            read_photo_details(path, &mut details)
+        } else if in_albums_folder {
+            read_album_csv(path, &name, &mut csv_albums)
+        } else {
+            Ok(())
+        };
+        if let Err(e) = result {
+            error!("Failed to read {}: {}", path.display(), e);
+        }
+    }
+    let icloud = !details.is_empty() || !csv_albums.is_empty();
+
+    let media_files: Vec<&PathBuf> = files
+        .iter()
+        .filter(|p| p.to_str().and_then(is_media_file).is_some())
+        .collect();
+    info!("Found {} media files, {} albums in CSVs", media_files.len(), csv_albums.len());
+
+    let required_bytes: u64 = media_files.iter().filter_map(|p| fs::metadata(p).ok()).map(|m| m.len()).sum();
+    ensure_free_space(destination, required_bytes)?;
+    fs::create_dir_all(destination)?;
+
+    let conn = init_database()?;
+    let mut existing_id = conn.prepare("SELECT id FROM media_files WHERE file_hash = ?1 LIMIT 1")?;
+    let mut screen = DuplicateScreen::load(&conn)?;
+    let mut ctx = IngestContext::local(&conn)?;
+
+    let mut result = ApplePhotosImportResult::default();
+    let mut imported: Vec<MediaFile> = Vec::new();
+    let mut imported_files: Vec<ImportedFile> = Vec::new();
+    let mut by_hash: HashMap<String, usize> = HashMap::new();
+    let mut album_names = BTreeSet::new();
+    let total = media_files.len();
+
+    for (index, source_path) in media_files.into_iter().enumerate() {
+        let relative = source_path.strip_prefix(source).unwrap_or(source_path);
+        let file_name = source_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+
+        let albums: Vec<String> = if icloud {
+            csv_albums.get(&file_name).map(|a| a.iter().cloned().collect()).unwrap_or_default()
+        } else {
+            // An export made album by album has one folder per album
+            relative
+                .parent()
+                .filter(|p| !p.as_os_str().is_empty())
+                .map(|p| vec![p.to_string_lossy().replace(std::path::MAIN_SEPARATOR, " - ")])
+                .unwrap_or_default()
+        };
+        let album_tags: Vec<String> = albums
+            .iter()
+            .map(|album| format!("{}{}{}", ALBUM_TAG, TAG_PATH_SEPARATOR, album.replace(TAG_PATH_SEPARATOR, "-")))
+            .collect();
+        album_names.extend(albums);
+
+        let target_dir = match relative.parent() {
+            Some(parent) if !icloud => destination.join(parent),
+            _ => destination.to_path_buf(),
+        };
+
+        let file_size = fs::metadata(source_path).map(|m| m.len()).unwrap_or(0);
+        let mut on_progress = |bytes_copied| {
+            let progress = ImportProgress {
+                processed: index,
+                total,
+                current_file: source_path.to_string_lossy().to_string(),
+                bytes_copied,
+                file_size,
+            };
+            emit_import_progress(app, progress);
+        };
+
+        // Exports repeat a photo in every album folder it belongs to; most repeats are
+        // recognized without being copied
+        let known = screen.find_file(source_path).unwrap_or_else(|e| {
+            error!("Failed to check {} for duplicates: {}", source_path.display(), e);
+            None
+        });
+        let copied = match known {
+            Some(hash) => Ok((None, hash)),
+            None => copy_file(source_path, &target_dir, &file_name, &mut on_progress)
+                .map(|(target, hash)| (Some(target), hash)),
+        };
+        let (target, hash) = match copied {
+            Ok(copied) => copied,
+            Err(e) => {
+                error!("Failed to copy {}: {}", source_path.display(), e);
+                result.failed += 1;
+                continue;
+            }
+        };
+
+        if let Some(&index) = by_hash.get(&hash) {
+            remove_duplicate_copy(target.as_deref(), &mut result);
+            add_tags(&mut imported[index], album_tags);
+            result.duplicates_skipped += 1;
+            continue;
+        }
+        if let Some(media_id) = existing_id.query_row([&hash], |row| row.get::<_, i64>(0)).optional()? {
+            remove_duplicate_copy(target.as_deref(), &mut result);
+            for tag in &album_tags {
+                if let Some(tag_id) = ensure_tag(&conn, tag)? {
+                    conn.execute(
+                        "INSERT OR IGNORE INTO media_tags (media_id, tag_id) VALUES (?1, ?2)",
+                        params![media_id, tag_id],
+                    )?;
+                }
+            }
+            result.duplicates_skipped += 1;
+            continue;
+        }
+
+        // Only without a copy if the library changed while importing
+        let target = match target {
+            Some(target) => target,
+            None => match copy_file(source_path, &target_dir, &file_name, &mut on_progress) {
+                Ok((target, _)) => target,
+                Err(e) => {
+                    error!("Failed to copy {}: {}", source_path.display(), e);
+                    result.failed += 1;
+                    continue;
+                }
+            },
+        };
+
+        // The copy was hashed on the way, so it needn't be read again
+        ctx.record_hash(&target, &hash);
+        let mut media = match process_file(&target, &ctx) {
+            Ok(media) => media,
+            Err(e) => {
+                error!("Failed to read {}: {}", target.display(), e);
+                // Leave no copy behind that the library doesn't know about
+                if let Err(e) = fs::remove_file(&target) {
+                    error!("Failed to remove {}: {}", target.display(), e);
+                }
+                result.failed += 1;
+                continue;
+            }
+        };
+
+        // The copy carries the download time; prefer iCloud's record, then the source's date
+        let details = details.get(&file_name);
+        let original_date = details
+            .and_then(|d| d.taken_at)
+            .or_else(|| fs::metadata(source_path).and_then(|m| m.modified()).ok().map(DateTime::<Utc>::from));
+        if let Some(date) = original_date {
+            // Still imported, just with the download time
+            match filetime::set_file_mtime(&target, filetime::FileTime::from_unix_time(date.timestamp(), 0)) {
+                Ok(()) => {
+                    ctx.record_hash(&target, &hash);
+                    media.modified_at = date;
+                }
+                Err(e) => error!("Failed to set the modification time of {}: {}", target.display(), e),
+            }
+        }
+        if let (None, Some(taken_at)) = (media.taken_at, details.and_then(|d| d.taken_at)) {
+            // iCloud records the instant in GMT; dates taken are kept as wall-clock time
+            let (wall_clock, offset) = wall_clock(taken_at);
+            media.taken_at = Some(wall_clock);
+            media.taken_offset = Some(offset);
+        }
+        media.favorite |= details.is_some_and(|d| d.favorite);
+        add_tags(&mut media, album_tags);
+
+        screen.add(target.clone(), media.file_size.max(0) as u64, hash.clone());
+        by_hash.insert(hash, imported.len());
+        imported_files.push(ImportedFile { index: imported.len(), source: source_path.clone() });
+        imported.push(media);
+        result.imported += 1;
+    }
+    drop(existing_id);
+
+    let pairs = live_photo_pairs(&imported, &imported_files);
+    let paths: Vec<(String, String)> = pairs
+        .iter()
+        .map(|&(photo, video)| (imported[photo].file_path.clone(), imported[video].file_path.clone()))
+        .collect();
+
+    let bytes = imported.iter().map(|media| media.file_size.max(0) as u64).sum();
+    timer.finish(imported.len(), bytes);
+    ctx.save(&conn)?;
+    save_media_files_internal(imported)?;
+
+    for (photo_path, video_path) in paths {
+        result.live_photos += conn.execute(
+            "UPDATE media_files SET live_video_id = (SELECT id FROM media_files WHERE file_path = ?2)
+             WHERE file_path = ?1",
+            params![photo_path, video_path],
+        )?;
+    }
+    result.albums = album_names.len();
+
+    info!(
+        "Apple Photos import finished: {} imported, {} duplicates skipped, {} albums, {} Live Photos, {} failed",
+        result.imported, result.duplicates_skipped, result.albums, result.live_photos, result.failed
+    );
+
+    Ok(result)
+}
+
+/// Delete the copy of a file the library already has; one that can't be removed is
+/// left behind and counted as failed
+fn remove_duplicate_copy(target: Option<&Path>, result: &mut ApplePhotosImportResult) {
+    if let Some(target) = target {
+        if let Err(e) = fs::remove_file(target) {
+            error!("Failed to remove duplicate copy {}: {}", target.display(), e);
+            result.failed += 1;
+        }
+    }
+}
+
+fn add_tags(media: &mut MediaFile, tags: Vec<String>) {
+    for tag in tags {
+        if !media.tags.contains(&tag) {
+            media.tags.push(tag);
+        }
+    }
+}
+
+fn copy_file(
+    source: &Path,
+    target_dir: &Path,
+    file_name: &str,
+    on_progress: &mut dyn FnMut(u64),
+) -> Result<(PathBuf, String)> {
+    fs::create_dir_all(target_dir)?;
+    let target = unique_destination(target_dir, file_name);
+    let reader = File::open(source)?;
+    let hash = write_atomically(&target, |part_path| copy_and_hash(reader, part_path, on_progress))?;
+    Ok((target, hash))
+}
+
+/// Photos and short clips that sat side by side under the same name (IMG_0001.HEIC and
+/// IMG_0001.MOV); returns indices into `imported` as (photo, video)
+fn live_photo_pairs(imported: &[MediaFile], files: &[ImportedFile]) -> Vec<(usize, usize)> {
+    let key = |path: &Path| (path.parent().map(Path::to_path_buf), path.file_stem().map(|s| s.to_ascii_lowercase()));
+
+    let mut photos = HashMap::new();
+    for file in files.iter().filter(|f| imported[f.index].media_type == MediaType::Image) {
+        photos.insert(key(&file.source), file.index);
+    }
+
+    files
+        .iter()
+        .filter(|f| {
+            let media = &imported[f.index];
+            media.media_type == MediaType::Video
+                && media.video_info.as_ref().is_none_or(|v| v.duration <= MAX_LIVE_PHOTO_SECONDS)
+        })
+        .filter_map(|video| photos.get(&key(&video.source)).map(|&photo| (photo, video.index)))
+        .collect()
+}
+
+/// "Photo Details.csv": imgName, fileChecksum, favorite, hidden, deleted, originalCreationDate, ...
+fn read_photo_details(path: &Path, details: &mut HashMap<String, PhotoDetails>) -> Result<()> {
+    let contents = fs::read_to_string(path)?;
+    let mut lines = contents.lines();
+    let header = split_csv_line(lines.next().unwrap_or_default());
+    let column = |name: &str| header.iter().position(|h| h == name);
+    let (name_col, favorite_col, date_col) = (column("imgName"), column("favorite"), column("originalCreationDate"));
+    let name_col = name_col.ok_or_else(|| anyhow::anyhow!("No imgName column"))?;
+
+    for line in lines {
+        let fields = split_csv_line(line);
+        let Some(name) = fields.get(name_col) else { continue };
+        let field = |col: Option<usize>| col.and_then(|c| fields.get(c)).map(String::as_str);
+        details.insert(
+            name.clone(),
+            PhotoDetails {
+                taken_at: field(date_col).and_then(parse_icloud_date),
+                favorite: field(favorite_col) == Some("yes"),
+            },
+        );
+    }
+    Ok(())
+}
+
+/// `Albums/<name>.csv` lists the album's file names under an "Images" header
+fn read_album_csv(path: &Path, album: &str, albums: &mut HashMap<String, BTreeSet<String>>) -> Result<()> {
+    let contents = fs::read_to_string(path)?;
+    for line in contents.lines().skip(1) {
+        if let Some(name) = split_csv_line(line).into_iter().next().filter(|n| !n.is_empty()) {
+            albums.entry(name).or_default().insert(album.to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Split one CSV line, honouring double-quoted fields
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields.into_iter().map(|f| f.trim().to_string()).collect()
+}
+
+/// iCloud writes dates like "Thursday October 7,2021 3:04 PM GMT"
+fn parse_icloud_date(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim().trim_end_matches("GMT").trim();
+    NaiveDateTime::parse_from_str(value, "%A %B %d,%Y %I:%M %p")
+        .ok()
+        .map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_plain_and_quoted_fields() {
+        assert_eq!(split_csv_line("IMG_0001.HEIC,Trip, 2021"), vec!["IMG_0001.HEIC", "Trip", "2021"]);
+        assert_eq!(
+            split_csv_line(r#""IMG_0002.JPG","Paris, France","said ""hi""""#),
+            vec!["IMG_0002.JPG", "Paris, France", r#"said "hi""#]
+        );
+    }
+
+    #[test]
+    fn keeps_empty_fields_and_drops_byte_order_mark() {
+        assert_eq!(split_csv_line("\u{feff}imgName,,fileChecksum,"), vec!["imgName", "", "fileChecksum", ""]);
+        assert_eq!(split_csv_line(""), vec![""]);
+    }
+
+    #[test]
+    fn parses_icloud_dates() {
+        let expected = DateTime::parse_from_rfc3339("2021-10-07T15:04:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(parse_icloud_date("Thursday October 7,2021 3:04 PM GMT"), Some(expected));
+        assert_eq!(parse_icloud_date(" Thursday October 7,2021 3:04 PM "), Some(expected));
+        let midnight = DateTime::parse_from_rfc3339("2020-02-29T00:30:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(parse_icloud_date("Saturday February 29,2020 12:30 AM GMT"), Some(midnight));
+    }
+
+    #[test]
+    fn rejects_other_date_formats() {
+        for value in ["", "2021-10-07 15:04:00", "October 7,2021 3:04 PM GMT", "Thursday October 7,2021 15:04 GMT"] {
+            assert_eq!(parse_icloud_date(value), None, "{}", value);
+        }
+    }
+}
@@ -0,0 +1,617 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use walkdir::WalkDir;
+use anyhow::Result;
+use tracing::{error, info, warn};
+
+use crate::error::PenglerError;
+use crate::config::{BackupConfig, Config};
+use crate::utils::{ensure_free_space, hash_file, short_hash, write_atomically};
+use crate::commands::cache::init_database;
+use crate::commands::private::visible_condition;
+
+/// Emitted after each library file is checked
+pub const BACKUP_PROGRESS_EVENT: &str = "backup-progress";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupProgress {
+    pub processed: usize,
+    pub total: usize,
+    pub current_file: String,
+}
+
+/// Stats of one `run_backup`
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupRun {
+    pub id: i64,
+    pub target: String,
+    pub started_at: String,
+    /// None while running, or if the run was interrupted
+    pub finished_at: Option<String>,
+    pub copied: usize,
+    pub unchanged: usize,
+    /// Copies removed because the original is gone (only with `mirror_deletions`)
+    pub deleted: usize,
+    pub failed: usize,
+    pub bytes_copied: u64,
+}
+
+/// Result of `verify_backup`; paths are those of the originals
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupVerification {
+    pub checked: usize,
+    pub verified: usize,
+    pub missing: Vec<String>,
+    pub corrupted: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreResult {
+    /// Originals copied back from the backup
+    pub restored: usize,
+    /// Originals that were already intact
+    pub intact: usize,
+    pub failed: usize,
+    /// Media whose path in the library changed because its folder moved
+    pub relinked: usize,
+}
+
+/// What the manifest says was last copied for an original
+struct ManifestEntry {
+    backup_path: String,
+    file_hash: String,
+    file_size: u64,
+    modified_at: i64,
+}
+
+/// Mirror the library folders into the configured backup target: new and changed
+/// originals are copied, unchanged ones skipped by size, date and hash. Originals are
+/// only ever read. Copies of deleted originals are kept unless `mirror_deletions` is set.
+#[tauri::command]
+pub async fn run_backup(app: AppHandle) -> Result<BackupRun, PenglerError> {
+    run_backup_internal(&|progress| emit_backup_progress(&app, progress))
+        .map_err(|e| PenglerError::report("Backup failed", e))
+}
+
+/// Recent backup runs, newest first
+#[tauri::command]
+pub async fn get_backup_history(limit: Option<u32>) -> Result<Vec<BackupRun>, PenglerError> {
+    get_backup_history_internal(limit.unwrap_or(20))
+        .map_err(|e| PenglerError::report("Failed to load backup history", e))
+}
+
+/// Check that backup copies still match the hashes recorded when they were made.
+/// Checks `sample` randomly picked copies, or all of them when not given.
+#[tauri::command]
+pub async fn verify_backup(app: AppHandle, sample: Option<usize>) -> Result<BackupVerification, PenglerError> {
+    verify_backup_internal(&app, sample)
+        .map_err(|e| PenglerError::report("Failed to verify backup", e))
+}
+
+/// Copy missing or damaged originals back from the backup. Takes media ids and/or
+/// original paths (for files no longer in the library). Originals edited since the
+/// last backup are left alone. If an original's library folder was moved or renamed,
+/// the file is restored into the library folder of the same name and its path updated.
+#[tauri::command]
+pub async fn restore_files(
+    app: AppHandle,
+    media_ids: Option<Vec<i64>>,
+    paths: Option<Vec<String>>,
+) -> Result<RestoreResult, PenglerError> {
+    restore_files_internal(&app, &media_ids.unwrap_or_default(), &paths.unwrap_or_default())
+        .map_err(|e| PenglerError::report("Failed to restore files", e))
+}
+
+/// `run_backup`, reporting each original to `on_progress`
+pub fn run_backup_internal(on_progress: &dyn Fn(BackupProgress)) -> Result<BackupRun> {
+    let config = Config::load()?;
+    let backup = config
+        .backup
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("No backup target is set up in settings"))?;
+    let target = backup_root(backup)?;
+
+    for folder in &config.folders {
+        if target.starts_with(&folder.path) {
+            return Err(anyhow::anyhow!("The backup target is inside the library folder {}", folder.path));
+        }
+    }
+
+    let conn = init_database()?;
+    let started_at = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO backup_runs (target, started_at) VALUES (?1, ?2)",
+        params![backup.target, started_at],
+    )?;
+    let mut run = BackupRun {
+        id: conn.last_insert_rowid(),
+        target: backup.target.clone(),
+        started_at,
+        ..Default::default()
+    };
+
+    let folders = available_folders(&config.library_folders());
+    let files = library_files(&folders);
+
+    let total = files.len();
+    let mut seen = HashSet::with_capacity(total);
+
+    for (index, (source, backup_path)) in files.iter().enumerate() {
+        let source_key = source.to_string_lossy().to_string();
+
+        match back_up_file(&conn, &target, source, &source_key, backup_path) {
+            Ok(Some(bytes)) => {
+                run.copied += 1;
+                run.bytes_copied += bytes;
+            }
+            Ok(None) => run.unchanged += 1,
+            Err(e) => {
+                error!("Failed to back up {}: {}", source.display(), e);
+                run.failed += 1;
+            }
+        }
+        seen.insert(source_key.clone());
+
+        on_progress(BackupProgress { processed: index + 1, total, current_file: source_key });
+    }
+
+    if backup.mirror_deletions {
+        run.deleted = mirror_deletions(&conn, &target, &folders, &seen)?;
+    }
+
+    let finished_at = Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE backup_runs SET finished_at = ?1, copied = ?2, unchanged = ?3, deleted = ?4, failed = ?5,
+            bytes_copied = ?6 WHERE id = ?7",
+        params![
+            finished_at,
+            run.copied as i64,
+            run.unchanged as i64,
+            run.deleted as i64,
+            run.failed as i64,
+            run.bytes_copied as i64,
+            run.id
+        ],
+    )?;
+    run.finished_at = Some(finished_at);
+
+    info!(
+        "Backup to {}: {} copied, {} unchanged, {} deleted, {} failed",
+        run.target, run.copied, run.unchanged, run.deleted, run.failed
+    );
+
+    Ok(run)
+}
+
+fn emit_backup_progress(app: &AppHandle, progress: BackupProgress) {
+    if let Err(e) = app.emit(BACKUP_PROGRESS_EVENT, progress) {
+        warn!("Failed to emit {}: {}", BACKUP_PROGRESS_EVENT, e);
+    }
+}
+
+/// The configured target, which must already exist: a missing target usually means
+/// the drive isn't connected, and writing to its mount point would fill the system disk
+pub fn backup_root(backup: &BackupConfig) -> Result<PathBuf> {
+    let target = PathBuf::from(&backup.target);
+    if !target.is_dir() {
+        return Err(anyhow::anyhow!("Backup target not found: {}. Is the drive connected?", backup.target));
+    }
+    Ok(target)
+}
+
+/// Each library folder's name inside the backup target; folders sharing a name are
+/// told apart by a hash of their full path
+fn folder_names(library_folders: &[String]) -> Vec<(PathBuf, String)> {
+    let base_name = |folder: &str| {
+        Path::new(folder)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "library".to_string())
+    };
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for folder in library_folders {
+        *counts.entry(base_name(folder)).or_default() += 1;
+    }
+
+    library_folders
+        .iter()
+        .map(|folder| {
+            let name = base_name(folder);
+            let name = if counts[&name] > 1 {
+                format!("{}-{}", name, short_hash(blake3::hash(folder.as_bytes()).to_hex().as_str()))
+            } else {
+                name
+            };
+            (PathBuf::from(folder), name)
+        })
+        .collect()
+}
+
+/// Library folders with their names in the backup. Folders on a disconnected drive
+/// are left out entirely, so their copies are never mistaken for deletions
+pub fn available_folders(library_folders: &[String]) -> Vec<(PathBuf, String)> {
+    folder_names(library_folders)
+        .into_iter()
+        .filter(|(folder, _)| {
+            let available = folder.is_dir();
+            if !available {
+                warn!("Skipping unavailable library folder {}", folder.display());
+            }
+            available
+        })
+        .collect()
+}
+
+/// Every file to back up in `folders`, with its path relative to the backup root
+pub fn library_files(folders: &[(PathBuf, String)]) -> Vec<(PathBuf, String)> {
+    folders
+        .iter()
+        .flat_map(|(folder, name)| {
+            WalkDir::new(folder)
+                .into_iter()
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_type().is_file() && !is_ignored(entry.path()))
+                .filter_map(move |entry| {
+                    let relative = entry.path().strip_prefix(folder).ok()?;
+                    let backup_path = Path::new(name).join(relative).to_string_lossy().to_string();
+                    Some((entry.path().to_path_buf(), backup_path))
+                })
+        })
+        .collect()
+}
+
+/// Unfinished writes and OS clutter (.DS_Store, Thumbs.db) aren't worth keeping
+fn is_ignored(path: &Path) -> bool {
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    name.starts_with('.') || name.ends_with(".part") || name.eq_ignore_ascii_case("Thumbs.db")
+}
+
+/// Copy one original if the backup doesn't already hold it; returns the bytes copied
+fn back_up_file(
+    conn: &Connection,
+    target: &Path,
+    source: &Path,
+    source_key: &str,
+    backup_path: &str,
+) -> Result<Option<u64>> {
+    let metadata = fs::metadata(source)?;
+    let size = metadata.len();
+    let modified_at = filetime::FileTime::from_last_modification_time(&metadata).unix_seconds();
+    let dest = target.join(backup_path);
+    let dest_size = fs::metadata(&dest).ok().map(|m| m.len());
+
+    let entry = load_entry(conn, source_key)?;
+    let in_backup = |entry: &ManifestEntry| entry.backup_path == backup_path && dest_size == Some(size);
+
+    if let Some(entry) = &entry {
+        if in_backup(entry) && entry.file_size == size && entry.modified_at == modified_at {
+            return Ok(None);
+        }
+    }
+
+    // The date changed but the content may not have (e.g. a touched file)
+    let file_hash = hash_file(source)?;
+    let unchanged = entry.as_ref().is_some_and(|entry| in_backup(entry) && entry.file_hash == file_hash);
+
+    if !unchanged {
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        ensure_free_space(&dest, size)?;
+        write_atomically(&dest, |part| {
+            fs::copy(source, part)?;
+            Ok(())
+        })?;
+        filetime::set_file_mtime(&dest, filetime::FileTime::from_unix_time(modified_at, 0))?;
+    }
+
+    conn.execute(
+        "INSERT INTO backup_files (source_path, backup_path, file_hash, file_size, modified_at, backed_up_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(source_path) DO UPDATE SET backup_path = excluded.backup_path, file_hash = excluded.file_hash,
+            file_size = excluded.file_size, modified_at = excluded.modified_at, backed_up_at = excluded.backed_up_at",
+        params![source_key, backup_path, file_hash, size as i64, modified_at, Utc::now().to_rfc3339()],
+    )?;
+
+    // The original was moved or renamed (see move_media), so its old copy is now orphaned
+    if let Some(entry) = entry.filter(|entry| entry.backup_path != backup_path) {
+        remove_old_copy(conn, target, &entry.backup_path)?;
+    }
+
+    Ok(if unchanged { None } else { Some(size) })
+}
+
+/// Delete a backup copy no manifest entry points to anymore
+fn remove_old_copy(conn: &Connection, target: &Path, backup_path: &str) -> Result<()> {
+    if conn.prepare("SELECT 1 FROM backup_files WHERE backup_path = ?1")?.exists([backup_path])? {
+        return Ok(());
+    }
+    let dest = target.join(backup_path);
+    match fs::remove_file(&dest) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => error!("Failed to delete old backup copy {}: {}", dest.display(), e),
+    }
+    Ok(())
+}
+
+fn load_entry(conn: &Connection, source_path: &str) -> Result<Option<ManifestEntry>> {
+    Ok(conn
+        .query_row(
+            "SELECT backup_path, file_hash, file_size, modified_at FROM backup_files WHERE source_path = ?1",
+            [source_path],
+            |row| {
+                Ok(ManifestEntry {
+                    backup_path: row.get(0)?,
+                    file_hash: row.get(1)?,
+                    file_size: row.get::<_, i64>(2)? as u64,
+                    modified_at: row.get(3)?,
+                })
+            },
+        )
+        .optional()?)
+}
+
+/// Remove copies whose original was deleted from one of the scanned folders
+fn mirror_deletions(conn: &Connection, target: &Path, folders: &[(PathBuf, String)], seen: &HashSet<String>) -> Result<usize> {
+    let mut stmt = conn.prepare("SELECT source_path, backup_path FROM backup_files")?;
+    let entries: Vec<(String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut deleted = 0;
+    for (source_path, backup_path) in entries {
+        let source = Path::new(&source_path);
+        let scanned = folders.iter().any(|(folder, _)| source.starts_with(folder));
+        if !scanned || seen.contains(&source_path) || source.exists() {
+            continue;
+        }
+
+        let dest = target.join(&backup_path);
+        match fs::remove_file(&dest) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                error!("Failed to delete backup copy {}: {}", dest.display(), e);
+                continue;
+            }
+        }
+        conn.execute("DELETE FROM backup_files WHERE source_path = ?1", [&source_path])?;
+        deleted += 1;
+    }
+
+    Ok(deleted)
+}
+
+fn verify_backup_internal(app: &AppHandle, sample: Option<usize>) -> Result<BackupVerification> {
+    let config = Config::load()?;
+    let backup = config
+        .backup
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("No backup target is set up in settings"))?;
+    let target = backup_root(backup)?;
+    let conn = init_database()?;
+
+    let entries: Vec<(String, String, String)> = {
+        let (sql, limit) = match sample {
+            Some(sample) => (
+                "SELECT source_path, backup_path, file_hash FROM backup_files ORDER BY RANDOM() LIMIT ?1",
+                sample as i64,
+            ),
+            None => ("SELECT source_path, backup_path, file_hash FROM backup_files LIMIT ?1", -1),
+        };
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt
+            .query_map([limit], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        rows
+    };
+
+    let total = entries.len();
+    let mut verification = BackupVerification { checked: total, ..Default::default() };
+
+    for (index, (source_path, backup_path, file_hash)) in entries.into_iter().enumerate() {
+        let copy = target.join(&backup_path);
+        if !copy.is_file() {
+            verification.missing.push(source_path.clone());
+        } else {
+            match hash_file(&copy) {
+                Ok(hash) if hash == file_hash => verification.verified += 1,
+                Ok(_) => verification.corrupted.push(source_path.clone()),
+                Err(e) => {
+                    error!("Failed to read backup copy {}: {}", copy.display(), e);
+                    verification.corrupted.push(source_path.clone());
+                }
+            }
+        }
+
+        let progress = BackupProgress { processed: index + 1, total, current_file: source_path };
+        emit_backup_progress(app, progress);
+    }
+
+    info!(
+        "Verified backup in {}: {} of {} intact, {} missing, {} corrupted",
+        backup.target,
+        verification.verified,
+        verification.checked,
+        verification.missing.len(),
+        verification.corrupted.len()
+    );
+
+    Ok(verification)
+}
+
+fn restore_files_internal(app: &AppHandle, media_ids: &[i64], paths: &[String]) -> Result<RestoreResult> {
+    let config = Config::load()?;
+    let backup = config
+        .backup
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("No backup target is set up in settings"))?;
+    let target = backup_root(backup)?;
+    let folders = available_folders(&config.library_folders());
+    let conn = init_database()?;
+
+    let mut sources = Vec::with_capacity(media_ids.len() + paths.len());
+    for media_id in media_ids {
+        let path: Option<String> = conn
+            .query_row(
+                &format!("SELECT file_path FROM media_files WHERE id = ?1 AND {}", visible_condition()),
+                [media_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match path {
+            Some(path) => sources.push(path),
+            None => warn!("Media {} not found, skipping restore", media_id),
+        }
+    }
+    sources.extend(paths.iter().cloned());
+
+    let total = sources.len();
+    let mut result = RestoreResult::default();
+
+    for (index, source_path) in sources.into_iter().enumerate() {
+        match restore_file(&conn, &target, &folders, &source_path) {
+            Ok((restored, relinked)) => {
+                if restored {
+                    result.restored += 1;
+                } else {
+                    result.intact += 1;
+                }
+                if relinked {
+                    result.relinked += 1;
+                }
+            }
+            Err(e) => {
+                error!("Failed to restore {}: {}", source_path, e);
+                result.failed += 1;
+            }
+        }
+
+        let progress = BackupProgress { processed: index + 1, total, current_file: source_path };
+        emit_backup_progress(app, progress);
+    }
+
+    info!(
+        "Restore from {}: {} restored, {} intact, {} relinked, {} failed",
+        backup.target, result.restored, result.intact, result.relinked, result.failed
+    );
+
+    Ok(result)
+}
+
+/// Restore one original; returns whether it was copied back and whether its path changed
+fn restore_file(
+    conn: &Connection,
+    target: &Path,
+    folders: &[(PathBuf, String)],
+    source_path: &str,
+) -> Result<(bool, bool)> {
+    let entry = load_entry(conn, source_path)?
+        .ok_or_else(|| anyhow::anyhow!("Not in the backup"))?;
+    let dest = restore_destination(folders, source_path, &entry.backup_path)?;
+
+    let restored = match fs::metadata(&dest) {
+        Ok(metadata) => {
+            if hash_file(&dest)? == entry.file_hash {
+                false
+            } else {
+                // Content changed but the date didn't: damage rather than an edit
+                let modified_at = filetime::FileTime::from_last_modification_time(&metadata).unix_seconds();
+                if modified_at != entry.modified_at {
+                    return Err(anyhow::anyhow!("Changed since the last backup; not overwriting it"));
+                }
+                true
+            }
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => true,
+        Err(e) => return Err(e.into()),
+    };
+
+    if restored {
+        let copy = target.join(&entry.backup_path);
+        if !copy.is_file() {
+            return Err(anyhow::anyhow!("The backup copy is missing"));
+        }
+        if hash_file(&copy)? != entry.file_hash {
+            return Err(anyhow::anyhow!("The backup copy is damaged"));
+        }
+
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        ensure_free_space(&dest, entry.file_size)?;
+        write_atomically(&dest, |part| {
+            fs::copy(&copy, part)?;
+            Ok(())
+        })?;
+        filetime::set_file_mtime(&dest, filetime::FileTime::from_unix_time(entry.modified_at, 0))?;
+    }
+
+    let dest_path = dest.to_string_lossy().to_string();
+    let relinked = dest_path != source_path;
+    if relinked {
+        conn.execute(
+            "UPDATE OR IGNORE media_files SET file_path = ?1 WHERE file_path = ?2",
+            params![dest_path, source_path],
+        )?;
+        conn.execute(
+            "UPDATE OR IGNORE backup_files SET source_path = ?1 WHERE source_path = ?2",
+            params![dest_path, source_path],
+        )?;
+    }
+
+    Ok((restored, relinked))
+}
+
+/// Where an original goes back to: its own path if its library folder is still
+/// there, otherwise the library folder with the name it has in the backup
+fn restore_destination(folders: &[(PathBuf, String)], source_path: &str, backup_path: &str) -> Result<PathBuf> {
+    let source = Path::new(source_path);
+    if folders.iter().any(|(folder, _)| source.starts_with(folder)) {
+        return Ok(source.to_path_buf());
+    }
+
+    let backup_path = Path::new(backup_path);
+    folders
+        .iter()
+        .find_map(|(folder, name)| {
+            backup_path.strip_prefix(name).ok().map(|relative| folder.join(relative))
+        })
+        .ok_or_else(|| anyhow::anyhow!("No library folder to restore it into"))
+}
+
+fn get_backup_history_internal(limit: u32) -> Result<Vec<BackupRun>> {
+    let conn = init_database()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, target, started_at, finished_at, copied, unchanged, deleted, failed, bytes_copied
+         FROM backup_runs ORDER BY id DESC LIMIT ?1",
+    )?;
+    let runs = stmt
+        .query_map([limit], |row| {
+            Ok(BackupRun {
+                id: row.get(0)?,
+                target: row.get(1)?,
+                started_at: row.get(2)?,
+                finished_at: row.get(3)?,
+                copied: row.get::<_, i64>(4)? as usize,
+                unchanged: row.get::<_, i64>(5)? as usize,
+                deleted: row.get::<_, i64>(6)? as usize,
+                failed: row.get::<_, i64>(7)? as usize,
+                bytes_copied: row.get::<_, i64>(8)? as u64,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(runs)
+}
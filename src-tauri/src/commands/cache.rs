@@ -1,9 +1,25 @@
 use std::path::PathBuf;
-use rusqlite::{Connection, params};
+use std::time::Duration;
+use rusqlite::{Connection, OpenFlags, Row, params};
+use rusqlite::types::Type;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use anyhow::Result;
 
-use crate::models::MediaFile;
+use crate::error::PenglerError;
+use crate::models::{MediaFile, MediaType, PickState, VideoInfo};
+use crate::utils::{is_read_only_instance, wall_clock};
 use crate::commands::thumbnail::get_cache_directory;
+use crate::commands::changes::create_change_log;
+use crate::commands::private::{create_private_tables, visible_condition};
+use crate::commands::tags::ensure_tag;
+use crate::commands::smart_albums::notify_smart_albums_changed;
+use crate::commands::suggest::FOLDER_EXPRESSION;
+use crate::commands::memories::MONTH_DAY_EXPRESSION;
+use crate::commands::search::{file_name_expression, order_clause, MediaSort, DAY_EXPRESSION};
+
+/// How long a statement waits for another connection, in this process or another
+/// instance, to release the database before failing with `DbLocked`
+const DB_BUSY_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub fn get_db_path() -> Result<PathBuf> {
     let cache_dir = get_cache_directory()?;
@@ -18,7 +34,15 @@ pub fn init_database() -> Result<Connection> {
         std::fs::create_dir_all(parent)?;
     }
 
+    // The instance that owns the catalog keeps the schema current; another one only reads
+    if is_read_only_instance() {
+        let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)?;
+        conn.busy_timeout(DB_BUSY_TIMEOUT)?;
+        return Ok(conn);
+    }
+
     let conn = Connection::open(db_path)?;
+    conn.busy_timeout(DB_BUSY_TIMEOUT)?;
 
     conn.execute(
         "CREATE TABLE IF NOT EXISTS media_files (
@@ -37,8 +61,150 @@ pub fn init_database() -> Result<Connection> {
         [],
     )?;
 
+    // Columns added after the initial schema
+    ensure_column(&conn, "media_files", "latitude", "REAL")?;
+    ensure_column(&conn, "media_files", "longitude", "REAL")?;
+    ensure_column(&conn, "media_files", "duration", "REAL")?;
+    ensure_column(&conn, "media_files", "fps", "REAL")?;
+    ensure_column(&conn, "media_files", "video_codec", "TEXT")?;
+    ensure_column(&conn, "media_files", "bitrate", "INTEGER")?;
+    ensure_column(&conn, "media_files", "audio_tracks", "TEXT")?;
+    ensure_column(&conn, "media_files", "camera_model", "TEXT")?;
+    ensure_column(&conn, "media_files", "stack_id", "INTEGER")?;
+    ensure_column(&conn, "media_files", "rating", "INTEGER")?;
+    ensure_column(&conn, "media_files", "color_label", "TEXT")?;
+    ensure_column(&conn, "media_files", "favorite", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(&conn, "media_files", "color_space", "TEXT")?;
+    ensure_column(&conn, "media_files", "perceptual_hash", "TEXT")?;
+    ensure_column(&conn, "media_files", "pick", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(&conn, "media_files", "live_video_id", "INTEGER")?;
+    ensure_column(&conn, "media_files", "online_only", "INTEGER NOT NULL DEFAULT 0")?;
+    // Minutes east of UTC at taken_at, when known
+    ensure_column(&conn, "media_files", "taken_offset", "INTEGER")?;
+    // modified_at as a day in the configured timezone, for grouping files without a date taken
+    ensure_column(&conn, "media_files", "modified_day", "TEXT")?;
+    // Unix time the thumbnail was last shown; the cache janitor evicts the oldest first
+    ensure_column(&conn, "media_files", "last_viewed_at", "INTEGER")?;
+    // In a private folder; hidden unless the session is unlocked
+    ensure_column(&conn, "media_files", "private", "INTEGER NOT NULL DEFAULT 0")?;
+
+    // Burst stacks; cover_id is the photo shown in place of the whole stack
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS stacks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            cover_id INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS tags (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT UNIQUE NOT NULL COLLATE NOCASE
+        )",
+        [],
+    )?;
+    ensure_column(&conn, "tags", "parent_id", "INTEGER")?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS media_tags (
+            media_id INTEGER NOT NULL,
+            tag_id INTEGER NOT NULL,
+            PRIMARY KEY (media_id, tag_id)
+        )",
+        [],
+    )?;
+
+    // Rule is a JSON-encoded SmartRule
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS smart_albums (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            rule TEXT NOT NULL,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS persons (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT UNIQUE NOT NULL COLLATE NOCASE,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    // Box is relative to the image (0-1). Manual regions have source 'manual';
+    // a face detector fills source, confidence and embedding.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS face_regions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            media_id INTEGER NOT NULL,
+            person_id INTEGER,
+            x REAL NOT NULL,
+            y REAL NOT NULL,
+            width REAL NOT NULL,
+            height REAL NOT NULL,
+            source TEXT NOT NULL DEFAULT 'manual',
+            confidence REAL,
+            embedding BLOB,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    // Listings are sorted by one column with taken_at and modified_at breaking ties (see
+    // `order_clause`). These indexes match those sorts so a page is read straight off
+    // the index, walked backwards for newest first; id is their implicit last column.
+    // They replace single-column indexes of the first schema.
+    conn.execute_batch(
+        "DROP INDEX IF EXISTS idx_taken_at; DROP INDEX IF EXISTS idx_folder; DROP INDEX IF EXISTS idx_day_taken;",
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_taken_modified ON media_files(taken_at, modified_at)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_modified_taken ON media_files(modified_at, taken_at)",
+        [],
+    )?;
+
     conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_taken_at ON media_files(taken_at)",
+        "CREATE INDEX IF NOT EXISTS idx_size_taken ON media_files(file_size, taken_at)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_rating_taken ON media_files(rating, taken_at)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_type_taken ON media_files(media_type, taken_at, modified_at)",
+        [],
+    )?;
+
+    conn.execute(
+        &format!(
+            "CREATE INDEX IF NOT EXISTS idx_folder_taken ON media_files({}, taken_at, modified_at)",
+            FOLDER_EXPRESSION
+        ),
+        [],
+    )?;
+
+    conn.execute(
+        &format!(
+            "CREATE INDEX IF NOT EXISTS idx_name_taken ON media_files({} COLLATE NOCASE, taken_at)",
+            file_name_expression()
+        ),
+        [],
+    )?;
+
+    conn.execute(
+        &format!("CREATE INDEX IF NOT EXISTS idx_local_day_taken ON media_files({}, taken_at, modified_at)", DAY_EXPRESSION),
         [],
     )?;
 
@@ -47,23 +213,389 @@ pub fn init_database() -> Result<Connection> {
         [],
     )?;
 
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_stack_id ON media_files(stack_id)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_location ON media_files(latitude, longitude)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_camera_model ON media_files(camera_model)",
+        [],
+    )?;
+
+    conn.execute(
+        &format!("CREATE INDEX IF NOT EXISTS idx_taken_month_day ON media_files({})", MONTH_DAY_EXPRESSION),
+        [],
+    )?;
+
+    // Result of the last near-duplicate grouping run
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS similar_groups (
+            media_id INTEGER PRIMARY KEY,
+            group_id INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Result of the last library-wide duplicate search; kind is 'exact' or 'similar'
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS duplicate_groups (
+            group_id INTEGER NOT NULL,
+            media_id INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            PRIMARY KEY (group_id, media_id)
+        )",
+        [],
+    )?;
+
+    // Text recognized in images, keyed by media id (rowid)
+    conn.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS media_text USING fts5(content)",
+        [],
+    )?;
+
+    // CLIP image embedding per photo; file_hash tells when it is stale
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS media_embeddings (
+            media_id INTEGER PRIMARY KEY,
+            file_hash TEXT NOT NULL,
+            embedding BLOB NOT NULL
+        )",
+        [],
+    )?;
+
+    // Adjustment recipes (JSON); every save adds a revision and the newest one applies
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS edits (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            media_id INTEGER NOT NULL,
+            recipe TEXT NOT NULL,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_edits_media ON edits(media_id)",
+        [],
+    )?;
+
+    // Backup manifest: what was last copied for each original; backup_path is relative
+    // to the backup target so the drive can be mounted elsewhere
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS backup_files (
+            source_path TEXT PRIMARY KEY,
+            backup_path TEXT NOT NULL,
+            file_hash TEXT NOT NULL,
+            file_size INTEGER NOT NULL,
+            modified_at INTEGER NOT NULL,
+            backed_up_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS backup_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            target TEXT NOT NULL,
+            started_at TEXT NOT NULL,
+            finished_at TEXT,
+            copied INTEGER NOT NULL DEFAULT 0,
+            unchanged INTEGER NOT NULL DEFAULT 0,
+            deleted INTEGER NOT NULL DEFAULT 0,
+            failed INTEGER NOT NULL DEFAULT 0,
+            bytes_copied INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+
+    // Cloud backup manifest, keyed like backup_files; object_key is the full key in the bucket
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS cloud_backup_files (
+            source_path TEXT PRIMARY KEY,
+            object_key TEXT NOT NULL,
+            file_hash TEXT NOT NULL,
+            file_size INTEGER NOT NULL,
+            modified_at INTEGER NOT NULL,
+            uploaded_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Multipart uploads in progress, so an interrupted upload resumes after its last
+    // finished part; parts is a JSON array of [part_number, etag]
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS cloud_uploads (
+            source_path TEXT PRIMARY KEY,
+            object_key TEXT NOT NULL,
+            upload_id TEXT NOT NULL,
+            file_hash TEXT NOT NULL,
+            nonce_prefix TEXT NOT NULL,
+            parts TEXT NOT NULL DEFAULT '[]',
+            started_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Content hashes remembered by path, size and modification time (nanoseconds), so
+    // unchanged files aren't read again; see utils::hash::HashCache
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS file_hashes (
+            file_path TEXT PRIMARY KEY,
+            file_size INTEGER NOT NULL,
+            modified_ns INTEGER NOT NULL,
+            file_hash TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Content hashes of import sources whose copies were rewritten on the way in (Takeout
+    // dates and places written into the file), so importing them again is still spotted
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS import_sources (
+            source_hash TEXT PRIMARY KEY,
+            file_hash TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    ensure_column(&conn, "media_files", "faces_detected", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(&conn, "media_files", "ocr_done", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(&conn, "face_regions", "cluster_id", "INTEGER")?;
+    ensure_column(&conn, "face_regions", "suggested_person_id", "INTEGER")?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_face_regions_media ON face_regions(media_id)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_face_regions_person ON face_regions(person_id)",
+        [],
+    )?;
+
+    // Fill in modified_day for rows from before it existed; the partial index stays
+    // empty afterwards, so finding none is instant
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_modified_day_missing ON media_files(id) WHERE modified_day IS NULL",
+        [],
+    )?;
+    refresh_modified_days(&conn, true)?;
+
+    create_private_tables(&conn)?;
+    create_change_log(&conn)?;
+
+    // Keep the planner's statistics current; cheap unless the tables changed a lot
+    conn.execute_batch("PRAGMA optimize=0x10002")?;
+
     Ok(conn)
 }
 
+/// Add a column to an existing table if an older database lacks it
+fn ensure_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let exists = conn
+        .prepare(&format!("SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1", table))?
+        .exists([column])?;
+
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    }
+
+    Ok(())
+}
+
+/// Columns read by `media_file_from_row`, in order
+pub const MEDIA_COLUMNS: &str = "id, file_path, file_hash, file_size, width, height,
+    taken_at, modified_at, thumbnail_path, media_type, created_at, latitude, longitude,
+    duration, fps, video_codec, bitrate, audio_tracks, camera_model, stack_id, rating, color_label, favorite, color_space,
+    (SELECT group_concat(t.name, char(31)) FROM media_tags mt JOIN tags t ON t.id = mt.tag_id
+     WHERE mt.media_id = media_files.id),
+    perceptual_hash, pick, live_video_id, online_only, taken_offset";
+
+/// Number of columns in `MEDIA_COLUMNS`; extra columns selected after them start at this index
+pub const MEDIA_COLUMN_COUNT: usize = 30;
+
+/// Separator used by `MEDIA_COLUMNS` to aggregate tag names
+const TAG_SEPARATOR: char = '\u{1f}';
+
+/// Build a MediaFile from a row selected with `MEDIA_COLUMNS`
+pub fn media_file_from_row(row: &Row) -> rusqlite::Result<MediaFile> {
+    let taken_at_str: Option<String> = row.get(6)?;
+    let modified_at_str: Option<String> = row.get(7)?;
+    let created_at_str: Option<String> = row.get(10)?;
+
+    let media_type_str: String = row.get(9)?;
+    let media_type: MediaType = serde_json::from_str(&media_type_str)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(9, Type::Text, Box::new(e)))?;
+
+    let tags_str: Option<String> = row.get(24)?;
+    let tags = tags_str
+        .map(|s| s.split(TAG_SEPARATOR).map(str::to_string).collect())
+        .unwrap_or_default();
+
+    let duration: Option<f64> = row.get(13)?;
+    let audio_tracks_str: Option<String> = row.get(17)?;
+    let video_info = match duration {
+        Some(duration) => Some(VideoInfo {
+            duration,
+            fps: row.get(14)?,
+            video_codec: row.get(15)?,
+            bitrate: row.get(16)?,
+            audio_tracks: audio_tracks_str
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default(),
+        }),
+        None => None,
+    };
+
+    Ok(MediaFile {
+        id: row.get(0)?,
+        file_path: row.get(1)?,
+        file_hash: row.get(2)?,
+        file_size: row.get(3)?,
+        width: row.get(4)?,
+        height: row.get(5)?,
+        taken_at: taken_at_str.as_deref().and_then(parse_db_datetime),
+        taken_offset: row.get(29)?,
+        latitude: row.get(11)?,
+        longitude: row.get(12)?,
+        camera_model: row.get(18)?,
+        modified_at: modified_at_str.as_deref().and_then(parse_db_datetime).unwrap_or_default(),
+        thumbnail_path: row.get(8)?,
+        media_type,
+        video_info,
+        color_space: row.get(23)?,
+        perceptual_hash: row.get(25)?,
+        stack_id: row.get(19)?,
+        rating: row.get(20)?,
+        color_label: row.get(21)?,
+        favorite: row.get(22)?,
+        pick: PickState::from_db(row.get(26)?),
+        live_video_id: row.get(27)?,
+        online_only: row.get(28)?,
+        tags,
+        created_at: created_at_str.as_deref().and_then(parse_db_datetime).unwrap_or_default(),
+    })
+}
+
+/// `modified_day` of a file modified at `modified_at`, as "YYYY-MM-DD"
+pub fn modified_day(modified_at: DateTime<Utc>) -> String {
+    wall_clock(modified_at).0.format("%Y-%m-%d").to_string()
+}
+
+/// Work out `modified_day` again, for every file or only those without one yet,
+/// e.g. after the date timezone changed
+pub fn refresh_modified_days(conn: &Connection, only_missing: bool) -> Result<usize> {
+    let query = if only_missing {
+        "SELECT id, modified_at FROM media_files WHERE modified_day IS NULL"
+    } else {
+        "SELECT id, modified_at FROM media_files"
+    };
+    let rows: Vec<(i64, Option<String>)> = conn
+        .prepare(query)?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    if rows.is_empty() {
+        return Ok(0);
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut stmt = tx.prepare("UPDATE media_files SET modified_day = ?1 WHERE id = ?2")?;
+        for (id, modified_at) in &rows {
+            let day = modified_at.as_deref().and_then(parse_db_datetime).map(modified_day);
+            stmt.execute(params![day, id])?;
+        }
+    }
+    tx.commit()?;
+    Ok(rows.len())
+}
+
+/// Parse a stored timestamp: RFC 3339 as written by us, or SQLite's CURRENT_TIMESTAMP format
+fn parse_db_datetime(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+                .ok()
+                .map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc))
+        })
+}
+
 #[tauri::command]
-pub async fn save_media_files(files: Vec<MediaFile>) -> Result<(), String> {
+pub async fn save_media_files(app: tauri::AppHandle, files: Vec<MediaFile>) -> Result<(), PenglerError> {
     save_media_files_internal(files)
-        .map_err(|e| format!("Failed to save media files: {}", e))
+        .map_err(|e| PenglerError::report("Failed to save media files", e))?;
+    notify_smart_albums_changed(&app);
+    Ok(())
 }
 
-fn save_media_files_internal(files: Vec<MediaFile>) -> Result<()> {
-    let conn = init_database()?;
+pub fn save_media_files_internal(files: Vec<MediaFile>) -> Result<()> {
+    let mut conn = init_database()?;
+    let tx = conn.transaction()?;
 
     for file in files {
-        conn.execute(
-            "INSERT OR REPLACE INTO media_files
-            (file_path, file_hash, file_size, width, height, taken_at, modified_at, thumbnail_path, media_type)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        // Only the flag changes for a known file that was freed up; its metadata stays
+        if file.online_only {
+            tx.execute(
+                "INSERT INTO media_files (file_path, file_hash, file_size, width, height, modified_at, modified_day, media_type, online_only)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 1)
+                 ON CONFLICT(file_path) DO UPDATE SET online_only = 1",
+                params![
+                    file.file_path,
+                    file.file_hash,
+                    file.file_size,
+                    file.width,
+                    file.height,
+                    file.modified_at.to_rfc3339(),
+                    modified_day(file.modified_at),
+                    serde_json::to_string(&file.media_type).unwrap(),
+                ],
+            )?;
+            continue;
+        }
+
+        let video = file.video_info.as_ref();
+
+        // Upsert rather than REPLACE so the row id and user-assigned columns survive a rescan
+        tx.execute(
+            "INSERT INTO media_files
+            (file_path, file_hash, file_size, width, height, taken_at, modified_at, thumbnail_path, media_type, latitude, longitude,
+             duration, fps, video_codec, bitrate, audio_tracks, camera_model, rating, color_label, favorite, color_space,
+             perceptual_hash, taken_offset, modified_day)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24)
+            ON CONFLICT(file_path) DO UPDATE SET
+                file_hash = excluded.file_hash,
+                file_size = excluded.file_size,
+                width = excluded.width,
+                height = excluded.height,
+                taken_at = excluded.taken_at,
+                taken_offset = excluded.taken_offset,
+                modified_at = excluded.modified_at,
+                modified_day = excluded.modified_day,
+                thumbnail_path = COALESCE(excluded.thumbnail_path, media_files.thumbnail_path),
+                media_type = excluded.media_type,
+                latitude = excluded.latitude,
+                longitude = excluded.longitude,
+                duration = excluded.duration,
+                fps = excluded.fps,
+                video_codec = excluded.video_codec,
+                bitrate = excluded.bitrate,
+                audio_tracks = excluded.audio_tracks,
+                camera_model = excluded.camera_model,
+                color_space = excluded.color_space,
+                perceptual_hash = COALESCE(excluded.perceptual_hash, media_files.perceptual_hash),
+                ocr_done = media_files.ocr_done AND media_files.file_hash = excluded.file_hash,
+                rating = COALESCE(excluded.rating, media_files.rating),
+                color_label = COALESCE(excluded.color_label, media_files.color_label),
+                favorite = media_files.favorite OR excluded.favorite,
+                online_only = 0",
             params![
                 file.file_path,
                 file.file_hash,
@@ -74,61 +606,68 @@ fn save_media_files_internal(files: Vec<MediaFile>) -> Result<()> {
                 file.modified_at.to_rfc3339(),
                 file.thumbnail_path,
                 serde_json::to_string(&file.media_type).unwrap(),
+                file.latitude,
+                file.longitude,
+                video.map(|v| v.duration),
+                video.and_then(|v| v.fps),
+                video.and_then(|v| v.video_codec.clone()),
+                video.and_then(|v| v.bitrate),
+                video.map(|v| serde_json::to_string(&v.audio_tracks).unwrap()),
+                file.camera_model,
+                file.rating,
+                file.color_label,
+                file.favorite,
+                file.color_space,
+                file.perceptual_hash,
+                file.taken_offset,
+                modified_day(file.modified_at),
             ],
         )?;
+
+        if !file.tags.is_empty() {
+            let media_id: i64 = tx.query_row(
+                "SELECT id FROM media_files WHERE file_path = ?1",
+                [&file.file_path],
+                |row| row.get(0),
+            )?;
+            for tag in &file.tags {
+                if let Some(tag_id) = ensure_tag(&tx, tag)? {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO media_tags (media_id, tag_id) VALUES (?1, ?2)",
+                        params![media_id, tag_id],
+                    )?;
+                }
+            }
+        }
     }
 
+    tx.commit()?;
+
     Ok(())
 }
 
+/// Load the library; with `collapse_stacks` each burst stack is represented by its cover only
 #[tauri::command]
-pub async fn load_media_files() -> Result<Vec<MediaFile>, String> {
-    load_media_files_internal()
-        .map_err(|e| format!("Failed to load media files: {}", e))
+pub async fn load_media_files(collapse_stacks: Option<bool>, sort: Option<MediaSort>) -> Result<Vec<MediaFile>, PenglerError> {
+    load_media_files_internal(collapse_stacks.unwrap_or(false), &sort.unwrap_or_default())
+        .map_err(|e| PenglerError::report("Failed to load media files", e))
 }
 
-fn load_media_files_internal() -> Result<Vec<MediaFile>> {
+fn load_media_files_internal(collapse_stacks: bool, sort: &MediaSort) -> Result<Vec<MediaFile>> {
     let conn = init_database()?;
 
-    let mut stmt = conn.prepare(
-        "SELECT id, file_path, file_hash, file_size, width, height,
-         taken_at, modified_at, thumbnail_path, media_type, created_at
-         FROM media_files
-         ORDER BY taken_at DESC, modified_at DESC"
-    )?;
-
-    let files = stmt.query_map([], |row| {
-        let taken_at_str: Option<String> = row.get(6)?;
-        let taken_at = taken_at_str.and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
-            .map(|dt| dt.with_timezone(&chrono::Utc));
-
-        let modified_at_str: String = row.get(7)?;
-        let modified_at = chrono::DateTime::parse_from_rfc3339(&modified_at_str)
-            .unwrap()
-            .with_timezone(&chrono::Utc);
-
-        let created_at_str: String = row.get(10)?;
-        let created_at = chrono::DateTime::parse_from_rfc3339(&created_at_str)
-            .unwrap()
-            .with_timezone(&chrono::Utc);
-
-        let media_type_str: String = row.get(9)?;
-        let media_type: crate::models::MediaType = serde_json::from_str(&media_type_str).unwrap();
-
-        Ok(MediaFile {
-            id: row.get(0)?,
-            file_path: row.get(1)?,
-            file_hash: row.get(2)?,
-            file_size: row.get(3)?,
-            width: row.get(4)?,
-            height: row.get(5)?,
-            taken_at,
-            modified_at,
-            thumbnail_path: row.get(8)?,
-            media_type,
-            created_at,
-        })
-    })?;
+    let filter = if collapse_stacks {
+        "AND (stack_id IS NULL OR id IN (SELECT cover_id FROM stacks))"
+    } else {
+        ""
+    };
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM media_files WHERE {} {} ORDER BY {}",
+        MEDIA_COLUMNS, visible_condition(), filter, order_clause(sort, None)
+    ))?;
+
+    let files = stmt.query_map([], media_file_from_row)?;
 
     let mut result = Vec::new();
     for file in files {
@@ -137,3 +676,23 @@ fn load_media_files_internal() -> Result<Vec<MediaFile>> {
 
     Ok(result)
 }
+
+/// Drop a deleted file and everything attached to it from the catalog
+pub fn forget_media(conn: &Connection, media_id: i64) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM media_tags WHERE media_id = ?1", [media_id])?;
+    tx.execute("DELETE FROM face_regions WHERE media_id = ?1", [media_id])?;
+    tx.execute("DELETE FROM media_text WHERE rowid = ?1", [media_id])?;
+    tx.execute("DELETE FROM media_embeddings WHERE media_id = ?1", [media_id])?;
+    tx.execute("DELETE FROM similar_groups WHERE media_id = ?1", [media_id])?;
+    tx.execute("DELETE FROM duplicate_groups WHERE media_id = ?1", [media_id])?;
+    tx.execute("DELETE FROM edits WHERE media_id = ?1", [media_id])?;
+    tx.execute("UPDATE media_files SET live_video_id = NULL WHERE live_video_id = ?1", [media_id])?;
+    tx.execute(
+        "DELETE FROM file_hashes WHERE file_path = (SELECT file_path FROM media_files WHERE id = ?1)",
+        [media_id],
+    )?;
+    tx.execute("DELETE FROM media_files WHERE id = ?1", [media_id])?;
+    tx.commit()?;
+    Ok(())
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use anyhow::Result;
+use tracing::{error, info, warn};
+
+use crate::utils::short_hash;
+use crate::commands::cache::init_database;
+use crate::commands::thumbnail::cached_thumbnails;
+
+/// Cache size allowed when the config doesn't say otherwise
+pub const DEFAULT_CACHE_MAX_BYTES: u64 = 500 * 1024 * 1024;
+
+/// How often the janitor checks the cache when nothing wakes it earlier
+const JANITOR_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Eviction goes down to this share of the budget, so the next few thumbnails
+/// don't trigger it again
+const EVICT_TO_PERCENT: u64 = 90;
+
+static CACHE_BUDGET: AtomicU64 = AtomicU64::new(DEFAULT_CACHE_MAX_BYTES);
+
+/// Thumbnail views not yet saved to the catalog: file hash -> unix time
+static PENDING_VIEWS: Mutex<Option<HashMap<String, i64>>> = Mutex::new(None);
+
+static WAKE: Mutex<bool> = Mutex::new(false);
+static WAKE_CHANGED: Condvar = Condvar::new();
+
+/// Cap the thumbnail cache at `max_bytes`; 0 lets it grow without limit
+pub fn set_cache_budget(max_bytes: u64) {
+    CACHE_BUDGET.store(max_bytes, Ordering::Relaxed);
+    wake_cache_janitor();
+}
+
+pub fn cache_budget() -> u64 {
+    CACHE_BUDGET.load(Ordering::Relaxed)
+}
+
+/// Note that the thumbnail of `file_hash` was shown. Kept in memory and saved as
+/// `last_viewed_at` on the janitor's next run, so the grid never waits on a write.
+pub fn record_view(file_hash: &str) {
+    let mut views = PENDING_VIEWS.lock().unwrap_or_else(|e| e.into_inner());
+    views.get_or_insert_with(HashMap::new).insert(file_hash.to_string(), Utc::now().timestamp());
+}
+
+/// Have the janitor check the cache now instead of at its next interval,
+/// e.g. after a scan or import wrote many thumbnails
+pub fn wake_cache_janitor() {
+    *WAKE.lock().unwrap_or_else(|e| e.into_inner()) = true;
+    WAKE_CHANGED.notify_one();
+}
+
+/// Start the background thread that keeps the cache within `cache_max_bytes`
+pub fn start_cache_janitor() {
+    let spawned = thread::Builder::new()
+        .name("pengler-cache-janitor".to_string())
+        .spawn(|| loop {
+            if let Err(e) = run_janitor() {
+                warn!("Cache janitor failed: {}", e);
+            }
+            wait_for_wake();
+        });
+    if let Err(e) = spawned {
+        error!("Failed to start the cache janitor: {}", e);
+    }
+}
+
+fn wait_for_wake() {
+    let woken = WAKE.lock().unwrap_or_else(|e| e.into_inner());
+    let (mut woken, _) = WAKE_CHANGED
+        .wait_timeout_while(woken, JANITOR_INTERVAL, |woken| !*woken)
+        .unwrap_or_else(|e| e.into_inner());
+    *woken = false;
+}
+
+fn run_janitor() -> Result<()> {
+    let conn = init_database()?;
+    save_views(&conn)?;
+
+    let (files, bytes) = enforce_cache_budget(&conn)?;
+    if files > 0 {
+        info!("Evicted {} cached thumbnails ({} bytes) to stay within the cache budget", files, bytes);
+    }
+    Ok(())
+}
+
+fn save_views(conn: &Connection) -> Result<()> {
+    let Some(views) = PENDING_VIEWS.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+        return Ok(());
+    };
+
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut stmt = tx.prepare("UPDATE media_files SET last_viewed_at = ?1 WHERE file_hash = ?2")?;
+        for (file_hash, viewed_at) in &views {
+            stmt.execute(params![viewed_at, file_hash])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Delete thumbnails of the least recently viewed media until the cache is back under
+/// budget. Thumbnails of files no longer in the catalog go first, then those never
+/// viewed, oldest render first. Returns the files and bytes freed.
+pub fn enforce_cache_budget(conn: &Connection) -> Result<(usize, u64)> {
+    let budget = cache_budget();
+    if budget == 0 {
+        return Ok((0, 0));
+    }
+
+    let mut thumbnails = cached_thumbnails()?;
+    let mut total: u64 = thumbnails.iter().map(|thumbnail| thumbnail.size).sum();
+    if total <= budget {
+        return Ok((0, 0));
+    }
+
+    let last_viewed = last_viewed_by_key(conn)?;
+    thumbnails.sort_by_key(|thumbnail| {
+        let viewed = last_viewed.get(&thumbnail.key).map(|viewed| viewed.unwrap_or(0));
+        (viewed, thumbnail.modified)
+    });
+
+    let target = budget / 100 * EVICT_TO_PERCENT;
+    let (mut files, mut bytes) = (0, 0);
+    for thumbnail in thumbnails {
+        if total <= target {
+            break;
+        }
+        match fs::remove_file(&thumbnail.path) {
+            Ok(()) => {
+                total -= thumbnail.size;
+                files += 1;
+                bytes += thumbnail.size;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => total -= thumbnail.size,
+            Err(e) => warn!("Failed to evict {}: {}", thumbnail.path.display(), e),
+        }
+    }
+    Ok((files, bytes))
+}
+
+/// Latest view of the cataloged files behind each thumbnail key; `None` if never viewed
+fn last_viewed_by_key(conn: &Connection) -> Result<HashMap<String, Option<i64>>> {
+    let mut stmt = conn.prepare("SELECT file_hash, last_viewed_at FROM media_files")?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<i64>>(1)?)))?;
+
+    let mut last_viewed: HashMap<String, Option<i64>> = HashMap::new();
+    for row in rows {
+        let (file_hash, viewed_at) = row?;
+        let entry = last_viewed.entry(short_hash(&file_hash)).or_default();
+        *entry = (*entry).max(viewed_at);
+    }
+    Ok(last_viewed)
+}
@@ -0,0 +1,223 @@
+use std::thread;
+use std::time::{Duration, Instant};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use anyhow::Result;
+use tracing::{error, warn};
+
+use crate::error::PenglerError;
+use crate::utils::is_read_only_instance;
+use crate::commands::cache::init_database;
+use crate::commands::private::hidden_condition;
+
+/// Emitted with the latest change `seq` whenever the catalog changed, in this
+/// instance or another one
+pub const LIBRARY_CHANGED_EVENT: &str = "library-changed";
+
+/// How often the change log is checked for new entries
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often old entries are pruned
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Entries kept; clients further behind reload everything
+const CHANGES_KEPT: i64 = 100_000;
+
+/// Changes returned at once when no limit is given
+const DEFAULT_CHANGE_LIMIT: usize = 5000;
+
+/// Stored in `PRAGMA user_version` once the change log triggers exist; bump it when
+/// they or `TRACKED_MEDIA_COLUMNS` change so existing catalogs get them recreated
+const CHANGE_LOG_VERSION: i64 = 1;
+
+/// media_files columns the frontend shows; updates touching only others (view times,
+/// cached hashes, detection flags) aren't logged
+const TRACKED_MEDIA_COLUMNS: &[&str] = &[
+    "file_path", "file_hash", "file_size", "width", "height", "taken_at", "taken_offset", "modified_at",
+    "media_type", "latitude", "longitude", "duration", "fps", "video_codec", "bitrate", "audio_tracks",
+    "camera_model", "stack_id", "rating", "color_label", "favorite", "color_space", "pick", "live_video_id",
+    "online_only",
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Change {
+    pub seq: i64,
+    /// "media", "tag" or "album"
+    pub entity: String,
+    /// Id of the media file, tag or smart album
+    pub id: i64,
+    /// "insert", "update" or "delete"; the last of the entity's changes
+    pub op: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeSet {
+    /// One entry per changed entity, in the order of their last change
+    pub changes: Vec<Change>,
+    /// Where to continue from next time
+    pub seq: i64,
+    /// Changes were cut off at the limit; ask again from `seq`
+    pub more: bool,
+    /// `since` is older than the log reaches back; reload everything
+    pub reset: bool,
+}
+
+/// Log every insert, update and delete of media files, tags and smart albums in
+/// `change_log`. Triggers catch changes from every command and process alike. Tagging
+/// or editing a file counts as an update of the file.
+pub fn create_change_log(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS change_log (
+            seq INTEGER PRIMARY KEY AUTOINCREMENT,
+            entity TEXT NOT NULL,
+            entity_id INTEGER NOT NULL,
+            op TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version >= CHANGE_LOG_VERSION {
+        return Ok(());
+    }
+
+    let media_changed = TRACKED_MEDIA_COLUMNS
+        .iter()
+        .map(|column| format!("NEW.{0} IS NOT OLD.{0}", column))
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    let log = |entity: &str, id: &str, op: &str| {
+        format!("INSERT INTO change_log (entity, entity_id, op) VALUES ('{}', {}, '{}');", entity, id, op)
+    };
+
+    let triggers = [
+        ("media_files", "INSERT", String::new(), log("media", "NEW.id", "insert")),
+        ("media_files", "UPDATE", format!("WHEN {}", media_changed), log("media", "NEW.id", "update")),
+        ("media_files", "DELETE", String::new(), log("media", "OLD.id", "delete")),
+        ("media_tags", "INSERT", String::new(), log("media", "NEW.media_id", "update")),
+        ("media_tags", "DELETE", String::new(), log("media", "OLD.media_id", "update")),
+        ("edits", "INSERT", String::new(), log("media", "NEW.media_id", "update")),
+        ("edits", "DELETE", String::new(), log("media", "OLD.media_id", "update")),
+        ("tags", "INSERT", String::new(), log("tag", "NEW.id", "insert")),
+        ("tags", "UPDATE", String::new(), log("tag", "NEW.id", "update")),
+        ("tags", "DELETE", String::new(), log("tag", "OLD.id", "delete")),
+        ("smart_albums", "INSERT", String::new(), log("album", "NEW.id", "insert")),
+        ("smart_albums", "UPDATE", String::new(), log("album", "NEW.id", "update")),
+        ("smart_albums", "DELETE", String::new(), log("album", "OLD.id", "delete")),
+    ];
+
+    let tx = conn.unchecked_transaction()?;
+    for (table, event, condition, action) in &triggers {
+        let name = format!("log_{}_{}", table, event.to_lowercase());
+        tx.execute_batch(&format!(
+            "DROP TRIGGER IF EXISTS {name};
+             CREATE TRIGGER {name} AFTER {event} ON {table} {condition} BEGIN {action} END;"
+        ))?;
+    }
+    tx.execute_batch(&format!("PRAGMA user_version = {}", CHANGE_LOG_VERSION))?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Latest change logged so far; 0 before the first
+fn latest_seq(conn: &Connection) -> Result<i64> {
+    let seq: Option<i64> = conn
+        .query_row("SELECT seq FROM sqlite_sequence WHERE name = 'change_log'", [], |row| row.get(0))
+        .optional()?;
+    Ok(seq.unwrap_or(0))
+}
+
+/// What changed in the catalog after `since` (a `seq` from an earlier call or a
+/// `library-changed` event; 0 for everything still logged), so lists can be updated
+/// in place instead of reloaded
+#[tauri::command]
+pub async fn get_changes_since(seq: i64, limit: Option<usize>) -> Result<ChangeSet, PenglerError> {
+    get_changes_since_internal(seq, limit.unwrap_or(DEFAULT_CHANGE_LIMIT))
+        .map_err(|e| PenglerError::report("Failed to load changes", e))
+}
+
+fn get_changes_since_internal(since: i64, limit: usize) -> Result<ChangeSet> {
+    let conn = init_database()?;
+    let latest = latest_seq(&conn)?;
+    let oldest: Option<i64> = conn.query_row("SELECT MIN(seq) FROM change_log", [], |row| row.get(0))?;
+
+    // Entries after `since` were pruned, or the catalog was replaced
+    if since + 1 < oldest.unwrap_or(latest + 1) || since > latest {
+        return Ok(ChangeSet { changes: Vec::new(), seq: latest, more: false, reset: true });
+    }
+
+    // MAX(seq) makes SQLite take entity, entity_id and op from each entity's last change.
+    // Changes of private media stay hidden while they are locked.
+    let mut stmt = conn.prepare(&format!(
+        "SELECT MAX(seq), entity, entity_id, op FROM change_log WHERE seq > ?1 AND seq <= ?2
+           AND NOT (entity = 'media' AND entity_id IN (SELECT id FROM media_files WHERE {}))
+         GROUP BY entity, entity_id ORDER BY 1 LIMIT ?3",
+        hidden_condition()
+    ))?;
+    let changes: Vec<Change> = stmt
+        .query_map(params![since, latest, limit as i64 + 1], |row| {
+            Ok(Change { seq: row.get(0)?, entity: row.get(1)?, id: row.get(2)?, op: row.get(3)? })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    if changes.len() > limit {
+        let mut changes = changes;
+        changes.truncate(limit);
+        let seq = changes.last().map_or(since, |change| change.seq);
+        return Ok(ChangeSet { changes, seq, more: true, reset: false });
+    }
+    Ok(ChangeSet { changes, seq: latest, more: false, reset: false })
+}
+
+/// Forget all but the last `CHANGES_KEPT` entries
+fn prune_change_log(conn: &Connection) -> Result<()> {
+    conn.execute("DELETE FROM change_log WHERE seq <= ?1", [latest_seq(conn)? - CHANGES_KEPT])?;
+    Ok(())
+}
+
+/// Start the background thread that emits `LIBRARY_CHANGED_EVENT` when new changes are
+/// logged. It reads the log rather than hooking into commands, so changes made by
+/// another instance or the command line show up too.
+pub fn start_change_feed(app: AppHandle) {
+    let spawned = thread::Builder::new()
+        .name("pengler-change-feed".to_string())
+        .spawn(move || {
+            let conn = match init_database() {
+                Ok(conn) => conn,
+                Err(e) => {
+                    error!("Change feed stopped: {}", e);
+                    return;
+                }
+            };
+
+            let mut seen = latest_seq(&conn).unwrap_or(0);
+            let mut last_pruned: Option<Instant> = None;
+            loop {
+                thread::sleep(POLL_INTERVAL);
+
+                if !is_read_only_instance() && last_pruned.is_none_or(|time| time.elapsed() >= PRUNE_INTERVAL) {
+                    if let Err(e) = prune_change_log(&conn) {
+                        warn!("Failed to prune the change log: {}", e);
+                    }
+                    last_pruned = Some(Instant::now());
+                }
+
+                match latest_seq(&conn) {
+                    Ok(latest) if latest != seen => {
+                        seen = latest;
+                        if let Err(e) = app.emit(LIBRARY_CHANGED_EVENT, latest) {
+                            warn!("Failed to emit {}: {}", LIBRARY_CHANGED_EVENT, e);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("Failed to read the change log: {}", e),
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        error!("Failed to start the change feed: {}", e);
+    }
+}
@@ -0,0 +1,393 @@
+use serde::Serialize;
+use tauri::AppHandle;
+use anyhow::Result;
+
+use crate::error::PenglerError;
+
+/// Emitted after each file and each uploaded part
+#[cfg(feature = "cloud-backup")]
+pub const CLOUD_BACKUP_PROGRESS_EVENT: &str = "cloud-backup-progress";
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CloudBackupStatus {
+    pub running: bool,
+    pub processed: usize,
+    pub total: usize,
+    pub uploaded: usize,
+    pub unchanged: usize,
+    pub failed: usize,
+    pub bytes_uploaded: u64,
+    pub current_file: Option<String>,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    pub cancelled: bool,
+    /// Why the run stopped, if it failed as a whole
+    pub error: Option<String>,
+}
+
+/// Start uploading new and changed originals to the configured S3-compatible bucket in
+/// the background. Files are encrypted on this machine before upload; large files go up
+/// in parts, and an interrupted upload resumes where it stopped on the next run.
+/// Needs the `cloud-backup` build feature.
+#[tauri::command]
+pub async fn start_cloud_backup(app: AppHandle) -> Result<CloudBackupStatus, PenglerError> {
+    start_cloud_backup_internal(app)
+        .map_err(|e| PenglerError::report("Failed to start cloud backup", e))
+}
+
+/// Stop after the part being uploaded; the upload resumes on the next run
+#[tauri::command]
+pub async fn cancel_cloud_backup() -> Result<(), PenglerError> {
+    cancel_cloud_backup_internal();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_cloud_backup_status() -> Result<CloudBackupStatus, PenglerError> {
+    Ok(cloud_backup_status())
+}
+
+#[cfg(not(feature = "cloud-backup"))]
+fn start_cloud_backup_internal(_app: AppHandle) -> Result<CloudBackupStatus> {
+    Err(anyhow::anyhow!("This build of Pengler does not include cloud backup"))
+}
+
+#[cfg(not(feature = "cloud-backup"))]
+fn cancel_cloud_backup_internal() {}
+
+#[cfg(not(feature = "cloud-backup"))]
+fn cloud_backup_status() -> CloudBackupStatus {
+    CloudBackupStatus::default()
+}
+
+#[cfg(feature = "cloud-backup")]
+use worker::{cancel_cloud_backup_internal, cloud_backup_status, start_cloud_backup_internal};
+
+#[cfg(feature = "cloud-backup")]
+mod worker {
+    use std::fs::{self, File};
+    use std::io::{Read, Seek, SeekFrom};
+    use std::path::Path;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Mutex;
+    use std::thread;
+    use chrono::Utc;
+    use rusqlite::{params, Connection, OptionalExtension};
+    use tauri::{AppHandle, Emitter};
+    use anyhow::Result;
+    use tracing::{error, info, warn};
+
+    use super::{CloudBackupStatus, CLOUD_BACKUP_PROGRESS_EVENT};
+    use crate::config::{CloudBackupConfig, Config};
+    use crate::utils::{chunk_count, hash_file, EncryptionKey, FileEncryptor, S3Client, CHUNK_SIZE, HEADER_SIZE, TAG_SIZE};
+    use crate::commands::backup::{available_folders, library_files};
+    use crate::commands::cache::init_database;
+
+    /// Chunks per multipart part: 8 MiB, above S3's 5 MiB minimum part size
+    const PART_CHUNKS: u64 = 8;
+
+    static STATUS: Mutex<Option<CloudBackupStatus>> = Mutex::new(None);
+    static CANCEL: AtomicBool = AtomicBool::new(false);
+
+    /// A multipart upload recorded in `cloud_uploads`
+    struct PendingUpload {
+        object_key: String,
+        upload_id: String,
+        file_hash: String,
+        nonce_prefix: String,
+        parts: Vec<(u32, String)>,
+    }
+
+    pub(super) fn start_cloud_backup_internal(app: AppHandle) -> Result<CloudBackupStatus> {
+        let config = Config::load()?;
+        let cloud = config
+            .cloud_backup
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("No cloud backup is set up in settings"))?;
+
+        let status = {
+            let mut status = STATUS.lock().unwrap();
+            if status.as_ref().is_some_and(|s| s.running) {
+                return Err(anyhow::anyhow!("A cloud backup is already running"));
+            }
+            let started = CloudBackupStatus {
+                running: true,
+                started_at: Some(Utc::now().to_rfc3339()),
+                ..Default::default()
+            };
+            *status = Some(started.clone());
+            started
+        };
+        CANCEL.store(false, Ordering::SeqCst);
+
+        thread::spawn(move || {
+            let result = run(&app, &config, &cloud);
+            update(&app, |status| {
+                status.running = false;
+                status.current_file = None;
+                status.finished_at = Some(Utc::now().to_rfc3339());
+                status.cancelled = CANCEL.load(Ordering::SeqCst);
+                if let Err(e) = &result {
+                    status.error = Some(e.to_string());
+                }
+            });
+
+            let status = cloud_backup_status();
+            match result {
+                Ok(()) => info!(
+                    "Cloud backup to {}: {} uploaded, {} unchanged, {} failed{}",
+                    cloud.bucket,
+                    status.uploaded,
+                    status.unchanged,
+                    status.failed,
+                    if status.cancelled { " (cancelled)" } else { "" }
+                ),
+                Err(e) => error!("Cloud backup failed: {}", e),
+            }
+        });
+
+        Ok(status)
+    }
+
+    pub(super) fn cancel_cloud_backup_internal() {
+        CANCEL.store(true, Ordering::SeqCst);
+    }
+
+    pub(super) fn cloud_backup_status() -> CloudBackupStatus {
+        STATUS.lock().unwrap().clone().unwrap_or_default()
+    }
+
+    fn update(app: &AppHandle, change: impl FnOnce(&mut CloudBackupStatus)) {
+        let status = {
+            let mut status = STATUS.lock().unwrap();
+            let status = status.get_or_insert_with(Default::default);
+            change(status);
+            status.clone()
+        };
+        if let Err(e) = app.emit(CLOUD_BACKUP_PROGRESS_EVENT, status) {
+            warn!("Failed to emit {}: {}", CLOUD_BACKUP_PROGRESS_EVENT, e);
+        }
+    }
+
+    fn cancelled() -> bool {
+        CANCEL.load(Ordering::SeqCst)
+    }
+
+    fn run(app: &AppHandle, config: &Config, cloud: &CloudBackupConfig) -> Result<()> {
+        let client = S3Client::new(&cloud.endpoint, &cloud.region, &cloud.bucket, &cloud.access_key_id, &cloud.secret_access_key)?
+            .with_bandwidth_limit(cloud.bandwidth_limit_kbps as u64 * 1024);
+        let prefix = cloud.prefix.trim_matches('/');
+        let key = EncryptionKey::derive(&cloud.passphrase, &format!("{}/{}", cloud.bucket, prefix))?;
+        let conn = init_database()?;
+
+        let files = library_files(&available_folders(&config.library_folders()));
+        update(app, |status| status.total = files.len());
+
+        for (source, backup_path) in &files {
+            if cancelled() {
+                break;
+            }
+
+            let source_key = source.to_string_lossy().to_string();
+            let object_key = object_key(prefix, backup_path);
+            update(app, |status| status.current_file = Some(source_key.clone()));
+
+            match upload_file(app, &conn, &client, &key, source, &source_key, &object_key) {
+                Ok(Some(_)) => update(app, |status| status.uploaded += 1),
+                Ok(None) => update(app, |status| status.unchanged += 1),
+                // Stopped between parts; the upload resumes next time
+                Err(_) if cancelled() => break,
+                Err(e) => {
+                    error!("Failed to upload {}: {}", source.display(), e);
+                    update(app, |status| status.failed += 1);
+                }
+            }
+            update(app, |status| status.processed += 1);
+        }
+
+        Ok(())
+    }
+
+    /// `prefix/Folder/sub/IMG_0001.jpg.enc`, with `/` separators on every platform
+    fn object_key(prefix: &str, backup_path: &str) -> String {
+        let path = backup_path.replace('\\', "/");
+        if prefix.is_empty() {
+            format!("{}.enc", path)
+        } else {
+            format!("{}/{}.enc", prefix, path)
+        }
+    }
+
+    /// Upload one original unless the bucket already holds it; returns the bytes read
+    fn upload_file(
+        app: &AppHandle,
+        conn: &Connection,
+        client: &S3Client,
+        key: &EncryptionKey,
+        source: &Path,
+        source_key: &str,
+        object_key: &str,
+    ) -> Result<Option<u64>> {
+        let metadata = fs::metadata(source)?;
+        let size = metadata.len();
+        let modified_at = filetime::FileTime::from_last_modification_time(&metadata).unix_seconds();
+
+        let uploaded: Option<(String, String, u64, i64)> = conn
+            .query_row(
+                "SELECT object_key, file_hash, file_size, modified_at FROM cloud_backup_files WHERE source_path = ?1",
+                [source_key],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, i64>(2)? as u64, row.get(3)?)),
+            )
+            .optional()?;
+        let same_key = |entry: &(String, String, u64, i64)| entry.0 == object_key;
+
+        if let Some(entry) = &uploaded {
+            if same_key(entry) && entry.2 == size && entry.3 == modified_at {
+                return Ok(None);
+            }
+        }
+
+        let file_hash = hash_file(source)?;
+        let unchanged = uploaded.as_ref().is_some_and(|entry| same_key(entry) && entry.1 == file_hash);
+
+        if !unchanged {
+            let mut file = File::open(source)?;
+            if chunk_count(size) <= PART_CHUNKS {
+                let encryptor = FileEncryptor::new(key, FileEncryptor::random_nonce_prefix()?, size);
+                let body = encrypt_chunks(&mut file, &encryptor, size, 0, encryptor.chunk_count())?;
+                client.put_object(object_key, &body)?;
+                update(app, |status| status.bytes_uploaded += body.len() as u64);
+            } else {
+                upload_multipart(app, conn, client, key, &mut file, size, source_key, object_key, &file_hash)?;
+            }
+        }
+
+        conn.execute(
+            "INSERT INTO cloud_backup_files (source_path, object_key, file_hash, file_size, modified_at, uploaded_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(source_path) DO UPDATE SET object_key = excluded.object_key, file_hash = excluded.file_hash,
+                file_size = excluded.file_size, modified_at = excluded.modified_at, uploaded_at = excluded.uploaded_at",
+            params![source_key, object_key, file_hash, size as i64, modified_at, Utc::now().to_rfc3339()],
+        )?;
+
+        Ok(if unchanged { None } else { Some(size) })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn upload_multipart(
+        app: &AppHandle,
+        conn: &Connection,
+        client: &S3Client,
+        key: &EncryptionKey,
+        file: &mut File,
+        size: u64,
+        source_key: &str,
+        object_key: &str,
+        file_hash: &str,
+    ) -> Result<()> {
+        let mut upload = match load_upload(conn, source_key)? {
+            Some(upload) if upload.object_key == object_key && upload.file_hash == file_hash => upload,
+            stale => {
+                if let Some(stale) = stale {
+                    if let Err(e) = client.abort_multipart_upload(&stale.object_key, &stale.upload_id) {
+                        error!("Failed to abort stale upload of {}: {}", stale.object_key, e);
+                    }
+                }
+                let upload = PendingUpload {
+                    object_key: object_key.to_string(),
+                    upload_id: client.create_multipart_upload(object_key)?,
+                    file_hash: file_hash.to_string(),
+                    nonce_prefix: to_hex(&FileEncryptor::random_nonce_prefix()?),
+                    parts: Vec::new(),
+                };
+                conn.execute(
+                    "INSERT OR REPLACE INTO cloud_uploads (source_path, object_key, upload_id, file_hash, nonce_prefix, parts, started_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, '[]', ?6)",
+                    params![source_key, upload.object_key, upload.upload_id, upload.file_hash, upload.nonce_prefix, Utc::now().to_rfc3339()],
+                )?;
+                upload
+            }
+        };
+
+        let encryptor = FileEncryptor::new(key, from_hex(&upload.nonce_prefix)?, size);
+        let chunk_count = encryptor.chunk_count();
+        let part_count = chunk_count.div_ceil(PART_CHUNKS) as u32;
+
+        for part_number in 1..=part_count {
+            if upload.parts.iter().any(|(number, _)| *number == part_number) {
+                continue;
+            }
+            if cancelled() {
+                return Err(anyhow::anyhow!("Cancelled"));
+            }
+
+            let first = (part_number as u64 - 1) * PART_CHUNKS;
+            let body = encrypt_chunks(file, &encryptor, size, first, (first + PART_CHUNKS).min(chunk_count))?;
+            let etag = client.upload_part(object_key, &upload.upload_id, part_number, &body)?;
+            upload.parts.push((part_number, etag));
+            conn.execute(
+                "UPDATE cloud_uploads SET parts = ?1 WHERE source_path = ?2",
+                params![serde_json::to_string(&upload.parts)?, source_key],
+            )?;
+            update(app, |status| status.bytes_uploaded += body.len() as u64);
+        }
+
+        upload.parts.sort();
+        client.complete_multipart_upload(object_key, &upload.upload_id, &upload.parts)?;
+        conn.execute("DELETE FROM cloud_uploads WHERE source_path = ?1", [source_key])?;
+        Ok(())
+    }
+
+    fn load_upload(conn: &Connection, source_path: &str) -> Result<Option<PendingUpload>> {
+        let row: Option<(String, String, String, String, String)> = conn
+            .query_row(
+                "SELECT object_key, upload_id, file_hash, nonce_prefix, parts FROM cloud_uploads WHERE source_path = ?1",
+                [source_path],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+            )
+            .optional()?;
+
+        row.map(|(object_key, upload_id, file_hash, nonce_prefix, parts)| {
+            Ok(PendingUpload { object_key, upload_id, file_hash, nonce_prefix, parts: serde_json::from_str(&parts)? })
+        })
+        .transpose()
+    }
+
+    /// Encrypt chunks `first..end` of the file, preceded by the header for the first chunk
+    fn encrypt_chunks(file: &mut File, encryptor: &FileEncryptor, size: u64, first: u64, end: u64) -> Result<Vec<u8>> {
+        let mut body = Vec::with_capacity(HEADER_SIZE + (end - first) as usize * (CHUNK_SIZE + TAG_SIZE));
+        if first == 0 {
+            body.extend_from_slice(&encryptor.header());
+        }
+
+        file.seek(SeekFrom::Start(first * CHUNK_SIZE as u64))?;
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+        for index in first..end {
+            let expected = (size - index * CHUNK_SIZE as u64).min(CHUNK_SIZE as u64) as usize;
+            chunk.clear();
+            file.by_ref().take(expected as u64).read_to_end(&mut chunk)?;
+            if chunk.len() != expected {
+                return Err(anyhow::anyhow!("The file changed during upload"));
+            }
+            body.extend_from_slice(&encryptor.seal_chunk(index, &chunk)?);
+        }
+
+        Ok(body)
+    }
+
+    fn to_hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn from_hex(hex: &str) -> Result<[u8; 16]> {
+        let mut bytes = [0u8; 16];
+        if hex.len() != 32 {
+            return Err(anyhow::anyhow!("Invalid nonce in upload record"));
+        }
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)?;
+        }
+        Ok(bytes)
+    }
+}
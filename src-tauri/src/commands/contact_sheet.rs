@@ -0,0 +1,462 @@
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use ab_glyph::FontVec;
+use image::{imageops::FilterType, DynamicImage, Rgba, RgbaImage};
+use image::codecs::jpeg::JpegEncoder;
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, TextStr};
+use rayon::prelude::*;
+use rusqlite::params_from_iter;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use anyhow::Result;
+use tracing::{error, info, warn};
+
+use crate::error::PenglerError;
+use crate::models::{MediaFile, MediaType};
+use crate::utils::{
+    acquire_slot, apply_edits, convert_to_srgb, draw_text, line_height, load_font, open_upright_image_with_profile,
+    run_in_worker_pool, text_width, write_atomically, EditRecipe, Priority, Workload,
+};
+use crate::commands::cache::{init_database, media_file_from_row, MEDIA_COLUMNS};
+use crate::commands::edits::current_recipe;
+use crate::commands::thumbnail::{generate_thumbnail_internal, read_thumbnail};
+use crate::commands::private::visible_condition;
+
+/// Emitted after each photo placed on the sheet
+pub const CONTACT_SHEET_PROGRESS_EVENT: &str = "contact-sheet-progress";
+
+/// Pages are rendered at print resolution
+const DPI: f32 = 150.0;
+const MARGIN: u32 = 60;
+const GUTTER: u32 = 16;
+const TITLE_SIZE: f32 = 28.0;
+const CAPTION_SIZE: f32 = 16.0;
+const FOOTER_SIZE: f32 = 14.0;
+/// Cell width of single-image sheets, which have no page to fit
+const JPEG_CELL_WIDTH: u32 = 400;
+/// Largest dimension a JPEG can store
+const JPEG_MAX_DIMENSION: u32 = 65_535;
+const JPEG_QUALITY: u8 = 90;
+
+const BACKGROUND: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const CELL_BACKGROUND: Rgba<u8> = Rgba([240, 240, 240, 255]);
+const TEXT_COLOR: Rgba<u8> = Rgba([20, 20, 20, 255]);
+const SECONDARY_TEXT_COLOR: Rgba<u8> = Rgba([110, 110, 110, 255]);
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContactSheetFormat {
+    /// One page per `rows` x `columns` photos
+    #[default]
+    Pdf,
+    /// A single tall image holding every photo
+    Jpeg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageSize {
+    #[default]
+    A4,
+    Letter,
+}
+
+impl PageSize {
+    /// Size in pixels at `DPI`, portrait
+    fn pixels(self) -> (u32, u32) {
+        match self {
+            PageSize::A4 => (1240, 1754),
+            PageSize::Letter => (1275, 1650),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContactSheetOptions {
+    /// Output PDF or JPEG file
+    pub dest: String,
+    #[serde(default)]
+    pub format: ContactSheetFormat,
+    #[serde(default = "default_columns")]
+    pub columns: u32,
+    /// Rows per page; ignored for JPEG sheets
+    #[serde(default = "default_rows")]
+    pub rows: u32,
+    #[serde(default)]
+    pub page_size: PageSize,
+    /// Printed at the top of every page
+    pub title: Option<String>,
+}
+
+fn default_columns() -> u32 {
+    5
+}
+
+fn default_rows() -> u32 {
+    6
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContactSheetProgress {
+    pub processed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContactSheetResult {
+    pub path: String,
+    pub pages: usize,
+    pub photos: usize,
+    /// Cells left blank because the file couldn't be read
+    pub failed: usize,
+}
+
+/// Where everything goes on a page, in pixels
+struct Layout {
+    width: u32,
+    height: u32,
+    columns: u32,
+    rows: u32,
+    cell_width: u32,
+    cell_height: u32,
+    image_height: u32,
+    /// Top of the first row, below the title
+    top: u32,
+}
+
+/// Render the selected media as an index print for client review: a grid of
+/// photos, in the given order, with the file name and date under each one.
+/// Videos are shown by their poster frame; saved adjustments are applied to photos.
+#[tauri::command]
+pub async fn generate_contact_sheet(
+    app: AppHandle,
+    media_ids: Vec<i64>,
+    options: ContactSheetOptions,
+) -> Result<ContactSheetResult, PenglerError> {
+    generate_contact_sheet_internal(&app, &media_ids, &options)
+        .map_err(|e| PenglerError::report("Failed to generate contact sheet", e))
+}
+
+fn generate_contact_sheet_internal(
+    app: &AppHandle,
+    media_ids: &[i64],
+    options: &ContactSheetOptions,
+) -> Result<ContactSheetResult> {
+    if options.columns == 0 || options.columns > 20 {
+        return Err(anyhow::anyhow!("Columns must be between 1 and 20"));
+    }
+    if options.rows == 0 || options.rows > 20 {
+        return Err(anyhow::anyhow!("Rows must be between 1 and 20"));
+    }
+
+    let files = load_files(media_ids)?;
+    if files.is_empty() {
+        return Err(anyhow::anyhow!("No media selected"));
+    }
+
+    let font = load_font(None)?;
+    let title = options.title.as_deref().map(str::trim).filter(|title| !title.is_empty());
+    let layout = plan_layout(&font, options, title.is_some(), files.len())?;
+
+    let conn = init_database()?;
+    let recipes = files
+        .iter()
+        .map(|media| match media.media_type {
+            MediaType::Image => current_recipe(&conn, media.id),
+            MediaType::Video => Ok(None),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let total = files.len();
+    let per_page = (layout.columns * layout.rows) as usize;
+    let page_count = total.div_ceil(per_page);
+    let processed = AtomicUsize::new(0);
+    let mut failed = 0;
+    let mut pages = Vec::with_capacity(page_count);
+
+    for (page_index, (page_files, page_recipes)) in files.chunks(per_page).zip(recipes.chunks(per_page)).enumerate() {
+        // Cells are decoded in parallel, one page at a time to bound memory
+        let cells: Vec<Option<RgbaImage>> = run_in_worker_pool(Workload::Export, || {
+            page_files
+                .par_iter()
+                .zip(page_recipes.par_iter())
+                .map(|(media, recipe)| {
+                    let _slot = acquire_slot(Priority::Batch);
+                    let cell = match render_cell(media, recipe.as_ref(), layout.cell_width, layout.image_height) {
+                        Ok(cell) => Some(cell),
+                        Err(e) => {
+                            error!("Failed to add {} to contact sheet: {}", media.file_path, e);
+                            None
+                        }
+                    };
+
+                    let progress = ContactSheetProgress { processed: processed.fetch_add(1, Ordering::Relaxed) + 1, total };
+                    if let Err(e) = app.emit(CONTACT_SHEET_PROGRESS_EVENT, progress) {
+                        warn!("Failed to emit {}: {}", CONTACT_SHEET_PROGRESS_EVENT, e);
+                    }
+
+                    cell
+                })
+                .collect()
+        })?;
+        failed += cells.iter().filter(|cell| cell.is_none()).count();
+
+        // Only paged sheets get page numbers
+        let footer = (options.format == ContactSheetFormat::Pdf)
+            .then(|| format!("Page {} of {}", page_index + 1, page_count));
+        let page = render_page(&font, &layout, title, footer.as_deref(), page_files, &cells);
+        pages.push(encode_jpeg(page)?);
+    }
+
+    let dest = Path::new(&options.dest);
+    write_atomically(dest, |part| {
+        match options.format {
+            ContactSheetFormat::Pdf => std::fs::write(part, pdf_document(&pages, &layout, title))?,
+            ContactSheetFormat::Jpeg => std::fs::write(part, &pages[0])?,
+        }
+        Ok(())
+    })?;
+
+    info!("Generated contact sheet of {} items ({} pages) at {}", total, pages.len(), options.dest);
+
+    Ok(ContactSheetResult {
+        path: options.dest.clone(),
+        pages: pages.len(),
+        photos: total - failed,
+        failed,
+    })
+}
+
+/// The selected media, keeping the selection order
+fn load_files(media_ids: &[i64]) -> Result<Vec<MediaFile>> {
+    if media_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let conn = init_database()?;
+    let placeholders = vec!["?"; media_ids.len()].join(", ");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM media_files WHERE id IN ({}) AND {}",
+        MEDIA_COLUMNS,
+        placeholders,
+        visible_condition()
+    ))?;
+    let mut rows: Vec<MediaFile> = stmt
+        .query_map(params_from_iter(media_ids.iter()), media_file_from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(media_ids
+        .iter()
+        .filter_map(|id| rows.iter().position(|media| media.id == *id).map(|index| rows.swap_remove(index)))
+        .collect())
+}
+
+fn plan_layout(font: &FontVec, options: &ContactSheetOptions, has_title: bool, count: usize) -> Result<Layout> {
+    let caption_height = (2.0 * line_height(font, CAPTION_SIZE)).ceil() as u32 + GUTTER / 2;
+    let title_height = if has_title { line_height(font, TITLE_SIZE).ceil() as u32 + GUTTER } else { 0 };
+    let top = MARGIN + title_height;
+    let columns = options.columns;
+
+    let layout = match options.format {
+        ContactSheetFormat::Pdf => {
+            let (width, height) = options.page_size.pixels();
+            let footer_height = line_height(font, FOOTER_SIZE).ceil() as u32 + GUTTER;
+            let rows = options.rows;
+            let cell_width = (width - 2 * MARGIN - (columns - 1) * GUTTER) / columns;
+            let cell_height = (height - top - MARGIN - footer_height - (rows - 1) * GUTTER) / rows;
+            Layout {
+                width,
+                height,
+                columns,
+                rows,
+                cell_width,
+                cell_height,
+                image_height: cell_height.saturating_sub(caption_height),
+                top,
+            }
+        }
+        ContactSheetFormat::Jpeg => {
+            let rows = (count as u32).div_ceil(columns);
+            let cell_width = JPEG_CELL_WIDTH;
+            let image_height = cell_width * 3 / 4;
+            let cell_height = image_height + caption_height;
+            let height = top as u64 + MARGIN as u64 + rows as u64 * (cell_height + GUTTER) as u64 - GUTTER as u64;
+            if height > JPEG_MAX_DIMENSION as u64 {
+                return Err(anyhow::anyhow!("Too many photos for a single JPEG; use PDF or more columns"));
+            }
+            Layout {
+                width: 2 * MARGIN + columns * cell_width + (columns - 1) * GUTTER,
+                height: height as u32,
+                columns,
+                rows,
+                cell_width,
+                cell_height,
+                image_height,
+                top,
+            }
+        }
+    };
+
+    if layout.cell_width < 48 || layout.image_height < 48 {
+        return Err(anyhow::anyhow!("Too many rows or columns to fit on the page"));
+    }
+
+    Ok(layout)
+}
+
+/// The photo scaled to fit a `width` x `height` box
+fn render_cell(media: &MediaFile, recipe: Option<&EditRecipe>, width: u32, height: u32) -> Result<RgbaImage> {
+    let img = match media.media_type {
+        MediaType::Image => {
+            let (mut img, icc) = open_upright_image_with_profile(Path::new(&media.file_path))?;
+            if let Some(recipe) = recipe {
+                img = apply_edits(img, recipe);
+            }
+            // Scale before converting, since the cell is far smaller than the photo
+            img = img.resize(width, height, FilterType::Triangle);
+            if let Some(icc) = icc {
+                img = convert_to_srgb(img, &icc)?;
+            }
+            img
+        }
+        MediaType::Video => {
+            let poster = generate_thumbnail_internal(&media.file_path, &media.file_hash, false)?;
+            image::load_from_memory(&read_thumbnail(Path::new(&poster))?)?.resize(width, height, FilterType::Triangle)
+        }
+    };
+
+    Ok(img.to_rgba8())
+}
+
+fn render_page(
+    font: &FontVec,
+    layout: &Layout,
+    title: Option<&str>,
+    footer: Option<&str>,
+    files: &[MediaFile],
+    cells: &[Option<RgbaImage>],
+) -> RgbaImage {
+    let mut page = RgbaImage::from_pixel(layout.width, layout.height, BACKGROUND);
+
+    if let Some(title) = title {
+        let title = fit_text(font, title, TITLE_SIZE, (layout.width - 2 * MARGIN) as f32);
+        draw_text(&mut page, font, &title, MARGIN as f32, MARGIN as f32, TITLE_SIZE, TEXT_COLOR);
+    }
+
+    for (index, (media, cell)) in files.iter().zip(cells).enumerate() {
+        let column = index as u32 % layout.columns;
+        let row = index as u32 / layout.columns;
+        let x = MARGIN + column * (layout.cell_width + GUTTER);
+        let y = layout.top + row * (layout.cell_height + GUTTER);
+
+        let background = RgbaImage::from_pixel(layout.cell_width, layout.image_height, CELL_BACKGROUND);
+        image::imageops::overlay(&mut page, &background, x as i64, y as i64);
+        if let Some(cell) = cell {
+            image::imageops::overlay(
+                &mut page,
+                cell,
+                (x + (layout.cell_width - cell.width()) / 2) as i64,
+                (y + (layout.image_height - cell.height()) / 2) as i64,
+            );
+        }
+
+        let file_name = Path::new(&media.file_path).file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        let date = media
+            .taken_at
+            .map(|taken_at| taken_at.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "No date".to_string());
+
+        let max_width = layout.cell_width as f32;
+        let caption_y = (y + layout.image_height) as f32 + GUTTER as f32 / 4.0;
+        draw_text(&mut page, font, &fit_text(font, file_name, CAPTION_SIZE, max_width), x as f32, caption_y, CAPTION_SIZE, TEXT_COLOR);
+        draw_text(
+            &mut page,
+            font,
+            &fit_text(font, &date, CAPTION_SIZE, max_width),
+            x as f32,
+            caption_y + line_height(font, CAPTION_SIZE),
+            CAPTION_SIZE,
+            SECONDARY_TEXT_COLOR,
+        );
+    }
+
+    if let Some(footer) = footer {
+        let x = (layout.width - MARGIN) as f32 - text_width(font, footer, FOOTER_SIZE);
+        let y = (layout.height - MARGIN) as f32 - line_height(font, FOOTER_SIZE);
+        draw_text(&mut page, font, footer, x, y, FOOTER_SIZE, SECONDARY_TEXT_COLOR);
+    }
+
+    page
+}
+
+/// `text`, shortened with an ellipsis if it's wider than `max_width`
+fn fit_text(font: &FontVec, text: &str, size: f32, max_width: f32) -> String {
+    if text_width(font, text, size) <= max_width {
+        return text.to_string();
+    }
+
+    let mut shortened: String = text.to_string();
+    while !shortened.is_empty() {
+        shortened.pop();
+        let candidate = format!("{}…", shortened.trim_end());
+        if text_width(font, &candidate, size) <= max_width {
+            return candidate;
+        }
+    }
+    String::new()
+}
+
+fn encode_jpeg(page: RgbaImage) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    let encoder = JpegEncoder::new_with_quality(Cursor::new(&mut buffer), JPEG_QUALITY);
+    DynamicImage::ImageRgba8(page).to_rgb8().write_with_encoder(encoder)?;
+    Ok(buffer)
+}
+
+/// A PDF with each rendered page as a full-page JPEG image
+fn pdf_document(pages: &[Vec<u8>], layout: &Layout, title: Option<&str>) -> Vec<u8> {
+    let catalog_id = Ref::new(1);
+    let tree_id = Ref::new(2);
+    let info_id = Ref::new(3);
+    // Each page takes three ids: the page, its image and its content stream
+    let page_id = |index: usize| Ref::new(4 + 3 * index as i32);
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(tree_id);
+    pdf.pages(tree_id).kids((0..pages.len()).map(page_id)).count(pages.len() as i32);
+    if let Some(title) = title {
+        pdf.document_info(info_id).title(TextStr(title));
+    }
+
+    // Points are 1/72 inch
+    let width = layout.width as f32 * 72.0 / DPI;
+    let height = layout.height as f32 * 72.0 / DPI;
+    let image_name = Name(b"Page");
+
+    for (index, jpeg) in pages.iter().enumerate() {
+        let id = page_id(index);
+        let image_id = Ref::new(id.get() + 1);
+        let content_id = Ref::new(id.get() + 2);
+
+        let mut page = pdf.page(id);
+        page.media_box(Rect::new(0.0, 0.0, width, height)).parent(tree_id).contents(content_id);
+        page.resources().x_objects().pair(image_name, image_id);
+        page.finish();
+
+        let mut image = pdf.image_xobject(image_id, jpeg);
+        image.filter(Filter::DctDecode);
+        image.width(layout.width as i32).height(layout.height as i32).bits_per_component(8);
+        image.color_space().device_rgb();
+        image.finish();
+
+        let mut content = Content::new();
+        content.save_state().transform([width, 0.0, 0.0, height, 0.0, 0.0]).x_object(image_name).restore_state();
+        pdf.stream(content_id, &content.finish());
+    }
+
+    pdf.finish()
+}
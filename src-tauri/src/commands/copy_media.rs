@@ -0,0 +1,163 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use rusqlite::params_from_iter;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use anyhow::Result;
+use tracing::{error, info, warn};
+
+use crate::error::PenglerError;
+use crate::config::Config;
+use crate::models::MediaFile;
+use crate::utils::{date_folder, ensure_free_space, find_sidecar, wall_clock, write_atomically};
+use crate::commands::backup::available_folders;
+use crate::commands::cache::{init_database, media_file_from_row, MEDIA_COLUMNS};
+use crate::commands::move_media::{destination_with_sidecar, sidecar_destination};
+use crate::commands::private::visible_condition;
+
+/// Emitted after each copied file
+pub const COPY_PROGRESS_EVENT: &str = "copy-progress";
+
+/// How copies are arranged in the destination
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CopyLayout {
+    /// Library folder name and subfolders, e.g. "Photos/2019/Trip/IMG_0001.jpg"
+    #[default]
+    PreserveStructure,
+    /// Every file directly in the destination
+    Flatten,
+    /// By date taken, in the folders of `date_folder_template`, e.g. "2019/2019-05-04/IMG_0001.jpg"
+    DateFolders,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CopyProgress {
+    pub processed: usize,
+    pub total: usize,
+    pub current_file: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CopyMediaResult {
+    pub copied: usize,
+    pub failed: usize,
+    pub bytes_copied: u64,
+}
+
+/// Copy originals as they are, with their XMP sidecars, to another folder or drive.
+/// Existing files in `dest` are never overwritten; a taken name gets a " (1)" suffix.
+#[tauri::command]
+pub async fn copy_media(
+    app: AppHandle,
+    media_ids: Vec<i64>,
+    dest: String,
+    layout: Option<CopyLayout>,
+) -> Result<CopyMediaResult, PenglerError> {
+    copy_media_internal(&app, &media_ids, Path::new(&dest), layout.unwrap_or_default())
+        .map_err(|e| PenglerError::report("Failed to copy files", e))
+}
+
+fn copy_media_internal(app: &AppHandle, media_ids: &[i64], dest: &Path, layout: CopyLayout) -> Result<CopyMediaResult> {
+    if media_ids.is_empty() {
+        return Ok(CopyMediaResult::default());
+    }
+
+    let conn = init_database()?;
+    let placeholders = vec!["?"; media_ids.len()].join(", ");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM media_files WHERE id IN ({}) AND {} ORDER BY file_path",
+        MEDIA_COLUMNS,
+        placeholders,
+        visible_condition()
+    ))?;
+    let files: Vec<MediaFile> = stmt
+        .query_map(params_from_iter(media_ids.iter()), media_file_from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+
+    fs::create_dir_all(dest)?;
+    let total_size: i64 = files.iter().map(|f| f.file_size).sum();
+    ensure_free_space(dest, total_size.max(0) as u64)?;
+
+    let folders = match layout {
+        CopyLayout::PreserveStructure => available_folders(&Config::load()?.library_folders()),
+        CopyLayout::Flatten | CopyLayout::DateFolders => Vec::new(),
+    };
+
+    let mut result = CopyMediaResult::default();
+    let total = files.len();
+
+    for (index, file) in files.iter().enumerate() {
+        let target_dir = dest.join(relative_dir(file, layout, &folders));
+        match copy_file(Path::new(&file.file_path), &target_dir) {
+            Ok((_, bytes)) => {
+                result.copied += 1;
+                result.bytes_copied += bytes;
+            }
+            Err(e) => {
+                error!("Failed to copy {}: {}", file.file_path, e);
+                result.failed += 1;
+            }
+        }
+
+        let progress = CopyProgress { processed: index + 1, total, current_file: file.file_path.clone() };
+        if let Err(e) = app.emit(COPY_PROGRESS_EVENT, progress) {
+            warn!("Failed to emit {}: {}", COPY_PROGRESS_EVENT, e);
+        }
+    }
+
+    info!("Copied {} of {} files to {}", result.copied, total, dest.display());
+
+    Ok(result)
+}
+
+/// Folder of the copy inside the destination
+pub fn relative_dir(file: &MediaFile, layout: CopyLayout, folders: &[(PathBuf, String)]) -> PathBuf {
+    let source = Path::new(&file.file_path);
+    match layout {
+        CopyLayout::Flatten => PathBuf::new(),
+        CopyLayout::DateFolders => {
+            // Both as the local day, so evening shots don't land in the next day's folder
+            date_folder(file.taken_at.unwrap_or_else(|| wall_clock(file.modified_at).0))
+        }
+        // Files outside the library folders have no structure to keep
+        CopyLayout::PreserveStructure => folders
+            .iter()
+            .find_map(|(folder, name)| {
+                let relative = source.parent()?.strip_prefix(folder).ok()?;
+                Some(Path::new(name).join(relative))
+            })
+            .unwrap_or_default(),
+    }
+}
+
+/// Copy one original and its sidecar into `target_dir`; returns the copy's path and
+/// the bytes copied
+pub fn copy_file(source: &Path, target_dir: &Path) -> Result<(PathBuf, u64)> {
+    let file_name = source
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Not a file path"))?
+        .to_string_lossy()
+        .to_string();
+    fs::create_dir_all(target_dir)?;
+    let sidecar = find_sidecar(source);
+    let target = destination_with_sidecar(target_dir, &file_name, sidecar.as_deref());
+
+    let metadata = fs::metadata(source)?;
+    write_atomically(&target, |part| {
+        fs::copy(source, part)?;
+        Ok(())
+    })?;
+    filetime::set_file_mtime(&target, filetime::FileTime::from_last_modification_time(&metadata))?;
+
+    if let Some(sidecar) = sidecar {
+        let sidecar_target = sidecar_destination(&sidecar, &file_name, &target);
+        if let Err(e) = fs::copy(&sidecar, &sidecar_target) {
+            error!("Failed to copy sidecar {}: {}", sidecar.display(), e);
+        }
+    }
+
+    Ok((target, metadata.len()))
+}
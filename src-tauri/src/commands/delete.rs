@@ -0,0 +1,109 @@
+use std::fs;
+use std::path::Path;
+use rusqlite::{params_from_iter, Connection};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use anyhow::Result;
+use tracing::{error, info, warn};
+
+use crate::error::PenglerError;
+use crate::commands::cache::{forget_media, init_database};
+use crate::commands::smart_albums::notify_smart_albums_changed;
+use crate::commands::tags::remove_unused_tags;
+use crate::commands::thumbnail::remove_thumbnails;
+use crate::commands::private::visible_condition;
+
+/// Emitted with the ids of the files removed from the library
+pub const MEDIA_DELETED_EVENT: &str = "media-deleted";
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteMediaResult {
+    pub deleted: usize,
+    pub failed: usize,
+}
+
+/// Delete files and everything the library keeps about them. With `to_trash` the
+/// originals go to the system trash / recycle bin, otherwise they are deleted for good.
+/// A file that can't be removed from disk stays in the library untouched.
+#[tauri::command]
+pub async fn delete_media(app: AppHandle, media_ids: Vec<i64>, to_trash: bool) -> Result<DeleteMediaResult, PenglerError> {
+    let conn = init_database().map_err(|e| PenglerError::report("Failed to delete media", e))?;
+    let files = media_paths(&conn, &media_ids).map_err(|e| PenglerError::report("Failed to delete media", e))?;
+    let (result, deleted) = delete_media_files(&conn, &files, to_trash)
+        .map_err(|e| PenglerError::report("Failed to delete media", e))?;
+
+    if let Err(e) = app.emit(MEDIA_DELETED_EVENT, &deleted) {
+        warn!("Failed to emit {}: {}", MEDIA_DELETED_EVENT, e);
+    }
+    notify_smart_albums_changed(&app);
+    Ok(result)
+}
+
+fn media_paths(conn: &Connection, media_ids: &[i64]) -> Result<Vec<(i64, String, String)>> {
+    if media_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let placeholders = vec!["?"; media_ids.len()].join(", ");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, file_path, file_hash FROM media_files WHERE id IN ({}) AND {}",
+        placeholders,
+        visible_condition()
+    ))?;
+    let rows = stmt.query_map(params_from_iter(media_ids), |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Remove `(id, path, hash)` files from disk, then from the catalog and the thumbnail
+/// cache. Returns the counts and the ids actually deleted.
+pub fn delete_media_files(
+    conn: &Connection,
+    files: &[(i64, String, String)],
+    to_trash: bool,
+) -> Result<(DeleteMediaResult, Vec<i64>)> {
+    let mut result = DeleteMediaResult::default();
+    let mut deleted = Vec::new();
+
+    for (id, file_path, file_hash) in files {
+        let path = Path::new(file_path);
+        // Already gone from disk; just drop it from the catalog
+        if path.exists() {
+            let removed = if to_trash {
+                trash::delete(path).map_err(anyhow::Error::from)
+            } else {
+                fs::remove_file(path).map_err(anyhow::Error::from)
+            };
+            if let Err(e) = removed {
+                error!("Failed to delete {}: {}", file_path, e);
+                result.failed += 1;
+                continue;
+            }
+        }
+
+        forget_media(conn, *id)?;
+
+        // Identical copies elsewhere in the library share the thumbnail
+        let shared = conn
+            .prepare("SELECT 1 FROM media_files WHERE file_hash = ?1 LIMIT 1")?
+            .exists([file_hash])?;
+        if !shared {
+            if let Err(e) = remove_thumbnails(file_hash) {
+                error!("Failed to remove thumbnails of {}: {}", file_path, e);
+            }
+        }
+
+        deleted.push(*id);
+        result.deleted += 1;
+    }
+
+    remove_unused_tags(conn)?;
+
+    info!(
+        "{} {} files, {} failed",
+        if to_trash { "Moved to trash" } else { "Deleted" },
+        result.deleted,
+        result.failed
+    );
+
+    Ok((result, deleted))
+}
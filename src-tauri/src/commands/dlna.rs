@@ -0,0 +1,737 @@
+use serde::Serialize;
+use tauri::AppHandle;
+use anyhow::Result;
+
+use crate::error::PenglerError;
+
+/// Emitted whenever the DLNA server starts, stops or fails
+#[cfg(feature = "dlna")]
+pub const DLNA_STATUS_EVENT: &str = "dlna-status";
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DlnaStatus {
+    pub running: bool,
+    /// Name shown in the TV's source list
+    pub name: Option<String>,
+    pub port: Option<u16>,
+    /// Photos and videos streamed since the server started
+    pub served: u64,
+    /// Why the server stopped, if it failed
+    pub error: Option<String>,
+}
+
+/// Share the library with smart TVs and other UPnP/DLNA players on the local network.
+/// Runs in the background until stopped. DLNA has no authentication: anyone on the
+/// network can browse while it runs. Needs the `dlna` build feature.
+#[tauri::command]
+pub async fn start_dlna_server(app: AppHandle) -> Result<DlnaStatus, PenglerError> {
+    start_dlna_server_internal(app)
+        .await
+        .map_err(|e| PenglerError::report("Failed to start DLNA server", e))
+}
+
+#[tauri::command]
+pub async fn stop_dlna_server(app: AppHandle) -> Result<DlnaStatus, PenglerError> {
+    stop_dlna_server_internal(&app)
+        .await
+        .map_err(|e| PenglerError::report("Failed to stop DLNA server", e))
+}
+
+#[tauri::command]
+pub async fn get_dlna_status() -> Result<DlnaStatus, PenglerError> {
+    Ok(dlna_status())
+}
+
+#[cfg(not(feature = "dlna"))]
+async fn start_dlna_server_internal(_app: AppHandle) -> Result<DlnaStatus> {
+    Err(anyhow::anyhow!("This build of Pengler does not include the DLNA server"))
+}
+
+#[cfg(not(feature = "dlna"))]
+async fn stop_dlna_server_internal(_app: &AppHandle) -> Result<DlnaStatus> {
+    Ok(DlnaStatus::default())
+}
+
+#[cfg(not(feature = "dlna"))]
+fn dlna_status() -> DlnaStatus {
+    DlnaStatus::default()
+}
+
+#[cfg(feature = "dlna")]
+use server::{dlna_status, start_dlna_server_internal, stop_dlna_server_internal};
+
+#[cfg(feature = "dlna")]
+mod server {
+    use std::net::{Ipv4Addr, SocketAddr};
+    use std::path::Path as FilePath;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use axum::extract::{Path, Request, State};
+    use axum::http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode};
+    use axum::middleware::{self, Next};
+    use axum::response::{IntoResponse, Response};
+    use axum::routing::{any, get, post};
+    use axum::Router;
+    use tauri::{AppHandle, Emitter};
+    use tokio::net::UdpSocket;
+    use tokio::sync::watch;
+    use anyhow::Result;
+    use tracing::{error, info, warn};
+
+    use super::{DlnaStatus, DLNA_STATUS_EVENT};
+    use crate::config::Config;
+    use crate::models::{MediaFile, MediaType};
+    use crate::commands::lan_server::server::{blocking, find_media, local_address, render_photo, stream_file};
+    use crate::commands::search::{search_media_internal, MediaFilters};
+    use crate::commands::thumbnail::{generate_thumbnail_internal, read_thumbnail};
+
+    const SSDP_ADDRESS: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+    const SSDP_PORT: u16 = 1900;
+    /// How long announcements stay valid; they are repeated well before this
+    const SSDP_MAX_AGE: u64 = 1800;
+    const SSDP_ANNOUNCE_INTERVAL: Duration = Duration::from_secs(60);
+    const SERVER_HEADER: &str = "Pengler/1.0 UPnP/1.0 DLNADOC/1.50";
+    const DEVICE_TYPE: &str = "urn:schemas-upnp-org:device:MediaServer:1";
+    const CONTENT_DIRECTORY: &str = "urn:schemas-upnp-org:service:ContentDirectory:1";
+    const CONNECTION_MANAGER: &str = "urn:schemas-upnp-org:service:ConnectionManager:1";
+
+    const ROOT_ID: &str = "0";
+    const PHOTOS_ID: &str = "photos";
+    const VIDEOS_ID: &str = "videos";
+
+    struct RunningServer {
+        status: DlnaStatus,
+        served: Arc<AtomicU64>,
+        shutdown: watch::Sender<bool>,
+    }
+
+    static SERVER: Mutex<Option<RunningServer>> = Mutex::new(None);
+
+    #[derive(Clone)]
+    struct ServerState {
+        name: String,
+        uuid: String,
+        base_url: String,
+        max_resolution: u32,
+        served: Arc<AtomicU64>,
+    }
+
+    pub(super) async fn start_dlna_server_internal(app: AppHandle) -> Result<DlnaStatus> {
+        if SERVER.lock().unwrap().is_some() {
+            return Ok(dlna_status());
+        }
+
+        let config = Config::load()?;
+        let ip = local_address().ok_or_else(|| anyhow::anyhow!("Not connected to a network"))?;
+        let listener = tokio::net::TcpListener::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, config.dlna_port))).await?;
+        let port = listener.local_addr()?.port();
+        let ssdp = ssdp_socket()?;
+
+        let served = Arc::new(AtomicU64::new(0));
+        let state = ServerState {
+            name: config.dlna_name.clone(),
+            uuid: device_uuid(),
+            base_url: format!("http://{}:{}", ip, port),
+            max_resolution: config.max_resolution,
+            served: served.clone(),
+        };
+
+        let app_routes = Router::new()
+            .route("/description.xml", get(description))
+            .route("/ContentDirectory/scpd.xml", get(|| async { xml(CONTENT_DIRECTORY_SCPD.to_string()) }))
+            .route("/ConnectionManager/scpd.xml", get(|| async { xml(CONNECTION_MANAGER_SCPD.to_string()) }))
+            .route("/ContentDirectory/control", post(content_directory))
+            .route("/ConnectionManager/control", post(connection_manager))
+            .route("/ContentDirectory/event", any(subscribe))
+            .route("/ConnectionManager/event", any(subscribe))
+            .route("/thumb/{id}", get(thumbnail))
+            .route("/media/{id}", get(media))
+            .layer(middleware::from_fn(dlna_headers))
+            .with_state(state.clone());
+
+        let (shutdown, stopped) = watch::channel(false);
+
+        let http_stopped = stopped.clone();
+        let http_app = app.clone();
+        tokio::spawn(async move {
+            let mut stopped = http_stopped;
+            let server = axum::serve(listener, app_routes).with_graceful_shutdown(async move {
+                let _ = stopped.changed().await;
+            });
+            if let Err(e) = server.await {
+                fail(&http_app, format!("HTTP server stopped: {}", e));
+            }
+        });
+
+        let ssdp_app = app.clone();
+        let ssdp_state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = run_ssdp(ssdp, &ssdp_state, stopped).await {
+                fail(&ssdp_app, format!("Discovery stopped: {}", e));
+            }
+        });
+
+        let status = DlnaStatus {
+            running: true,
+            name: Some(state.name.clone()),
+            port: Some(port),
+            served: 0,
+            error: None,
+        };
+        *SERVER.lock().unwrap() = Some(RunningServer { status, served, shutdown });
+        info!("DLNA server \"{}\" listening on {}", state.name, state.base_url);
+
+        let status = dlna_status();
+        emit_status(&app, &status);
+        Ok(status)
+    }
+
+    pub(super) async fn stop_dlna_server_internal(app: &AppHandle) -> Result<DlnaStatus> {
+        let server = SERVER.lock().unwrap().take();
+        if let Some(server) = server {
+            // The SSDP task says goodbye before it exits
+            let _ = server.shutdown.send(true);
+            info!("DLNA server stopped");
+        }
+
+        let status = DlnaStatus::default();
+        emit_status(app, &status);
+        Ok(status)
+    }
+
+    pub(super) fn dlna_status() -> DlnaStatus {
+        match SERVER.lock().unwrap().as_ref() {
+            Some(server) => DlnaStatus { served: server.served.load(Ordering::Relaxed), ..server.status.clone() },
+            None => DlnaStatus::default(),
+        }
+    }
+
+    /// Stop after a background task failed, keeping the reason for the status
+    fn fail(app: &AppHandle, error: String) {
+        error!("DLNA server failed: {}", error);
+        if let Some(server) = SERVER.lock().unwrap().take() {
+            let _ = server.shutdown.send(true);
+        }
+        emit_status(app, &DlnaStatus { error: Some(error), ..Default::default() });
+    }
+
+    fn emit_status(app: &AppHandle, status: &DlnaStatus) {
+        if let Err(e) = app.emit(DLNA_STATUS_EVENT, status.clone()) {
+            warn!("Failed to emit {}: {}", DLNA_STATUS_EVENT, e);
+        }
+    }
+
+    /// Stable across restarts so TVs don't list the library twice
+    fn device_uuid() -> String {
+        let seed = dirs::home_dir().map(|home| home.to_string_lossy().to_string()).unwrap_or_default();
+        let hex = blake3::hash(format!("pengler-dlna:{}", seed).as_bytes()).to_hex();
+        format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+    }
+
+    /// Headers DLNA renderers look for before they agree to stream
+    async fn dlna_headers(request: Request, next: Next) -> Response {
+        let mut response = next.run(request).await;
+        let headers = response.headers_mut();
+        headers.insert(header::SERVER, HeaderValue::from_static(SERVER_HEADER));
+        if headers.get(header::CONTENT_TYPE).is_some_and(|value| !value.as_bytes().starts_with(b"text/xml")) {
+            headers.insert(HeaderName::from_static("transfermode.dlna.org"), HeaderValue::from_static("Streaming"));
+            headers.insert(
+                HeaderName::from_static("contentfeatures.dlna.org"),
+                HeaderValue::from_static("DLNA.ORG_OP=01;DLNA.ORG_CI=0;DLNA.ORG_FLAGS=01700000000000000000000000000000"),
+            );
+        }
+        response
+    }
+
+    fn xml(body: String) -> Response {
+        ([(header::CONTENT_TYPE, "text/xml; charset=\"utf-8\"")], body).into_response()
+    }
+
+    async fn description(State(state): State<ServerState>) -> Response {
+        xml(DESCRIPTION
+            .replace("{{name}}", &escape_xml(&state.name))
+            .replace("{{uuid}}", &state.uuid))
+    }
+
+    /// Accept event subscriptions without ever sending events; the library
+    /// doesn't announce changes, but some players refuse servers that reject them
+    async fn subscribe(State(state): State<ServerState>) -> Response {
+        (
+            [
+                (HeaderName::from_static("sid"), format!("uuid:{}-events", state.uuid)),
+                (HeaderName::from_static("timeout"), format!("Second-{}", SSDP_MAX_AGE)),
+            ],
+            "",
+        )
+            .into_response()
+    }
+
+    async fn connection_manager(headers: HeaderMap) -> Response {
+        match soap_action(&headers).as_deref() {
+            Some("GetProtocolInfo") => soap_response(
+                CONNECTION_MANAGER,
+                "GetProtocolInfo",
+                "<Source>http-get:*:image/jpeg:*,http-get:*:video/mp4:*,http-get:*:video/quicktime:*,http-get:*:video/webm:*</Source><Sink></Sink>",
+            ),
+            Some("GetCurrentConnectionIDs") => {
+                soap_response(CONNECTION_MANAGER, "GetCurrentConnectionIDs", "<ConnectionIDs>0</ConnectionIDs>")
+            }
+            Some("GetCurrentConnectionInfo") => soap_response(
+                CONNECTION_MANAGER,
+                "GetCurrentConnectionInfo",
+                "<RcsID>-1</RcsID><AVTransportID>-1</AVTransportID><ProtocolInfo></ProtocolInfo>\
+                 <PeerConnectionManager></PeerConnectionManager><PeerConnectionID>-1</PeerConnectionID>\
+                 <Direction>Output</Direction><Status>OK</Status>",
+            ),
+            _ => soap_fault(401, "Invalid Action"),
+        }
+    }
+
+    async fn content_directory(State(state): State<ServerState>, headers: HeaderMap, body: String) -> Response {
+        match soap_action(&headers).as_deref() {
+            Some("Browse") => {
+                let object_id = soap_argument(&body, "ObjectID").unwrap_or_else(|| ROOT_ID.to_string());
+                let flag = soap_argument(&body, "BrowseFlag").unwrap_or_default();
+                let start = soap_argument(&body, "StartingIndex").and_then(|v| v.parse().ok()).unwrap_or(0);
+                let count = soap_argument(&body, "RequestedCount").and_then(|v| v.parse().ok()).unwrap_or(0);
+
+                match blocking(move || browse(&state, &object_id, &flag, start, count)).await {
+                    Ok(Some(result)) => soap_response(CONTENT_DIRECTORY, "Browse", &result),
+                    Ok(None) => soap_fault(701, "No such object"),
+                    Err(response) => response,
+                }
+            }
+            Some("GetSystemUpdateID") => soap_response(CONTENT_DIRECTORY, "GetSystemUpdateID", "<Id>1</Id>"),
+            Some("GetSearchCapabilities") => {
+                soap_response(CONTENT_DIRECTORY, "GetSearchCapabilities", "<SearchCaps></SearchCaps>")
+            }
+            Some("GetSortCapabilities") => {
+                soap_response(CONTENT_DIRECTORY, "GetSortCapabilities", "<SortCaps></SortCaps>")
+            }
+            _ => soap_fault(401, "Invalid Action"),
+        }
+    }
+
+    /// Arguments of a Browse response, or None for an unknown object
+    fn browse(state: &ServerState, object_id: &str, flag: &str, start: u32, count: u32) -> Result<Option<String>> {
+        let containers = [(PHOTOS_ID, "Photos", MediaType::Image), (VIDEOS_ID, "Videos", MediaType::Video)];
+
+        let (didl, returned, total) = if flag == "BrowseMetadata" {
+            let didl = if object_id == ROOT_ID {
+                format!(
+                    "<container id=\"{}\" parentID=\"-1\" restricted=\"1\" childCount=\"{}\">\
+                     <dc:title>{}</dc:title><upnp:class>object.container</upnp:class></container>",
+                    ROOT_ID,
+                    containers.len(),
+                    escape_xml(&state.name)
+                )
+            } else if let Some((id, title, media_type)) = containers.iter().find(|(id, _, _)| *id == object_id) {
+                container(id, title, count_media(media_type.clone())?)
+            } else if let Some(media) = object_id.strip_prefix('m').and_then(|id| id.parse().ok()).and_then(|id| find_media(id).ok()) {
+                item(state, &media)
+            } else {
+                return Ok(None);
+            };
+            (didl, 1, 1)
+        } else if object_id == ROOT_ID {
+            let mut didl = String::new();
+            for (id, title, media_type) in &containers {
+                didl.push_str(&container(id, title, count_media(media_type.clone())?));
+            }
+            (didl, containers.len() as i64, containers.len() as i64)
+        } else if let Some((_, _, media_type)) = containers.iter().find(|(id, _, _)| *id == object_id) {
+            let filters = MediaFilters {
+                media_type: Some(media_type.clone()),
+                collapse_stacks: true,
+                shared: true,
+                offset: start,
+                // 0 means "as many as you have"; cap it so large libraries page
+                limit: Some(if count == 0 { 500 } else { count.min(500) }),
+                ..Default::default()
+            };
+            let result = search_media_internal(&filters)?;
+            let didl: String = result.items.iter().map(|media| item(state, media)).collect();
+            (didl, result.items.len() as i64, result.total)
+        } else {
+            return Ok(None);
+        };
+
+        let didl = format!(
+            "<DIDL-Lite xmlns=\"urn:schemas-upnp-org:metadata-1-0/DIDL-Lite/\" \
+             xmlns:dc=\"http://purl.org/dc/elements/1.1/\" \
+             xmlns:upnp=\"urn:schemas-upnp-org:metadata-1-0/upnp/\">{}</DIDL-Lite>",
+            didl
+        );
+        Ok(Some(format!(
+            "<Result>{}</Result><NumberReturned>{}</NumberReturned><TotalMatches>{}</TotalMatches><UpdateID>1</UpdateID>",
+            escape_xml(&didl),
+            returned,
+            total
+        )))
+    }
+
+    fn count_media(media_type: MediaType) -> Result<i64> {
+        let filters = MediaFilters { media_type: Some(media_type), collapse_stacks: true, shared: true, limit: Some(0), ..Default::default() };
+        Ok(search_media_internal(&filters)?.total)
+    }
+
+    fn container(id: &str, title: &str, child_count: i64) -> String {
+        format!(
+            "<container id=\"{}\" parentID=\"{}\" restricted=\"1\" childCount=\"{}\">\
+             <dc:title>{}</dc:title><upnp:class>object.container.storageFolder</upnp:class></container>",
+            id, ROOT_ID, child_count, title
+        )
+    }
+
+    fn item(state: &ServerState, media: &MediaFile) -> String {
+        let title = FilePath::new(&media.file_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let date = media
+            .taken_at
+            .map(|taken_at| format!("<dc:date>{}</dc:date>", taken_at.format("%Y-%m-%dT%H:%M:%S")))
+            .unwrap_or_default();
+        let url = format!("{}/media/{}", state.base_url, media.id);
+        let thumbnail = format!(
+            "<upnp:albumArtURI>{}/thumb/{}</upnp:albumArtURI>",
+            state.base_url, media.id
+        );
+
+        let (parent, class, resource) = match media.media_type {
+            MediaType::Image => (
+                PHOTOS_ID,
+                "object.item.imageItem.photo",
+                format!("<res protocolInfo=\"http-get:*:image/jpeg:DLNA.ORG_OP=01\">{}</res>", url),
+            ),
+            MediaType::Video => {
+                let duration = media
+                    .video_info
+                    .as_ref()
+                    .map(|info| format!(" duration=\"{}\"", format_duration(info.duration)))
+                    .unwrap_or_default();
+                let resolution = if media.width > 0 && media.height > 0 {
+                    format!(" resolution=\"{}x{}\"", media.width, media.height)
+                } else {
+                    String::new()
+                };
+                (
+                    VIDEOS_ID,
+                    "object.item.videoItem",
+                    format!(
+                        "<res protocolInfo=\"http-get:*:{}:DLNA.ORG_OP=01\" size=\"{}\"{}{}>{}</res>",
+                        video_mime_type(&media.file_path),
+                        media.file_size,
+                        duration,
+                        resolution,
+                        url
+                    ),
+                )
+            }
+        };
+
+        format!(
+            "<item id=\"m{}\" parentID=\"{}\" restricted=\"1\"><dc:title>{}</dc:title>{}\
+             <upnp:class>{}</upnp:class>{}{}</item>",
+            media.id,
+            parent,
+            escape_xml(&title),
+            date,
+            class,
+            thumbnail,
+            resource
+        )
+    }
+
+    fn video_mime_type(path: &str) -> &'static str {
+        match FilePath::new(path).extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("webm") => "video/webm",
+            Some("mov") => "video/quicktime",
+            _ => "video/mp4",
+        }
+    }
+
+    /// H:MM:SS.mmm, as DIDL-Lite expects
+    fn format_duration(seconds: f64) -> String {
+        let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+        format!("{}:{:02}:{:02}.{:03}", millis / 3_600_000, millis / 60_000 % 60, millis / 1000 % 60, millis % 1000)
+    }
+
+    async fn thumbnail(Path(id): Path<i64>) -> Response {
+        let result = blocking(move || {
+            let media = find_media(id)?;
+            let path = generate_thumbnail_internal(&media.file_path, &media.file_hash, true)?;
+            read_thumbnail(FilePath::new(&path))
+        })
+        .await;
+
+        match result {
+            Ok(bytes) => ([(header::CONTENT_TYPE, "image/webp")], bytes).into_response(),
+            Err(response) => response,
+        }
+    }
+
+    async fn media(State(state): State<ServerState>, Path(id): Path<i64>, headers: HeaderMap) -> Response {
+        let media = match blocking(move || find_media(id)).await {
+            Ok(media) => media,
+            Err(response) => return response,
+        };
+        state.served.fetch_add(1, Ordering::Relaxed);
+
+        match media.media_type {
+            MediaType::Image => {
+                let max_resolution = state.max_resolution;
+                match blocking(move || render_photo(&media, max_resolution)).await {
+                    Ok(bytes) => ([(header::CONTENT_TYPE, "image/jpeg")], bytes).into_response(),
+                    Err(response) => response,
+                }
+            }
+            MediaType::Video => stream_file(&media.file_path, &headers).await.unwrap_or_else(|e| {
+                error!("Failed to stream {}: {}", media.file_path, e);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }),
+        }
+    }
+
+    /// Action name from a `SOAPACTION: "urn:...:ContentDirectory:1#Browse"` header
+    fn soap_action(headers: &HeaderMap) -> Option<String> {
+        let value = headers.get("soapaction")?.to_str().ok()?;
+        Some(value.trim_matches('"').rsplit('#').next()?.to_string())
+    }
+
+    /// Text of the first `<name>` element in a SOAP body, ignoring namespace prefixes
+    fn soap_argument(body: &str, name: &str) -> Option<String> {
+        let open = body.find(&format!("<{}", name)).or_else(|| body.find(&format!(":{}>", name)).map(|i| i + 1))?;
+        let start = open + body[open..].find('>')? + 1;
+        let end = start + body[start..].find("</")?;
+        Some(unescape_xml(body[start..end].trim()))
+    }
+
+    fn soap_response(service: &str, action: &str, arguments: &str) -> Response {
+        xml(format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+             <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+             s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\"><s:Body>\
+             <u:{action}Response xmlns:u=\"{service}\">{arguments}</u:{action}Response>\
+             </s:Body></s:Envelope>",
+            action = action,
+            service = service,
+            arguments = arguments
+        ))
+    }
+
+    fn soap_fault(code: u32, description: &str) -> Response {
+        let body = format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+             <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+             s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\"><s:Body><s:Fault>\
+             <faultcode>s:Client</faultcode><faultstring>UPnPError</faultstring><detail>\
+             <UPnPError xmlns=\"urn:schemas-upnp-org:control-1-0\"><errorCode>{}</errorCode>\
+             <errorDescription>{}</errorDescription></UPnPError></detail></s:Fault></s:Body></s:Envelope>",
+            code, description
+        );
+        (StatusCode::INTERNAL_SERVER_ERROR, xml(body)).into_response()
+    }
+
+    fn escape_xml(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+            .replace('\'', "&apos;")
+    }
+
+    fn unescape_xml(text: &str) -> String {
+        text.replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&")
+    }
+
+    /// Multicast socket for discovery; shares port 1900 with other UPnP software
+    fn ssdp_socket() -> Result<UdpSocket> {
+        use socket2::{Domain, Protocol, Socket, Type};
+
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_reuse_address(true)?;
+        #[cfg(unix)]
+        socket.set_reuse_port(true)?;
+        socket.set_nonblocking(true)?;
+        socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, SSDP_PORT)).into())?;
+        socket.join_multicast_v4(&SSDP_ADDRESS, &Ipv4Addr::UNSPECIFIED)?;
+        socket.set_multicast_loop_v4(true)?;
+
+        Ok(UdpSocket::from_std(socket.into())?)
+    }
+
+    /// Answer searches and keep announcing the server until shutdown, then say goodbye
+    async fn run_ssdp(socket: UdpSocket, state: &ServerState, mut stopped: watch::Receiver<bool>) -> Result<()> {
+        let multicast = SocketAddr::from((SSDP_ADDRESS, SSDP_PORT));
+        let mut announce = tokio::time::interval(SSDP_ANNOUNCE_INTERVAL);
+        let mut buffer = [0u8; 2048];
+
+        loop {
+            tokio::select! {
+                _ = stopped.changed() => break,
+                _ = announce.tick() => {
+                    for (target, usn) in notification_types(&state.uuid) {
+                        socket.send_to(notify(state, "ssdp:alive", &target, &usn).as_bytes(), multicast).await?;
+                    }
+                }
+                received = socket.recv_from(&mut buffer) => {
+                    let (length, from) = received?;
+                    let request = String::from_utf8_lossy(&buffer[..length]);
+                    if !request.starts_with("M-SEARCH") {
+                        continue;
+                    }
+                    let Some(search_target) = ssdp_header(&request, "ST") else {
+                        continue;
+                    };
+                    for (target, usn) in notification_types(&state.uuid) {
+                        if search_target == "ssdp:all" || search_target == target {
+                            socket.send_to(search_response(state, &target, &usn).as_bytes(), from).await?;
+                        }
+                    }
+                }
+            }
+        }
+
+        for (target, usn) in notification_types(&state.uuid) {
+            let _ = socket.send_to(notify(state, "ssdp:byebye", &target, &usn).as_bytes(), multicast).await;
+        }
+        Ok(())
+    }
+
+    /// Every (NT, USN) pair a media server announces
+    fn notification_types(uuid: &str) -> Vec<(String, String)> {
+        let device = format!("uuid:{}", uuid);
+        let mut types = vec![
+            ("upnp:rootdevice".to_string(), format!("{}::upnp:rootdevice", device)),
+            (device.clone(), device.clone()),
+        ];
+        for target in [DEVICE_TYPE, CONTENT_DIRECTORY, CONNECTION_MANAGER] {
+            types.push((target.to_string(), format!("{}::{}", device, target)));
+        }
+        types
+    }
+
+    fn notify(state: &ServerState, kind: &str, target: &str, usn: &str) -> String {
+        format!(
+            "NOTIFY * HTTP/1.1\r\nHOST: {}:{}\r\nCACHE-CONTROL: max-age={}\r\nLOCATION: {}/description.xml\r\n\
+             NT: {}\r\nNTS: {}\r\nSERVER: {}\r\nUSN: {}\r\n\r\n",
+            SSDP_ADDRESS, SSDP_PORT, SSDP_MAX_AGE, state.base_url, target, kind, SERVER_HEADER, usn
+        )
+    }
+
+    fn search_response(state: &ServerState, target: &str, usn: &str) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age={}\r\nEXT:\r\nLOCATION: {}/description.xml\r\n\
+             SERVER: {}\r\nST: {}\r\nUSN: {}\r\n\r\n",
+            SSDP_MAX_AGE, state.base_url, SERVER_HEADER, target, usn
+        )
+    }
+
+    fn ssdp_header<'a>(request: &'a str, name: &str) -> Option<&'a str> {
+        request.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+
+    const DESCRIPTION: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<root xmlns="urn:schemas-upnp-org:device-1-0" xmlns:dlna="urn:schemas-dlna-org:device-1-0">
+  <specVersion><major>1</major><minor>0</minor></specVersion>
+  <device>
+    <deviceType>urn:schemas-upnp-org:device:MediaServer:1</deviceType>
+    <dlna:X_DLNADOC>DMS-1.50</dlna:X_DLNADOC>
+    <friendlyName>{{name}}</friendlyName>
+    <manufacturer>Pengler</manufacturer>
+    <modelName>Pengler</modelName>
+    <UDN>uuid:{{uuid}}</UDN>
+    <serviceList>
+      <service>
+        <serviceType>urn:schemas-upnp-org:service:ContentDirectory:1</serviceType>
+        <serviceId>urn:upnp-org:serviceId:ContentDirectory</serviceId>
+        <SCPDURL>/ContentDirectory/scpd.xml</SCPDURL>
+        <controlURL>/ContentDirectory/control</controlURL>
+        <eventSubURL>/ContentDirectory/event</eventSubURL>
+      </service>
+      <service>
+        <serviceType>urn:schemas-upnp-org:service:ConnectionManager:1</serviceType>
+        <serviceId>urn:upnp-org:serviceId:ConnectionManager</serviceId>
+        <SCPDURL>/ConnectionManager/scpd.xml</SCPDURL>
+        <controlURL>/ConnectionManager/control</controlURL>
+        <eventSubURL>/ConnectionManager/event</eventSubURL>
+      </service>
+    </serviceList>
+  </device>
+</root>
+"#;
+
+    const CONTENT_DIRECTORY_SCPD: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<scpd xmlns="urn:schemas-upnp-org:service-1-0">
+  <specVersion><major>1</major><minor>0</minor></specVersion>
+  <actionList>
+    <action><name>Browse</name><argumentList>
+      <argument><name>ObjectID</name><direction>in</direction><relatedStateVariable>A_ARG_TYPE_ObjectID</relatedStateVariable></argument>
+      <argument><name>BrowseFlag</name><direction>in</direction><relatedStateVariable>A_ARG_TYPE_BrowseFlag</relatedStateVariable></argument>
+      <argument><name>Filter</name><direction>in</direction><relatedStateVariable>A_ARG_TYPE_Filter</relatedStateVariable></argument>
+      <argument><name>StartingIndex</name><direction>in</direction><relatedStateVariable>A_ARG_TYPE_Index</relatedStateVariable></argument>
+      <argument><name>RequestedCount</name><direction>in</direction><relatedStateVariable>A_ARG_TYPE_Count</relatedStateVariable></argument>
+      <argument><name>SortCriteria</name><direction>in</direction><relatedStateVariable>A_ARG_TYPE_SortCriteria</relatedStateVariable></argument>
+      <argument><name>Result</name><direction>out</direction><relatedStateVariable>A_ARG_TYPE_Result</relatedStateVariable></argument>
+      <argument><name>NumberReturned</name><direction>out</direction><relatedStateVariable>A_ARG_TYPE_Count</relatedStateVariable></argument>
+      <argument><name>TotalMatches</name><direction>out</direction><relatedStateVariable>A_ARG_TYPE_Count</relatedStateVariable></argument>
+      <argument><name>UpdateID</name><direction>out</direction><relatedStateVariable>A_ARG_TYPE_UpdateID</relatedStateVariable></argument>
+    </argumentList></action>
+    <action><name>GetSystemUpdateID</name><argumentList>
+      <argument><name>Id</name><direction>out</direction><relatedStateVariable>SystemUpdateID</relatedStateVariable></argument>
+    </argumentList></action>
+    <action><name>GetSearchCapabilities</name><argumentList>
+      <argument><name>SearchCaps</name><direction>out</direction><relatedStateVariable>SearchCapabilities</relatedStateVariable></argument>
+    </argumentList></action>
+    <action><name>GetSortCapabilities</name><argumentList>
+      <argument><name>SortCaps</name><direction>out</direction><relatedStateVariable>SortCapabilities</relatedStateVariable></argument>
+    </argumentList></action>
+  </actionList>
+  <serviceStateTable>
+    <stateVariable sendEvents="no"><name>A_ARG_TYPE_ObjectID</name><dataType>string</dataType></stateVariable>
+    <stateVariable sendEvents="no"><name>A_ARG_TYPE_BrowseFlag</name><dataType>string</dataType>
+      <allowedValueList><allowedValue>BrowseMetadata</allowedValue><allowedValue>BrowseDirectChildren</allowedValue></allowedValueList>
+    </stateVariable>
+    <stateVariable sendEvents="no"><name>A_ARG_TYPE_Filter</name><dataType>string</dataType></stateVariable>
+    <stateVariable sendEvents="no"><name>A_ARG_TYPE_Index</name><dataType>ui4</dataType></stateVariable>
+    <stateVariable sendEvents="no"><name>A_ARG_TYPE_Count</name><dataType>ui4</dataType></stateVariable>
+    <stateVariable sendEvents="no"><name>A_ARG_TYPE_SortCriteria</name><dataType>string</dataType></stateVariable>
+    <stateVariable sendEvents="no"><name>A_ARG_TYPE_Result</name><dataType>string</dataType></stateVariable>
+    <stateVariable sendEvents="no"><name>A_ARG_TYPE_UpdateID</name><dataType>ui4</dataType></stateVariable>
+    <stateVariable sendEvents="yes"><name>SystemUpdateID</name><dataType>ui4</dataType></stateVariable>
+    <stateVariable sendEvents="no"><name>SearchCapabilities</name><dataType>string</dataType></stateVariable>
+    <stateVariable sendEvents="no"><name>SortCapabilities</name><dataType>string</dataType></stateVariable>
+  </serviceStateTable>
+</scpd>
+"#;
+
+    const CONNECTION_MANAGER_SCPD: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<scpd xmlns="urn:schemas-upnp-org:service-1-0">
+  <specVersion><major>1</major><minor>0</minor></specVersion>
+  <actionList>
+    <action><name>GetProtocolInfo</name><argumentList>
+      <argument><name>Source</name><direction>out</direction><relatedStateVariable>SourceProtocolInfo</relatedStateVariable></argument>
+      <argument><name>Sink</name><direction>out</direction><relatedStateVariable>SinkProtocolInfo</relatedStateVariable></argument>
+    </argumentList></action>
+    <action><name>GetCurrentConnectionIDs</name><argumentList>
+      <argument><name>ConnectionIDs</name><direction>out</direction><relatedStateVariable>CurrentConnectionIDs</relatedStateVariable></argument>
+    </argumentList></action>
+  </actionList>
+  <serviceStateTable>
+    <stateVariable sendEvents="yes"><name>SourceProtocolInfo</name><dataType>string</dataType></stateVariable>
+    <stateVariable sendEvents="yes"><name>SinkProtocolInfo</name><dataType>string</dataType></stateVariable>
+    <stateVariable sendEvents="yes"><name>CurrentConnectionIDs</name><dataType>string</dataType></stateVariable>
+  </serviceStateTable>
+</scpd>
+"#;
+}
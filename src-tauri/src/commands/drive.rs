@@ -0,0 +1,157 @@
+use std::path::Path;
+use anyhow::Result;
+
+use crate::error::PenglerError;
+
+/// Safely eject the removable drive that contains `path`
+#[tauri::command]
+pub async fn eject_drive(path: String) -> Result<(), PenglerError> {
+    eject_drive_internal(Path::new(&path))
+        .map_err(|e| PenglerError::report("Failed to eject drive", e))
+}
+
+fn eject_drive_internal(path: &Path) -> Result<()> {
+    if !path.exists() {
+        return Err(anyhow::anyhow!("Path does not exist: {}", path.display()));
+    }
+    let path = path.canonicalize()?;
+
+    eject_platform(&path)
+}
+
+#[cfg(target_os = "windows")]
+fn eject_platform(path: &Path) -> Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::{Component, Prefix};
+    use windows_sys::Win32::Foundation::{CloseHandle, GENERIC_READ, GENERIC_WRITE, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::Storage::FileSystem::{CreateFileW, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING};
+    use windows_sys::Win32::System::Ioctl::{FSCTL_DISMOUNT_VOLUME, FSCTL_LOCK_VOLUME, IOCTL_STORAGE_EJECT_MEDIA};
+    use windows_sys::Win32::System::IO::DeviceIoControl;
+
+    // Resolve the drive letter, e.g. "E:" from "\\?\E:\DCIM"
+    let letter = match path.components().next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => letter as char,
+            _ => return Err(anyhow::anyhow!("Not a drive letter path: {}", path.display())),
+        },
+        _ => return Err(anyhow::anyhow!("Not a drive letter path: {}", path.display())),
+    };
+
+    let volume: Vec<u16> = std::ffi::OsStr::new(&format!("\\\\.\\{}:", letter))
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+
+    unsafe {
+        let handle = CreateFileW(
+            volume.as_ptr(),
+            GENERIC_READ | GENERIC_WRITE,
+            FILE_SHARE_READ | FILE_SHARE_WRITE,
+            std::ptr::null(),
+            OPEN_EXISTING,
+            0,
+            std::ptr::null_mut(),
+        );
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(anyhow::anyhow!("Cannot open volume {}: {}", letter, std::io::Error::last_os_error()));
+        }
+
+        let control = |code: u32| -> Result<()> {
+            let mut returned = 0u32;
+            let ok = DeviceIoControl(
+                handle,
+                code,
+                std::ptr::null(),
+                0,
+                std::ptr::null_mut(),
+                0,
+                &mut returned,
+                std::ptr::null_mut(),
+            );
+            if ok == 0 {
+                Err(anyhow::anyhow!("{}", std::io::Error::last_os_error()))
+            } else {
+                Ok(())
+            }
+        };
+
+        // Lock and dismount so no open handles are left behind, then eject
+        let result = control(FSCTL_LOCK_VOLUME)
+            .map_err(|e| anyhow::anyhow!("Drive {} is in use: {}", letter, e))
+            .and_then(|_| control(FSCTL_DISMOUNT_VOLUME))
+            .and_then(|_| control(IOCTL_STORAGE_EJECT_MEDIA));
+
+        CloseHandle(handle);
+        result
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn eject_platform(path: &Path) -> Result<()> {
+    // diskutil accepts any path on the volume and ejects the whole disk
+    run_tool("diskutil", &["eject".as_ref(), path.as_os_str()])
+}
+
+#[cfg(target_os = "linux")]
+fn eject_platform(path: &Path) -> Result<()> {
+    let device = find_mount_device(path)?;
+
+    run_tool("udisksctl", &["unmount".as_ref(), "-b".as_ref(), device.as_os_str()])?;
+    run_tool("udisksctl", &["power-off".as_ref(), "-b".as_ref(), device.as_os_str()])
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+fn eject_platform(_path: &Path) -> Result<()> {
+    Err(anyhow::anyhow!("Ejecting drives is not supported on this platform"))
+}
+
+/// Find the block device backing the mount point that contains `path`
+#[cfg(target_os = "linux")]
+fn find_mount_device(path: &Path) -> Result<std::path::PathBuf> {
+    use std::path::PathBuf;
+
+    let mounts = std::fs::read_to_string("/proc/mounts")?;
+
+    // Pick the longest mount point that is a prefix of the path
+    let mut best: Option<(PathBuf, PathBuf)> = None;
+    for line in mounts.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(device), Some(mount_point)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        if !device.starts_with("/dev/") {
+            continue;
+        }
+
+        // /proc/mounts escapes spaces as \040
+        let mount_point = PathBuf::from(mount_point.replace("\\040", " "));
+        if path.starts_with(&mount_point)
+            && best.as_ref().is_none_or(|(mp, _)| mount_point.as_os_str().len() > mp.as_os_str().len())
+        {
+            best = Some((mount_point, PathBuf::from(device)));
+        }
+    }
+
+    best.map(|(_, device)| device)
+        .ok_or_else(|| anyhow::anyhow!("No removable device mounted at {}", path.display()))
+}
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn run_tool(program: &str, args: &[&std::ffi::OsStr]) -> Result<()> {
+    let output = std::process::Command::new(program).args(args).output();
+
+    match output {
+        Ok(result) if result.status.success() => Ok(()),
+        Ok(result) => {
+            let stderr = String::from_utf8_lossy(&result.stderr);
+            Err(anyhow::anyhow!("{} failed: {}", program, stderr.trim()))
+        },
+        Err(e) => {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                Err(anyhow::anyhow!("{} not found. Please install it to eject drives.", program))
+            } else {
+                Err(anyhow::anyhow!("Failed to run {}: {}", program, e))
+            }
+        }
+    }
+}
@@ -0,0 +1,419 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use anyhow::Result;
+use tracing::{error, info, warn};
+
+use crate::error::PenglerError;
+use crate::models::MediaFile;
+use crate::utils::{hash_file, part_path, same_file};
+use crate::commands::cache::{init_database, media_file_from_row, MEDIA_COLUMNS};
+use crate::commands::delete::delete_media_files;
+use crate::commands::private::visible_condition;
+use crate::commands::similar::{
+    backfill_perceptual_hashes, group_by_similarity, load_perceptual_hashes, DEFAULT_SIMILARITY_THRESHOLD,
+};
+use crate::commands::smart_albums::notify_smart_albums_changed;
+use crate::commands::tags::remove_unused_tags;
+
+/// Emitted while duplicates are searched for or resolved
+pub const DUPLICATES_PROGRESS_EVENT: &str = "duplicates-progress";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateKind {
+    /// Byte-identical files (same BLAKE3 hash)
+    Exact,
+    /// Visually near-identical photos, e.g. resized or re-exported copies
+    Similar,
+}
+
+impl DuplicateKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DuplicateKind::Exact => "exact",
+            DuplicateKind::Similar => "similar",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicatesProgress {
+    /// "hashing", "grouping" or "resolving"
+    pub phase: &'static str,
+    pub processed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateScanSummary {
+    pub exact_groups: usize,
+    pub similar_groups: usize,
+    /// Bytes freed by keeping one file of each exact group
+    pub reclaimable_bytes: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    pub group_id: i64,
+    pub kind: DuplicateKind,
+    /// Largest file first
+    pub items: Vec<MediaFile>,
+}
+
+/// Which file of a group survives
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeepStrategy {
+    /// Highest resolution, then biggest file
+    Largest,
+    /// Earliest modification time, i.e. the file the others were copied from
+    Original,
+}
+
+/// What happens to the other files
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateAction {
+    Delete,
+    /// Replace each copy with a hard link to the kept file; exact duplicates only
+    Hardlink,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolveResult {
+    pub resolved_groups: usize,
+    pub removed_files: usize,
+    pub freed_bytes: i64,
+    /// Copies left in place: on another volume than the kept file, changed since the
+    /// scan, or failed to move to the trash
+    pub skipped_files: usize,
+    pub failed: usize,
+}
+
+/// Find exact and near-duplicate groups across every folder in the catalog
+/// and store them for review
+#[tauri::command]
+pub async fn find_library_duplicates(app: AppHandle, threshold: Option<u32>) -> Result<DuplicateScanSummary, PenglerError> {
+    let on_progress = |phase, processed, total| emit_progress(&app, phase, processed, total);
+    find_library_duplicates_internal(&on_progress, threshold.unwrap_or(DEFAULT_SIMILARITY_THRESHOLD))
+        .map_err(|e| PenglerError::report("Failed to find duplicates", e))
+}
+
+/// `find_library_duplicates`, reporting each phase's `(phase, processed, total)` to `on_progress`
+pub fn find_library_duplicates_internal(
+    on_progress: &(dyn Fn(&'static str, usize, usize) + Sync),
+    threshold: u32,
+) -> Result<DuplicateScanSummary> {
+    let mut conn = init_database()?;
+
+    backfill_perceptual_hashes(&conn, |processed, total| {
+        on_progress("hashing", processed, total);
+    })?;
+
+    let exact = exact_groups(&conn)?;
+
+    // Near-duplicates that are all byte-identical are already covered by an exact group
+    let hashes = load_perceptual_hashes(&conn)?;
+    on_progress("grouping", 0, hashes.len());
+    let file_hashes = load_file_hashes(&conn)?;
+    let similar: Vec<Vec<i64>> = group_by_similarity(&hashes, threshold)
+        .into_iter()
+        .filter(|group| {
+            let distinct: HashSet<&str> = group
+                .iter()
+                .filter_map(|id| file_hashes.get(id).map(String::as_str))
+                .collect();
+            distinct.len() > 1
+        })
+        .collect();
+    on_progress("grouping", hashes.len(), hashes.len());
+
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM duplicate_groups", [])?;
+    let groups = exact
+        .iter()
+        .map(|group| (DuplicateKind::Exact, group))
+        .chain(similar.iter().map(|group| (DuplicateKind::Similar, group)));
+    for (group_id, (kind, members)) in groups.enumerate() {
+        for media_id in members {
+            tx.execute(
+                "INSERT INTO duplicate_groups (group_id, media_id, kind) VALUES (?1, ?2, ?3)",
+                params![group_id as i64 + 1, media_id, kind.as_str()],
+            )?;
+        }
+    }
+    tx.commit()?;
+
+    // Everything but the biggest file of each exact group
+    let reclaimable_bytes: i64 = conn.query_row(
+        "SELECT COALESCE(SUM(total - biggest), 0) FROM (
+             SELECT SUM(m.file_size) AS total, MAX(m.file_size) AS biggest
+             FROM duplicate_groups g JOIN media_files m ON m.id = g.media_id
+             WHERE g.kind = 'exact' GROUP BY g.group_id
+         )",
+        [],
+        |row| row.get(0),
+    )?;
+
+    info!("Found {} exact and {} similar duplicate groups", exact.len(), similar.len());
+
+    Ok(DuplicateScanSummary {
+        exact_groups: exact.len(),
+        similar_groups: similar.len(),
+        reclaimable_bytes,
+    })
+}
+
+/// Groups found by the last `find_library_duplicates` run
+#[tauri::command]
+pub async fn get_duplicate_groups() -> Result<Vec<DuplicateGroup>, PenglerError> {
+    get_duplicate_groups_internal()
+        .map_err(|e| PenglerError::report("Failed to load duplicates", e))
+}
+
+fn get_duplicate_groups_internal() -> Result<Vec<DuplicateGroup>> {
+    let conn = init_database()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM media_files WHERE id IN (SELECT media_id FROM duplicate_groups) AND {}",
+        MEDIA_COLUMNS, visible_condition()
+    ))?;
+    let files: HashMap<i64, MediaFile> = stmt
+        .query_map([], media_file_from_row)?
+        .map(|file| file.map(|file| (file.id, file)))
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT g.group_id, g.kind, g.media_id FROM duplicate_groups g
+         JOIN media_files m ON m.id = g.media_id
+         ORDER BY g.group_id, m.file_size DESC",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?))
+    })?;
+
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+    for row in rows {
+        let (group_id, kind, media_id) = row?;
+        let Some(media) = files.get(&media_id).cloned() else {
+            continue;
+        };
+        match groups.last_mut() {
+            Some(group) if group.group_id == group_id => group.items.push(media),
+            _ => groups.push(DuplicateGroup {
+                group_id,
+                kind: if kind == "exact" { DuplicateKind::Exact } else { DuplicateKind::Similar },
+                items: vec![media],
+            }),
+        }
+    }
+
+    groups.retain(|group| group.items.len() > 1);
+
+    Ok(groups)
+}
+
+/// Keep one file per group and delete or hard-link the rest
+#[tauri::command]
+pub async fn resolve_duplicate_groups(
+    app: AppHandle,
+    group_ids: Vec<i64>,
+    keep: KeepStrategy,
+    action: DuplicateAction,
+) -> Result<ResolveResult, PenglerError> {
+    let result = resolve_duplicate_groups_internal(&app, &group_ids, keep, action)
+        .map_err(|e| PenglerError::report("Failed to resolve duplicates", e))?;
+    notify_smart_albums_changed(&app);
+    Ok(result)
+}
+
+fn resolve_duplicate_groups_internal(
+    app: &AppHandle,
+    group_ids: &[i64],
+    keep: KeepStrategy,
+    action: DuplicateAction,
+) -> Result<ResolveResult> {
+    let groups = get_duplicate_groups_internal()?;
+    let selected: Vec<&DuplicateGroup> = groups
+        .iter()
+        .filter(|group| group_ids.contains(&group.group_id))
+        .collect();
+
+    let conn = init_database()?;
+    let mut result = ResolveResult::default();
+
+    for (index, group) in selected.iter().enumerate() {
+        match resolve_group(&conn, group, keep, action) {
+            Ok((removed, freed, skipped)) => {
+                result.resolved_groups += 1;
+                result.removed_files += removed;
+                result.freed_bytes += freed;
+                result.skipped_files += skipped;
+            }
+            Err(e) => {
+                error!("Failed to resolve duplicate group {}: {}", group.group_id, e);
+                result.failed += 1;
+            }
+        }
+        emit_progress(app, "resolving", index + 1, selected.len());
+    }
+
+    remove_unused_tags(&conn)?;
+
+    info!(
+        "Resolved {} duplicate groups: {} files removed, {} bytes freed, {} skipped",
+        result.resolved_groups, result.removed_files, result.freed_bytes, result.skipped_files
+    );
+
+    Ok(result)
+}
+
+/// Returns the number of files removed or linked, the bytes freed and the copies skipped
+fn resolve_group(
+    conn: &Connection,
+    group: &DuplicateGroup,
+    keep: KeepStrategy,
+    action: DuplicateAction,
+) -> Result<(usize, i64, usize)> {
+    if matches!(action, DuplicateAction::Hardlink) && group.kind != DuplicateKind::Exact {
+        return Err(anyhow::anyhow!("Only exact duplicates can be hard-linked"));
+    }
+
+    let kept = match keep {
+        KeepStrategy::Largest => group
+            .items
+            .iter()
+            .max_by_key(|m| (m.width as i64 * m.height as i64, m.file_size)),
+        KeepStrategy::Original => group.items.iter().min_by_key(|m| m.modified_at),
+    }
+    .ok_or_else(|| anyhow::anyhow!("Group {} is empty", group.group_id))?;
+
+    let kept_path = Path::new(&kept.file_path);
+    if !kept_path.exists() {
+        return Err(anyhow::anyhow!("Kept file is missing: {}", kept.file_path));
+    }
+
+    // Files may have been edited since the scan; only copies still identical to the
+    // kept file are removed from an exact group
+    let kept_hash = match group.kind {
+        DuplicateKind::Exact => Some(hash_file(kept_path)?),
+        _ => None,
+    };
+
+    let mut removed = 0;
+    let mut freed = 0;
+    let mut skipped = Vec::new();
+
+    for media in group.items.iter().filter(|m| m.id != kept.id) {
+        let path = Path::new(&media.file_path);
+        let changed = match &kept_hash {
+            // Already gone from disk, or linked by an earlier run
+            Some(_) if !path.exists() || same_file(kept_path, path)? => false,
+            Some(hash) => *hash != hash_file(path)?,
+            None => false,
+        };
+        if changed {
+            warn!("{} changed since the duplicate scan; leaving it", media.file_path);
+            skipped.push(media.id);
+            continue;
+        }
+
+        match action {
+            DuplicateAction::Delete => {
+                // To the trash, so a wrong pick of a similar group can be undone
+                let files = [(media.id, media.file_path.clone(), media.file_hash.clone())];
+                let (deleted, _) = delete_media_files(conn, &files, true)?;
+                if deleted.failed > 0 {
+                    skipped.push(media.id);
+                    continue;
+                }
+            }
+            DuplicateAction::Hardlink => {
+                if !path.exists() {
+                    return Err(anyhow::anyhow!("Copy is missing: {}", media.file_path));
+                }
+                // Linked by an earlier run: nothing left to free
+                if same_file(kept_path, path)? {
+                    removed += 1;
+                    continue;
+                }
+
+                // Link beside the copy first so the copy is only replaced once the link exists
+                let link = part_path(path);
+                match fs::hard_link(kept_path, &link) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+                        info!("{} is on another volume than {}; leaving it", media.file_path, kept.file_path);
+                        skipped.push(media.id);
+                        continue;
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+                if let Err(e) = fs::rename(&link, path) {
+                    let _ = fs::remove_file(&link);
+                    return Err(e.into());
+                }
+            }
+        }
+        removed += 1;
+        freed += media.file_size;
+    }
+
+    // Skipped copies stay listed with the kept file for another look
+    if skipped.is_empty() {
+        conn.execute("DELETE FROM duplicate_groups WHERE group_id = ?1", [group.group_id])?;
+    } else {
+        conn.execute(
+            &format!(
+                "DELETE FROM duplicate_groups WHERE group_id = ?1 AND media_id NOT IN ({}, {})",
+                kept.id,
+                skipped.iter().map(i64::to_string).collect::<Vec<_>>().join(", ")
+            ),
+            [group.group_id],
+        )?;
+    }
+
+    Ok((removed, freed, skipped.len()))
+}
+
+/// Ids of files sharing a content hash, one group per hash
+fn exact_groups(conn: &Connection) -> Result<Vec<Vec<i64>>> {
+    let mut stmt = conn.prepare(
+        "SELECT file_hash, id FROM media_files
+         WHERE file_hash IN (SELECT file_hash FROM media_files GROUP BY file_hash HAVING COUNT(*) > 1)
+         ORDER BY file_hash",
+    )?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?;
+
+    let mut groups: Vec<(String, Vec<i64>)> = Vec::new();
+    for row in rows {
+        let (hash, id) = row?;
+        match groups.last_mut() {
+            Some((last, members)) if *last == hash => members.push(id),
+            _ => groups.push((hash, vec![id])),
+        }
+    }
+
+    Ok(groups.into_iter().map(|(_, members)| members).collect())
+}
+
+fn load_file_hashes(conn: &Connection) -> Result<HashMap<i64, String>> {
+    let mut stmt = conn.prepare("SELECT id, file_hash FROM media_files WHERE perceptual_hash IS NOT NULL")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn emit_progress(app: &AppHandle, phase: &'static str, processed: usize, total: usize) {
+    let progress = DuplicatesProgress { phase, processed, total };
+    if let Err(e) = app.emit(DUPLICATES_PROGRESS_EVENT, progress) {
+        warn!("Failed to emit {}: {}", DUPLICATES_PROGRESS_EVENT, e);
+    }
+}
@@ -0,0 +1,119 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use anyhow::Result;
+
+use crate::error::PenglerError;
+use crate::utils::EditRecipe;
+use crate::commands::cache::init_database;
+use crate::commands::private::visible_condition;
+
+/// One saved state of a file's adjustments
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditRevision {
+    pub id: i64,
+    pub recipe: EditRecipe,
+    pub created_at: String,
+}
+
+/// Save adjustments for a media file as its newest revision. The original file is
+/// left untouched; thumbnails pick the recipe up when asked to render edits.
+#[tauri::command]
+pub async fn save_edits(media_id: i64, recipe: EditRecipe) -> Result<EditRevision, PenglerError> {
+    save_edits_internal(media_id, &recipe)
+        .map_err(|e| PenglerError::report("Failed to save edits", e))
+}
+
+fn save_edits_internal(media_id: i64, recipe: &EditRecipe) -> Result<EditRevision> {
+    recipe.validate()?;
+    let conn = init_database()?;
+    ensure_visible(&conn, media_id)?;
+
+    conn.execute(
+        "INSERT INTO edits (media_id, recipe) VALUES (?1, ?2)",
+        params![media_id, serde_json::to_string(recipe)?],
+    )?;
+    let id = conn.last_insert_rowid();
+
+    let created_at = conn.query_row("SELECT created_at FROM edits WHERE id = ?1", [id], |row| row.get(0))?;
+
+    Ok(EditRevision { id, recipe: recipe.clone(), created_at })
+}
+
+/// Current adjustments of a media file, if it was ever edited
+#[tauri::command]
+pub async fn load_edits(media_id: i64) -> Result<Option<EditRecipe>, PenglerError> {
+    load_edits_internal(media_id)
+        .map_err(|e| PenglerError::report("Failed to load edits", e))
+}
+
+fn load_edits_internal(media_id: i64) -> Result<Option<EditRecipe>> {
+    let conn = init_database()?;
+    ensure_visible(&conn, media_id)?;
+    current_recipe(&conn, media_id)
+}
+
+/// Every saved revision, newest first
+#[tauri::command]
+pub async fn get_edit_history(media_id: i64) -> Result<Vec<EditRevision>, PenglerError> {
+    get_edit_history_internal(media_id)
+        .map_err(|e| PenglerError::report("Failed to load edit history", e))
+}
+
+fn get_edit_history_internal(media_id: i64) -> Result<Vec<EditRevision>> {
+    let conn = init_database()?;
+    ensure_visible(&conn, media_id)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, recipe, created_at FROM edits WHERE media_id = ?1 ORDER BY id DESC",
+    )?;
+    let rows = stmt.query_map([media_id], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+    })?;
+
+    let mut history = Vec::new();
+    for row in rows {
+        let (id, recipe, created_at) = row?;
+        history.push(EditRevision { id, recipe: serde_json::from_str(&recipe)?, created_at });
+    }
+
+    Ok(history)
+}
+
+/// Drop all adjustments and their history, back to the original
+#[tauri::command]
+pub async fn clear_edits(media_id: i64) -> Result<(), PenglerError> {
+    clear_edits_internal(media_id)
+        .map_err(|e| PenglerError::report("Failed to clear edits", e))
+}
+
+fn clear_edits_internal(media_id: i64) -> Result<()> {
+    let conn = init_database()?;
+    ensure_visible(&conn, media_id)?;
+    conn.execute("DELETE FROM edits WHERE media_id = ?1", [media_id])?;
+    Ok(())
+}
+
+/// Private files are out of reach while they are locked
+fn ensure_visible(conn: &Connection, media_id: i64) -> Result<()> {
+    let exists = conn
+        .prepare(&format!("SELECT 1 FROM media_files WHERE id = ?1 AND {}", visible_condition()))?
+        .exists([media_id])?;
+    if !exists {
+        return Err(anyhow::anyhow!("Media {} not found", media_id));
+    }
+    Ok(())
+}
+
+/// Newest recipe of a media file, if it was ever edited
+pub fn current_recipe(conn: &Connection, media_id: i64) -> Result<Option<EditRecipe>> {
+    let recipe: Option<String> = conn
+        .query_row(
+            "SELECT recipe FROM edits WHERE media_id = ?1 ORDER BY id DESC LIMIT 1",
+            [media_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    Ok(recipe.map(|recipe| serde_json::from_str(&recipe)).transpose()?)
+}
@@ -0,0 +1,425 @@
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use image::codecs::jpeg::JpegEncoder;
+use image::metadata::Orientation;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter};
+use serde::Serialize;
+use anyhow::Result;
+use tracing::{error, info};
+
+use crate::error::PenglerError;
+use crate::utils::wall_clock_to_utc;
+use crate::commands::cache::{init_database, modified_day, save_media_files_internal};
+use crate::commands::ingest::{process_file, IngestContext};
+use crate::commands::search::folder_condition;
+use crate::commands::smart_albums::notify_smart_albums_changed;
+use crate::commands::private::visible_condition;
+
+/// JPEG quality used when re-encoding rotated photos
+const REENCODE_QUALITY: u8 = 95;
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExifEditResult {
+    pub updated: usize,
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+/// New taken-at time: a fixed date, or a shift applied to each file's current date
+enum TakenAtChange {
+    Absolute(NaiveDateTime),
+    Offset(Duration),
+}
+
+/// Rewrite the date taken of `files`.
+/// `datetime_or_offset` is either a date ("2024-05-01 10:30:00", RFC 3339) or a shift
+/// such as "+9:00" or "-1d 2:30:00" for a camera with a wrong clock.
+/// The original of every changed file is kept next to it as `<name>_original`.
+#[tauri::command]
+pub async fn set_taken_at(
+    app: tauri::AppHandle,
+    files: Vec<String>,
+    datetime_or_offset: String,
+) -> Result<ExifEditResult, PenglerError> {
+    let result = set_taken_at_internal(&files, &datetime_or_offset)
+        .map_err(|e| PenglerError::report("Failed to set date taken", e))?;
+    notify_smart_albums_changed(&app);
+    Ok(result)
+}
+
+fn set_taken_at_internal(files: &[String], datetime_or_offset: &str) -> Result<ExifEditResult> {
+    change_taken_at(files, &parse_taken_at_change(datetime_or_offset)?)
+}
+
+/// Shift the date taken of files by `offset_minutes`, e.g. -540 for photos taken in
+/// Tokyo by a camera still on UTC. Like `set_taken_at`, originals are kept as
+/// `<name>_original`.
+#[tauri::command]
+pub async fn shift_taken_at(
+    app: tauri::AppHandle,
+    media_ids: Vec<i64>,
+    offset_minutes: i64,
+) -> Result<ExifEditResult, PenglerError> {
+    let result = shift_taken_at_internal(&media_ids, offset_minutes)
+        .map_err(|e| PenglerError::report("Failed to shift date taken", e))?;
+    notify_smart_albums_changed(&app);
+    Ok(result)
+}
+
+fn shift_taken_at_internal(media_ids: &[i64], offset_minutes: i64) -> Result<ExifEditResult> {
+    if media_ids.is_empty() || offset_minutes == 0 {
+        return Ok(ExifEditResult { skipped: media_ids.len(), ..Default::default() });
+    }
+
+    let files: Vec<String> = {
+        let conn = init_database()?;
+        let placeholders = vec!["?"; media_ids.len()].join(", ");
+        let mut stmt = conn.prepare(&format!(
+            "SELECT file_path FROM media_files WHERE id IN ({}) AND {}",
+            placeholders,
+            visible_condition()
+        ))?;
+        let rows = stmt.query_map(params_from_iter(media_ids), |row| row.get(0))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+
+    change_taken_at(&files, &TakenAtChange::Offset(Duration::minutes(offset_minutes)))
+}
+
+fn change_taken_at(files: &[String], change: &TakenAtChange) -> Result<ExifEditResult> {
+    let conn = init_database()?;
+
+    let mut result = ExifEditResult::default();
+    let mut changed = Vec::new();
+
+    for file in files {
+        let path = Path::new(file);
+
+        let taken_at = match change {
+            TakenAtChange::Absolute(datetime) => *datetime,
+            TakenAtChange::Offset(offset) => {
+                let current: Option<String> = conn
+                    .query_row("SELECT taken_at FROM media_files WHERE file_path = ?1", [file], |row| row.get(0))
+                    .unwrap_or(None);
+                match current.and_then(|c| DateTime::parse_from_rfc3339(&c).ok()) {
+                    Some(current) => current.naive_utc() + *offset,
+                    None => {
+                        // Nothing to shift from
+                        result.skipped += 1;
+                        continue;
+                    }
+                }
+            }
+        };
+
+        let value = format!("-AllDates={}", taken_at.format("%Y:%m:%d %H:%M:%S"));
+        let written = backup_original(path)
+            .and_then(|_| run_exiftool(&["-overwrite_original".as_ref(), value.as_ref(), path.as_os_str()]));
+        match written {
+            Ok(()) => {
+                changed.push(path.to_path_buf());
+                result.updated += 1;
+            }
+            Err(e) => {
+                error!("Failed to set date taken of {}: {}", file, e);
+                result.failed += 1;
+            }
+        }
+    }
+
+    refresh_media_files(&changed)?;
+
+    Ok(result)
+}
+
+fn parse_taken_at_change(value: &str) -> Result<TakenAtChange> {
+    let value = value.trim();
+
+    if let Some((sign, offset)) = value
+        .strip_prefix('+')
+        .map(|o| (1, o))
+        .or_else(|| value.strip_prefix('-').map(|o| (-1, o)))
+    {
+        return parse_offset(offset)
+            .map(|offset| TakenAtChange::Offset(offset * sign))
+            .ok_or_else(|| anyhow::anyhow!("Invalid offset: {}", value));
+    }
+
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        // EXIF dates are local time without a zone
+        return Ok(TakenAtChange::Absolute(datetime.naive_local()));
+    }
+
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y:%m:%d %H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .map(TakenAtChange::Absolute)
+        .ok_or_else(|| anyhow::anyhow!("Invalid date or offset: {}", value))
+}
+
+/// Parse "[Nd ]H:MM[:SS]"
+fn parse_offset(value: &str) -> Option<Duration> {
+    let (days, time) = match value.split_once('d') {
+        Some((days, time)) => (days.trim().parse::<i64>().ok()?, time.trim()),
+        None => (0, value),
+    };
+
+    let mut parts = time.split(':').map(|part| part.parse::<i64>());
+    let hours = parts.next()?.ok()?;
+    let minutes = parts.next().transpose().ok()?.unwrap_or(0);
+    let seconds = parts.next().transpose().ok()?.unwrap_or(0);
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some(Duration::days(days) + Duration::hours(hours) + Duration::minutes(minutes) + Duration::seconds(seconds))
+}
+
+/// Set the modified time of files to their date taken, for tools that sort by file
+/// date after an import or copy reset it. Takes media ids and/or a folder, whose
+/// library files are all updated. The date taken is read as local time, like the
+/// camera wrote it. Files without a date taken are skipped.
+#[tauri::command]
+pub async fn sync_mtime_from_exif(
+    media_ids: Option<Vec<i64>>,
+    folder: Option<String>,
+) -> Result<ExifEditResult, PenglerError> {
+    sync_mtime_from_exif_internal(&media_ids.unwrap_or_default(), folder.as_deref())
+        .map_err(|e| PenglerError::report("Failed to set file dates", e))
+}
+
+fn sync_mtime_from_exif_internal(media_ids: &[i64], folder: Option<&str>) -> Result<ExifEditResult> {
+    let conn = init_database()?;
+
+    let mut conditions = Vec::new();
+    let mut values: Vec<Value> = Vec::new();
+    if !media_ids.is_empty() {
+        conditions.push(format!("id IN ({})", vec!["?"; media_ids.len()].join(", ")));
+        values.extend(media_ids.iter().map(|id| Value::from(*id)));
+    }
+    if let Some(folder) = folder {
+        conditions.push(folder_condition(folder, &mut values));
+    }
+
+    // One query, so a file both picked and inside the folder comes back once
+    let mut files: Vec<(String, Option<String>, Option<i32>)> = Vec::new();
+    if !conditions.is_empty() {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT file_path, taken_at, taken_offset FROM media_files WHERE ({}) AND {}",
+            conditions.join(" OR "),
+            visible_condition()
+        ))?;
+        let rows = stmt.query_map(params_from_iter(values.iter()), |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        files = rows.collect::<rusqlite::Result<_>>()?;
+    }
+
+    let mut result = ExifEditResult::default();
+
+    for (file_path, taken_at, taken_offset) in files {
+        let taken_at = taken_at
+            .and_then(|t| DateTime::parse_from_rfc3339(&t).ok())
+            // Stored as the camera's wall-clock time
+            .and_then(|t| wall_clock_to_utc(t.with_timezone(&Utc), taken_offset));
+        let Some(taken_at) = taken_at else {
+            result.skipped += 1;
+            continue;
+        };
+
+        let mtime = filetime::FileTime::from_unix_time(taken_at.timestamp(), taken_at.timestamp_subsec_nanos());
+        let path = Path::new(&file_path);
+        let current = fs::metadata(path).map(|metadata| filetime::FileTime::from_last_modification_time(&metadata));
+        match current {
+            Ok(current) if current == mtime => result.skipped += 1,
+            Ok(_) => match filetime::set_file_mtime(path, mtime) {
+                Ok(()) => {
+                    // Keep the catalog in step so the next scan doesn't see a changed file
+                    conn.execute(
+                        "UPDATE media_files SET modified_at = ?1, modified_day = ?2 WHERE file_path = ?3",
+                        params![taken_at.to_rfc3339(), modified_day(taken_at), file_path],
+                    )?;
+                    result.updated += 1;
+                }
+                Err(e) => {
+                    error!("Failed to set modified time of {}: {}", file_path, e);
+                    result.failed += 1;
+                }
+            },
+            Err(e) => {
+                error!("Failed to read {}: {}", file_path, e);
+                result.failed += 1;
+            }
+        }
+    }
+
+    info!(
+        "Set file dates from date taken: {} updated, {} skipped, {} failed",
+        result.updated, result.skipped, result.failed
+    );
+
+    Ok(result)
+}
+
+/// Rotate photo pixels to match their EXIF orientation and reset the tag,
+/// for viewers and services that ignore it.
+/// The original of every changed file is kept next to it as `<name>_original`.
+#[tauri::command]
+pub async fn normalize_orientation(files: Vec<String>) -> Result<ExifEditResult, PenglerError> {
+    normalize_orientation_internal(&files)
+        .map_err(|e| PenglerError::report("Failed to normalize orientation", e))
+}
+
+fn normalize_orientation_internal(files: &[String]) -> Result<ExifEditResult> {
+    let mut result = ExifEditResult::default();
+    let mut changed = Vec::new();
+
+    for file in files {
+        let path = Path::new(file);
+        match normalize_file_orientation(path) {
+            Ok(true) => {
+                changed.push(path.to_path_buf());
+                result.updated += 1;
+            }
+            Ok(false) => result.skipped += 1,
+            Err(e) => {
+                error!("Failed to normalize orientation of {}: {}", file, e);
+                result.failed += 1;
+            }
+        }
+    }
+
+    refresh_media_files(&changed)?;
+
+    Ok(result)
+}
+
+/// Returns false when the photo is already upright or its format can't be re-encoded (RAW)
+fn normalize_file_orientation(path: &Path) -> Result<bool> {
+    let mut decoder = ImageReader::open(path)?.with_guessed_format()?.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    if orientation == Orientation::NoTransforms {
+        return Ok(false);
+    }
+
+    let format = match ImageFormat::from_path(path) {
+        Ok(format @ (ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::Tiff | ImageFormat::WebP)) => format,
+        _ => return Ok(false),
+    };
+
+    let mut img = DynamicImage::from_decoder(decoder)?;
+    img.apply_orientation(orientation);
+
+    backup_original(path)?;
+
+    // Keep the extension on the temporary file so exiftool recognizes it
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    let temp_path = path.with_file_name(format!(".{}.rotated.{}", file_name, extension));
+
+    let width = format!("-ExifImageWidth={}", img.width());
+    let height = format!("-ExifImageHeight={}", img.height());
+    let written = encode_image(&img, &temp_path, format).and_then(|_| {
+        // The encoder drops metadata; copy it back from the source, the color profile
+        // included, with the tag reset, the sizes of the turned image and without the
+        // embedded preview, which is still unrotated
+        run_exiftool(&[
+            "-overwrite_original".as_ref(),
+            "-tagsFromFile".as_ref(),
+            path.as_os_str(),
+            "-all:all".as_ref(),
+            "-icc_profile".as_ref(),
+            "-Orientation#=1".as_ref(),
+            "-ThumbnailImage=".as_ref(),
+            width.as_ref(),
+            height.as_ref(),
+            temp_path.as_os_str(),
+        ])
+    });
+
+    match written {
+        Ok(()) => {
+            fs::rename(&temp_path, path)?;
+            Ok(true)
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&temp_path);
+            Err(e)
+        }
+    }
+}
+
+fn encode_image(img: &DynamicImage, path: &Path, format: ImageFormat) -> Result<()> {
+    if format == ImageFormat::Jpeg {
+        let encoder = JpegEncoder::new_with_quality(BufWriter::new(File::create(path)?), REENCODE_QUALITY);
+        img.to_rgb8().write_with_encoder(encoder)?;
+    } else {
+        img.save_with_format(path, format)?;
+    }
+    Ok(())
+}
+
+/// Copy the file to `<name>_original` unless an earlier edit already did
+pub fn backup_original(path: &Path) -> Result<PathBuf> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push("_original");
+    let backup = path.with_file_name(name);
+
+    if !backup.exists() {
+        fs::copy(path, &backup)?;
+    }
+
+    Ok(backup)
+}
+
+/// Re-read edited files so the library reflects their new dates, hashes and dimensions
+pub fn refresh_media_files(paths: &[PathBuf]) -> Result<()> {
+    let conn = init_database()?;
+    let ctx = IngestContext::local(&conn)?;
+    let media: Vec<_> = paths
+        .iter()
+        .filter_map(|path| match process_file(path, &ctx) {
+            Ok(media) => Some(media),
+            Err(e) => {
+                error!("Failed to re-read {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect();
+
+    ctx.save(&conn)?;
+    save_media_files_internal(media)?;
+
+    // Thumbnails are keyed by content hash, so the old one no longer applies
+    for path in paths {
+        conn.execute(
+            "UPDATE media_files SET thumbnail_path = NULL WHERE file_path = ?1",
+            params![path.to_string_lossy()],
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn run_exiftool(args: &[&std::ffi::OsStr]) -> Result<()> {
+    let output = Command::new("exiftool").args(args).output();
+
+    match output {
+        Ok(result) if result.status.success() => Ok(()),
+        Ok(result) => {
+            let stderr = String::from_utf8_lossy(&result.stderr);
+            Err(anyhow::anyhow!("exiftool failed: {}", stderr.trim()))
+        },
+        Err(e) => {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                Err(PenglerError::ExiftoolMissing("exiftool not found. Please install exiftool to edit EXIF metadata.".to_string()).into())
+            } else {
+                Err(anyhow::anyhow!("Failed to run exiftool: {}", e))
+            }
+        }
+    }
+}
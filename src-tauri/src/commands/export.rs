@@ -0,0 +1,535 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use image::{imageops::FilterType, DynamicImage, ImageEncoder, ImageFormat};
+use image::codecs::{jpeg::JpegEncoder, png::PngEncoder, webp::WebPEncoder};
+use rayon::prelude::*;
+use rusqlite::params_from_iter;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use anyhow::Result;
+use tracing::{error, info, warn};
+
+use crate::error::PenglerError;
+use crate::config::Config;
+use crate::models::{MediaFile, MediaType};
+use crate::utils::{
+    acquire_slot, apply_edits, convert_to_srgb, ensure_free_space, open_upright_image_with_profile, privacy_filtered_exif,
+    run_in_worker_pool, write_atomically, EditRecipe, OperationTimer, Priority, Watermark, Workload,
+};
+use crate::commands::cache::{init_database, media_file_from_row, MEDIA_COLUMNS};
+use crate::commands::edits::current_recipe;
+use crate::commands::exif_edit::run_exiftool;
+use crate::commands::private::visible_condition;
+
+/// Emitted after each exported file
+pub const EXPORT_PROGRESS_EVENT: &str = "export-progress";
+
+const DEFAULT_QUALITY: u8 = 85;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Jpeg,
+    /// Lossless; the encoder ignores `quality`
+    Webp,
+    /// Keep each file's format; formats that can't be written (HEIC, RAW) become JPEG
+    #[default]
+    Original,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetadataMode {
+    /// Drop EXIF, GPS and XMP, e.g. before sharing online
+    #[default]
+    Strip,
+    /// Copy metadata from the original (needs exiftool)
+    Keep,
+    /// Keep date, camera and exposure settings but drop GPS, serial numbers and XMP.
+    /// Done while encoding, without external tools.
+    Private,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportOptions {
+    /// Longest side in pixels; smaller photos are never upscaled
+    pub max_dimension: Option<u32>,
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// JPEG quality, 1-100
+    pub quality: Option<u8>,
+    #[serde(default)]
+    pub metadata: MetadataMode,
+    /// Render saved adjustments (crop, rotation, exposure) into the exported photo
+    #[serde(default = "default_with_edits")]
+    pub with_edits: bool,
+    /// Stamp the watermark configured in settings on photos and videos
+    #[serde(default)]
+    pub watermark: bool,
+}
+
+fn default_with_edits() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportProgress {
+    pub processed: usize,
+    pub total: usize,
+    pub current_file: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportResult {
+    pub exported: usize,
+    pub failed: usize,
+    /// Paths of the written files
+    pub files: Vec<String>,
+}
+
+/// Export copies of media files into `dest`, resized and converted for sharing.
+/// Videos keep their streams unless watermarked; with `strip` or `private` metadata they are
+/// remuxed without it (needs ffmpeg). Existing files in `dest` are never overwritten.
+#[tauri::command]
+pub async fn export_media(
+    app: AppHandle,
+    media_ids: Vec<i64>,
+    dest: String,
+    options: ExportOptions,
+) -> Result<ExportResult, PenglerError> {
+    export_media_internal(&app, &media_ids, Path::new(&dest), &options)
+        .map_err(|e| PenglerError::report("Failed to export media", e))
+}
+
+/// Make JPEG copies of photos (HEIC, RAW, ...) for apps that can't read the originals.
+/// Copies go into `dest`, or next to each original when it's not given; originals are
+/// left untouched. Quality defaults to the one in settings. Date and camera details
+/// are kept, GPS and serial numbers are not.
+#[tauri::command]
+pub async fn convert_to_jpeg(
+    app: AppHandle,
+    media_ids: Vec<i64>,
+    dest: Option<String>,
+    quality: Option<u8>,
+) -> Result<ExportResult, PenglerError> {
+    convert_to_jpeg_internal(&app, &media_ids, dest.as_deref().map(Path::new), quality)
+        .map_err(|e| PenglerError::report("Failed to convert to JPEG", e))
+}
+
+fn convert_to_jpeg_internal(
+    app: &AppHandle,
+    media_ids: &[i64],
+    dest: Option<&Path>,
+    quality: Option<u8>,
+) -> Result<ExportResult> {
+    let options = ExportOptions {
+        max_dimension: None,
+        format: ExportFormat::Jpeg,
+        quality: Some(quality.unwrap_or(Config::load()?.optimization_quality)),
+        metadata: MetadataMode::Private,
+        with_edits: false,
+        watermark: false,
+    };
+
+    let (photos, others): (Vec<MediaFile>, Vec<MediaFile>) = load_files(media_ids)?
+        .into_iter()
+        .partition(|file| file.media_type == MediaType::Image);
+    for file in &others {
+        warn!("Not converting {}: not a photo", file.file_path);
+    }
+
+    let mut result = export_files(app, photos, dest, &options)?;
+    result.failed += others.len();
+
+    Ok(result)
+}
+
+fn export_media_internal(
+    app: &AppHandle,
+    media_ids: &[i64],
+    dest: &Path,
+    options: &ExportOptions,
+) -> Result<ExportResult> {
+    export_files(app, load_files(media_ids)?, Some(dest), options)
+}
+
+fn load_files(media_ids: &[i64]) -> Result<Vec<MediaFile>> {
+    if media_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let conn = init_database()?;
+    let placeholders = vec!["?"; media_ids.len()].join(", ");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM media_files WHERE id IN ({}) AND {} ORDER BY taken_at, file_path",
+        MEDIA_COLUMNS,
+        placeholders,
+        visible_condition()
+    ))?;
+    let files = stmt
+        .query_map(params_from_iter(media_ids.iter()), media_file_from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(files)
+}
+
+/// Export `files` into `dest`, or next to each original when `dest` is `None`
+fn export_files(
+    app: &AppHandle,
+    files: Vec<MediaFile>,
+    dest: Option<&Path>,
+    options: &ExportOptions,
+) -> Result<ExportResult> {
+    if options.quality.is_some_and(|q| !(1..=100).contains(&q)) {
+        return Err(anyhow::anyhow!("Quality must be between 1 and 100"));
+    }
+    if options.max_dimension == Some(0) {
+        return Err(anyhow::anyhow!("Maximum dimension must be greater than 0"));
+    }
+    if files.is_empty() {
+        return Ok(ExportResult::default());
+    }
+
+    let watermark = if options.watermark {
+        let watermark = Config::load()?
+            .watermark
+            .ok_or_else(|| anyhow::anyhow!("No watermark is set up in settings"))?;
+        watermark.validate()?;
+        Some(watermark)
+    } else {
+        None
+    };
+
+    let conn = init_database()?;
+
+    let target_dir = |file: &MediaFile| match dest {
+        Some(dest) => dest.to_path_buf(),
+        None => Path::new(&file.file_path).parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+
+    // Resized exports are smaller, so the originals' size is an upper bound in practice
+    let mut dir_sizes: HashMap<PathBuf, i64> = HashMap::new();
+    for file in &files {
+        *dir_sizes.entry(target_dir(file)).or_default() += file.file_size;
+    }
+    for (dir, size) in &dir_sizes {
+        fs::create_dir_all(dir)?;
+        ensure_free_space(dir, (*size).max(0) as u64)?;
+    }
+
+    // Pick every target name up front so parallel workers can't collide
+    let mut reserved = HashSet::new();
+    let mut jobs = Vec::with_capacity(files.len());
+    for file in files {
+        let recipe = if options.with_edits && file.media_type == MediaType::Image {
+            current_recipe(&conn, file.id)?.filter(|recipe| !recipe.is_identity())
+        } else {
+            None
+        };
+        let target = unique_target(&target_dir(&file), &target_name(&file, recipe.as_ref(), options), &mut reserved);
+        jobs.push((file, recipe, target));
+    }
+
+    let total = jobs.len();
+    let processed = AtomicUsize::new(0);
+    let timer = OperationTimer::start("export");
+
+    let outcomes: Vec<Option<String>> = run_in_worker_pool(Workload::Export, || {
+        jobs
+            .par_iter()
+            .map(|(file, recipe, target)| {
+                let _slot = acquire_slot(Priority::Batch);
+                let outcome = match export_file(file, recipe.as_ref(), watermark.as_ref(), target, options) {
+                    Ok(()) => Some(target.to_string_lossy().to_string()),
+                    Err(e) => {
+                        error!("Failed to export {}: {}", file.file_path, e);
+                        None
+                    }
+                };
+
+                let progress = ExportProgress {
+                    processed: processed.fetch_add(1, Ordering::Relaxed) + 1,
+                    total,
+                    current_file: file.file_path.clone(),
+                };
+                if let Err(e) = app.emit(EXPORT_PROGRESS_EVENT, progress) {
+                    warn!("Failed to emit {}: {}", EXPORT_PROGRESS_EVENT, e);
+                }
+
+                outcome
+            })
+            .collect()
+    })?;
+
+    timer.finish(total, jobs.iter().map(|(file, _, _)| file.file_size.max(0) as u64).sum());
+
+    let mut result = ExportResult::default();
+    for outcome in outcomes {
+        match outcome {
+            Some(path) => {
+                result.exported += 1;
+                result.files.push(path);
+            }
+            None => result.failed += 1,
+        }
+    }
+
+    match dest {
+        Some(dest) => info!("Exported {} of {} files to {}", result.exported, total, dest.display()),
+        None => info!("Exported {} of {} files next to their originals", result.exported, total),
+    }
+
+    Ok(result)
+}
+
+fn export_file(
+    file: &MediaFile,
+    recipe: Option<&EditRecipe>,
+    watermark: Option<&Watermark>,
+    target: &Path,
+    options: &ExportOptions,
+) -> Result<()> {
+    let source = Path::new(&file.file_path);
+
+    if is_plain_copy(file, recipe, options) {
+        return write_atomically(target, |part| {
+            fs::copy(source, part)?;
+            Ok(())
+        });
+    }
+
+    if file.media_type == MediaType::Video {
+        if !options.watermark {
+            return export_stripped_video(file, target);
+        }
+        let watermark = watermark.ok_or_else(|| anyhow::anyhow!("Videos are only re-encoded to watermark them"))?;
+        return export_watermarked_video(file, watermark, target, options.metadata);
+    }
+
+    // Bake the orientation in, since the tag is dropped or reset below
+    let (mut img, icc) = open_upright_image_with_profile(source)?;
+
+    if let Some(recipe) = recipe {
+        img = apply_edits(img, recipe);
+    }
+
+    if let Some(max) = options.max_dimension {
+        if img.width() > max || img.height() > max {
+            img = img.resize(max, max, FilterType::Lanczos3);
+        }
+    }
+
+    // The encoders don't embed profiles, so convert to sRGB to keep colors right
+    if let Some(icc) = icc {
+        img = convert_to_srgb(img, &icc)?;
+    }
+
+    if let Some(watermark) = watermark {
+        img = watermark.apply(img)?;
+    }
+
+    let format = output_format(source, options.format).unwrap_or(ImageFormat::Jpeg);
+    let temp = temp_path(target);
+
+    let exif = match options.metadata {
+        MetadataMode::Private => privacy_filtered_exif(source),
+        MetadataMode::Strip | MetadataMode::Keep => None,
+    };
+
+    let written = encode(&img, &temp, format, options.quality.unwrap_or(DEFAULT_QUALITY), exif).and_then(|_| {
+        if options.metadata == MetadataMode::Keep {
+            // Pixels are upright and sRGB now, so don't carry over the old orientation or profile
+            run_exiftool(&[
+                "-overwrite_original".as_ref(),
+                "-tagsFromFile".as_ref(),
+                source.as_os_str(),
+                "-all:all".as_ref(),
+                "--ICC_Profile:all".as_ref(),
+                "-Orientation#=1".as_ref(),
+                temp.as_os_str(),
+            ])
+        } else {
+            Ok(())
+        }
+    });
+
+    match written {
+        Ok(()) => {
+            fs::rename(&temp, target)?;
+            Ok(())
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&temp);
+            Err(e)
+        }
+    }
+}
+
+/// Re-encode a video to H.264 with the watermark overlaid, keeping its audio as is
+fn export_watermarked_video(file: &MediaFile, watermark: &Watermark, target: &Path, metadata: MetadataMode) -> Result<()> {
+    let mark_path = temp_path(&target.with_extension("png"));
+    watermark.render(file.width.max(1) as u32)?.save(&mark_path)?;
+
+    let temp = temp_path(target);
+    let output = Command::new("ffmpeg")
+        .arg("-y")
+        .arg("-loglevel").arg("error")
+        .arg("-i").arg(&file.file_path)
+        .arg("-i").arg(&mark_path)
+        .arg("-filter_complex").arg(format!("[0:v][1:v]overlay={}", watermark.overlay_expression()))
+        .arg("-map").arg("0:a?")
+        .arg("-map_metadata").arg(if metadata == MetadataMode::Keep { "0" } else { "-1" })
+        .arg("-c:v").arg("libx264")
+        .arg("-crf").arg("20")
+        .arg("-pix_fmt").arg("yuv420p")
+        .arg("-c:a").arg("copy")
+        .arg(&temp)
+        .output();
+    let _ = fs::remove_file(&mark_path);
+
+    finish_ffmpeg(output, &temp, target, "watermark videos")
+}
+
+/// Copy a video's streams into a new file without its metadata, which keeps the location
+/// in QuickTime/MP4 atoms; nothing is re-encoded
+fn export_stripped_video(file: &MediaFile, target: &Path) -> Result<()> {
+    let temp = temp_path(target);
+    let output = Command::new("ffmpeg")
+        .arg("-y")
+        .arg("-loglevel").arg("error")
+        .arg("-i").arg(&file.file_path)
+        .arg("-map").arg("0:v")
+        .arg("-map").arg("0:a?")
+        .arg("-map_metadata").arg("-1")
+        .arg("-c").arg("copy")
+        .arg(&temp)
+        .output();
+
+    finish_ffmpeg(output, &temp, target, "export videos without their metadata")
+}
+
+/// Move ffmpeg's output into place, or clean it up and report why it failed
+fn finish_ffmpeg(output: std::io::Result<Output>, temp: &Path, target: &Path, purpose: &str) -> Result<()> {
+    match output {
+        Ok(result) if result.status.success() => {
+            fs::rename(temp, target)?;
+            Ok(())
+        }
+        Ok(result) => {
+            let _ = fs::remove_file(temp);
+            let stderr = String::from_utf8_lossy(&result.stderr);
+            Err(anyhow::anyhow!("ffmpeg failed: {}", stderr.trim()))
+        }
+        Err(e) => {
+            let _ = fs::remove_file(temp);
+            if e.kind() == std::io::ErrorKind::NotFound {
+                Err(PenglerError::FfmpegMissing(format!("ffmpeg not found. Please install ffmpeg to {}.", purpose)).into())
+            } else {
+                Err(anyhow::anyhow!("Failed to run ffmpeg: {}", e))
+            }
+        }
+    }
+}
+
+/// Encode `img`, embedding `exif` (raw TIFF data) where the format supports it
+fn encode(img: &DynamicImage, path: &Path, format: ImageFormat, quality: u8, exif: Option<Vec<u8>>) -> Result<()> {
+    match format {
+        ImageFormat::Jpeg => {
+            let mut encoder = JpegEncoder::new_with_quality(BufWriter::new(File::create(path)?), quality);
+            if let Some(exif) = exif {
+                encoder.set_exif_metadata(exif)?;
+            }
+            img.to_rgb8().write_with_encoder(encoder)?;
+        }
+        ImageFormat::WebP => {
+            let mut encoder = WebPEncoder::new_lossless(BufWriter::new(File::create(path)?));
+            if let Some(exif) = exif {
+                encoder.set_exif_metadata(exif)?;
+            }
+            img.to_rgba8().write_with_encoder(encoder)?;
+        }
+        ImageFormat::Png => {
+            let mut encoder = PngEncoder::new(BufWriter::new(File::create(path)?));
+            if let Some(exif) = exif {
+                encoder.set_exif_metadata(exif)?;
+            }
+            img.write_with_encoder(encoder)?;
+        }
+        _ => img.save_with_format(path, format)?,
+    }
+    Ok(())
+}
+
+/// Format to encode to; `None` when the original format can't be written
+fn output_format(source: &Path, format: ExportFormat) -> Option<ImageFormat> {
+    match format {
+        ExportFormat::Jpeg => Some(ImageFormat::Jpeg),
+        ExportFormat::Webp => Some(ImageFormat::WebP),
+        ExportFormat::Original => match ImageFormat::from_path(source) {
+            Ok(format @ (ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::Tiff | ImageFormat::WebP | ImageFormat::Gif)) => {
+                Some(format)
+            }
+            _ => None,
+        },
+    }
+}
+
+/// Files exported as they are, with all their metadata, are copied byte for byte
+fn is_plain_copy(file: &MediaFile, recipe: Option<&EditRecipe>, options: &ExportOptions) -> bool {
+    if options.watermark || options.metadata != MetadataMode::Keep {
+        return false;
+    }
+    file.media_type == MediaType::Video
+        || (options.format == ExportFormat::Original && options.max_dimension.is_none() && recipe.is_none())
+}
+
+/// File name in the export folder, with the extension of the output format
+fn target_name(file: &MediaFile, recipe: Option<&EditRecipe>, options: &ExportOptions) -> String {
+    let source = Path::new(&file.file_path);
+    let file_name = source.file_name().and_then(|n| n.to_str()).unwrap_or("export");
+    let stem = source.file_stem().and_then(|s| s.to_str()).unwrap_or(file_name);
+
+    match output_format(source, options.format) {
+        _ if is_plain_copy(file, recipe, options) => file_name.to_string(),
+        // Watermarked videos are re-encoded to H.264; stripped ones keep their container
+        _ if file.media_type == MediaType::Video && options.watermark => format!("{}.mp4", stem),
+        _ if file.media_type == MediaType::Video => file_name.to_string(),
+        Some(ImageFormat::WebP) if options.format == ExportFormat::Webp => format!("{}.webp", stem),
+        Some(_) if options.format == ExportFormat::Original => file_name.to_string(),
+        _ => format!("{}.jpg", stem),
+    }
+}
+
+/// `dest/name`, or `dest/name (2)` etc. if taken on disk or by an earlier file in this export
+fn unique_target(dest: &Path, name: &str, reserved: &mut HashSet<PathBuf>) -> PathBuf {
+    let path = Path::new(name);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or(name);
+    let extension = path.extension().and_then(|e| e.to_str());
+
+    let mut candidate = dest.join(name);
+    let mut counter = 2;
+    while candidate.exists() || reserved.contains(&candidate) {
+        let numbered = match extension {
+            Some(extension) => format!("{} ({}).{}", stem, counter, extension),
+            None => format!("{} ({})", stem, counter),
+        };
+        candidate = dest.join(numbered);
+        counter += 1;
+    }
+
+    reserved.insert(candidate.clone());
+    candidate
+}
+
+/// Hidden sibling that keeps the extension, so exiftool recognizes the format
+fn temp_path(target: &Path) -> PathBuf {
+    let stem = target.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+    let extension = target.extension().and_then(|e| e.to_str()).unwrap_or_default();
+    target.with_file_name(format!(".{}.export.{}", stem, extension))
+}
@@ -0,0 +1,290 @@
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use anyhow::Result;
+
+use crate::error::PenglerError;
+use crate::utils::{blob_to_embedding, dot};
+use crate::commands::cache::init_database;
+use crate::commands::private::visible_condition;
+
+/// Emitted while `detect_faces` works through the library
+#[cfg(feature = "face-detection")]
+pub const FACE_DETECTION_PROGRESS_EVENT: &str = "face-detection-progress";
+
+/// Cosine similarity above which two faces are treated as the same person
+const SAME_PERSON_SIMILARITY: f32 = 0.5;
+
+#[cfg(feature = "face-detection")]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaceDetectionProgress {
+    pub processed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaceDetectionSummary {
+    pub processed: usize,
+    pub faces_found: usize,
+    pub clusters: usize,
+}
+
+/// Unnamed faces the clustering believes belong to one person
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaceCluster {
+    pub cluster_id: i64,
+    pub face_count: i64,
+    /// Most confident face in the cluster
+    pub cover_region_id: i64,
+    pub cover_media_id: i64,
+    pub cover_thumbnail: Option<String>,
+    /// Named person these faces look like, if any
+    pub suggested_person_id: Option<i64>,
+    pub suggested_person_name: Option<String>,
+}
+
+/// Run the face detector over photos not yet scanned, then regroup unnamed faces
+/// into suggested persons. Needs the `face-detection` build feature and the
+/// `face_detection` config option.
+#[tauri::command]
+pub async fn detect_faces(app: tauri::AppHandle) -> Result<FaceDetectionSummary, PenglerError> {
+    detect_faces_internal(&app)
+        .map_err(|e| PenglerError::report("Failed to detect faces", e))
+}
+
+#[cfg(feature = "face-detection")]
+fn detect_faces_internal(app: &tauri::AppHandle) -> Result<FaceDetectionSummary> {
+    use std::path::Path;
+    use tauri::Emitter;
+    use tracing::{error, info, warn};
+    use crate::config::{Config, get_models_folder};
+    use crate::models::MediaType;
+    use crate::utils::{embedding_to_blob, open_image, FaceDetector};
+
+    if !Config::load()?.face_detection {
+        return Err(anyhow::anyhow!("Face detection is disabled in settings"));
+    }
+
+    let mut detector = FaceDetector::load(&get_models_folder()?)?;
+    let conn = init_database()?;
+
+    let pending: Vec<(i64, String)> = {
+        let mut stmt = conn.prepare(
+            "SELECT id, file_path FROM media_files WHERE media_type = ?1 AND NOT faces_detected AND NOT online_only",
+        )?;
+        let rows = stmt.query_map([serde_json::to_string(&MediaType::Image)?], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+
+    let total = pending.len();
+    let mut faces_found = 0;
+
+    for (index, (media_id, file_path)) in pending.iter().enumerate() {
+        let faces = match open_image(Path::new(file_path)).and_then(|img| detector.detect(&img)) {
+            Ok(faces) => faces,
+            Err(e) => {
+                error!("Failed to detect faces in {}: {}", file_path, e);
+                Vec::new()
+            }
+        };
+
+        let tx = conn.unchecked_transaction()?;
+        // Re-detection replaces earlier detector output but keeps manual regions
+        tx.execute("DELETE FROM face_regions WHERE media_id = ?1 AND source = 'detector'", [media_id])?;
+        for face in &faces {
+            tx.execute(
+                "INSERT INTO face_regions (media_id, x, y, width, height, source, confidence, embedding)
+                 VALUES (?1, ?2, ?3, ?4, ?5, 'detector', ?6, ?7)",
+                params![
+                    media_id,
+                    face.x,
+                    face.y,
+                    face.width,
+                    face.height,
+                    face.confidence,
+                    embedding_to_blob(&face.embedding),
+                ],
+            )?;
+        }
+        tx.execute("UPDATE media_files SET faces_detected = 1 WHERE id = ?1", [media_id])?;
+        tx.commit()?;
+
+        faces_found += faces.len();
+
+        let progress = FaceDetectionProgress { processed: index + 1, total };
+        if let Err(e) = app.emit(FACE_DETECTION_PROGRESS_EVENT, progress) {
+            warn!("Failed to emit {}: {}", FACE_DETECTION_PROGRESS_EVENT, e);
+        }
+    }
+
+    let clusters = cluster_faces(&conn)?;
+
+    info!("Detected {} faces in {} photos, {} clusters", faces_found, total, clusters);
+
+    Ok(FaceDetectionSummary { processed: total, faces_found, clusters })
+}
+
+#[cfg(not(feature = "face-detection"))]
+fn detect_faces_internal(_app: &tauri::AppHandle) -> Result<FaceDetectionSummary> {
+    Err(anyhow::anyhow!("This build of Pengler does not include face detection"))
+}
+
+#[tauri::command]
+pub async fn get_face_clusters() -> Result<Vec<FaceCluster>, PenglerError> {
+    get_face_clusters_internal()
+        .map_err(|e| PenglerError::report("Failed to load face clusters", e))
+}
+
+fn get_face_clusters_internal() -> Result<Vec<FaceCluster>> {
+    let conn = init_database()?;
+
+    // MAX(confidence) makes SQLite return the bare columns from the most confident face
+    let mut stmt = conn.prepare(&format!(
+        "SELECT f.cluster_id, COUNT(*) AS faces, MAX(f.confidence), f.id, f.media_id, m.thumbnail_path,
+                f.suggested_person_id, p.name
+         FROM face_regions f
+         JOIN media_files m ON m.id = f.media_id
+         LEFT JOIN persons p ON p.id = f.suggested_person_id
+         WHERE f.person_id IS NULL AND f.cluster_id IS NOT NULL AND {}
+         GROUP BY f.cluster_id
+         ORDER BY faces DESC",
+        visible_condition()
+    ))?;
+    let clusters = stmt.query_map([], |row| {
+        Ok(FaceCluster {
+            cluster_id: row.get(0)?,
+            face_count: row.get(1)?,
+            cover_region_id: row.get(3)?,
+            cover_media_id: row.get(4)?,
+            cover_thumbnail: row.get(5)?,
+            suggested_person_id: row.get(6)?,
+            suggested_person_name: row.get(7)?,
+        })
+    })?;
+
+    Ok(clusters.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Regroup unnamed faces, e.g. after naming some of them so suggestions improve.
+/// Returns the number of clusters.
+#[tauri::command]
+pub async fn recluster_faces() -> Result<usize, PenglerError> {
+    recluster_faces_internal()
+        .map_err(|e| PenglerError::report("Failed to cluster faces", e))
+}
+
+fn recluster_faces_internal() -> Result<usize> {
+    let conn = init_database()?;
+    cluster_faces(&conn)
+}
+
+/// Assign every unnamed face in a cluster to a person
+#[tauri::command]
+pub async fn name_face_cluster(cluster_id: i64, person_id: i64) -> Result<usize, PenglerError> {
+    name_face_cluster_internal(cluster_id, person_id)
+        .map_err(|e| PenglerError::report("Failed to name face cluster", e))
+}
+
+fn name_face_cluster_internal(cluster_id: i64, person_id: i64) -> Result<usize> {
+    let conn = init_database()?;
+
+    let exists = conn.prepare("SELECT 1 FROM persons WHERE id = ?1")?.exists([person_id])?;
+    if !exists {
+        return Err(anyhow::anyhow!("Person {} not found", person_id));
+    }
+
+    let updated = conn.execute(
+        "UPDATE face_regions SET person_id = ?1, cluster_id = NULL, suggested_person_id = NULL
+         WHERE cluster_id = ?2 AND person_id IS NULL",
+        params![person_id, cluster_id],
+    )?;
+
+    Ok(updated)
+}
+
+struct Centroid {
+    person_id: Option<i64>,
+    sum: Vec<f32>,
+}
+
+impl Centroid {
+    fn similarity(&self, embedding: &[f32]) -> f32 {
+        let norm = self.sum.iter().map(|v| v * v).sum::<f32>().sqrt().max(f32::EPSILON);
+        dot(&self.sum, embedding) / norm
+    }
+
+    fn add(&mut self, embedding: &[f32]) {
+        for (sum, value) in self.sum.iter_mut().zip(embedding) {
+            *sum += value;
+        }
+    }
+}
+
+/// Group unnamed faces by embedding similarity. Named faces seed one centroid per
+/// person, so clusters close to a known person come back with that person suggested.
+/// Returns the number of clusters of unnamed faces.
+fn cluster_faces(conn: &Connection) -> Result<usize> {
+    let mut stmt = conn.prepare(
+        "SELECT id, person_id, embedding FROM face_regions
+         WHERE embedding IS NOT NULL ORDER BY person_id IS NULL, confidence DESC",
+    )?;
+    let faces = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<i64>>(1)?, row.get::<_, Vec<u8>>(2)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut centroids: Vec<Centroid> = Vec::new();
+    let mut assignments: Vec<(i64, usize)> = Vec::new();
+
+    for (face_id, person_id, blob) in faces {
+        let embedding = blob_to_embedding(&blob);
+
+        if let Some(person_id) = person_id {
+            match centroids.iter_mut().find(|c| c.person_id == Some(person_id)) {
+                Some(centroid) => centroid.add(&embedding),
+                None => centroids.push(Centroid { person_id: Some(person_id), sum: embedding }),
+            }
+            continue;
+        }
+
+        let best = centroids
+            .iter()
+            .enumerate()
+            .map(|(index, centroid)| (index, centroid.similarity(&embedding)))
+            .filter(|(_, similarity)| *similarity >= SAME_PERSON_SIMILARITY)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        let index = match best {
+            Some((index, _)) => {
+                centroids[index].add(&embedding);
+                index
+            }
+            None => {
+                centroids.push(Centroid { person_id: None, sum: embedding });
+                centroids.len() - 1
+            }
+        };
+        assignments.push((face_id, index));
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("UPDATE face_regions SET cluster_id = NULL, suggested_person_id = NULL", [])?;
+    for (face_id, index) in &assignments {
+        tx.execute(
+            "UPDATE face_regions SET cluster_id = ?1, suggested_person_id = ?2 WHERE id = ?3",
+            params![*index as i64 + 1, centroids[*index].person_id, face_id],
+        )?;
+    }
+    tx.commit()?;
+
+    let mut clustered: Vec<usize> = assignments.iter().map(|(_, index)| *index).collect();
+    clustered.sort_unstable();
+    clustered.dedup();
+
+    Ok(clustered.len())
+}
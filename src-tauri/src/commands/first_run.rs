@@ -0,0 +1,181 @@
+use std::path::{Path, PathBuf};
+use rayon::prelude::*;
+use serde::Serialize;
+use walkdir::WalkDir;
+use anyhow::Result;
+
+use crate::error::PenglerError;
+use crate::config::Config;
+use crate::models::is_media_file;
+use crate::utils::{run_in_worker_pool, Workload};
+
+/// Media counting in a candidate stops here, so huge drives don't hold up the wizard
+const MAX_COUNTED: usize = 10_000;
+
+/// Camera upload folders of sync clients, relative to the home folder
+const CAMERA_UPLOAD_FOLDERS: &[(&str, &str)] = &[
+    ("OneDrive/Pictures/Camera Roll", "OneDrive Camera Roll"),
+    ("OneDrive/Camera Roll", "OneDrive Camera Roll"),
+    ("Dropbox/Camera Uploads", "Dropbox Camera Uploads"),
+    ("Pictures/iCloud Photos/Photos", "iCloud Photos"),
+];
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CandidateKind {
+    /// The system Pictures folder
+    Pictures,
+    /// The system Videos (Movies on macOS) folder
+    Videos,
+    /// Where a sync client puts phone photos
+    CameraUploads,
+    /// A camera-style DCIM tree at the root of a drive
+    Dcim,
+}
+
+/// A folder the first-run wizard can offer as a library folder
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CandidateFolder {
+    pub path: String,
+    pub kind: CandidateKind,
+    /// Name to show, e.g. "Dropbox Camera Uploads" or "DCIM on E:\"
+    pub label: String,
+    /// Photos and videos found, up to the counting limit
+    pub media_count: usize,
+    /// Counting stopped at the limit; there are more
+    pub more: bool,
+    /// Already a library folder or inside one
+    pub in_library: bool,
+}
+
+/// Standard places photos live on this computer, with how many photos and videos each
+/// holds, so the first-run wizard can offer one-click library setup
+#[tauri::command]
+pub async fn detect_candidate_folders() -> Result<Vec<CandidateFolder>, PenglerError> {
+    detect_candidate_folders_internal()
+        .map_err(|e| PenglerError::report("Failed to detect photo folders", e))
+}
+
+fn detect_candidate_folders_internal() -> Result<Vec<CandidateFolder>> {
+    let library_folders: Vec<PathBuf> = Config::load()
+        .map(|config| config.library_folders())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|folder| Path::new(folder).canonicalize().ok())
+        .collect();
+
+    let mut found: Vec<(PathBuf, CandidateKind, String)> = Vec::new();
+    if let Some(pictures) = dirs::picture_dir() {
+        found.push((pictures, CandidateKind::Pictures, "Pictures".to_string()));
+    }
+    if let Some(videos) = dirs::video_dir() {
+        found.push((videos, CandidateKind::Videos, "Videos".to_string()));
+    }
+    if let Some(home) = dirs::home_dir() {
+        for (relative, label) in CAMERA_UPLOAD_FOLDERS {
+            found.push((home.join(relative), CandidateKind::CameraUploads, label.to_string()));
+        }
+    }
+    // OneDrive can live anywhere, including a business account next to a personal one
+    for variable in ["OneDrive", "OneDriveConsumer", "OneDriveCommercial"] {
+        if let Some(onedrive) = std::env::var_os(variable) {
+            let onedrive = PathBuf::from(onedrive);
+            for relative in ["Pictures/Camera Roll", "Camera Roll"] {
+                found.push((onedrive.join(relative), CandidateKind::CameraUploads, "OneDrive Camera Roll".to_string()));
+            }
+        }
+    }
+    for root in drive_roots() {
+        let label = format!("DCIM on {}", root.display());
+        found.push((root.join("DCIM"), CandidateKind::Dcim, label));
+    }
+
+    // Keep existing folders, once each
+    let mut candidates: Vec<(PathBuf, CandidateKind, String)> = Vec::new();
+    for (path, kind, label) in found {
+        let Ok(path) = path.canonicalize() else { continue };
+        if path.is_dir() && !candidates.iter().any(|(known, _, _)| *known == path) {
+            candidates.push((path, kind, label));
+        }
+    }
+
+    run_in_worker_pool(Workload::Scan, || {
+        candidates
+            .into_par_iter()
+            .map(|(path, kind, label)| {
+                let (media_count, more) = count_media(&path);
+                let in_library = library_folders.iter().any(|folder| path.starts_with(folder));
+                CandidateFolder {
+                    path: path.to_string_lossy().to_string(),
+                    kind,
+                    label,
+                    media_count,
+                    more,
+                    in_library,
+                }
+            })
+            .collect()
+    })
+}
+
+/// Photos and videos under `folder`, and whether counting hit `MAX_COUNTED`
+fn count_media(folder: &Path) -> (usize, bool) {
+    let count = WalkDir::new(folder)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| entry.path().to_str().and_then(is_media_file).is_some())
+        .take(MAX_COUNTED + 1)
+        .count();
+    (count.min(MAX_COUNTED), count > MAX_COUNTED)
+}
+
+/// Fixed drives, e.g. "D:\"
+#[cfg(target_os = "windows")]
+pub fn drive_roots() -> Vec<PathBuf> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDriveTypeW;
+    use windows_sys::Win32::System::WindowsProgramming::DRIVE_FIXED;
+
+    (b'A'..=b'Z')
+        .map(|letter| PathBuf::from(format!("{}:\\", letter as char)))
+        .filter(|root| {
+            let wide: Vec<u16> = root.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+            unsafe { GetDriveTypeW(wide.as_ptr()) == DRIVE_FIXED }
+        })
+        .collect()
+}
+
+/// Mounted volumes other than the startup disk
+#[cfg(target_os = "macos")]
+pub fn drive_roots() -> Vec<PathBuf> {
+    std::fs::read_dir("/Volumes")
+        .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+        .unwrap_or_default()
+}
+
+/// Mount points of block devices, skipping boot and snap mounts
+#[cfg(target_os = "linux")]
+pub fn drive_roots() -> Vec<PathBuf> {
+    let Ok(mounts) = std::fs::read_to_string("/proc/mounts") else {
+        return Vec::new();
+    };
+
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (device, mount_point) = (fields.next()?, fields.next()?);
+            // /proc/mounts escapes spaces as \040
+            let mount_point = PathBuf::from(mount_point.replace("\\040", " "));
+            let system = mount_point.starts_with("/boot") || mount_point.starts_with("/snap");
+            (device.starts_with("/dev/") && !system).then_some(mount_point)
+        })
+        .collect()
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+pub fn drive_roots() -> Vec<PathBuf> {
+    Vec::new()
+}
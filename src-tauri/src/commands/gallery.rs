@@ -0,0 +1,305 @@
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use image::{imageops::FilterType, DynamicImage};
+use image::codecs::jpeg::JpegEncoder;
+use rayon::prelude::*;
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use anyhow::Result;
+use tracing::{error, info, warn};
+
+use crate::error::PenglerError;
+use crate::models::{MediaFile, MediaType};
+use crate::utils::{
+    acquire_slot, apply_edits, convert_to_srgb, exposure_summary, open_upright_image_with_profile, run_in_worker_pool,
+    write_atomically, EditRecipe, Priority, Workload,
+};
+use crate::commands::cache::init_database;
+use crate::commands::edits::current_recipe;
+use crate::commands::smart_albums::evaluate_smart_album_internal;
+
+/// Emitted after each photo written to the gallery
+pub const GALLERY_PROGRESS_EVENT: &str = "gallery-progress";
+
+/// Height of the grid thumbnails; the justified rows scale them down from here
+const THUMBNAIL_HEIGHT: u32 = 320;
+/// Longest side of the photos opened in the lightbox
+const PHOTO_SIZE: u32 = 2048;
+const JPEG_QUALITY: u8 = 85;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GalleryProgress {
+    pub processed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GalleryResult {
+    /// Path of the generated index.html
+    pub index_path: String,
+    pub photos: usize,
+    pub failed: usize,
+}
+
+/// One photo as embedded in the page
+#[derive(Debug, Serialize)]
+struct GalleryItem {
+    thumb: String,
+    photo: String,
+    width: u32,
+    height: u32,
+    caption: String,
+}
+
+/// Write an album as a static site into `dest_dir`: a justified grid of thumbnails,
+/// a lightbox and EXIF captions, with no server or external files needed.
+/// Photos are re-encoded, so the originals' metadata (including GPS) is not published.
+#[tauri::command]
+pub async fn export_html_gallery(app: AppHandle, album_id: i64, dest_dir: String) -> Result<GalleryResult, PenglerError> {
+    export_html_gallery_internal(&app, album_id, Path::new(&dest_dir))
+        .map_err(|e| PenglerError::report("Failed to export gallery", e))
+}
+
+fn export_html_gallery_internal(app: &AppHandle, album_id: i64, dest_dir: &Path) -> Result<GalleryResult> {
+    let conn = init_database()?;
+    let title: String = conn
+        .query_row("SELECT name FROM smart_albums WHERE id = ?1", [album_id], |row| row.get(0))
+        .optional()?
+        .ok_or_else(|| anyhow::anyhow!("Smart album {} not found", album_id))?;
+
+    // Oldest first reads like a story
+    let mut photos: Vec<MediaFile> = evaluate_smart_album_internal(album_id)?
+        .into_iter()
+        .filter(|media| media.media_type == MediaType::Image)
+        .collect();
+    photos.reverse();
+
+    let recipes = photos
+        .iter()
+        .map(|media| current_recipe(&conn, media.id))
+        .collect::<Result<Vec<_>>>()?;
+
+    fs::create_dir_all(dest_dir.join("thumbs"))?;
+    fs::create_dir_all(dest_dir.join("photos"))?;
+
+    let total = photos.len();
+    let processed = AtomicUsize::new(0);
+
+    let items: Vec<Option<GalleryItem>> = run_in_worker_pool(Workload::Export, || {
+        photos
+            .par_iter()
+            .zip(recipes.par_iter())
+            .enumerate()
+            .map(|(index, (media, recipe))| {
+                let _slot = acquire_slot(Priority::Batch);
+                let item = match write_photo(dest_dir, index, media, recipe.as_ref()) {
+                    Ok(item) => Some(item),
+                    Err(e) => {
+                        error!("Failed to add {} to gallery: {}", media.file_path, e);
+                        None
+                    }
+                };
+
+                let progress = GalleryProgress { processed: processed.fetch_add(1, Ordering::Relaxed) + 1, total };
+                if let Err(e) = app.emit(GALLERY_PROGRESS_EVENT, progress) {
+                    warn!("Failed to emit {}: {}", GALLERY_PROGRESS_EVENT, e);
+                }
+
+                item
+            })
+            .collect()
+    })?;
+
+    let failed = items.iter().filter(|item| item.is_none()).count();
+    let items: Vec<GalleryItem> = items.into_iter().flatten().collect();
+
+    let index_path = dest_dir.join("index.html");
+    write_atomically(&index_path, |part| Ok(fs::write(part, render_page(&title, &items)?)?))?;
+
+    info!("Exported gallery of {} photos to {}", items.len(), dest_dir.display());
+
+    Ok(GalleryResult {
+        index_path: index_path.to_string_lossy().to_string(),
+        photos: items.len(),
+        failed,
+    })
+}
+
+/// Write the thumbnail and full-size copy of one photo; files are numbered so the
+/// library's folder layout doesn't end up in the published site
+fn write_photo(
+    dest_dir: &Path,
+    index: usize,
+    media: &MediaFile,
+    recipe: Option<&EditRecipe>,
+) -> Result<GalleryItem> {
+    let source = Path::new(&media.file_path);
+    let (mut img, icc) = open_upright_image_with_profile(source)?;
+    if let Some(recipe) = recipe {
+        img = apply_edits(img, recipe);
+    }
+    if let Some(icc) = icc {
+        img = convert_to_srgb(img, &icc)?;
+    }
+
+    let photo = if img.width() > PHOTO_SIZE || img.height() > PHOTO_SIZE {
+        img.resize(PHOTO_SIZE, PHOTO_SIZE, FilterType::Lanczos3)
+    } else {
+        img
+    };
+    let thumb = photo.resize(u32::MAX, THUMBNAIL_HEIGHT, FilterType::Lanczos3);
+
+    let name = format!("{:05}.jpg", index + 1);
+    write_jpeg(&photo, &dest_dir.join("photos").join(&name))?;
+    write_jpeg(&thumb, &dest_dir.join("thumbs").join(&name))?;
+
+    let file_name = source.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    let caption = [
+        Some(file_name.to_string()),
+        media.taken_at.map(|taken_at| taken_at.format("%Y-%m-%d %H:%M").to_string()),
+        media.camera_model.clone(),
+        exposure_summary(source),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join(" · ");
+
+    Ok(GalleryItem {
+        thumb: format!("thumbs/{}", name),
+        photo: format!("photos/{}", name),
+        width: photo.width(),
+        height: photo.height(),
+        caption,
+    })
+}
+
+fn write_jpeg(img: &DynamicImage, path: &Path) -> Result<()> {
+    write_atomically(path, |part| {
+        let encoder = JpegEncoder::new_with_quality(BufWriter::new(File::create(part)?), JPEG_QUALITY);
+        img.to_rgb8().write_with_encoder(encoder)?;
+        Ok(())
+    })
+}
+
+fn render_page(title: &str, items: &[GalleryItem]) -> Result<String> {
+    let tiles: String = items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let ratio = item.width as f64 / item.height.max(1) as f64;
+            format!(
+                "<a href=\"{photo}\" data-index=\"{index}\" style=\"flex-grow:{grow:.3};flex-basis:{basis:.1}px\">\
+                 <i style=\"padding-bottom:{padding:.3}%\"></i>\
+                 <img src=\"{thumb}\" alt=\"{alt}\" loading=\"lazy\"></a>\n",
+                photo = item.photo,
+                index = index,
+                grow = ratio,
+                basis = ratio * 240.0,
+                padding = 100.0 / ratio,
+                thumb = item.thumb,
+                alt = escape_html(&item.caption),
+            )
+        })
+        .collect();
+
+    // Serialized JSON is safe inside <script> once "</" can't close the tag
+    let data = serde_json::to_string(items)?.replace("</", "<\\/");
+
+    Ok(PAGE_TEMPLATE
+        .replace("{{title}}", &escape_html(title))
+        .replace("{{tiles}}", &tiles)
+        .replace("{{data}}", &data))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+const PAGE_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{{title}}</title>
+<style>
+  body { margin: 0; background: #111; color: #eee; font-family: system-ui, sans-serif; }
+  h1 { font-weight: 500; font-size: 1.5rem; margin: 1.5rem 1rem 1rem; }
+  .grid { display: flex; flex-wrap: wrap; gap: 4px; padding: 0 4px 4px; }
+  .grid::after { content: ""; flex-grow: 999999999; }
+  .grid a { position: relative; display: block; background: #222; }
+  .grid i { display: block; }
+  .grid img { position: absolute; top: 0; width: 100%; height: 100%; object-fit: cover; }
+  .lightbox { position: fixed; inset: 0; display: none; flex-direction: column; align-items: center;
+              justify-content: center; background: rgba(0, 0, 0, 0.95); }
+  .lightbox.open { display: flex; }
+  .lightbox img { max-width: 100vw; max-height: calc(100vh - 4rem); object-fit: contain; }
+  .lightbox p { margin: 1rem; font-size: 0.875rem; color: #bbb; text-align: center; }
+  .lightbox button { position: absolute; top: 50%; transform: translateY(-50%); padding: 1rem; border: 0;
+                     background: none; color: #fff; font-size: 2rem; cursor: pointer; }
+  .lightbox .prev { left: 0; }
+  .lightbox .next { right: 0; }
+  .lightbox .close { top: 2rem; right: 0; }
+</style>
+</head>
+<body>
+<h1>{{title}}</h1>
+<div class="grid">
+{{tiles}}</div>
+<div class="lightbox" role="dialog">
+  <img alt="">
+  <p></p>
+  <button class="prev" aria-label="Previous">&#8249;</button>
+  <button class="next" aria-label="Next">&#8250;</button>
+  <button class="close" aria-label="Close">&#215;</button>
+</div>
+<script>
+  const items = {{data}};
+  const box = document.querySelector('.lightbox');
+  const image = box.querySelector('img');
+  const caption = box.querySelector('p');
+  let current = -1;
+
+  function show(index) {
+    current = (index + items.length) % items.length;
+    image.src = items[current].photo;
+    caption.textContent = items[current].caption;
+    box.classList.add('open');
+  }
+
+  function close() {
+    box.classList.remove('open');
+    current = -1;
+  }
+
+  document.querySelectorAll('.grid a').forEach((tile) => {
+    tile.addEventListener('click', (event) => {
+      event.preventDefault();
+      show(Number(tile.dataset.index));
+    });
+  });
+
+  box.querySelector('.prev').addEventListener('click', () => show(current - 1));
+  box.querySelector('.next').addEventListener('click', () => show(current + 1));
+  box.querySelector('.close').addEventListener('click', close);
+  box.addEventListener('click', (event) => { if (event.target === box) close(); });
+
+  document.addEventListener('keydown', (event) => {
+    if (current < 0) return;
+    if (event.key === 'Escape') close();
+    if (event.key === 'ArrowLeft') show(current - 1);
+    if (event.key === 'ArrowRight') show(current + 1);
+  });
+</script>
+</body>
+</html>
+"#;
@@ -0,0 +1,85 @@
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+
+use crate::error::PenglerError;
+use crate::commands::cache::init_database;
+use crate::commands::private::visible_condition;
+
+/// Grid cells per 256px map tile; ~64px clusters
+const CELLS_PER_TILE: f64 = 4.0;
+
+const MAX_ZOOM: u32 = 22;
+
+/// Visible map area in degrees; `west` > `east` when it crosses the antimeridian
+#[derive(Debug, Deserialize)]
+pub struct GeoBounds {
+    pub north: f64,
+    pub south: f64,
+    pub east: f64,
+    pub west: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeoCluster {
+    /// Mean position of the media in the cluster
+    pub latitude: f64,
+    pub longitude: f64,
+    pub count: i64,
+    /// Most recent media in the cluster
+    pub cover_id: i64,
+    pub cover_thumbnail: Option<String>,
+}
+
+/// Bucket geotagged media within `bounds` into grid clusters sized for the map zoom level
+#[tauri::command]
+pub async fn get_geo_clusters(bounds: GeoBounds, zoom: u32) -> Result<Vec<GeoCluster>, PenglerError> {
+    get_geo_clusters_internal(&bounds, zoom)
+        .map_err(|e| PenglerError::report("Failed to load map clusters", e))
+}
+
+fn get_geo_clusters_internal(bounds: &GeoBounds, zoom: u32) -> Result<Vec<GeoCluster>> {
+    let conn = init_database()?;
+
+    let cell_size = 360.0 / (2f64.powi(zoom.min(MAX_ZOOM) as i32) * CELLS_PER_TILE);
+    let crosses_antimeridian = bounds.west > bounds.east;
+
+    // The grid is anchored to the globe, not the viewport, so clusters don't jump while panning.
+    // Longitudes past the antimeridian are shifted by 360 so the grid stays continuous.
+    // MAX(sort_date) makes SQLite return the bare id/thumbnail columns from the newest row.
+    let mut stmt = conn.prepare(&format!(
+        "SELECT AVG(latitude), AVG(lon), COUNT(*), MAX(sort_date), id, thumbnail_path
+         FROM (
+             SELECT id, thumbnail_path, latitude, COALESCE(taken_at, modified_at) AS sort_date,
+                    CASE WHEN longitude < ?3 THEN longitude + 360 ELSE longitude END AS lon
+             FROM media_files
+             WHERE latitude IS NOT NULL AND longitude IS NOT NULL
+               AND latitude BETWEEN ?1 AND ?2
+               AND {} AND {}
+         )
+         GROUP BY CAST((latitude + 90) / ?5 AS INTEGER), CAST((lon + 180) / ?5 AS INTEGER)",
+        if crosses_antimeridian {
+            "(longitude >= ?3 OR longitude <= ?4)"
+        } else {
+            "longitude BETWEEN ?3 AND ?4"
+        },
+        visible_condition()
+    ))?;
+
+    let clusters = stmt.query_map(
+        params![bounds.south, bounds.north, bounds.west, bounds.east, cell_size],
+        |row| {
+            let longitude: f64 = row.get(1)?;
+            Ok(GeoCluster {
+                latitude: row.get(0)?,
+                longitude: if longitude > 180.0 { longitude - 360.0 } else { longitude },
+                count: row.get(2)?,
+                cover_id: row.get(4)?,
+                cover_thumbnail: row.get(5)?,
+            })
+        },
+    )?;
+
+    Ok(clusters.collect::<rusqlite::Result<_>>()?)
+}
@@ -0,0 +1,177 @@
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection};
+use anyhow::Result;
+use tracing::{error, info};
+
+use crate::error::PenglerError;
+use crate::utils::{locate, read_gpx, wall_clock_to_utc};
+use crate::commands::cache::init_database;
+use crate::commands::exif_edit::{backup_original, refresh_media_files, run_exiftool, ExifEditResult};
+use crate::commands::search::folder_condition;
+use crate::commands::smart_albums::notify_smart_albums_changed;
+use crate::commands::private::visible_condition;
+
+/// Write a location into files, for cameras without GPS.
+/// The original of every changed file is kept next to it as `<name>_original`.
+#[tauri::command]
+pub async fn set_location(
+    app: tauri::AppHandle,
+    media_ids: Vec<i64>,
+    latitude: f64,
+    longitude: f64,
+) -> Result<ExifEditResult, PenglerError> {
+    let result = set_location_internal(&media_ids, latitude, longitude)
+        .map_err(|e| PenglerError::report("Failed to set location", e))?;
+    notify_smart_albums_changed(&app);
+    Ok(result)
+}
+
+fn set_location_internal(media_ids: &[i64], latitude: f64, longitude: f64) -> Result<ExifEditResult> {
+    if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+        return Err(anyhow::anyhow!("Invalid coordinates: {}, {}", latitude, longitude));
+    }
+
+    let conn = init_database()?;
+    let files = select_files(&conn, media_ids, None)?;
+    let locations = files.into_iter().map(|(path, _, _)| (path, Some((latitude, longitude)))).collect();
+    write_locations(&conn, locations)
+}
+
+/// Geotag files from a GPX track by matching their date taken to the track's times.
+/// Takes media ids and/or a folder. Dates taken are camera clock time; `utc_offset_minutes`
+/// is that clock's offset from UTC (e.g. 540 for a camera set to Tokyo time) and defaults
+/// to the offset the photo recorded, then the date timezone setting. Files the track
+/// doesn't cover are skipped.
+#[tauri::command]
+pub async fn apply_gpx_track(
+    app: tauri::AppHandle,
+    media_ids: Option<Vec<i64>>,
+    folder: Option<String>,
+    gpx_path: String,
+    utc_offset_minutes: Option<i64>,
+) -> Result<ExifEditResult, PenglerError> {
+    let result = apply_gpx_track_internal(
+        &media_ids.unwrap_or_default(),
+        folder.as_deref(),
+        Path::new(&gpx_path),
+        utc_offset_minutes,
+    )
+    .map_err(|e| PenglerError::report("Failed to apply GPX track", e))?;
+    notify_smart_albums_changed(&app);
+    Ok(result)
+}
+
+fn apply_gpx_track_internal(
+    media_ids: &[i64],
+    folder: Option<&str>,
+    gpx_path: &Path,
+    utc_offset_minutes: Option<i64>,
+) -> Result<ExifEditResult> {
+    let points = read_gpx(gpx_path)?;
+    let conn = init_database()?;
+
+    let locations = select_files(&conn, media_ids, folder)?
+        .into_iter()
+        .map(|(path, taken_at, taken_offset)| {
+            let offset = utc_offset_minutes.map(|minutes| minutes as i32).or(taken_offset);
+            let location = taken_at
+                .and_then(|taken_at| wall_clock_to_utc(taken_at, offset))
+                .and_then(|time| locate(&points, time));
+            (path, location)
+        })
+        .collect();
+
+    write_locations(&conn, locations)
+}
+
+/// A file's path, wall-clock date taken and that date's UTC offset in minutes
+type FileDate = (String, Option<DateTime<Utc>>, Option<i32>);
+
+/// Paths and dates taken of the given media and every library file under `folder`
+fn select_files(conn: &Connection, media_ids: &[i64], folder: Option<&str>) -> Result<Vec<FileDate>> {
+    let row_to_file = |row: &rusqlite::Row| -> rusqlite::Result<FileDate> {
+        let taken_at: Option<String> = row.get(1)?;
+        let taken_at = taken_at
+            .and_then(|t| DateTime::parse_from_rfc3339(&t).ok())
+            .map(|t| t.with_timezone(&Utc));
+        Ok((row.get(0)?, taken_at, row.get(2)?))
+    };
+
+    let mut conditions = Vec::new();
+    let mut values: Vec<Value> = Vec::new();
+    if !media_ids.is_empty() {
+        conditions.push(format!("id IN ({})", vec!["?"; media_ids.len()].join(", ")));
+        values.extend(media_ids.iter().map(|id| Value::from(*id)));
+    }
+    if let Some(folder) = folder {
+        conditions.push(folder_condition(folder, &mut values));
+    }
+    if conditions.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // One query, so a file both picked and inside the folder comes back once
+    let mut stmt = conn.prepare(&format!(
+        "SELECT file_path, taken_at, taken_offset FROM media_files WHERE ({}) AND {}",
+        conditions.join(" OR "),
+        visible_condition()
+    ))?;
+    let files = stmt.query_map(params_from_iter(values.iter()), row_to_file)?;
+
+    Ok(files.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Write GPS tags with exiftool and update the library; files without a location are skipped
+fn write_locations(conn: &Connection, files: Vec<(String, Option<(f64, f64)>)>) -> Result<ExifEditResult> {
+    let mut result = ExifEditResult::default();
+    let mut changed: Vec<(PathBuf, (f64, f64))> = Vec::new();
+
+    for (file, location) in files {
+        let Some((latitude, longitude)) = location else {
+            result.skipped += 1;
+            continue;
+        };
+
+        let path = Path::new(&file);
+        let args = [
+            format!("-GPSLatitude={}", latitude.abs()),
+            format!("-GPSLatitudeRef={}", if latitude < 0.0 { "S" } else { "N" }),
+            format!("-GPSLongitude={}", longitude.abs()),
+            format!("-GPSLongitudeRef={}", if longitude < 0.0 { "W" } else { "E" }),
+        ];
+        let mut exiftool_args: Vec<&std::ffi::OsStr> = vec!["-overwrite_original".as_ref()];
+        exiftool_args.extend(args.iter().map(std::ffi::OsStr::new));
+        exiftool_args.push(path.as_os_str());
+
+        match backup_original(path).and_then(|_| run_exiftool(&exiftool_args)) {
+            Ok(()) => {
+                changed.push((path.to_path_buf(), (latitude, longitude)));
+                result.updated += 1;
+            }
+            Err(e) => {
+                error!("Failed to set location of {}: {}", file, e);
+                result.failed += 1;
+            }
+        }
+    }
+
+    let paths: Vec<PathBuf> = changed.iter().map(|(path, _)| path.clone()).collect();
+    refresh_media_files(&paths)?;
+
+    // Not every format's GPS tags are read back by the scanner (e.g. videos)
+    for (path, (latitude, longitude)) in &changed {
+        conn.execute(
+            "UPDATE media_files SET latitude = ?1, longitude = ?2 WHERE file_path = ?3",
+            params![latitude, longitude, path.to_string_lossy()],
+        )?;
+    }
+
+    info!(
+        "Geotagged {} files, {} skipped, {} failed",
+        result.updated, result.skipped, result.failed
+    );
+
+    Ok(result)
+}
@@ -0,0 +1,302 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use walkdir::WalkDir;
+use anyhow::Result;
+use tracing::{error, info, warn};
+
+use crate::error::PenglerError;
+use crate::config::Config;
+use crate::models::is_media_file;
+use crate::utils::short_hash;
+use crate::commands::cache::{init_database, save_media_files_internal};
+use crate::commands::delete::{delete_media_files, MEDIA_DELETED_EVENT};
+use crate::commands::ingest::{process_file, IngestContext};
+use crate::commands::smart_albums::notify_smart_albums_changed;
+use crate::commands::thumbnail::{cached_thumbnails, get_cache_directory};
+
+/// Paths listed per problem; the counts cover the rest
+const MAX_EXAMPLES: usize = 200;
+
+/// Tables holding per-file data that is worthless once the file leaves the catalog
+const MEDIA_ID_TABLES: &[&str] = &[
+    "media_tags",
+    "face_regions",
+    "media_embeddings",
+    "similar_groups",
+    "duplicate_groups",
+    "edits",
+];
+
+/// Fixes `check_library_health` may apply to what it finds
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HealthRepair {
+    /// Drop catalog entries whose original is gone, like deleting them in the app
+    ForgetMissing,
+    /// Delete remembered hashes of vanished files and data of files no longer cataloged
+    RemoveStaleEntries,
+    /// Delete thumbnails of files no longer cataloged
+    RemoveOrphanedThumbnails,
+    /// Add media files found in library folders to the catalog
+    ImportUntracked,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthIssue {
+    pub count: usize,
+    /// The first `MAX_EXAMPLES` paths
+    pub examples: Vec<String>,
+    /// How many were fixed by the requested repairs
+    pub repaired: usize,
+}
+
+impl HealthIssue {
+    fn add(&mut self, example: impl Into<String>) {
+        self.count += 1;
+        if self.examples.len() < MAX_EXAMPLES {
+            self.examples.push(example.into());
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryHealth {
+    /// Cataloged files no longer on disk
+    pub missing_originals: HealthIssue,
+    /// Remembered hashes and per-file data without a file behind them
+    pub stale_entries: HealthIssue,
+    /// Cached thumbnails of files not in the catalog
+    pub orphaned_thumbnails: HealthIssue,
+    pub orphaned_thumbnail_bytes: u64,
+    /// Media files in library folders that aren't cataloged
+    pub untracked_files: HealthIssue,
+    /// Files and folders that can't be read, and the cache folder if it can't be written
+    pub permission_problems: HealthIssue,
+    /// Library folders that aren't there, e.g. on a disconnected drive. Files in them
+    /// aren't counted as missing.
+    pub offline_folders: Vec<String>,
+}
+
+/// Look for everything that can get out of step between the catalog, the thumbnail
+/// cache and the library folders, and fix what `repairs` asks for. Without repairs
+/// nothing is changed.
+#[tauri::command]
+pub async fn check_library_health(app: AppHandle, repairs: Option<Vec<HealthRepair>>) -> Result<LibraryHealth, PenglerError> {
+    check_library_health_internal(&app, &repairs.unwrap_or_default())
+        .map_err(|e| PenglerError::report("Failed to check library health", e))
+}
+
+fn check_library_health_internal(app: &AppHandle, repairs: &[HealthRepair]) -> Result<LibraryHealth> {
+    let config = Config::load()?;
+    let conn = init_database()?;
+    let mut health = LibraryHealth::default();
+
+    let library_folders: Vec<PathBuf> = config.library_folders().iter().map(PathBuf::from).collect();
+    let (online, offline): (Vec<PathBuf>, Vec<PathBuf>) =
+        library_folders.into_iter().partition(|folder| folder.is_dir());
+    health.offline_folders = offline.iter().map(|folder| folder.to_string_lossy().to_string()).collect();
+    let is_offline = |path: &Path| offline.iter().any(|folder| path.starts_with(folder));
+
+    // Originals
+    let mut missing = Vec::new();
+    let mut cataloged = HashSet::new();
+    {
+        let mut stmt = conn.prepare("SELECT id, file_path, file_hash FROM media_files")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?;
+        for row in rows {
+            let (id, file_path, file_hash) = row?;
+            let path = Path::new(&file_path);
+            match fs::metadata(path) {
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::PermissionDenied => health.permission_problems.add(&file_path),
+                Err(_) if is_offline(path) => {}
+                Err(_) => {
+                    health.missing_originals.add(&file_path);
+                    missing.push((id, file_path.clone(), file_hash));
+                }
+            }
+            cataloged.insert(file_path);
+        }
+    }
+    if repairs.contains(&HealthRepair::ForgetMissing) && !missing.is_empty() {
+        let (result, forgotten) = delete_media_files(&conn, &missing, false)?;
+        health.missing_originals.repaired = result.deleted;
+        for (_, file_path, _) in &missing {
+            cataloged.remove(file_path);
+        }
+        if let Err(e) = app.emit(MEDIA_DELETED_EVENT, &forgotten) {
+            warn!("Failed to emit {}: {}", MEDIA_DELETED_EVENT, e);
+        }
+        notify_smart_albums_changed(app);
+    }
+
+    check_stale_entries(&conn, &mut health, &is_offline, repairs.contains(&HealthRepair::RemoveStaleEntries))?;
+    check_thumbnails(&conn, &mut health, repairs.contains(&HealthRepair::RemoveOrphanedThumbnails))?;
+
+    // Library folders
+    let mut untracked = Vec::new();
+    for folder in &online {
+        let settings = config.folder_settings(folder);
+        let walk = WalkDir::new(folder).follow_links(true).into_iter().filter_entry(|entry| {
+            entry.depth() == 0 || !settings.is_some_and(|s| s.is_excluded(&entry.file_name().to_string_lossy()))
+        });
+        for entry in walk {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    if e.io_error().is_some_and(|e| e.kind() == ErrorKind::PermissionDenied) {
+                        let path = e.path().unwrap_or(folder);
+                        health.permission_problems.add(path.to_string_lossy());
+                    }
+                    continue;
+                }
+            };
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let Some(file_path) = entry.path().to_str() else { continue };
+            if is_media_file(file_path).is_some() && !cataloged.contains(file_path) {
+                health.untracked_files.add(file_path);
+                untracked.push((entry.into_path(), settings.is_some_and(|s| s.network)));
+            }
+        }
+    }
+    if repairs.contains(&HealthRepair::ImportUntracked) && !untracked.is_empty() {
+        health.untracked_files.repaired = import_files(&conn, &untracked)?;
+        notify_smart_albums_changed(app);
+    }
+
+    // A cache that can't be written stops thumbnails and catalog updates alike
+    let cache_dir = get_cache_directory()?;
+    let probe = cache_dir.join(".pengler-write-test");
+    match fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = fs::remove_file(&probe);
+        }
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => health.permission_problems.add(cache_dir.to_string_lossy()),
+        Err(e) => warn!("Failed to write to {}: {}", cache_dir.display(), e),
+    }
+
+    info!(
+        "Library health: {} missing, {} stale entries, {} orphaned thumbnails, {} untracked, {} permission problems",
+        health.missing_originals.count,
+        health.stale_entries.count,
+        health.orphaned_thumbnails.count,
+        health.untracked_files.count,
+        health.permission_problems.count
+    );
+    Ok(health)
+}
+
+/// Hashes remembered for files that are gone, and per-file rows of files no longer
+/// in the catalog
+fn check_stale_entries(
+    conn: &Connection,
+    health: &mut LibraryHealth,
+    is_offline: &dyn Fn(&Path) -> bool,
+    repair: bool,
+) -> Result<()> {
+    let stale_hashes: Vec<String> = {
+        let mut stmt = conn.prepare("SELECT file_path FROM file_hashes")?;
+        let paths = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        paths
+            .collect::<rusqlite::Result<Vec<String>>>()?
+            .into_iter()
+            .filter(|file_path| {
+                let path = Path::new(file_path);
+                !is_offline(path) && fs::symlink_metadata(path).is_err_and(|e| e.kind() == ErrorKind::NotFound)
+            })
+            .collect()
+    };
+    for file_path in &stale_hashes {
+        health.stale_entries.add(file_path);
+    }
+
+    for table in MEDIA_ID_TABLES {
+        let dangling: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM {} WHERE media_id NOT IN (SELECT id FROM media_files)", table),
+            [],
+            |row| row.get(0),
+        )?;
+        health.stale_entries.count += dangling as usize;
+    }
+    let dangling_text: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM media_text WHERE rowid NOT IN (SELECT id FROM media_files)",
+        [],
+        |row| row.get(0),
+    )?;
+    health.stale_entries.count += dangling_text as usize;
+
+    if repair && health.stale_entries.count > 0 {
+        let tx = conn.unchecked_transaction()?;
+        let mut repaired = 0;
+        {
+            let mut stmt = tx.prepare("DELETE FROM file_hashes WHERE file_path = ?1")?;
+            for file_path in &stale_hashes {
+                repaired += stmt.execute([file_path])?;
+            }
+        }
+        for table in MEDIA_ID_TABLES {
+            repaired += tx.execute(
+                &format!("DELETE FROM {} WHERE media_id NOT IN (SELECT id FROM media_files)", table),
+                [],
+            )?;
+        }
+        repaired += tx.execute("DELETE FROM media_text WHERE rowid NOT IN (SELECT id FROM media_files)", [])?;
+        tx.commit()?;
+        health.stale_entries.repaired = repaired;
+    }
+    Ok(())
+}
+
+/// Thumbnails whose key matches no cataloged file
+fn check_thumbnails(conn: &Connection, health: &mut LibraryHealth, repair: bool) -> Result<()> {
+    let keys: HashSet<String> = {
+        let mut stmt = conn.prepare("SELECT DISTINCT file_hash FROM media_files")?;
+        let hashes = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        hashes
+            .map(|file_hash| file_hash.map(|file_hash| short_hash(&file_hash)))
+            .collect::<rusqlite::Result<_>>()?
+    };
+
+    for thumbnail in cached_thumbnails()? {
+        if keys.contains(&thumbnail.key) {
+            continue;
+        }
+        health.orphaned_thumbnails.add(thumbnail.path.to_string_lossy());
+        health.orphaned_thumbnail_bytes += thumbnail.size;
+        if repair {
+            match fs::remove_file(&thumbnail.path) {
+                Ok(()) => health.orphaned_thumbnails.repaired += 1,
+                Err(e) => warn!("Failed to remove {}: {}", thumbnail.path.display(), e),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Catalog `(path, on a network share)` files; returns how many were added
+fn import_files(conn: &Connection, files: &[(PathBuf, bool)]) -> Result<usize> {
+    let local = IngestContext::local(conn)?;
+    let network = IngestContext::network();
+
+    let mut media_files = Vec::new();
+    for (path, on_network) in files {
+        match process_file(path, if *on_network { &network } else { &local }) {
+            Ok(media_file) => media_files.push(media_file),
+            Err(e) => error!("Failed to import {}: {}", path.display(), e),
+        }
+    }
+    local.save(conn)?;
+
+    let imported = media_files.len();
+    save_media_files_internal(media_files)?;
+    Ok(imported)
+}
@@ -0,0 +1,89 @@
+use std::path::Path;
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use anyhow::Result;
+use tracing::{error, info, warn};
+
+use crate::error::PenglerError;
+use crate::utils::hydrate;
+use crate::commands::cache::{init_database, save_media_files_internal};
+use crate::commands::ingest::{process_file, IngestContext};
+use crate::commands::smart_albums::notify_smart_albums_changed;
+use crate::commands::private::visible_condition;
+
+/// Emitted after each file is downloaded
+pub const HYDRATE_PROGRESS_EVENT: &str = "hydrate-progress";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HydrateProgress {
+    pub processed: usize,
+    pub total: usize,
+    pub current_file: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HydrateResult {
+    pub hydrated: usize,
+    pub failed: usize,
+}
+
+/// Have the sync client download online-only files, then read them like a fresh scan
+/// so their hash, dimensions and dates are filled in
+#[tauri::command]
+pub async fn hydrate_files(app: AppHandle, media_ids: Vec<i64>) -> Result<HydrateResult, PenglerError> {
+    let result = hydrate_files_internal(&app, &media_ids)
+        .map_err(|e| PenglerError::report("Failed to download files", e))?;
+    notify_smart_albums_changed(&app);
+    Ok(result)
+}
+
+fn hydrate_files_internal(app: &AppHandle, media_ids: &[i64]) -> Result<HydrateResult> {
+    let conn = init_database()?;
+    let ctx = IngestContext::local(&conn)?;
+    let mut result = HydrateResult::default();
+    let mut hydrated = Vec::new();
+
+    for (index, media_id) in media_ids.iter().enumerate() {
+        let path: Option<String> = conn
+            .query_row(
+                &format!("SELECT file_path FROM media_files WHERE id = ?1 AND {}", visible_condition()),
+                [media_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(path) = path else {
+            result.failed += 1;
+            continue;
+        };
+
+        let media = hydrate(Path::new(&path)).and_then(|()| process_file(Path::new(&path), &ctx));
+        match media {
+            Ok(media) if !media.online_only => {
+                hydrated.push(media);
+                result.hydrated += 1;
+            }
+            Ok(_) => {
+                warn!("{} is still online-only after reading it", path);
+                result.failed += 1;
+            }
+            Err(e) => {
+                error!("Failed to download {}: {}", path, e);
+                result.failed += 1;
+            }
+        }
+
+        let progress = HydrateProgress { processed: index + 1, total: media_ids.len(), current_file: path };
+        if let Err(e) = app.emit(HYDRATE_PROGRESS_EVENT, progress) {
+            warn!("Failed to emit {}: {}", HYDRATE_PROGRESS_EVENT, e);
+        }
+    }
+
+    ctx.save(&conn)?;
+    save_media_files_internal(hydrated)?;
+    info!("Downloaded {} online-only files, {} failed", result.hydrated, result.failed);
+
+    Ok(result)
+}
@@ -0,0 +1,160 @@
+use std::path::Path;
+use rusqlite::Connection;
+use anyhow::Result;
+use tracing::error;
+
+use crate::models::{MediaFile, MediaType, is_media_file, detect_media_type};
+use crate::utils::{
+    quick_hash_file, retry_network_io, perceptual_hash, extract_exif_metadata, image_dimensions, open_image_with_profile,
+    image_color_space, profile_color_space, probe_video, read_xmp_metadata, is_online_only, HashCache,
+};
+use crate::commands::thumbnail::cache_image_thumbnail;
+use crate::commands::cache_janitor::wake_cache_janitor;
+
+/// How files are hashed while ingesting them
+enum Hashing {
+    /// Full hashes, skipping files whose size and modification time haven't changed
+    Cached(HashCache),
+    /// Quick hashes, retrying dropped connections
+    Network,
+}
+
+/// Shared state for reading files into the catalog, whether they come from a scan, an
+/// import or an edit. Hashes read through it are kept for later runs once `save`d.
+pub struct IngestContext {
+    hashing: Hashing,
+}
+
+impl IngestContext {
+    pub fn local(conn: &Connection) -> Result<Self> {
+        Ok(IngestContext { hashing: Hashing::Cached(HashCache::load(conn)?) })
+    }
+
+    /// For network shares, which slow down under full reads and drop connections now and then
+    pub fn network() -> Self {
+        IngestContext { hashing: Hashing::Network }
+    }
+
+    /// Remember the hash of a file that was hashed some other way, e.g. while copying
+    /// it, so it isn't read again
+    pub fn record_hash(&mut self, path: &Path, hash: &str) {
+        if let Hashing::Cached(hashes) = &mut self.hashing {
+            if let Err(e) = hashes.record(path, hash) {
+                error!("Failed to remember the hash of {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// Store the hashes computed so far
+    pub fn save(&self, conn: &Connection) -> Result<()> {
+        // New thumbnails may have pushed the cache over budget
+        wake_cache_janitor();
+        match &self.hashing {
+            Hashing::Cached(hashes) => hashes.save(conn),
+            Hashing::Network => Ok(()),
+        }
+    }
+}
+
+/// Read a media file into a catalog entry
+pub fn process_file(path: &Path, ctx: &IngestContext) -> Result<MediaFile> {
+    match &ctx.hashing {
+        Hashing::Cached(hashes) => read_media_file(path, &|path| hashes.hash(path)),
+        Hashing::Network => retry_network_io(|| read_media_file(path, &quick_hash_file)),
+    }
+}
+
+/// Read a media file into a catalog entry, hashing it with `hash`
+fn read_media_file(path: &Path, hash: &dyn Fn(&Path) -> Result<String>) -> Result<MediaFile> {
+    let file_path = path.to_string_lossy().to_string();
+    if is_online_only(path) {
+        return online_only_media_file(path, file_path);
+    }
+
+    let media_type = detect_media_type(path)
+        .ok_or_else(|| anyhow::anyhow!("Not a media file"))?;
+
+    // Get file metadata
+    let metadata = std::fs::metadata(path)?;
+    let file_size = metadata.len() as i64;
+    let modified = metadata.modified()?;
+    let modified_at = chrono::DateTime::<chrono::Utc>::from(modified);
+
+    // Calculate file hash
+    let file_hash = hash(path)?;
+
+    // Get dimensions and perceptual hash (or stream details for videos). The one decode
+    // of a photo also gives its thumbnail; photos that can't be decoded still get their
+    // size from the header.
+    let (width, height, perceptual, video_info, color_space) = match media_type {
+        MediaType::Image => match open_image_with_profile(path) {
+            Ok((img, icc)) => {
+                if let Err(e) = cache_image_thumbnail(&file_path, &file_hash, &img, icc.as_deref()) {
+                    error!("Failed to save thumbnail of {}: {}", path.display(), e);
+                }
+                let color_space = icc.as_deref().and_then(profile_color_space);
+                (img.width(), img.height(), Some(perceptual_hash(&img)), None, color_space)
+            }
+            Err(_) => {
+                let (width, height) = image_dimensions(path).unwrap_or((0, 0));
+                (width, height, None, None, image_color_space(path))
+            }
+        },
+        MediaType::Video => match probe_video(path) {
+            Ok(probe) => (probe.width, probe.height, None, Some(probe.info), probe.color_space),
+            Err(e) => {
+                error!("Failed to probe video {}: {}", path.display(), e);
+                (0, 0, None, None, None)
+            }
+        },
+    };
+
+    // Extract EXIF date, location and camera
+    let exif = if media_type == MediaType::Image {
+        extract_exif_metadata(path)
+    } else {
+        Default::default()
+    };
+
+    // Ratings and keywords from Lightroom/Darktable, embedded or in a sidecar
+    let xmp = read_xmp_metadata(path, media_type == MediaType::Image);
+
+    let mut media = MediaFile::new(
+        file_path,
+        file_hash,
+        file_size,
+        width as i32,
+        height as i32,
+        media_type,
+    );
+
+    media.taken_at = exif.taken_at;
+    media.taken_offset = exif.taken_offset;
+    media.latitude = exif.gps.map(|(lat, _)| lat);
+    media.longitude = exif.gps.map(|(_, lon)| lon);
+    media.camera_model = exif.camera_model;
+    media.modified_at = modified_at;
+    media.video_info = video_info;
+    media.color_space = color_space;
+    media.perceptual_hash = perceptual;
+    media.rating = xmp.rating;
+    media.color_label = xmp.color_label;
+    media.favorite = xmp.favorite;
+    media.tags = xmp.keywords;
+
+    Ok(media)
+}
+
+/// Catalog entry for a cloud placeholder from its metadata alone, since reading it would
+/// download it. The hash stands in until the file is hydrated and it can be computed.
+fn online_only_media_file(path: &Path, file_path: String) -> Result<MediaFile> {
+    let media_type = is_media_file(&file_path)
+        .ok_or_else(|| anyhow::anyhow!("Not a media file"))?;
+    let metadata = std::fs::metadata(path)?;
+    let file_hash = format!("online-only:{}", blake3::hash(file_path.as_bytes()).to_hex());
+
+    let mut media = MediaFile::new(file_path, file_hash, metadata.len() as i64, 0, 0, media_type);
+    media.modified_at = chrono::DateTime::<chrono::Utc>::from(metadata.modified()?);
+    media.online_only = true;
+    Ok(media)
+}
@@ -0,0 +1,554 @@
+use serde::Serialize;
+use anyhow::Result;
+
+use crate::error::PenglerError;
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LanServerStatus {
+    pub running: bool,
+    pub port: Option<u16>,
+    /// Addresses to open on other devices, token included
+    pub urls: Vec<String>,
+}
+
+/// Serve a read-only gallery of the library to other devices on the local network
+/// (TVs, tablets). Every request must carry the access token from settings, which is
+/// generated on first start. Needs the `lan-server` build feature.
+#[tauri::command]
+pub async fn start_lan_server(port: Option<u16>) -> Result<LanServerStatus, PenglerError> {
+    start_lan_server_internal(port)
+        .await
+        .map_err(|e| PenglerError::report("Failed to start LAN server", e))
+}
+
+#[tauri::command]
+pub async fn stop_lan_server() -> Result<LanServerStatus, PenglerError> {
+    stop_lan_server_internal()
+        .map_err(|e| PenglerError::report("Failed to stop LAN server", e))
+}
+
+#[tauri::command]
+pub async fn get_lan_server_status() -> Result<LanServerStatus, PenglerError> {
+    lan_server_status().map_err(|e| PenglerError::report("Failed to get LAN server status", e))
+}
+
+#[cfg(not(feature = "lan-server"))]
+async fn start_lan_server_internal(_port: Option<u16>) -> Result<LanServerStatus> {
+    Err(anyhow::anyhow!("This build of Pengler does not include the LAN server"))
+}
+
+#[cfg(not(feature = "lan-server"))]
+fn stop_lan_server_internal() -> Result<LanServerStatus> {
+    Ok(LanServerStatus::default())
+}
+
+#[cfg(not(feature = "lan-server"))]
+fn lan_server_status() -> Result<LanServerStatus> {
+    Ok(LanServerStatus::default())
+}
+
+#[cfg(feature = "lan-server")]
+use server::{lan_server_status, start_lan_server_internal, stop_lan_server_internal};
+
+#[cfg(feature = "lan-server")]
+pub(crate) mod server {
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+    use std::path::Path as FilePath;
+    use std::sync::Mutex;
+    use axum::body::Body;
+    use axum::extract::{Path, Query, Request, State};
+    use axum::http::{header, HeaderMap, StatusCode};
+    use axum::middleware::{self, Next};
+    use axum::response::{Html, IntoResponse, Response};
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use image::imageops::FilterType;
+    use rusqlite::OptionalExtension;
+    use serde::{Deserialize, Serialize};
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+    use tokio::sync::oneshot;
+    use tokio_util::io::ReaderStream;
+    use anyhow::Result;
+    use tracing::{error, info};
+
+    use super::LanServerStatus;
+    use crate::config::Config;
+    use crate::error::PenglerError;
+    use crate::models::{MediaFile, MediaType};
+    use crate::utils::{apply_edits, convert_to_srgb, open_upright_image_with_profile};
+    use crate::commands::cache::{init_database, media_file_from_row, MEDIA_COLUMNS};
+    use crate::commands::edits::current_recipe;
+    use crate::commands::private::SHARED_CONDITION;
+    use crate::commands::search::{search_media_internal, MediaFilters};
+    use crate::commands::thumbnail::{generate_thumbnail_internal, read_thumbnail};
+
+    const PAGE_SIZE: u32 = 120;
+    const JPEG_QUALITY: u8 = 85;
+
+    struct RunningServer {
+        port: u16,
+        token: String,
+        shutdown: oneshot::Sender<()>,
+    }
+
+    static SERVER: Mutex<Option<RunningServer>> = Mutex::new(None);
+
+    #[derive(Clone)]
+    struct ServerState {
+        token_hash: blake3::Hash,
+        max_resolution: u32,
+    }
+
+    pub(super) async fn start_lan_server_internal(port: Option<u16>) -> Result<LanServerStatus> {
+        if SERVER.lock().unwrap().is_some() {
+            return lan_server_status();
+        }
+
+        let mut config = Config::load()?;
+        let port = port.unwrap_or(config.lan_server_port);
+        let token = match &config.lan_server_token {
+            Some(token) => token.clone(),
+            None => {
+                let token = generate_token()?;
+                config.lan_server_token = Some(token.clone());
+                config.lan_server_port = port;
+                config.save()?;
+                token
+            }
+        };
+
+        let listener = tokio::net::TcpListener::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, port))).await?;
+        let port = listener.local_addr()?.port();
+
+        let state = ServerState {
+            token_hash: blake3::hash(token.as_bytes()),
+            max_resolution: config.max_resolution,
+        };
+        let app = Router::new()
+            .route("/", get(index))
+            .route("/api/media", get(list))
+            .route("/thumb/{id}", get(thumbnail))
+            .route("/media/{id}", get(media))
+            .layer(middleware::from_fn_with_state(state.clone(), authorize))
+            .with_state(state);
+
+        let (shutdown, stopped) = oneshot::channel();
+        tokio::spawn(async move {
+            let server = axum::serve(listener, app).with_graceful_shutdown(async {
+                let _ = stopped.await;
+            });
+            if let Err(e) = server.await {
+                error!("LAN server stopped: {}", e);
+            }
+        });
+
+        *SERVER.lock().unwrap() = Some(RunningServer { port, token, shutdown });
+        info!("LAN server listening on port {}", port);
+
+        lan_server_status()
+    }
+
+    pub(super) fn stop_lan_server_internal() -> Result<LanServerStatus> {
+        if let Some(server) = SERVER.lock().unwrap().take() {
+            let _ = server.shutdown.send(());
+            info!("LAN server stopped");
+        }
+        Ok(LanServerStatus::default())
+    }
+
+    pub(super) fn lan_server_status() -> Result<LanServerStatus> {
+        let server = SERVER.lock().unwrap();
+        let Some(server) = server.as_ref() else {
+            return Ok(LanServerStatus::default());
+        };
+
+        let host = local_address().map(|ip| ip.to_string()).unwrap_or_else(|| "localhost".to_string());
+        Ok(LanServerStatus {
+            running: true,
+            port: Some(server.port),
+            urls: vec![format!("http://{}:{}/?token={}", host, server.port, server.token)],
+        })
+    }
+
+    /// 128 random bits as hex, so it can go in a URL as is
+    fn generate_token() -> Result<String> {
+        let mut bytes = [0u8; 16];
+        getrandom::fill(&mut bytes).map_err(|e| anyhow::anyhow!("No random source: {}", e))?;
+        Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    /// The address other devices reach this machine on; connecting a UDP socket
+    /// picks the outgoing interface without sending anything
+    pub(crate) fn local_address() -> Option<IpAddr> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+        socket.connect((Ipv4Addr::new(8, 8, 8, 8), 80)).ok()?;
+        Some(socket.local_addr().ok()?.ip())
+    }
+
+    /// Reject requests without the token, given as `?token=` (links, <img>, <video>)
+    /// or as an `Authorization: Bearer` header
+    async fn authorize(State(state): State<ServerState>, request: Request, next: Next) -> Response {
+        let from_query = request
+            .uri()
+            .query()
+            .and_then(|query| query.split('&').find_map(|pair| pair.strip_prefix("token=")));
+        let from_header = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+
+        // blake3::Hash compares in constant time
+        match from_query.or(from_header) {
+            Some(token) if blake3::hash(token.as_bytes()) == state.token_hash => next.run(request).await,
+            _ => (StatusCode::UNAUTHORIZED, "Missing or invalid token").into_response(),
+        }
+    }
+
+    async fn index() -> Html<&'static str> {
+        Html(PAGE)
+    }
+
+    #[derive(Deserialize)]
+    struct ListParams {
+        offset: Option<u32>,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct WebMediaItem {
+        id: i64,
+        name: String,
+        taken_at: Option<String>,
+        video: bool,
+    }
+
+    #[derive(Serialize)]
+    struct WebMediaPage {
+        items: Vec<WebMediaItem>,
+        total: i64,
+    }
+
+    /// Newest first, with bursts collapsed to their cover
+    async fn list(Query(params): Query<ListParams>) -> Response {
+        let result = blocking(move || {
+            let filters = MediaFilters {
+                collapse_stacks: true,
+                shared: true,
+                offset: params.offset.unwrap_or(0),
+                limit: Some(PAGE_SIZE),
+                ..Default::default()
+            };
+            let result = search_media_internal(&filters)?;
+            Ok(WebMediaPage {
+                items: result
+                    .items
+                    .into_iter()
+                    .map(|media| WebMediaItem {
+                        id: media.id,
+                        name: FilePath::new(&media.file_path)
+                            .file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                            .unwrap_or_default(),
+                        taken_at: media.taken_at.map(|taken_at| taken_at.format("%Y-%m-%d %H:%M").to_string()),
+                        video: media.media_type == MediaType::Video,
+                    })
+                    .collect(),
+                total: result.total,
+            })
+        })
+        .await;
+
+        match result {
+            Ok(page) => Json(page).into_response(),
+            Err(response) => response,
+        }
+    }
+
+    async fn thumbnail(Path(id): Path<i64>) -> Response {
+        let result = blocking(move || {
+            let media = find_media(id)?;
+            let path = generate_thumbnail_internal(&media.file_path, &media.file_hash, true)?;
+            read_thumbnail(FilePath::new(&path))
+        })
+        .await;
+
+        match result {
+            Ok(bytes) => ([(header::CONTENT_TYPE, "image/webp"), (header::CACHE_CONTROL, "max-age=86400")], bytes)
+                .into_response(),
+            Err(response) => response,
+        }
+    }
+
+    /// Photos are scaled down to the configured resolution with edits applied;
+    /// videos are streamed as is, with range requests so players can seek
+    async fn media(State(state): State<ServerState>, Path(id): Path<i64>, headers: HeaderMap) -> Response {
+        let media = match blocking(move || find_media(id)).await {
+            Ok(media) => media,
+            Err(response) => return response,
+        };
+
+        match media.media_type {
+            MediaType::Image => {
+                let max_resolution = state.max_resolution;
+                match blocking(move || render_photo(&media, max_resolution)).await {
+                    Ok(bytes) => ([(header::CONTENT_TYPE, "image/jpeg")], bytes).into_response(),
+                    Err(response) => response,
+                }
+            }
+            MediaType::Video => stream_file(&media.file_path, &headers).await.unwrap_or_else(|e| {
+                error!("Failed to stream {}: {}", media.file_path, e);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }),
+        }
+    }
+
+    pub(crate) fn find_media(id: i64) -> Result<MediaFile> {
+        let conn = init_database()?;
+        conn.query_row(
+            &format!("SELECT {} FROM media_files WHERE id = ?1 AND {}", MEDIA_COLUMNS, SHARED_CONDITION),
+            [id],
+            media_file_from_row,
+        )
+        .optional()?
+        .ok_or_else(|| PenglerError::NotFound(format!("Media {} not found", id)).into())
+    }
+
+    pub(crate) fn render_photo(media: &MediaFile, max_resolution: u32) -> Result<Vec<u8>> {
+        let (mut img, icc) = open_upright_image_with_profile(FilePath::new(&media.file_path))?;
+        if let Some(recipe) = current_recipe(&init_database()?, media.id)? {
+            img = apply_edits(img, &recipe);
+        }
+        if img.width() > max_resolution || img.height() > max_resolution {
+            img = img.resize(max_resolution, max_resolution, FilterType::Lanczos3);
+        }
+        if let Some(icc) = icc {
+            img = convert_to_srgb(img, &icc)?;
+        }
+
+        let mut bytes = Vec::new();
+        let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY);
+        img.to_rgb8().write_with_encoder(encoder)?;
+        Ok(bytes)
+    }
+
+    /// Serve a file, honoring a single `Range: bytes=start-end` header
+    pub(crate) async fn stream_file(path: &str, headers: &HeaderMap) -> Result<Response> {
+        let mut file = tokio::fs::File::open(path).await?;
+        let size = file.metadata().await?.len();
+        let content_type = match FilePath::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("webm") => "video/webm",
+            Some(ext) if ext.eq_ignore_ascii_case("mov") => "video/quicktime",
+            _ => "video/mp4",
+        };
+
+        let range = headers
+            .get(header::RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_range(value, size));
+
+        let Some((start, end)) = range else {
+            let body = Body::from_stream(ReaderStream::new(file));
+            return Ok((
+                [
+                    (header::CONTENT_TYPE, content_type.to_string()),
+                    (header::CONTENT_LENGTH, size.to_string()),
+                    (header::ACCEPT_RANGES, "bytes".to_string()),
+                ],
+                body,
+            )
+                .into_response());
+        };
+
+        file.seek(std::io::SeekFrom::Start(start)).await?;
+        let body = Body::from_stream(ReaderStream::new(file.take(end - start + 1)));
+        Ok((
+            StatusCode::PARTIAL_CONTENT,
+            [
+                (header::CONTENT_TYPE, content_type.to_string()),
+                (header::CONTENT_LENGTH, (end - start + 1).to_string()),
+                (header::ACCEPT_RANGES, "bytes".to_string()),
+                (header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, size)),
+            ],
+            body,
+        )
+            .into_response())
+    }
+
+    /// First and last byte of a `bytes=start-end`, `bytes=start-` or `bytes=-suffix` range
+    fn parse_range(value: &str, size: u64) -> Option<(u64, u64)> {
+        let (start, end) = value.strip_prefix("bytes=")?.split(',').next()?.trim().split_once('-')?;
+        let (start, end) = match (start.parse::<u64>().ok(), end.parse::<u64>().ok()) {
+            (Some(start), Some(end)) => (start, end.min(size.checked_sub(1)?)),
+            (Some(start), None) => (start, size.checked_sub(1)?),
+            (None, Some(suffix)) => (size.saturating_sub(suffix), size.checked_sub(1)?),
+            (None, None) => return None,
+        };
+        (start <= end).then_some((start, end))
+    }
+
+    /// Run database and image work off the async threads; errors become a 404 or 500
+    pub(crate) async fn blocking<T: Send + 'static>(work: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T, Response> {
+        match tokio::task::spawn_blocking(work).await {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(e)) if matches!(e.downcast_ref::<PenglerError>(), Some(PenglerError::NotFound(_))) => {
+                Err(StatusCode::NOT_FOUND.into_response())
+            }
+            Ok(Err(e)) => {
+                error!("LAN server request failed: {}", e);
+                Err(StatusCode::INTERNAL_SERVER_ERROR.into_response())
+            }
+            Err(e) => {
+                error!("LAN server request panicked: {}", e);
+                Err(StatusCode::INTERNAL_SERVER_ERROR.into_response())
+            }
+        }
+    }
+
+    const PAGE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Pengler</title>
+<style>
+  body { margin: 0; background: #111; color: #eee; font-family: system-ui, sans-serif; }
+  h1 { font-weight: 500; font-size: 1.5rem; margin: 1.5rem 1rem 1rem; }
+  .grid { display: grid; grid-template-columns: repeat(auto-fill, minmax(180px, 1fr)); gap: 4px; padding: 0 4px; }
+  .grid a { position: relative; display: block; aspect-ratio: 1; background: #222; }
+  .grid img { width: 100%; height: 100%; object-fit: cover; }
+  .grid .video::after { content: "\25B6"; position: absolute; right: 8px; bottom: 6px; text-shadow: 0 0 4px #000; }
+  .more { display: block; margin: 1.5rem auto; padding: 0.75rem 2rem; border: 0; border-radius: 4px;
+          background: #333; color: #eee; font-size: 1rem; cursor: pointer; }
+  .viewer { position: fixed; inset: 0; display: none; flex-direction: column; align-items: center;
+            justify-content: center; background: rgba(0, 0, 0, 0.95); }
+  .viewer.open { display: flex; }
+  .viewer img, .viewer video { max-width: 100vw; max-height: calc(100vh - 4rem); }
+  .viewer p { margin: 1rem; font-size: 0.875rem; color: #bbb; }
+  .viewer button { position: absolute; top: 50%; transform: translateY(-50%); padding: 1rem; border: 0;
+                   background: none; color: #fff; font-size: 2rem; cursor: pointer; }
+  .viewer .prev { left: 0; }
+  .viewer .next { right: 0; }
+  .viewer .close { top: 2rem; right: 0; }
+</style>
+</head>
+<body>
+<h1>Pengler</h1>
+<div class="grid"></div>
+<button class="more" hidden>Load more</button>
+<div class="viewer" role="dialog">
+  <div class="stage"></div>
+  <p></p>
+  <button class="prev" aria-label="Previous">&#8249;</button>
+  <button class="next" aria-label="Next">&#8250;</button>
+  <button class="close" aria-label="Close">&#215;</button>
+</div>
+<script>
+  const token = new URLSearchParams(location.search).get('token');
+  const url = (path) => path + (path.includes('?') ? '&' : '?') + 'token=' + encodeURIComponent(token);
+  const grid = document.querySelector('.grid');
+  const more = document.querySelector('.more');
+  const viewer = document.querySelector('.viewer');
+  const stage = viewer.querySelector('.stage');
+  const caption = viewer.querySelector('p');
+  const items = [];
+  let current = -1;
+
+  async function load() {
+    more.hidden = true;
+    const response = await fetch(url('/api/media?offset=' + items.length));
+    const page = await response.json();
+    for (const item of page.items) {
+      const index = items.push(item) - 1;
+      const tile = document.createElement('a');
+      tile.href = url('/media/' + item.id);
+      tile.className = item.video ? 'video' : '';
+      const img = document.createElement('img');
+      img.src = url('/thumb/' + item.id);
+      img.loading = 'lazy';
+      img.alt = item.name;
+      tile.appendChild(img);
+      tile.addEventListener('click', (event) => { event.preventDefault(); show(index); });
+      grid.appendChild(tile);
+    }
+    more.hidden = items.length >= page.total;
+  }
+
+  function show(index) {
+    current = (index + items.length) % items.length;
+    const item = items[current];
+    const element = document.createElement(item.video ? 'video' : 'img');
+    element.src = url('/media/' + item.id);
+    if (item.video) { element.controls = true; element.autoplay = true; }
+    stage.replaceChildren(element);
+    caption.textContent = [item.name, item.takenAt].filter(Boolean).join(' · ');
+    viewer.classList.add('open');
+  }
+
+  function close() {
+    viewer.classList.remove('open');
+    stage.replaceChildren();
+    current = -1;
+  }
+
+  more.addEventListener('click', load);
+  viewer.querySelector('.prev').addEventListener('click', () => show(current - 1));
+  viewer.querySelector('.next').addEventListener('click', () => show(current + 1));
+  viewer.querySelector('.close').addEventListener('click', close);
+  document.addEventListener('keydown', (event) => {
+    if (current < 0) return;
+    if (event.key === 'Escape') close();
+    if (event.key === 'ArrowLeft') show(current - 1);
+    if (event.key === 'ArrowRight') show(current + 1);
+  });
+
+  load();
+</script>
+</body>
+</html>
+"#;
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn parses_closed_ranges() {
+            assert_eq!(parse_range("bytes=0-99", 1000), Some((0, 99)));
+            assert_eq!(parse_range("bytes=500-500", 1000), Some((500, 500)));
+            // Only the first of several ranges is served
+            assert_eq!(parse_range("bytes=10-19, 30-39", 1000), Some((10, 19)));
+        }
+
+        #[test]
+        fn parses_open_ended_ranges() {
+            assert_eq!(parse_range("bytes=100-", 1000), Some((100, 999)));
+            assert_eq!(parse_range("bytes=0-", 1), Some((0, 0)));
+        }
+
+        #[test]
+        fn parses_suffix_ranges() {
+            assert_eq!(parse_range("bytes=-100", 1000), Some((900, 999)));
+            // A suffix longer than the file is the whole file
+            assert_eq!(parse_range("bytes=-5000", 1000), Some((0, 999)));
+            assert_eq!(parse_range("bytes=-0", 1000), None);
+        }
+
+        #[test]
+        fn clamps_or_rejects_out_of_bounds_ranges() {
+            assert_eq!(parse_range("bytes=900-5000", 1000), Some((900, 999)));
+            assert_eq!(parse_range("bytes=1000-", 1000), None);
+            assert_eq!(parse_range("bytes=1000-1010", 1000), None);
+            assert_eq!(parse_range("bytes=0-", 0), None);
+            assert_eq!(parse_range("bytes=-10", 0), None);
+        }
+
+        #[test]
+        fn rejects_malformed_ranges() {
+            for value in ["", "bytes=", "bytes=-", "bytes=5", "bytes=a-b", "bytes=20-10", "items=0-10", "bytes 0-10"] {
+                assert_eq!(parse_range(value, 1000), None, "{}", value);
+            }
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use chrono::NaiveDateTime;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use serde::Serialize;
+use anyhow::Result;
+use tracing::{error, info};
+
+use crate::error::PenglerError;
+use crate::models::PickState;
+use crate::utils::cached_hash_file;
+use crate::commands::cache::init_database;
+use crate::commands::metadata::sync_sidecar;
+use crate::commands::private::visible_condition;
+use crate::commands::smart_albums::notify_smart_albums_changed;
+use crate::commands::tags::{ensure_tag, TAG_PATH_SEPARATOR};
+
+/// Parent tag for imported collections, e.g. "Collections/Travel/Japan 2019"
+const COLLECTION_TAG: &str = "Collections";
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LightroomImportResult {
+    /// Photos in the catalog
+    pub photos: usize,
+    pub matched: usize,
+    pub unmatched: usize,
+    pub ratings: usize,
+    pub flags: usize,
+    pub color_labels: usize,
+    /// Keywords and collections applied, each counted once
+    pub keywords: usize,
+    pub collections: usize,
+}
+
+/// One row of the catalog's `Adobe_images`
+struct CatalogPhoto {
+    id: i64,
+    path: String,
+    file_name: String,
+    rating: Option<f64>,
+    pick: Option<f64>,
+    color_label: Option<String>,
+    capture_time: Option<String>,
+}
+
+/// Copy ratings, pick flags, color labels, keywords and collections from a Lightroom
+/// Classic catalog onto the matching library files. Photos are matched by path, then by
+/// content hash if the file is still at its catalog path, then by file name and capture
+/// time. Keywords keep their hierarchy; collections become tags under "Collections/".
+/// The catalog is only read.
+#[tauri::command]
+pub async fn import_lightroom_catalog(app: tauri::AppHandle, catalog_path: String) -> Result<LightroomImportResult, PenglerError> {
+    let result = import_lightroom_catalog_internal(Path::new(&catalog_path))
+        .map_err(|e| PenglerError::report("Failed to import Lightroom catalog", e))?;
+    notify_smart_albums_changed(&app);
+    Ok(result)
+}
+
+fn import_lightroom_catalog_internal(catalog_path: &Path) -> Result<LightroomImportResult> {
+    info!("Importing Lightroom catalog: {}", catalog_path.display());
+
+    let catalog = Connection::open_with_flags(catalog_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    if !is_catalog(&catalog)? {
+        return Err(anyhow::anyhow!("{} is not a Lightroom catalog", catalog_path.display()));
+    }
+    let photos = catalog_photos(&catalog)?;
+    let keywords = image_tags(
+        &catalog,
+        "SELECT id_local, name, parent FROM AgLibraryKeyword",
+        "SELECT image, tag FROM AgLibraryKeywordImage",
+        None,
+    )?;
+    let collections = image_tags(
+        &catalog,
+        // Smart collections are saved searches, not membership
+        "SELECT id_local, name, parent FROM AgLibraryCollection
+         WHERE creationId IN ('com.adobe.ag.library.collection', 'com.adobe.ag.library.group')",
+        "SELECT image, collection FROM AgLibraryCollectionImage",
+        Some(COLLECTION_TAG),
+    )?;
+
+    let mut conn = init_database()?;
+    let library = LibraryIndex::load(&conn)?;
+
+    let mut result = LightroomImportResult { photos: photos.len(), ..Default::default() };
+    let mut keywords_used = HashSet::new();
+    let mut collections_used = HashSet::new();
+    let mut matched_ids = Vec::new();
+
+    let tx = conn.transaction()?;
+    for photo in &photos {
+        let Some(media_id) = library.find(&tx, photo) else {
+            result.unmatched += 1;
+            continue;
+        };
+        result.matched += 1;
+        matched_ids.push(media_id);
+
+        // Lightroom stores 0 stars and unflagged as 0 or NULL; only copy what was set
+        if let Some(rating) = photo.rating.map(|r| r.round() as i32).filter(|r| (1..=5).contains(r)) {
+            tx.execute("UPDATE media_files SET rating = ?1 WHERE id = ?2", params![rating, media_id])?;
+            result.ratings += 1;
+        }
+        let pick = match photo.pick.map(|p| p.signum() as i32) {
+            Some(1) => Some(PickState::Pick),
+            Some(-1) => Some(PickState::Reject),
+            _ => None,
+        };
+        if let Some(pick) = pick {
+            tx.execute("UPDATE media_files SET pick = ?1 WHERE id = ?2", params![pick.to_db(), media_id])?;
+            result.flags += 1;
+        }
+        if let Some(label) = &photo.color_label {
+            tx.execute("UPDATE media_files SET color_label = ?1 WHERE id = ?2", params![label, media_id])?;
+            result.color_labels += 1;
+        }
+
+        let tags = [(&keywords, &mut keywords_used), (&collections, &mut collections_used)];
+        for (image_tags, used) in tags {
+            for tag in image_tags.get(&photo.id).into_iter().flatten() {
+                if let Some(tag_id) = ensure_tag(&tx, tag)? {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO media_tags (media_id, tag_id) VALUES (?1, ?2)",
+                        params![media_id, tag_id],
+                    )?;
+                    used.insert(tag.clone());
+                }
+            }
+        }
+    }
+    tx.commit()?;
+
+    for media_id in matched_ids {
+        if let Err(e) = sync_sidecar(&conn, media_id) {
+            error!("Failed to update sidecar for media {}: {}", media_id, e);
+        }
+    }
+
+    result.keywords = keywords_used.len();
+    result.collections = collections_used.len();
+
+    info!(
+        "Lightroom import finished: {} of {} photos matched, {} ratings, {} flags, {} keywords, {} collections",
+        result.matched, result.photos, result.ratings, result.flags, result.keywords, result.collections
+    );
+
+    Ok(result)
+}
+
+fn catalog_photos(catalog: &Connection) -> Result<Vec<CatalogPhoto>> {
+    let mut stmt = catalog.prepare(
+        "SELECT i.id_local, root.absolutePath || folder.pathFromRoot || file.originalFilename,
+                file.originalFilename, i.rating, i.pick, i.colorLabels, i.captureTime
+         FROM Adobe_images i
+         JOIN AgLibraryFile file ON file.id_local = i.rootFile
+         JOIN AgLibraryFolder folder ON folder.id_local = file.folder
+         JOIN AgLibraryRootFolder root ON root.id_local = folder.rootFolder",
+    )?;
+    let photos = stmt
+        .query_map([], |row| {
+            Ok(CatalogPhoto {
+                id: row.get(0)?,
+                path: row.get(1)?,
+                file_name: row.get(2)?,
+                rating: row.get(3)?,
+                pick: row.get(4)?,
+                color_label: row.get::<_, Option<String>>(5)?.filter(|label| !label.is_empty()),
+                capture_time: row.get(6)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(photos)
+}
+
+/// Full tag paths per catalog image for a tree table (keywords or collections) and
+/// its membership table
+fn image_tags(
+    catalog: &Connection,
+    tree_sql: &str,
+    membership_sql: &str,
+    root_tag: Option<&str>,
+) -> Result<HashMap<i64, Vec<String>>> {
+    let nodes: HashMap<i64, (Option<String>, Option<i64>)> = {
+        let mut stmt = catalog.prepare(tree_sql)?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?
+            .collect::<rusqlite::Result<_>>()?;
+        rows
+    };
+
+    // The unnamed root keyword is skipped; "/" inside a name would read as a level
+    let path_of = |mut id: i64| {
+        let mut parts = Vec::new();
+        let mut depth = 0;
+        while let Some((name, parent)) = nodes.get(&id) {
+            depth += 1;
+            if let Some(name) = name.as_deref().map(str::trim).filter(|n| !n.is_empty()) {
+                parts.push(name.replace(TAG_PATH_SEPARATOR, "-"));
+            }
+            match parent {
+                // Guard against cycles in a damaged catalog
+                Some(parent) if depth < 64 => id = *parent,
+                _ => break,
+            }
+        }
+        parts.extend(root_tag.map(str::to_string));
+        parts.reverse();
+        parts.join(&TAG_PATH_SEPARATOR.to_string())
+    };
+
+    let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+    let mut stmt = catalog.prepare(membership_sql)?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))?;
+    for row in rows {
+        let (image, node) = row?;
+        if !nodes.contains_key(&node) {
+            continue;
+        }
+        let path = path_of(node);
+        if !path.is_empty() && Some(path.as_str()) != root_tag {
+            tags.entry(image).or_default().push(path);
+        }
+    }
+    Ok(tags)
+}
+
+/// Library lookups for matching catalog photos
+struct LibraryIndex {
+    by_path: HashMap<String, i64>,
+    by_hash: HashMap<String, i64>,
+    /// Lowercased file name and capture time; `None` when several files share both
+    by_name_and_time: HashMap<(String, String), Option<i64>>,
+}
+
+impl LibraryIndex {
+    fn load(conn: &Connection) -> Result<Self> {
+        let mut index = LibraryIndex { by_path: HashMap::new(), by_hash: HashMap::new(), by_name_and_time: HashMap::new() };
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, file_path, file_hash, taken_at FROM media_files WHERE {}",
+            visible_condition()
+        ))?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, Option<String>>(3)?))
+        })?;
+        for row in rows {
+            let (id, path, hash, taken_at) = row?;
+            let name = Path::new(&path).file_name().map(|n| n.to_string_lossy().to_lowercase());
+            if let (Some(name), Some(time)) = (name, taken_at.as_deref().and_then(second_precision)) {
+                index
+                    .by_name_and_time
+                    .entry((name, time))
+                    .and_modify(|existing| *existing = None)
+                    .or_insert(Some(id));
+            }
+            index.by_path.insert(normalize_path(&path), id);
+            index.by_hash.insert(hash, id);
+        }
+
+        Ok(index)
+    }
+
+    fn find(&self, conn: &Connection, photo: &CatalogPhoto) -> Option<i64> {
+        if let Some(&id) = self.by_path.get(&normalize_path(&photo.path)) {
+            return Some(id);
+        }
+
+        // The file may still exist at its catalog path outside the library
+        let on_disk = Path::new(&photo.path);
+        if on_disk.is_file() {
+            if let Some(&id) = cached_hash_file(conn, on_disk).ok().and_then(|hash| self.by_hash.get(&hash)) {
+                return Some(id);
+            }
+        }
+
+        let time = photo.capture_time.as_deref().and_then(second_precision)?;
+        self.by_name_and_time.get(&(photo.file_name.to_lowercase(), time)).copied().flatten()
+    }
+}
+
+/// Lightroom writes forward slashes on every platform
+fn normalize_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    if cfg!(windows) { path.to_lowercase() } else { path }
+}
+
+/// "2019-05-04T10:21:09" from Lightroom's capture time or our RFC 3339 taken_at
+fn second_precision(value: &str) -> Option<String> {
+    let value = value.get(..19)?;
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S")
+        .ok()
+        .map(|dt| dt.format("%Y-%m-%dT%H:%M:%S").to_string())
+}
+
+/// Whether a file looks like a Lightroom catalog rather than some other SQLite database
+fn is_catalog(catalog: &Connection) -> Result<bool> {
+    Ok(catalog
+        .query_row("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'Adobe_images'", [], |_| Ok(()))
+        .optional()?
+        .is_some())
+}
@@ -0,0 +1,13 @@
+use crate::error::PenglerError;
+use crate::utils::{recent_logs, LogEntry};
+
+/// Entries shown when no limit is given
+const DEFAULT_LOG_LIMIT: usize = 200;
+
+/// Recent log entries, oldest first, for attaching to problem reports. `level` keeps
+/// only entries at least that severe ("error", "warn", "info", "debug", "trace").
+#[tauri::command]
+pub async fn get_recent_logs(level: Option<String>, limit: Option<usize>) -> Result<Vec<LogEntry>, PenglerError> {
+    recent_logs(level.as_deref(), limit.unwrap_or(DEFAULT_LOG_LIMIT))
+        .map_err(|e| PenglerError::report("Failed to read logs", e))
+}
@@ -0,0 +1,92 @@
+use chrono::{Datelike, NaiveDate};
+use rusqlite::params_from_iter;
+use rusqlite::types::Value;
+use serde::Serialize;
+use anyhow::Result;
+
+use crate::error::PenglerError;
+use crate::models::MediaFile;
+use crate::commands::cache::{init_database, media_file_from_row, MEDIA_COLUMNS};
+use crate::commands::search::{build_filter, MediaFilters};
+use crate::commands::private::visible_condition;
+
+/// SQL expression for the "MM-DD" part of `taken_at`. Dates taken are stored as
+/// wall-clock time, so this is the day as it was where the photo was taken.
+pub const MONTH_DAY_EXPRESSION: &str = "substr(taken_at, 6, 5)";
+
+/// Sample size used when the caller doesn't pass one
+const DEFAULT_SAMPLE_SIZE: u32 = 20;
+
+/// Photos taken on the same day of the year in an earlier year
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Memory {
+    pub year: i32,
+    pub years_ago: i32,
+    pub items: Vec<MediaFile>,
+}
+
+/// Photos taken on this day in previous years, most recent year first.
+/// `today` is the local date ("YYYY-MM-DD") as seen by the user.
+#[tauri::command]
+pub async fn get_memories(today: NaiveDate) -> Result<Vec<Memory>, PenglerError> {
+    get_memories_internal(today)
+        .map_err(|e| PenglerError::report("Failed to load memories", e))
+}
+
+fn get_memories_internal(today: NaiveDate) -> Result<Vec<Memory>> {
+    let conn = init_database()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM media_files
+         WHERE {} = ?1 AND taken_at < ?2 AND {}
+         ORDER BY taken_at DESC",
+        MEDIA_COLUMNS, MONTH_DAY_EXPRESSION, visible_condition()
+    ))?;
+    let files = stmt
+        .query_map(
+            (today.format("%m-%d").to_string(), format!("{:04}", today.year())),
+            media_file_from_row,
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut memories: Vec<Memory> = Vec::new();
+    for file in files {
+        let Some(year) = file.taken_at.map(|taken_at| taken_at.year()) else {
+            continue;
+        };
+
+        match memories.last_mut() {
+            Some(memory) if memory.year == year => memory.items.push(file),
+            _ => memories.push(Memory { year, years_ago: today.year() - year, items: vec![file] }),
+        }
+    }
+
+    Ok(memories)
+}
+
+/// A random selection of media matching the filters; `offset` and `limit` are ignored
+#[tauri::command]
+pub async fn get_random_sample(filters: MediaFilters, n: Option<u32>) -> Result<Vec<MediaFile>, PenglerError> {
+    get_random_sample_internal(&filters, n.unwrap_or(DEFAULT_SAMPLE_SIZE))
+        .map_err(|e| PenglerError::report("Failed to load random sample", e))
+}
+
+fn get_random_sample_internal(filters: &MediaFilters, n: u32) -> Result<Vec<MediaFile>> {
+    let conn = init_database()?;
+    let (where_clause, mut values) = build_filter(filters)?;
+
+    // Shuffle only the ids so the full rows are read just for the picked files
+    values.push(Value::from(n as i64));
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM media_files WHERE id IN (
+            SELECT id FROM media_files {} ORDER BY RANDOM() LIMIT ?
+         ) ORDER BY RANDOM()",
+        MEDIA_COLUMNS, where_clause
+    ))?;
+    let items = stmt
+        .query_map(params_from_iter(values.iter()), media_file_from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(items)
+}
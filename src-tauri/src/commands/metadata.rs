@@ -0,0 +1,179 @@
+use std::path::Path;
+use rusqlite::{Connection, OptionalExtension, params};
+use anyhow::Result;
+use tracing::{error, info};
+
+use crate::error::PenglerError;
+use crate::config::Config;
+use crate::models::MediaFile;
+use crate::utils::{write_xmp_sidecar, XmpMetadata};
+use crate::commands::cache::{init_database, media_file_from_row, MEDIA_COLUMNS};
+use crate::commands::tags::{ensure_tag, remove_unused_tags};
+use crate::commands::smart_albums::notify_smart_albums_changed;
+use crate::commands::private::visible_condition;
+
+/// Set the star rating (0-5, -1 for rejected), or clear it with `None`
+#[tauri::command]
+pub async fn set_rating(app: tauri::AppHandle, media_id: i64, rating: Option<i32>) -> Result<(), PenglerError> {
+    set_rating_internal(media_id, rating)
+        .map_err(|e| PenglerError::report("Failed to set rating", e))?;
+    notify_smart_albums_changed(&app);
+    Ok(())
+}
+
+fn set_rating_internal(media_id: i64, rating: Option<i32>) -> Result<()> {
+    if rating.is_some_and(|r| !(-1..=5).contains(&r)) {
+        return Err(anyhow::anyhow!("Rating must be between -1 and 5"));
+    }
+
+    let conn = init_database()?;
+    update_media(
+        &conn,
+        media_id,
+        &format!("UPDATE media_files SET rating = ?1 WHERE id = ?2 AND {}", visible_condition()),
+        params![rating, media_id],
+    )?;
+    sync_sidecar(&conn, media_id)
+}
+
+#[tauri::command]
+pub async fn set_color_label(media_id: i64, color_label: Option<String>) -> Result<(), PenglerError> {
+    set_color_label_internal(media_id, color_label)
+        .map_err(|e| PenglerError::report("Failed to set color label", e))
+}
+
+fn set_color_label_internal(media_id: i64, color_label: Option<String>) -> Result<()> {
+    let conn = init_database()?;
+    update_media(
+        &conn,
+        media_id,
+        &format!("UPDATE media_files SET color_label = ?1 WHERE id = ?2 AND {}", visible_condition()),
+        params![color_label, media_id],
+    )?;
+    sync_sidecar(&conn, media_id)
+}
+
+#[tauri::command]
+pub async fn set_favorite(media_id: i64, favorite: bool) -> Result<(), PenglerError> {
+    set_favorite_internal(media_id, favorite)
+        .map_err(|e| PenglerError::report("Failed to set favorite", e))
+}
+
+fn set_favorite_internal(media_id: i64, favorite: bool) -> Result<()> {
+    let conn = init_database()?;
+    update_media(
+        &conn,
+        media_id,
+        &format!("UPDATE media_files SET favorite = ?1 WHERE id = ?2 AND {}", visible_condition()),
+        params![favorite, media_id],
+    )?;
+    sync_sidecar(&conn, media_id)
+}
+
+/// Replace the tags of a media file
+#[tauri::command]
+pub async fn set_tags(app: tauri::AppHandle, media_id: i64, tags: Vec<String>) -> Result<(), PenglerError> {
+    set_tags_internal(media_id, tags)
+        .map_err(|e| PenglerError::report("Failed to set tags", e))?;
+    notify_smart_albums_changed(&app);
+    Ok(())
+}
+
+fn set_tags_internal(media_id: i64, tags: Vec<String>) -> Result<()> {
+    let mut conn = init_database()?;
+    let tx = conn.transaction()?;
+
+    let exists = tx
+        .prepare(&format!("SELECT 1 FROM media_files WHERE id = ?1 AND {}", visible_condition()))?
+        .exists([media_id])?;
+    if !exists {
+        return Err(anyhow::anyhow!("Media {} not found", media_id));
+    }
+
+    tx.execute("DELETE FROM media_tags WHERE media_id = ?1", [media_id])?;
+
+    for tag in &tags {
+        if let Some(tag_id) = ensure_tag(&tx, tag)? {
+            tx.execute(
+                "INSERT OR IGNORE INTO media_tags (media_id, tag_id) VALUES (?1, ?2)",
+                params![media_id, tag_id],
+            )?;
+        }
+    }
+
+    // Forget tags no longer used anywhere
+    remove_unused_tags(&tx)?;
+    tx.commit()?;
+
+    sync_sidecar(&conn, media_id)
+}
+
+/// Write sidecars for every file with a rating, label, favorite or tags.
+/// Returns the number of sidecars written.
+#[tauri::command]
+pub async fn write_xmp_sidecars() -> Result<usize, PenglerError> {
+    write_xmp_sidecars_internal()
+        .map_err(|e| PenglerError::report("Failed to write XMP sidecars", e))
+}
+
+fn write_xmp_sidecars_internal() -> Result<usize> {
+    let conn = init_database()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM media_files
+         WHERE rating IS NOT NULL OR color_label IS NOT NULL OR favorite
+            OR id IN (SELECT media_id FROM media_tags)",
+        MEDIA_COLUMNS
+    ))?;
+    let files = stmt.query_map([], media_file_from_row)?;
+
+    let mut written = 0;
+    for file in files {
+        let file = file?;
+        match write_sidecar_for(&file) {
+            Ok(_) => written += 1,
+            Err(e) => error!("Failed to write sidecar for {}: {}", file.file_path, e),
+        }
+    }
+
+    info!("Wrote {} XMP sidecars", written);
+
+    Ok(written)
+}
+
+fn update_media(conn: &Connection, media_id: i64, sql: &str, params: impl rusqlite::Params) -> Result<()> {
+    if conn.execute(sql, params)? == 0 {
+        return Err(anyhow::anyhow!("Media {} not found", media_id));
+    }
+    Ok(())
+}
+
+/// Mirror the file's metadata into its sidecar when the option is enabled
+pub fn sync_sidecar(conn: &Connection, media_id: i64) -> Result<()> {
+    if !Config::load()?.write_xmp_sidecars {
+        return Ok(());
+    }
+
+    let media = conn
+        .query_row(
+            &format!("SELECT {} FROM media_files WHERE id = ?1 AND {}", MEDIA_COLUMNS, visible_condition()),
+            [media_id],
+            media_file_from_row,
+        )
+        .optional()?
+        .ok_or_else(|| anyhow::anyhow!("Media {} not found", media_id))?;
+    write_sidecar_for(&media)?;
+
+    Ok(())
+}
+
+fn write_sidecar_for(media: &MediaFile) -> Result<()> {
+    let metadata = XmpMetadata {
+        rating: media.rating,
+        color_label: media.color_label.clone(),
+        favorite: media.favorite,
+        keywords: media.tags.clone(),
+    };
+    write_xmp_sidecar(Path::new(&media.file_path), &metadata)?;
+    Ok(())
+}
@@ -0,0 +1,9 @@
+use crate::error::PenglerError;
+use crate::utils::{operation_metrics, OperationMetrics};
+
+/// Timing and throughput of recent scans, hashes, thumbnails, exports and imports,
+/// for telling where a slow library spends its time
+#[tauri::command]
+pub async fn get_performance_metrics() -> Result<Vec<OperationMetrics>, PenglerError> {
+    Ok(operation_metrics())
+}
@@ -1,7 +1,96 @@
 pub mod scanner;
+pub mod ingest;
 pub mod thumbnail;
 pub mod cache;
+pub mod drive;
+pub mod takeout;
+pub mod apple_photos;
+pub mod lightroom;
+pub mod hydrate;
+pub mod delete;
+pub mod move_media;
+pub mod copy_media;
+pub mod stacks;
+pub mod metadata;
+pub mod exif_edit;
+pub mod search;
+pub mod tags;
+pub mod smart_albums;
+pub mod geo;
+pub mod geotag;
+pub mod suggest;
+pub mod people;
+pub mod faces;
+pub mod similar;
+pub mod duplicates;
+pub mod ocr;
+pub mod semantic;
+pub mod memories;
+pub mod pick;
+pub mod edits;
+pub mod export;
+pub mod slideshow;
+pub mod gallery;
+pub mod contact_sheet;
+pub mod lan_server;
+pub mod dlna;
+pub mod backup;
+pub mod cloud_backup;
+pub mod metrics;
+pub mod logs;
+pub mod cache_janitor;
+pub mod first_run;
+pub mod health;
+pub mod changes;
+pub mod private;
+pub mod relink;
 
 pub use scanner::scan_folder;
 pub use thumbnail::{generate_thumbnail, get_cache_stats, clear_cache};
 pub use cache::{save_media_files, load_media_files};
+pub use drive::eject_drive;
+pub use takeout::import_google_takeout;
+pub use apple_photos::import_apple_photos;
+pub use lightroom::import_lightroom_catalog;
+pub use hydrate::hydrate_files;
+pub use delete::delete_media;
+pub use move_media::move_media;
+pub use copy_media::copy_media;
+pub use stacks::{detect_bursts, get_stack_members, set_stack_cover};
+pub use metadata::{set_rating, set_color_label, set_favorite, set_tags, write_xmp_sidecars};
+pub use exif_edit::{set_taken_at, shift_taken_at, normalize_orientation, sync_mtime_from_exif};
+pub use search::{search_media, list_media};
+pub use tags::{get_tags, move_tag, merge_tags};
+pub use smart_albums::{
+    list_smart_albums, create_smart_album, update_smart_album, delete_smart_album, evaluate_smart_album,
+};
+pub use geo::get_geo_clusters;
+pub use geotag::{set_location, apply_gpx_track};
+pub use suggest::suggest;
+pub use people::{
+    list_persons, create_person, rename_person, delete_person,
+    get_face_regions, add_face_region, update_face_region, delete_face_region,
+};
+pub use faces::{detect_faces, get_face_clusters, recluster_faces, name_face_cluster};
+pub use similar::{find_similar, group_similar_photos, get_similar_groups};
+pub use duplicates::{find_library_duplicates, get_duplicate_groups, resolve_duplicate_groups};
+pub use ocr::{extract_text, get_media_text};
+pub use semantic::{index_embeddings, semantic_search};
+pub use memories::{get_memories, get_random_sample};
+pub use pick::{set_pick, delete_all_rejected};
+pub use edits::{save_edits, load_edits, get_edit_history, clear_edits};
+pub use export::{export_media, convert_to_jpeg};
+pub use slideshow::render_slideshow;
+pub use gallery::export_html_gallery;
+pub use contact_sheet::generate_contact_sheet;
+pub use lan_server::{start_lan_server, stop_lan_server, get_lan_server_status};
+pub use dlna::{start_dlna_server, stop_dlna_server, get_dlna_status};
+pub use backup::{run_backup, get_backup_history, verify_backup, restore_files};
+pub use cloud_backup::{start_cloud_backup, cancel_cloud_backup, get_cloud_backup_status};
+pub use metrics::get_performance_metrics;
+pub use logs::get_recent_logs;
+pub use first_run::detect_candidate_folders;
+pub use health::check_library_health;
+pub use changes::get_changes_since;
+pub use private::{get_private_status, set_private_passphrase, unlock_private, lock_private, unlock_cache, lock_cache, set_folder_private};
+pub use relink::{relink_folder, suggest_relink_locations};
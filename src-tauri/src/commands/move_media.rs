@@ -0,0 +1,202 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use anyhow::Result;
+use tracing::{error, info, warn};
+
+use crate::error::PenglerError;
+use crate::config::Config;
+use crate::utils::{ensure_free_space, find_sidecar, hash_file, write_atomically};
+use crate::commands::cache::{forget_media, init_database};
+use crate::commands::smart_albums::notify_smart_albums_changed;
+use crate::commands::takeout::unique_destination_by;
+use crate::commands::private::{remove_exposed_thumbnails, visible_condition};
+
+/// Emitted with `[media_id, new_path]` pairs of the files that moved
+pub const MEDIA_MOVED_EVENT: &str = "media-moved";
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveMediaResult {
+    pub moved: usize,
+    /// Already in the target folder
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+/// Move files into another folder inside the library, taking their XMP sidecars along.
+/// The catalog row keeps its id, so tags, ratings, faces and edits stay attached, and the
+/// backup manifests follow the new path. A name already taken in the target gets a
+/// " (1)" suffix.
+#[tauri::command]
+pub async fn move_media(app: AppHandle, media_ids: Vec<i64>, target_folder: String) -> Result<MoveMediaResult, PenglerError> {
+    let (result, moved) = move_media_internal(&media_ids, Path::new(&target_folder))
+        .map_err(|e| PenglerError::report("Failed to move files", e))?;
+
+    if let Err(e) = app.emit(MEDIA_MOVED_EVENT, &moved) {
+        warn!("Failed to emit {}: {}", MEDIA_MOVED_EVENT, e);
+    }
+    notify_smart_albums_changed(&app);
+    Ok(result)
+}
+
+fn move_media_internal(media_ids: &[i64], target: &Path) -> Result<(MoveMediaResult, Vec<(i64, String)>)> {
+    let config = Config::load()?;
+    if !config.folders.iter().any(|folder| target.starts_with(&folder.path)) {
+        return Err(anyhow::anyhow!("{} is not inside a library folder", target.display()));
+    }
+    fs::create_dir_all(target)?;
+
+    let mut conn = init_database()?;
+    let mut result = MoveMediaResult::default();
+    let mut moved = Vec::new();
+
+    for &media_id in media_ids {
+        let file_path: Option<String> = conn
+            .query_row(
+                &format!("SELECT file_path FROM media_files WHERE id = ?1 AND {}", visible_condition()),
+                [media_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(file_path) = file_path else {
+            result.failed += 1;
+            continue;
+        };
+
+        let source = Path::new(&file_path);
+        if source.parent() == Some(target) {
+            result.skipped += 1;
+            continue;
+        }
+
+        match move_file(&mut conn, source, target) {
+            Ok(dest) => {
+                moved.push((media_id, dest.to_string_lossy().to_string()));
+                result.moved += 1;
+            }
+            Err(e) => {
+                error!("Failed to move {}: {}", file_path, e);
+                result.failed += 1;
+            }
+        }
+    }
+
+    info!(
+        "Moved {} files to {}, {} already there, {} failed",
+        result.moved,
+        target.display(),
+        result.skipped,
+        result.failed
+    );
+
+    Ok((result, moved))
+}
+
+/// Move one original and its sidecar, then repoint the catalog; returns the new path
+fn move_file(conn: &mut Connection, source: &Path, target: &Path) -> Result<PathBuf> {
+    let file_name = source
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Not a file path"))?
+        .to_string_lossy()
+        .to_string();
+    let sidecar = find_sidecar(source);
+    let dest = destination_with_sidecar(target, &file_name, sidecar.as_deref());
+
+    relocate(source, &dest)?;
+
+    let source_key = source.to_string_lossy().to_string();
+    let dest_key = dest.to_string_lossy().to_string();
+    let mut renamed = vec![(source_key.clone(), dest_key.clone())];
+
+    if let Some(sidecar) = sidecar {
+        let sidecar_dest = sidecar_destination(&sidecar, &file_name, &dest);
+        match relocate(&sidecar, &sidecar_dest) {
+            Ok(()) => renamed.push((sidecar.to_string_lossy().to_string(), sidecar_dest.to_string_lossy().to_string())),
+            Err(e) => error!("Failed to move sidecar {}: {}", sidecar.display(), e),
+        }
+    }
+
+    // A leftover row for a file that used to have this name
+    let stale: Option<i64> = conn
+        .query_row("SELECT id FROM media_files WHERE file_path = ?1", [&dest_key], |row| row.get(0))
+        .optional()?;
+    if let Some(stale) = stale {
+        forget_media(conn, stale)?;
+    }
+    let tx = conn.transaction()?;
+    tx.execute("UPDATE media_files SET file_path = ?1 WHERE file_path = ?2", params![dest_key, source_key])?;
+    // The next backup copies the file and its sidecar under their new names and deletes the
+    // old copies; cloud backups upload them again and keep the old objects
+    for (old_path, new_path) in &renamed {
+        for table in ["backup_files", "cloud_backup_files", "cloud_uploads"] {
+            tx.execute(
+                &format!("UPDATE OR REPLACE {} SET source_path = ?1 WHERE source_path = ?2", table),
+                params![new_path, old_path],
+            )?;
+        }
+    }
+    tx.commit()?;
+
+    // Moved into a private folder: its thumbnail must not stay in the cache unencrypted
+    let moved: Option<(String, bool)> = conn
+        .query_row("SELECT file_hash, private FROM media_files WHERE file_path = ?1", [&dest_key], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .optional()?;
+    if let Some((file_hash, true)) = moved {
+        remove_exposed_thumbnails(conn, &file_hash)?;
+    }
+
+    Ok(dest)
+}
+
+/// Rename in place, or copy and verify when the target is on another volume
+fn relocate(source: &Path, dest: &Path) -> Result<()> {
+    match fs::rename(source, dest) {
+        Ok(()) => return Ok(()),
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    let metadata = fs::metadata(source)?;
+    ensure_free_space(dest, metadata.len())?;
+    write_atomically(dest, |part| {
+        fs::copy(source, part)?;
+        Ok(())
+    })?;
+    if hash_file(dest)? != hash_file(source)? {
+        fs::remove_file(dest)?;
+        return Err(anyhow::anyhow!("The copy in {} doesn't match the original", dest.display()));
+    }
+    filetime::set_file_mtime(dest, filetime::FileTime::from_last_modification_time(&metadata))?;
+    fs::remove_file(source)?;
+    Ok(())
+}
+
+/// Free name in `dir` for a file moved or copied together with its sidecar, so the sidecar
+/// never replaces that of another photo with the same stem: IMG.CR2 next to IMG.jpg and
+/// IMG.xmp becomes "IMG (1).CR2" with "IMG (1).xmp"
+pub fn destination_with_sidecar(dir: &Path, file_name: &str, sidecar: Option<&Path>) -> PathBuf {
+    unique_destination_by(dir, file_name, |dest| {
+        dest.exists() || sidecar.is_some_and(|sidecar| sidecar_destination(sidecar, file_name, dest).exists())
+    })
+}
+
+/// "IMG_0001.xmp" or "IMG_0001.CR2.xmp" renamed after the moved file
+pub fn sidecar_destination(sidecar: &Path, file_name: &str, dest: &Path) -> PathBuf {
+    let sidecar_name = sidecar.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let dest_name = dest.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let name = match sidecar_name.strip_prefix(file_name) {
+        Some(suffix) => format!("{}{}", dest_name, suffix),
+        None => {
+            let extension = sidecar.extension().unwrap_or_default().to_string_lossy();
+            let stem = dest.file_stem().unwrap_or_default().to_string_lossy();
+            format!("{}.{}", stem, extension)
+        }
+    };
+    dest.with_file_name(name)
+}
@@ -0,0 +1,141 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use rayon::prelude::*;
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use anyhow::Result;
+use tracing::{error, info, warn};
+
+use crate::error::PenglerError;
+use crate::config::Config;
+use crate::models::MediaType;
+use crate::utils::{acquire_slot, recognize_text, run_in_worker_pool, Priority, Workload};
+use crate::commands::cache::init_database;
+use crate::commands::private::visible_condition;
+
+/// Emitted while `extract_text` works through the library
+pub const OCR_PROGRESS_EVENT: &str = "ocr-progress";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OcrProgress {
+    pub processed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OcrSummary {
+    pub processed: usize,
+    /// Images in which some text was found
+    pub with_text: usize,
+    pub failed: usize,
+}
+
+/// Recognize text in images not processed yet and add it to the search index.
+/// By default only images without camera EXIF (screenshots, scans, saved images)
+/// are read; `include_camera_photos` covers everything.
+#[tauri::command]
+pub async fn extract_text(app: AppHandle, include_camera_photos: Option<bool>) -> Result<OcrSummary, PenglerError> {
+    extract_text_internal(&app, include_camera_photos.unwrap_or(false))
+        .map_err(|e| PenglerError::report("Failed to extract text", e))
+}
+
+fn extract_text_internal(app: &AppHandle, include_camera_photos: bool) -> Result<OcrSummary> {
+    let config = Config::load()?;
+    if !config.ocr {
+        return Err(anyhow::anyhow!("Text recognition is disabled in settings"));
+    }
+
+    let conn = init_database()?;
+
+    let pending: Vec<(i64, String)> = {
+        let mut stmt = conn.prepare(
+            "SELECT id, file_path FROM media_files
+             WHERE media_type = ?1 AND NOT ocr_done AND NOT online_only AND (?2 OR camera_model IS NULL)",
+        )?;
+        let rows = stmt.query_map(
+            params![serde_json::to_string(&MediaType::Image)?, include_camera_photos],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+
+    let total = pending.len();
+    let processed = AtomicUsize::new(0);
+
+    let results: Vec<(i64, Option<String>)> = run_in_worker_pool(Workload::Scan, || {
+        pending
+            .par_iter()
+            .map(|(id, file_path)| {
+                let _slot = acquire_slot(Priority::Batch);
+                let text = match recognize_text(Path::new(file_path), &config.ocr_languages) {
+                    Ok(text) => Some(text),
+                    Err(e) => {
+                        error!("Failed to recognize text in {}: {}", file_path, e);
+                        None
+                    }
+                };
+
+                let progress = OcrProgress { processed: processed.fetch_add(1, Ordering::Relaxed) + 1, total };
+                if let Err(e) = app.emit(OCR_PROGRESS_EVENT, progress) {
+                    warn!("Failed to emit {}: {}", OCR_PROGRESS_EVENT, e);
+                }
+
+                (*id, text)
+            })
+            .collect()
+    })?;
+
+    let mut summary = OcrSummary { processed: total, with_text: 0, failed: 0 };
+
+    let tx = conn.unchecked_transaction()?;
+    for (id, text) in &results {
+        let Some(text) = text else {
+            // Leave it pending so it is retried, e.g. after installing tesseract
+            summary.failed += 1;
+            continue;
+        };
+
+        tx.execute("DELETE FROM media_text WHERE rowid = ?1", [id])?;
+        if !text.is_empty() {
+            tx.execute("INSERT INTO media_text (rowid, content) VALUES (?1, ?2)", params![id, text])?;
+            summary.with_text += 1;
+        }
+        tx.execute("UPDATE media_files SET ocr_done = 1 WHERE id = ?1", [id])?;
+    }
+    tx.commit()?;
+
+    info!("Recognized text in {} of {} images", summary.with_text, total);
+
+    Ok(summary)
+}
+
+/// Text recognized in a media file, if any
+#[tauri::command]
+pub async fn get_media_text(media_id: i64) -> Result<Option<String>, PenglerError> {
+    get_media_text_internal(media_id)
+        .map_err(|e| PenglerError::report("Failed to load recognized text", e))
+}
+
+fn get_media_text_internal(media_id: i64) -> Result<Option<String>> {
+    let conn = init_database()?;
+    Ok(conn
+        .query_row(
+            &format!(
+                "SELECT t.content FROM media_text t JOIN media_files m ON m.id = t.rowid
+                 WHERE t.rowid = ?1 AND {}",
+                visible_condition()
+            ),
+            [media_id],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+/// Quote user input as a single FTS5 phrase so operators in it are matched literally;
+/// the last word may be incomplete, as when typing
+pub fn fts_phrase(text: &str) -> String {
+    format!("\"{}\"*", text.replace('"', "\"\""))
+}
@@ -0,0 +1,247 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+
+use crate::error::PenglerError;
+use crate::commands::cache::init_database;
+use crate::commands::private::visible_condition;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Person {
+    pub id: i64,
+    pub name: String,
+    pub face_count: i64,
+}
+
+/// Face rectangle relative to the image size (0.0 - 1.0), so it survives resizing
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FaceBox {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaceRegion {
+    pub id: i64,
+    pub media_id: i64,
+    pub person_id: Option<i64>,
+    pub person_name: Option<String>,
+    #[serde(flatten)]
+    pub bounds: FaceBox,
+    /// "manual", or the detector that found the face
+    pub source: String,
+    pub confidence: Option<f64>,
+}
+
+#[tauri::command]
+pub async fn list_persons() -> Result<Vec<Person>, PenglerError> {
+    list_persons_internal()
+        .map_err(|e| PenglerError::report("Failed to load people", e))
+}
+
+fn list_persons_internal() -> Result<Vec<Person>> {
+    let conn = init_database()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT p.id, p.name, (SELECT COUNT(*) FROM face_regions f JOIN media_files m ON m.id = f.media_id
+                               WHERE f.person_id = p.id AND {})
+         FROM persons p ORDER BY p.name COLLATE NOCASE",
+        visible_condition()
+    ))?;
+    let persons = stmt.query_map([], |row| {
+        Ok(Person {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            face_count: row.get(2)?,
+        })
+    })?;
+
+    Ok(persons.collect::<rusqlite::Result<_>>()?)
+}
+
+#[tauri::command]
+pub async fn create_person(name: String) -> Result<Person, PenglerError> {
+    create_person_internal(&name)
+        .map_err(|e| PenglerError::report("Failed to create person", e))
+}
+
+fn create_person_internal(name: &str) -> Result<Person> {
+    let name = validate_name(name)?;
+    let conn = init_database()?;
+
+    conn.execute("INSERT INTO persons (name) VALUES (?1)", [name])?;
+
+    Ok(Person {
+        id: conn.last_insert_rowid(),
+        name: name.to_string(),
+        face_count: 0,
+    })
+}
+
+#[tauri::command]
+pub async fn rename_person(person_id: i64, name: String) -> Result<(), PenglerError> {
+    rename_person_internal(person_id, &name)
+        .map_err(|e| PenglerError::report("Failed to rename person", e))
+}
+
+fn rename_person_internal(person_id: i64, name: &str) -> Result<()> {
+    let name = validate_name(name)?;
+    let conn = init_database()?;
+
+    if conn.execute("UPDATE persons SET name = ?1 WHERE id = ?2", params![name, person_id])? == 0 {
+        return Err(anyhow::anyhow!("Person {} not found", person_id));
+    }
+
+    Ok(())
+}
+
+/// Delete a person; their face regions stay as unnamed faces
+#[tauri::command]
+pub async fn delete_person(person_id: i64) -> Result<(), PenglerError> {
+    delete_person_internal(person_id)
+        .map_err(|e| PenglerError::report("Failed to delete person", e))
+}
+
+fn delete_person_internal(person_id: i64) -> Result<()> {
+    let mut conn = init_database()?;
+    let tx = conn.transaction()?;
+
+    tx.execute("UPDATE face_regions SET person_id = NULL WHERE person_id = ?1", [person_id])?;
+    tx.execute("DELETE FROM persons WHERE id = ?1", [person_id])?;
+
+    tx.commit()?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_face_regions(media_id: i64) -> Result<Vec<FaceRegion>, PenglerError> {
+    get_face_regions_internal(media_id)
+        .map_err(|e| PenglerError::report("Failed to load face regions", e))
+}
+
+fn get_face_regions_internal(media_id: i64) -> Result<Vec<FaceRegion>> {
+    let conn = init_database()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} WHERE f.media_id = ?1 AND f.media_id IN (SELECT id FROM media_files WHERE {}) ORDER BY f.x",
+        FACE_REGION_QUERY,
+        visible_condition()
+    ))?;
+    let regions = stmt.query_map([media_id], face_region_from_row)?;
+
+    Ok(regions.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Mark a face on a photo, optionally naming who it is
+#[tauri::command]
+pub async fn add_face_region(media_id: i64, person_id: Option<i64>, bounds: FaceBox) -> Result<FaceRegion, PenglerError> {
+    add_face_region_internal(media_id, person_id, bounds)
+        .map_err(|e| PenglerError::report("Failed to add face region", e))
+}
+
+fn add_face_region_internal(media_id: i64, person_id: Option<i64>, bounds: FaceBox) -> Result<FaceRegion> {
+    validate_bounds(&bounds)?;
+    let conn = init_database()?;
+
+    let exists = conn
+        .prepare(&format!("SELECT 1 FROM media_files WHERE id = ?1 AND {}", visible_condition()))?
+        .exists([media_id])?;
+    if !exists {
+        return Err(anyhow::anyhow!("Media {} not found", media_id));
+    }
+
+    conn.execute(
+        "INSERT INTO face_regions (media_id, person_id, x, y, width, height) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![media_id, person_id, bounds.x, bounds.y, bounds.width, bounds.height],
+    )?;
+
+    load_face_region(&conn, conn.last_insert_rowid())
+}
+
+/// Change who a face is and/or where it is
+#[tauri::command]
+pub async fn update_face_region(
+    region_id: i64,
+    person_id: Option<i64>,
+    bounds: Option<FaceBox>,
+) -> Result<FaceRegion, PenglerError> {
+    update_face_region_internal(region_id, person_id, bounds)
+        .map_err(|e| PenglerError::report("Failed to update face region", e))
+}
+
+fn update_face_region_internal(region_id: i64, person_id: Option<i64>, bounds: Option<FaceBox>) -> Result<FaceRegion> {
+    let conn = init_database()?;
+
+    if conn.execute("UPDATE face_regions SET person_id = ?1 WHERE id = ?2", params![person_id, region_id])? == 0 {
+        return Err(anyhow::anyhow!("Face region {} not found", region_id));
+    }
+
+    if let Some(bounds) = bounds {
+        validate_bounds(&bounds)?;
+        conn.execute(
+            "UPDATE face_regions SET x = ?1, y = ?2, width = ?3, height = ?4 WHERE id = ?5",
+            params![bounds.x, bounds.y, bounds.width, bounds.height, region_id],
+        )?;
+    }
+
+    load_face_region(&conn, region_id)
+}
+
+#[tauri::command]
+pub async fn delete_face_region(region_id: i64) -> Result<(), PenglerError> {
+    delete_face_region_internal(region_id)
+        .map_err(|e| PenglerError::report("Failed to delete face region", e))
+}
+
+fn delete_face_region_internal(region_id: i64) -> Result<()> {
+    let conn = init_database()?;
+    conn.execute("DELETE FROM face_regions WHERE id = ?1", [region_id])?;
+    Ok(())
+}
+
+/// Columns read by `face_region_from_row`, in order
+const FACE_REGION_QUERY: &str = "f.id, f.media_id, f.person_id, p.name, f.x, f.y, f.width, f.height,
+    f.source, f.confidence FROM face_regions f LEFT JOIN persons p ON p.id = f.person_id";
+
+fn face_region_from_row(row: &rusqlite::Row) -> rusqlite::Result<FaceRegion> {
+    Ok(FaceRegion {
+        id: row.get(0)?,
+        media_id: row.get(1)?,
+        person_id: row.get(2)?,
+        person_name: row.get(3)?,
+        bounds: FaceBox {
+            x: row.get(4)?,
+            y: row.get(5)?,
+            width: row.get(6)?,
+            height: row.get(7)?,
+        },
+        source: row.get(8)?,
+        confidence: row.get(9)?,
+    })
+}
+
+fn load_face_region(conn: &Connection, region_id: i64) -> Result<FaceRegion> {
+    conn.query_row(&format!("SELECT {} WHERE f.id = ?1", FACE_REGION_QUERY), [region_id], face_region_from_row)
+        .optional()?
+        .ok_or_else(|| anyhow::anyhow!("Face region {} not found", region_id))
+}
+
+fn validate_name(name: &str) -> Result<&str> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(anyhow::anyhow!("Name must not be empty"));
+    }
+    Ok(name)
+}
+
+fn validate_bounds(bounds: &FaceBox) -> Result<()> {
+    let within = |start: f64, size: f64| start >= 0.0 && size > 0.0 && start + size <= 1.0 + f64::EPSILON;
+    if !within(bounds.x, bounds.width) || !within(bounds.y, bounds.height) {
+        return Err(anyhow::anyhow!("Face region must lie within the image"));
+    }
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use rusqlite::params_from_iter;
+use rusqlite::types::Value;
+use serde::Serialize;
+use tauri::AppHandle;
+use anyhow::Result;
+
+use crate::error::PenglerError;
+use crate::models::PickState;
+use crate::commands::cache::init_database;
+use crate::commands::delete::delete_media_files;
+use crate::commands::smart_albums::notify_smart_albums_changed;
+use crate::commands::private::visible_condition;
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteRejectedResult {
+    pub deleted: usize,
+    pub failed: usize,
+}
+
+/// Flag files as picked, rejected or unflagged. Returns the number of files updated.
+#[tauri::command]
+pub async fn set_pick(app: AppHandle, media_ids: Vec<i64>, state: PickState) -> Result<usize, PenglerError> {
+    let updated = set_pick_internal(&media_ids, state)
+        .map_err(|e| PenglerError::report("Failed to set pick", e))?;
+    notify_smart_albums_changed(&app);
+    Ok(updated)
+}
+
+fn set_pick_internal(media_ids: &[i64], state: PickState) -> Result<usize> {
+    if media_ids.is_empty() {
+        return Ok(0);
+    }
+
+    let conn = init_database()?;
+
+    let mut values = vec![Value::from(state.to_db())];
+    values.extend(media_ids.iter().map(|id| Value::from(*id)));
+    let placeholders = vec!["?"; media_ids.len()].join(", ");
+
+    let updated = conn.execute(
+        &format!("UPDATE media_files SET pick = ? WHERE id IN ({}) AND {}", placeholders, visible_condition()),
+        params_from_iter(values.iter()),
+    )?;
+
+    Ok(updated)
+}
+
+/// Move every rejected file to the system trash and drop it from the library
+#[tauri::command]
+pub async fn delete_all_rejected(app: AppHandle) -> Result<DeleteRejectedResult, PenglerError> {
+    let result = delete_all_rejected_internal()
+        .map_err(|e| PenglerError::report("Failed to delete rejected files", e))?;
+    notify_smart_albums_changed(&app);
+    Ok(result)
+}
+
+fn delete_all_rejected_internal() -> Result<DeleteRejectedResult> {
+    let conn = init_database()?;
+
+    let rejected: Vec<(i64, String, String)> = {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, file_path, file_hash FROM media_files WHERE pick = ?1 AND {}",
+            visible_condition()
+        ))?;
+        let rows = stmt.query_map([PickState::Reject.to_db()], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+
+    let (result, _) = delete_media_files(&conn, &rejected, true)?;
+
+    Ok(DeleteRejectedResult { deleted: result.deleted, failed: result.failed })
+}
@@ -0,0 +1,415 @@
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use tauri::http::{header, Request, Response, StatusCode};
+use anyhow::Result;
+use tracing::{info, warn};
+
+use crate::error::PenglerError;
+use crate::utils::{hash_passphrase, verify_passphrase, EncryptionKey};
+use crate::commands::cache::init_database;
+use crate::commands::relink::relinked_path;
+use crate::commands::thumbnail::{read_thumbnail, remove_cached_thumbnails, remove_plain_thumbnails, thumbnails_encrypted, get_cache_directory};
+
+/// URI scheme encrypted thumbnails (of private files, or all of them with `encrypt_cache`)
+/// are shown through, e.g. `convertFileSrc(path, 'private')`
+pub const PRIVATE_THUMBNAIL_SCHEME: &str = "private";
+
+/// File extension of encrypted thumbnails, after the `.webp` of the image inside
+pub const ENCRYPTED_THUMBNAIL_EXTENSION: &str = "enc";
+
+/// Whether a file path is inside a private folder; the same test as `Path::starts_with`
+/// for either separator
+const IN_PRIVATE_FOLDER: &str = "EXISTS (SELECT 1 FROM private_folders p
+    WHERE substr({path}, 1, length(p.path) + 1) IN (p.path || '/', p.path || '\\'))";
+
+/// Key for encrypted thumbnails once the passphrase was entered
+static SESSION_KEY: RwLock<Option<Arc<EncryptionKey>>> = RwLock::new(None);
+
+/// Private items are shown; the key alone only opens the encrypted cache
+static PRIVATE_UNLOCKED: AtomicBool = AtomicBool::new(false);
+
+/// Private folders, kept in memory for checks on every thumbnail; `None` until first read
+static PRIVATE_FOLDERS: RwLock<Option<Vec<String>>> = RwLock::new(None);
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrivateStatus {
+    /// A passphrase was set, so folders can be made private
+    pub has_passphrase: bool,
+    pub unlocked: bool,
+    /// Encrypted thumbnails can be shown, after `unlock_private` or `unlock_cache`
+    pub cache_unlocked: bool,
+    /// Private folders; only listed while unlocked
+    pub folders: Vec<String>,
+}
+
+/// Tables for private folders and the passphrase, and the triggers that flag media in
+/// those folders as `private` however they get into the catalog
+pub fn create_private_tables(conn: &Connection) -> Result<()> {
+    conn.execute("CREATE TABLE IF NOT EXISTS private_folders (path TEXT PRIMARY KEY)", [])?;
+    // Argon2 hash of the passphrase that unlocks private items; a single row
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS private_passphrase (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            hash TEXT NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_media_private ON media_files(id) WHERE private", [])?;
+
+    let in_private_folder = IN_PRIVATE_FOLDER.replace("{path}", "NEW.file_path");
+    conn.execute_batch(&format!(
+        "CREATE TRIGGER IF NOT EXISTS mark_private_insert AFTER INSERT ON media_files BEGIN
+             UPDATE media_files SET private = {0} WHERE id = NEW.id;
+         END;
+         CREATE TRIGGER IF NOT EXISTS mark_private_move AFTER UPDATE OF file_path ON media_files BEGIN
+             UPDATE media_files SET private = {0} WHERE id = NEW.id;
+         END;",
+        in_private_folder
+    ))?;
+
+    load_private_folders(conn)?;
+    Ok(())
+}
+
+pub fn load_private_folders(conn: &Connection) -> Result<Vec<String>> {
+    // A read-only instance never set up the tables; an older catalog may lack them
+    let has_table = conn
+        .prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'private_folders'")?
+        .exists([])?;
+    if !has_table {
+        return Ok(Vec::new());
+    }
+
+    let folders: Vec<String> = conn
+        .prepare("SELECT path FROM private_folders ORDER BY path")?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    *PRIVATE_FOLDERS.write().unwrap_or_else(|e| e.into_inner()) = Some(folders.clone());
+    Ok(folders)
+}
+
+/// Whether `file_path` is inside a private folder. Fails rather than guessing when the
+/// folders can't be read, so a private thumbnail is never written in the clear.
+pub fn is_private_path(file_path: &str) -> Result<bool> {
+    let cached = PRIVATE_FOLDERS.read().unwrap_or_else(|e| e.into_inner()).clone();
+    let folders = match cached {
+        Some(folders) => folders,
+        None => load_private_folders(&init_database()?)?,
+    };
+    Ok(folders.iter().any(|folder| Path::new(file_path).starts_with(folder)))
+}
+
+/// SQL condition for media the session may show: everything while unlocked, only
+/// media outside private folders otherwise
+pub fn visible_condition() -> &'static str {
+    if is_private_unlocked() {
+        "1"
+    } else {
+        "NOT private"
+    }
+}
+
+/// SQL condition for media the session must not show; the opposite of `visible_condition`,
+/// written so the partial index on `private` finds them
+pub fn hidden_condition() -> &'static str {
+    if is_private_unlocked() {
+        "0"
+    } else {
+        "private"
+    }
+}
+
+/// For media shown to other devices (LAN gallery, DLNA), which never see private items
+pub const SHARED_CONDITION: &str = "NOT private";
+
+pub fn is_private_unlocked() -> bool {
+    PRIVATE_UNLOCKED.load(Ordering::Relaxed)
+}
+
+/// Key for encrypting and decrypting thumbnails; fails with `Locked` until the passphrase
+/// was entered
+pub fn private_key() -> Result<Arc<EncryptionKey>> {
+    SESSION_KEY
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .ok_or_else(|| PenglerError::Locked("Encrypted thumbnails are locked".to_string()).into())
+}
+
+/// Key to seal the thumbnail of `file_path` with, or `None` if it may be stored plain
+pub fn thumbnail_key(file_path: &str) -> Result<Option<Arc<EncryptionKey>>> {
+    if is_private_path(file_path)? {
+        if !is_private_unlocked() {
+            return Err(PenglerError::Locked("Private items are locked".to_string()).into());
+        }
+        return Ok(Some(private_key()?));
+    }
+    if thumbnails_encrypted() {
+        return Ok(Some(private_key()?));
+    }
+    Ok(None)
+}
+
+fn set_session(key: Option<EncryptionKey>, private_unlocked: bool) {
+    *SESSION_KEY.write().unwrap_or_else(|e| e.into_inner()) = key.map(Arc::new);
+    PRIVATE_UNLOCKED.store(private_unlocked, Ordering::Relaxed);
+}
+
+/// Whether a private passphrase was set
+pub fn has_private_passphrase(conn: &Connection) -> Result<bool> {
+    Ok(stored_hash(conn)?.is_some())
+}
+
+fn stored_hash(conn: &Connection) -> Result<Option<String>> {
+    Ok(conn.query_row("SELECT hash FROM private_passphrase WHERE id = 1", [], |row| row.get(0)).optional()?)
+}
+
+/// Thumbnail key from the passphrase; salted by its hash, so a new passphrase gives a new key
+fn derive_key(passphrase: &str, hash: &str) -> Result<EncryptionKey> {
+    EncryptionKey::derive(passphrase, &format!("private:{}", hash))
+}
+
+#[tauri::command]
+pub async fn get_private_status() -> Result<PrivateStatus, PenglerError> {
+    get_private_status_internal().map_err(|e| PenglerError::report("Failed to get private status", e))
+}
+
+fn get_private_status_internal() -> Result<PrivateStatus> {
+    let conn = init_database()?;
+    let unlocked = is_private_unlocked();
+    Ok(PrivateStatus {
+        has_passphrase: stored_hash(&conn)?.is_some(),
+        unlocked,
+        cache_unlocked: SESSION_KEY.read().unwrap_or_else(|e| e.into_inner()).is_some(),
+        folders: if unlocked { load_private_folders(&conn)? } else { Vec::new() },
+    })
+}
+
+/// Set the passphrase for private folders, or change it with the `current` one. Encrypted
+/// thumbnails are made again with the new key as they're viewed.
+#[tauri::command]
+pub async fn set_private_passphrase(passphrase: String, current: Option<String>) -> Result<PrivateStatus, PenglerError> {
+    set_private_passphrase_internal(&passphrase, current.as_deref())
+        .map_err(|e| PenglerError::report("Failed to set private passphrase", e))
+}
+
+fn set_private_passphrase_internal(passphrase: &str, current: Option<&str>) -> Result<PrivateStatus> {
+    if passphrase.is_empty() {
+        return Err(PenglerError::InvalidInput("The passphrase can't be empty".to_string()).into());
+    }
+    let conn = init_database()?;
+    if let Some(hash) = stored_hash(&conn)? {
+        if !verify_passphrase(current.unwrap_or_default(), &hash)? {
+            return Err(PenglerError::InvalidInput("The current passphrase is wrong".to_string()).into());
+        }
+    }
+
+    let hash = hash_passphrase(passphrase)?;
+    let key = derive_key(passphrase, &hash)?;
+    conn.execute(
+        "INSERT INTO private_passphrase (id, hash) VALUES (1, ?1) ON CONFLICT(id) DO UPDATE SET hash = excluded.hash",
+        [&hash],
+    )?;
+    set_session(Some(key), true);
+
+    // Sealed with the old key, so they can't be shown anymore
+    remove_cached_thumbnails(true)?;
+    get_private_status_internal()
+}
+
+/// Show private items in this session if `passphrase` is right; returns whether it was
+#[tauri::command]
+pub async fn unlock_private(passphrase: String) -> Result<bool, PenglerError> {
+    unlock_private_internal(&passphrase).map_err(|e| PenglerError::report("Failed to unlock private items", e))
+}
+
+fn unlock_private_internal(passphrase: &str) -> Result<bool> {
+    let Some(key) = unlock(passphrase)? else { return Ok(false) };
+    set_session(Some(key), true);
+    info!("Private items unlocked");
+    Ok(true)
+}
+
+/// Show encrypted thumbnails (see `encrypt_cache`) without showing private items;
+/// returns whether the passphrase was right
+#[tauri::command]
+pub async fn unlock_cache(passphrase: String) -> Result<bool, PenglerError> {
+    unlock_cache_internal(&passphrase).map_err(|e| PenglerError::report("Failed to unlock the thumbnail cache", e))
+}
+
+fn unlock_cache_internal(passphrase: &str) -> Result<bool> {
+    let Some(key) = unlock(passphrase)? else { return Ok(false) };
+    set_session(Some(key), is_private_unlocked());
+    info!("Thumbnail cache unlocked");
+    Ok(true)
+}
+
+/// The thumbnail key if `passphrase` is the private passphrase
+fn unlock(passphrase: &str) -> Result<Option<EncryptionKey>> {
+    let hash = stored_hash(&init_database()?)?
+        .ok_or_else(|| PenglerError::InvalidInput("No private passphrase has been set".to_string()))?;
+    if !verify_passphrase(passphrase, &hash)? {
+        warn!("Wrong private passphrase");
+        return Ok(None);
+    }
+    Ok(Some(derive_key(passphrase, &hash)?))
+}
+
+/// Hide private items again. With `encrypt_cache` the key stays, so other thumbnails
+/// can still be shown; `lock_cache` forgets it too.
+#[tauri::command]
+pub async fn lock_private() -> Result<(), PenglerError> {
+    if thumbnails_encrypted() {
+        PRIVATE_UNLOCKED.store(false, Ordering::Relaxed);
+    } else {
+        set_session(None, false);
+    }
+    info!("Private items locked");
+    Ok(())
+}
+
+/// Hide private items and encrypted thumbnails until the passphrase is entered again
+#[tauri::command]
+pub async fn lock_cache() -> Result<(), PenglerError> {
+    set_session(None, false);
+    info!("Thumbnail cache locked");
+    Ok(())
+}
+
+/// Make a folder (with its subfolders) private or public again. Only the unlocked
+/// session can make one public.
+#[tauri::command]
+pub async fn set_folder_private(folder: String, private: bool) -> Result<PrivateStatus, PenglerError> {
+    set_folder_private_internal(&folder, private).map_err(|e| PenglerError::report("Failed to set private folder", e))
+}
+
+fn set_folder_private_internal(folder: &str, private: bool) -> Result<PrivateStatus> {
+    let folder = folder.trim_end_matches(['/', '\\']);
+    if folder.is_empty() {
+        return Err(PenglerError::InvalidInput("No folder given".to_string()).into());
+    }
+    let conn = init_database()?;
+    if stored_hash(&conn)?.is_none() {
+        return Err(PenglerError::InvalidInput("Set a private passphrase first".to_string()).into());
+    }
+
+    if private {
+        conn.execute("INSERT OR IGNORE INTO private_folders (path) VALUES (?1)", [folder])?;
+    } else {
+        if !is_private_unlocked() {
+            return Err(PenglerError::Locked("Private items are locked".to_string()).into());
+        }
+        conn.execute("DELETE FROM private_folders WHERE path = ?1", [folder])?;
+    }
+    load_private_folders(&conn)?;
+    let (hidden, shown) = refresh_private_flags(&conn)?;
+    info!("{} {}: {} files hidden, {} shown", if private { "Made private" } else { "Made public" }, folder, hidden, shown);
+    get_private_status_internal()
+}
+
+/// Flag media by the current private folders and drop the plain thumbnails of files that
+/// just became private. Returns how many were hidden and shown.
+fn refresh_private_flags(conn: &Connection) -> Result<(usize, usize)> {
+    let in_private_folder = IN_PRIVATE_FOLDER.replace("{path}", "media_files.file_path");
+    let newly_private: Vec<String> = conn
+        .prepare(&format!("SELECT DISTINCT file_hash FROM media_files WHERE NOT private AND {}", in_private_folder))?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let tx = conn.unchecked_transaction()?;
+    let hidden = tx.execute(&format!("UPDATE media_files SET private = 1 WHERE NOT private AND {}", in_private_folder), [])?;
+    let shown = tx.execute(&format!("UPDATE media_files SET private = 0 WHERE private AND NOT {}", in_private_folder), [])?;
+    tx.commit()?;
+
+    for file_hash in newly_private {
+        remove_exposed_thumbnails(conn, &file_hash)?;
+    }
+    Ok((hidden, shown))
+}
+
+/// Remove the plain thumbnails of a file that became private, e.g. by being moved into a
+/// private folder; a copy outside the private folders still shows the same picture in
+/// the open, so they stay while there is one
+pub fn remove_exposed_thumbnails(conn: &Connection, file_hash: &str) -> Result<()> {
+    let public_copy = conn
+        .prepare("SELECT 1 FROM media_files WHERE file_hash = ?1 AND NOT private LIMIT 1")?
+        .exists(params![file_hash])?;
+    if !public_copy {
+        remove_plain_thumbnails(file_hash)?;
+    }
+    Ok(())
+}
+
+/// Point private folders at or below `old` to the same place under `new`, for a library
+/// folder that moved. Run before the media paths change so the trigger flags them by the
+/// new folders; `load_private_folders` once committed.
+pub fn relink_private_folders(conn: &Connection, old: &Path, new: &Path) -> Result<usize> {
+    let folders: Vec<String> = conn
+        .prepare("SELECT path FROM private_folders")?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut relinked = 0;
+    for folder in folders {
+        if let Some(moved) = relinked_path(&folder, old, new) {
+            conn.execute("UPDATE OR REPLACE private_folders SET path = ?1 WHERE path = ?2", params![moved, folder])?;
+            relinked += 1;
+        }
+    }
+    Ok(relinked)
+}
+
+/// Serve a decrypted thumbnail for the `private` URI scheme. Only files in the thumbnail
+/// cache are served, only with the key, and those of private files only while unlocked.
+pub fn serve_private_thumbnail(request: &Request<Vec<u8>>) -> Response<Cow<'static, [u8]>> {
+    let respond = |status: StatusCode, body: Vec<u8>| {
+        Response::builder()
+            .status(status)
+            .header(header::CONTENT_TYPE, if status == StatusCode::OK { "image/webp" } else { "text/plain" })
+            .body(Cow::Owned(body))
+            .unwrap_or_default()
+    };
+
+    // The frontend passes the whole thumbnail path, URL-encoded; its file name is all that counts
+    let path = request.uri().path().replace("%2F", "/").replace("%5C", "/");
+    let name = path.rsplit(['/', '\\']).next().unwrap_or_default();
+    let valid = name.ends_with(&format!(".webp.{}", ENCRYPTED_THUMBNAIL_EXTENSION))
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+    if !valid {
+        return respond(StatusCode::NOT_FOUND, b"Not found".to_vec());
+    }
+
+    let key = name.split(['-', '.']).next().unwrap_or_default();
+    if private_key().is_err() || (!is_private_unlocked() && shows_only_private(key).unwrap_or(true)) {
+        return respond(StatusCode::FORBIDDEN, b"Locked".to_vec());
+    }
+
+    let path = match get_cache_directory() {
+        Ok(dir) => dir.join("thumbnails").join(name),
+        Err(_) => return respond(StatusCode::NOT_FOUND, b"Not found".to_vec()),
+    };
+    if !path.is_file() {
+        return respond(StatusCode::NOT_FOUND, b"Not found".to_vec());
+    }
+    match read_thumbnail(&path) {
+        Ok(plain) => respond(StatusCode::OK, plain),
+        Err(e) => {
+            warn!("Failed to decrypt thumbnail {}: {}", name, e);
+            respond(StatusCode::INTERNAL_SERVER_ERROR, b"Failed to decrypt".to_vec())
+        }
+    }
+}
+
+/// Whether the thumbnail key (`short_hash`) belongs to private files only
+fn shows_only_private(key: &str) -> Result<bool> {
+    let conn = init_database()?;
+    let mut stmt = conn.prepare(
+        "SELECT COALESCE(MIN(private), 0) FROM media_files WHERE file_hash >= ?1 AND file_hash < ?1 || 'g'",
+    )?;
+    Ok(stmt.query_row([key], |row| row.get(0))?)
+}
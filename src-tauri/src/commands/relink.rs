@@ -0,0 +1,295 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use tauri::AppHandle;
+use anyhow::Result;
+use tracing::info;
+
+use crate::error::PenglerError;
+use crate::config::Config;
+use crate::utils::{hash_file, quick_hash_file};
+use crate::commands::cache::{forget_media, init_database};
+use crate::commands::first_run::drive_roots;
+use crate::commands::private::{load_private_folders, relink_private_folders};
+use crate::commands::search::folder_range;
+use crate::commands::smart_albums::{notify_smart_albums_changed, relink_smart_album_folders};
+
+/// Files checked at the new location before anything is rewritten
+const VERIFY_SAMPLE: usize = 20;
+
+/// Share of sampled files that must be there unchanged; files deleted or edited while
+/// the folder was offline make up the rest
+const MIN_MATCHING_SHARE: f64 = 0.9;
+
+/// Leading folders dropped when looking for a moved folder on other drives, so
+/// "/media/alice/Old Disk/Photos" is also looked for as "Photos"
+const MAX_STRIPPED_COMPONENTS: usize = 3;
+
+/// Tables besides media_files keyed by the path of an original
+const PATH_COLUMNS: &[(&str, &str)] = &[
+    ("file_hashes", "file_path"),
+    ("backup_files", "source_path"),
+    ("cloud_backup_files", "source_path"),
+    ("cloud_uploads", "source_path"),
+];
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelinkResult {
+    /// Catalog entries now pointing into the new folder
+    pub relinked: usize,
+    /// Files hashed at the new location, and how many of them matched
+    pub sampled: usize,
+    pub matched: usize,
+    /// Entries a scan of the new folder had already added, dropped for the relinked ones
+    /// so tags, ratings and edits carry over
+    pub replaced: usize,
+}
+
+/// A folder that may be where an offline library folder went
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelinkCandidate {
+    pub path: String,
+    /// Sampled files found there with their cataloged size
+    pub matched: usize,
+    pub sampled: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelinkSuggestion {
+    /// The library folder that can't be found
+    pub folder: String,
+    /// Best match first
+    pub candidates: Vec<RelinkCandidate>,
+}
+
+struct SampleFile {
+    file_path: String,
+    file_hash: String,
+    file_size: i64,
+}
+
+/// Point the catalog at a library folder that moved, e.g. to another drive letter or
+/// mount point. A sample of files is hashed at the new location first; then media,
+/// hash and backup records, private folders, smart albums and the folder list follow.
+#[tauri::command]
+pub async fn relink_folder(app: AppHandle, old_path: String, new_path: String) -> Result<RelinkResult, PenglerError> {
+    let result = relink_folder_internal(&old_path, &new_path)
+        .map_err(|e| PenglerError::report("Failed to relink folder", e))?;
+    notify_smart_albums_changed(&app);
+    Ok(result)
+}
+
+fn relink_folder_internal(old_path: &str, new_path: &str) -> Result<RelinkResult> {
+    let old = old_path.trim_end_matches(['/', '\\']);
+    let new = new_path.trim_end_matches(['/', '\\']);
+    if old.is_empty() || new.is_empty() {
+        return Err(PenglerError::InvalidInput("No folder given".to_string()).into());
+    }
+    if Path::new(new).starts_with(old) || Path::new(old).starts_with(new) {
+        return Err(PenglerError::InvalidInput(format!("{} and {} overlap", old, new)).into());
+    }
+    if !Path::new(new).is_dir() {
+        return Err(PenglerError::NotFound(format!("{} is not a folder", new)).into());
+    }
+
+    let mut config = Config::load()?;
+    let holds_library_folder = config.folders.iter().any(|folder| Path::new(&folder.path).starts_with(old));
+    if !holds_library_folder && config.folder_settings(Path::new(old)).is_none() {
+        return Err(PenglerError::InvalidInput(format!("{} is not a library folder", old)).into());
+    }
+    // A subfolder moved out of its library folder would no longer be scanned
+    if !holds_library_folder && config.folder_settings(Path::new(new)).is_none() {
+        return Err(PenglerError::InvalidInput(format!("{} is not inside a library folder", new)).into());
+    }
+
+    let mut conn = init_database()?;
+    let mut result = RelinkResult::default();
+
+    let sample = sample_files(&conn, old)?;
+    result.sampled = sample.len();
+    result.matched = sample
+        .iter()
+        .filter(|file| {
+            let network = config.folder_settings(Path::new(&file.file_path)).is_some_and(|folder| folder.network);
+            same_content(&moved_path(&file.file_path, old, new), &file.file_hash, network)
+        })
+        .count();
+    if (result.matched as f64) < result.sampled as f64 * MIN_MATCHING_SHARE {
+        return Err(PenglerError::InvalidInput(format!(
+            "{} doesn't look like {}: {} of {} sampled files are missing or changed",
+            new,
+            old,
+            result.sampled - result.matched,
+            result.sampled
+        ))
+        .into());
+    }
+
+    let (lower, upper) = folder_range(old);
+    let stale: Vec<i64> = conn
+        .prepare(
+            "SELECT stale.id FROM media_files moved
+             JOIN media_files stale ON stale.file_path = ?1 || substr(moved.file_path, length(?2) + 1)
+             WHERE moved.file_path >= ?3 AND moved.file_path < ?4",
+        )?
+        .query_map(params![new, old, lower, upper], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    for &media_id in &stale {
+        forget_media(&conn, media_id)?;
+    }
+    result.replaced = stale.len();
+
+    let tx = conn.transaction()?;
+    // Private folders first, so the trigger flags moved media by their new location
+    relink_private_folders(&tx, Path::new(old), Path::new(new))?;
+    result.relinked = tx.execute(
+        "UPDATE media_files SET file_path = ?1 || substr(file_path, length(?2) + 1)
+         WHERE file_path >= ?3 AND file_path < ?4",
+        params![new, old, lower, upper],
+    )?;
+    for (table, column) in PATH_COLUMNS {
+        tx.execute(
+            &format!(
+                "UPDATE OR REPLACE {0} SET {1} = ?1 || substr({1}, length(?2) + 1) WHERE {1} >= ?3 AND {1} < ?4",
+                table, column
+            ),
+            params![new, old, lower, upper],
+        )?;
+    }
+    let smart_albums = relink_smart_album_folders(&tx, Path::new(old), Path::new(new))?;
+    tx.commit()?;
+    load_private_folders(&conn)?;
+
+    let mut seen = HashSet::new();
+    for folder in &mut config.folders {
+        if let Some(moved) = relinked_path(&folder.path, Path::new(old), Path::new(new)) {
+            folder.path = moved;
+        }
+    }
+    // The new location may have been added as a library folder of its own already
+    config.folders.retain(|folder| seen.insert(folder.path.clone()));
+    config.save()?;
+
+    info!(
+        "Relinked {} to {}: {} files, {} of {} sampled files verified, {} rescanned entries replaced, {} smart albums updated",
+        old,
+        new,
+        result.relinked,
+        result.matched,
+        result.sampled,
+        result.replaced,
+        smart_albums
+    );
+    Ok(result)
+}
+
+/// Likely new locations of library folders that can't be found: folders with the same
+/// trailing path on other drives and mount points, scored by how many cataloged files
+/// are there
+#[tauri::command]
+pub async fn suggest_relink_locations() -> Result<Vec<RelinkSuggestion>, PenglerError> {
+    suggest_relink_locations_internal().map_err(|e| PenglerError::report("Failed to look for moved folders", e))
+}
+
+fn suggest_relink_locations_internal() -> Result<Vec<RelinkSuggestion>> {
+    let config = Config::load()?;
+    let conn = init_database()?;
+    let mut roots = drive_roots();
+    roots.extend(dirs::home_dir());
+
+    let mut suggestions = Vec::new();
+    for folder in config.folders.iter().filter(|folder| !Path::new(&folder.path).is_dir()) {
+        let old = folder.path.trim_end_matches(['/', '\\']);
+        let sample = sample_files(&conn, old)?;
+
+        let mut candidates: Vec<RelinkCandidate> = candidate_locations(Path::new(old), &roots)
+            .into_iter()
+            .filter_map(|path| {
+                let path = path.to_string_lossy().to_string();
+                let matched = sample
+                    .iter()
+                    .filter(|file| {
+                        fs::metadata(moved_path(&file.file_path, old, &path))
+                            .is_ok_and(|metadata| metadata.len() as i64 == file.file_size)
+                    })
+                    .count();
+                (matched > 0 || sample.is_empty()).then_some(RelinkCandidate { path, matched, sampled: sample.len() })
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.matched.cmp(&a.matched).then_with(|| a.path.cmp(&b.path)));
+
+        suggestions.push(RelinkSuggestion { folder: folder.path.clone(), candidates });
+    }
+    Ok(suggestions)
+}
+
+/// Where `path` ends up when `old` moves to `new`; `None` if it isn't at or below `old`
+pub fn relinked_path(path: &str, old: &Path, new: &Path) -> Option<String> {
+    let rest = Path::new(path).strip_prefix(old).ok()?;
+    let moved = if rest.as_os_str().is_empty() { new.to_path_buf() } else { new.join(rest) };
+    Some(moved.to_string_lossy().to_string())
+}
+
+/// `file_path` below `old` rewritten like the catalog update does it
+fn moved_path(file_path: &str, old: &str, new: &str) -> PathBuf {
+    PathBuf::from(format!("{}{}", new, &file_path[old.len()..]))
+}
+
+/// Random cataloged files below `folder`, leaving out cloud placeholders that would
+/// have to be downloaded to be read
+fn sample_files(conn: &Connection, folder: &str) -> Result<Vec<SampleFile>> {
+    let (lower, upper) = folder_range(folder);
+    let files = conn
+        .prepare(
+            "SELECT file_path, file_hash, file_size FROM media_files
+             WHERE file_path >= ?1 AND file_path < ?2 AND NOT online_only
+             ORDER BY RANDOM() LIMIT ?3",
+        )?
+        .query_map(params![lower, upper, VERIFY_SAMPLE as i64], |row| {
+            Ok(SampleFile {
+                file_path: row.get(0)?,
+                file_hash: row.get(1)?,
+                file_size: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(files)
+}
+
+/// Whether the file at `path` has `file_hash`, as a full hash or the quick hash network
+/// folders are scanned with; the folder's current kind is tried first
+fn same_content(path: &Path, file_hash: &str, network: bool) -> bool {
+    let hashers: [fn(&Path) -> Result<String>; 2] =
+        if network { [quick_hash_file, hash_file] } else { [hash_file, quick_hash_file] };
+    hashers.iter().any(|hash| hash(path).is_ok_and(|hash| hash == file_hash))
+}
+
+/// Existing folders with the trailing path of `old` under each root, e.g. "F:\Photos"
+/// for "E:\Photos"
+fn candidate_locations(old: &Path, roots: &[PathBuf]) -> Vec<PathBuf> {
+    let names: Vec<_> = old
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name),
+            _ => None,
+        })
+        .collect();
+
+    let mut seen = HashSet::new();
+    let mut candidates = Vec::new();
+    for skip in 0..names.len().min(MAX_STRIPPED_COMPONENTS + 1) {
+        let rest: PathBuf = names[skip..].iter().collect();
+        for root in roots {
+            let candidate = root.join(&rest);
+            if candidate != old && candidate.is_dir() && seen.insert(candidate.clone()) {
+                candidates.push(candidate);
+            }
+        }
+    }
+    candidates
+}
@@ -4,42 +4,44 @@ use std::hash::{Hash, Hasher};
 use walkdir::WalkDir;
 use rayon::prelude::*;
 use anyhow::Result;
+use tracing::info;
 
-use crate::models::{MediaFile, MediaType, is_media_file};
-use crate::utils::{hash_file, extract_date_taken, get_image_dimensions};
+use crate::error::PenglerError;
+use crate::config::{Config, FolderSettings};
+use crate::models::{MediaFile, is_media_file};
+use crate::utils::{acquire_slot, build_worker_pool, run_in_worker_pool, OperationTimer, Priority, Workload};
+use crate::commands::cache::init_database;
+use crate::commands::ingest::{process_file, IngestContext};
+
+/// Files read at once when scanning a network share
+const NETWORK_SCAN_THREADS: usize = 4;
 
 #[tauri::command]
-pub async fn scan_folder(path: String) -> Result<Vec<MediaFile>, String> {
-    println!("Scanning folder: {}", path);
+pub async fn scan_folder(path: String) -> Result<Vec<MediaFile>, PenglerError> {
+    info!("Scanning folder: {}", path);
 
     let folder_path = PathBuf::from(&path);
     if !folder_path.exists() || !folder_path.is_dir() {
-        return Err("Invalid folder path".to_string());
+        return Err(PenglerError::NotFound(format!("Invalid folder path: {}", path)));
     }
 
-    // Collect all media files
-    let entries: Vec<PathBuf> = WalkDir::new(&folder_path)
-        .follow_links(true)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .filter_map(|e| {
-            let path = e.path();
-            if is_media_file(path.to_str()?).is_some() {
-                Some(path.to_path_buf())
-            } else {
-                None
-            }
-        })
-        .collect();
-
-    println!("Found {} media files", entries.len());
+    scan_folder_internal(&folder_path).map_err(|e| PenglerError::report("Failed to scan folder", e))
+}
 
-    // Process files in parallel
-    let mut media_files: Vec<MediaFile> = entries
-        .par_iter()
-        .filter_map(|path| process_media_file(path).ok())
-        .collect();
+/// Read every media file under an existing folder. The catalog is left as it is; the
+/// results are saved with `save_media_files`.
+pub fn scan_folder_internal(folder_path: &Path) -> Result<Vec<MediaFile>> {
+    let timer = OperationTimer::start("scan");
+
+    // Files are read as the walk finds them, so huge trees never sit in memory as a path list
+    let settings = Config::load().ok().and_then(|config| config.folder_settings(folder_path).cloned());
+    let network = settings.as_ref().is_some_and(|settings| settings.network);
+    let entries = candidate_files(folder_path, settings);
+    let mut media_files: Vec<MediaFile> = if network {
+        scan_network_files(entries)?
+    } else {
+        scan_local_files(entries)?
+    };
 
     // Assign unique IDs based on file path hash
     for file in media_files.iter_mut() {
@@ -48,49 +50,68 @@ pub async fn scan_folder(path: String) -> Result<Vec<MediaFile>, String> {
         file.id = (hasher.finish() & 0x7FFFFFFFFFFFFFFF) as i64; // Ensure positive i64
     }
 
-    println!("Processed {} media files", media_files.len());
+    info!("Processed {} media files", media_files.len());
+    let bytes = media_files.iter().map(|file| file.file_size.max(0) as u64).sum();
+    timer.finish(media_files.len(), bytes);
 
     Ok(media_files)
 }
 
-fn process_media_file(path: &Path) -> Result<MediaFile> {
-    let file_path = path.to_string_lossy().to_string();
-    let media_type = is_media_file(&file_path)
-        .ok_or_else(|| anyhow::anyhow!("Not a media file"))?;
-
-    // Get file metadata
-    let metadata = std::fs::metadata(path)?;
-    let file_size = metadata.len() as i64;
-    let modified = metadata.modified()?;
-    let modified_at = chrono::DateTime::<chrono::Utc>::from(modified);
-
-    // Calculate file hash
-    let file_hash = hash_file(path)?;
-
-    // Get dimensions
-    let (width, height) = match media_type {
-        MediaType::Image => get_image_dimensions(path).unwrap_or((0, 0)),
-        MediaType::Video => (0, 0), // TODO: Extract video dimensions
-    };
-
-    // Extract EXIF date
-    let taken_at = if media_type == MediaType::Image {
-        extract_date_taken(path)
-    } else {
-        None
-    };
-
-    let mut media = MediaFile::new(
-        file_path,
-        file_hash,
-        file_size,
-        width as i32,
-        height as i32,
-        media_type,
-    );
+/// Media files under `folder`, yielded as the walk reaches them. Names excluded by the
+/// library folder's settings are skipped, along with everything inside such folders.
+fn candidate_files(folder: &Path, settings: Option<FolderSettings>) -> impl Iterator<Item = PathBuf> + Send {
+    WalkDir::new(folder)
+        .follow_links(true)
+        .into_iter()
+        .filter_entry(move |e| {
+            e.depth() == 0 || !settings.as_ref().is_some_and(|s| s.is_excluded(&e.file_name().to_string_lossy()))
+        })
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let path = e.path();
+            // Extensionless files are kept so their content can be sniffed
+            if is_media_file(path.to_str()?).is_some() || path.extension().is_none() {
+                Some(e.into_path())
+            } else {
+                None
+            }
+        })
+}
 
-    media.taken_at = taken_at;
-    media.modified_at = modified_at;
+/// Rescans skip reading files whose size and modification time haven't changed
+fn scan_local_files(entries: impl Iterator<Item = PathBuf> + Send) -> Result<Vec<MediaFile>> {
+    let conn = init_database()?;
+    let ctx = IngestContext::local(&conn)?;
+
+    let media_files = run_in_worker_pool(Workload::Scan, || {
+        entries
+            .par_bridge()
+            .filter_map(|path| {
+                let _slot = acquire_slot(Priority::Batch);
+                process_file(&path, &ctx).ok()
+            })
+            .collect()
+    })?;
+
+    ctx.save(&conn)?;
+    Ok(media_files)
+}
 
-    Ok(media)
+/// Network shares slow down under many concurrent reads, and drop connections now and
+/// then; read fewer files at once, quick-hash them and retry transient failures
+fn scan_network_files(entries: impl Iterator<Item = PathBuf> + Send) -> Result<Vec<MediaFile>> {
+    let pool = build_worker_pool(NETWORK_SCAN_THREADS)?;
+    info!("Scanning a network folder: quick hashes, {} parallel reads", pool.current_num_threads());
+
+    let ctx = IngestContext::network();
+    Ok(pool.install(|| {
+        entries
+            .par_bridge()
+            .filter_map(|path| {
+                let _slot = acquire_slot(Priority::Batch);
+                process_file(&path, &ctx).ok()
+            })
+            .collect()
+    }))
 }
@@ -0,0 +1,379 @@
+use chrono::{DateTime, Utc};
+use rusqlite::params_from_iter;
+use rusqlite::types::Value;
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+
+use crate::error::PenglerError;
+use crate::models::{MediaFile, MediaType, PickState};
+use crate::commands::cache::{init_database, media_file_from_row, MEDIA_COLUMNS, MEDIA_COLUMN_COUNT};
+use crate::commands::tags::descendant_pattern;
+use crate::commands::ocr::fts_phrase;
+use crate::commands::suggest::FOLDER_EXPRESSION;
+use crate::commands::private::{visible_condition, SHARED_CONDITION};
+
+/// Page size used when the caller doesn't pass a limit
+const DEFAULT_LIMIT: u32 = 500;
+
+/// Day a file was taken, or modified when there is no capture date, as "YYYY-MM-DD".
+/// Both are local days: taken_at is wall-clock time and modified_day is in the date timezone.
+pub const DAY_EXPRESSION: &str = "COALESCE(substr(taken_at, 1, 10), modified_day)";
+
+/// SQL expression for the file name without its folder
+pub fn file_name_expression() -> String {
+    format!("substr(file_path, length({}) + 1)", FOLDER_EXPRESSION)
+}
+
+/// Filters for `search_media`; every field is optional and all given filters must match
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MediaFilters {
+    /// Matched against file path, camera and tag names
+    pub text: Option<String>,
+    pub taken_after: Option<DateTime<Utc>>,
+    pub taken_before: Option<DateTime<Utc>>,
+    pub media_type: Option<MediaType>,
+    /// Only files inside this folder (recursively)
+    pub folder: Option<String>,
+    /// Files must carry all of these tags
+    pub tags: Vec<String>,
+    /// Let a tag also match its nested tags, so "Travel" finds "Travel/Japan"
+    pub include_tag_descendants: bool,
+    /// Files must show all of these people
+    pub person_ids: Vec<i64>,
+    pub min_rating: Option<i32>,
+    pub favorites_only: bool,
+    pub pick: Option<PickState>,
+    pub has_gps: Option<bool>,
+    pub camera: Option<String>,
+    pub min_width: Option<i32>,
+    pub min_height: Option<i32>,
+    /// Video duration range in seconds
+    pub min_duration: Option<f64>,
+    pub max_duration: Option<f64>,
+    /// Show only the cover of each burst stack
+    pub collapse_stacks: bool,
+    pub sort: MediaSort,
+    pub offset: u32,
+    pub limit: Option<u32>,
+    /// Listed for another device (LAN gallery, DLNA), which never sees private items
+    #[serde(skip)]
+    pub shared: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SortKey {
+    TakenAt,
+    ModifiedAt,
+    Name,
+    Size,
+    Rating,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaSort {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl Default for MediaSort {
+    /// Newest first
+    fn default() -> Self {
+        Self { key: SortKey::TakenAt, descending: true }
+    }
+}
+
+/// How `list_media` splits its results into sections
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GroupBy {
+    /// Day the photo was taken, or modified when there is no capture date
+    Day,
+    Folder,
+}
+
+impl GroupBy {
+    /// SQL expression for the group key of a row
+    fn expression(self) -> &'static str {
+        match self {
+            GroupBy::Day => DAY_EXPRESSION,
+            GroupBy::Folder => FOLDER_EXPRESSION,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    pub items: Vec<MediaFile>,
+    /// Number of matches ignoring offset and limit
+    pub total: i64,
+}
+
+#[tauri::command]
+pub async fn search_media(filters: MediaFilters) -> Result<SearchResult, PenglerError> {
+    search_media_internal(&filters)
+        .map_err(|e| PenglerError::report("Failed to search media", e))
+}
+
+pub fn search_media_internal(filters: &MediaFilters) -> Result<SearchResult> {
+    let conn = init_database()?;
+    let (where_clause, values) = build_filter(filters)?;
+
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM media_files {}", where_clause),
+        params_from_iter(values.iter()),
+        |row| row.get(0),
+    )?;
+
+    let mut page_values = values;
+    page_values.push(Value::from(filters.limit.unwrap_or(DEFAULT_LIMIT) as i64));
+    page_values.push(Value::from(filters.offset as i64));
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM media_files {} ORDER BY {} LIMIT ? OFFSET ?",
+        MEDIA_COLUMNS, where_clause, order_clause(&filters.sort, None)
+    ))?;
+    let items = stmt
+        .query_map(params_from_iter(page_values.iter()), media_file_from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(SearchResult { items, total })
+}
+
+/// A run of consecutive items in a `MediaListing` that share a group key
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaGroupHint {
+    /// "YYYY-MM-DD" for days, the folder path (with trailing separator) for folders
+    pub key: String,
+    /// Index of the first item of the group in `items`
+    pub start: usize,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaListing {
+    pub items: Vec<MediaFile>,
+    /// Sections of `items`, in order; empty when no grouping was asked for
+    pub groups: Vec<MediaGroupHint>,
+    /// Number of matches ignoring offset and limit
+    pub total: i64,
+}
+
+/// Like `search_media`, but ordered so that items of a group are adjacent and with
+/// the group boundaries of the returned page computed alongside
+#[tauri::command]
+pub async fn list_media(filters: MediaFilters, group_by: Option<GroupBy>) -> Result<MediaListing, PenglerError> {
+    list_media_internal(&filters, group_by)
+        .map_err(|e| PenglerError::report("Failed to list media", e))
+}
+
+fn list_media_internal(filters: &MediaFilters, group_by: Option<GroupBy>) -> Result<MediaListing> {
+    let Some(group_by) = group_by else {
+        let SearchResult { items, total } = search_media_internal(filters)?;
+        return Ok(MediaListing { items, groups: Vec::new(), total });
+    };
+
+    let conn = init_database()?;
+    let (where_clause, values) = build_filter(filters)?;
+
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM media_files {}", where_clause),
+        params_from_iter(values.iter()),
+        |row| row.get(0),
+    )?;
+
+    let mut page_values = values;
+    page_values.push(Value::from(filters.limit.unwrap_or(DEFAULT_LIMIT) as i64));
+    page_values.push(Value::from(filters.offset as i64));
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, {} FROM media_files {} ORDER BY {} LIMIT ? OFFSET ?",
+        MEDIA_COLUMNS,
+        group_by.expression(),
+        where_clause,
+        order_clause(&filters.sort, Some(group_by))
+    ))?;
+    let rows = stmt.query_map(params_from_iter(page_values.iter()), |row| {
+        Ok((media_file_from_row(row)?, row.get::<_, Option<String>>(MEDIA_COLUMN_COUNT)?.unwrap_or_default()))
+    })?;
+
+    let mut items = Vec::new();
+    let mut groups: Vec<MediaGroupHint> = Vec::new();
+    for row in rows {
+        let (file, key) = row?;
+        match groups.last_mut() {
+            Some(group) if group.key == key => group.count += 1,
+            _ => groups.push(MediaGroupHint { key, start: items.len(), count: 1 }),
+        }
+        items.push(file);
+    }
+
+    Ok(MediaListing { items, groups, total })
+}
+
+/// ORDER BY terms for a sort, keeping groups together when grouping.
+/// Groups follow the direction of the sort; ties fall back to newest first.
+pub fn order_clause(sort: &MediaSort, group_by: Option<GroupBy>) -> String {
+    let direction = if sort.descending { "DESC" } else { "ASC" };
+
+    let mut terms: Vec<String> = Vec::new();
+    if let Some(group_by) = group_by {
+        terms.push(format!("{} {}", group_by.expression(), direction));
+    }
+
+    match sort.key {
+        SortKey::TakenAt => {
+            terms.push(format!("taken_at {}", direction));
+            terms.push(format!("modified_at {}", direction));
+        }
+        SortKey::ModifiedAt => terms.push(format!("modified_at {}", direction)),
+        SortKey::Name => terms.push(format!("{} COLLATE NOCASE {}", file_name_expression(), direction)),
+        SortKey::Size => terms.push(format!("file_size {}", direction)),
+        SortKey::Rating => terms.push(format!("rating {}", direction)),
+    }
+
+    if sort.key != SortKey::TakenAt {
+        terms.push("taken_at DESC".to_string());
+    }
+    terms.push("id DESC".to_string());
+
+    terms.join(", ")
+}
+
+/// Compile the filters into a WHERE clause with positional parameters
+pub fn build_filter(filters: &MediaFilters) -> Result<(String, Vec<Value>)> {
+    let mut conditions: Vec<String> = vec![if filters.shared { SHARED_CONDITION } else { visible_condition() }.to_string()];
+    let mut values: Vec<Value> = Vec::new();
+
+    if let Some(text) = filters.text.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+        let pattern = format!("%{}%", escape_like(text));
+        conditions.push(
+            "(file_path LIKE ? ESCAPE '\\' OR camera_model LIKE ? ESCAPE '\\'
+              OR id IN (SELECT mt.media_id FROM media_tags mt JOIN tags t ON t.id = mt.tag_id
+                        WHERE t.name LIKE ? ESCAPE '\\')
+              OR id IN (SELECT rowid FROM media_text WHERE media_text MATCH ?))"
+                .to_string(),
+        );
+        values.extend(std::iter::repeat_n(Value::from(pattern), 3));
+        values.push(Value::from(fts_phrase(text)));
+    }
+
+    if let Some(after) = filters.taken_after {
+        conditions.push("taken_at >= ?".to_string());
+        values.push(Value::from(after.to_rfc3339()));
+    }
+    if let Some(before) = filters.taken_before {
+        conditions.push("taken_at <= ?".to_string());
+        values.push(Value::from(before.to_rfc3339()));
+    }
+
+    if let Some(media_type) = &filters.media_type {
+        conditions.push("media_type = ?".to_string());
+        values.push(Value::from(serde_json::to_string(media_type)?));
+    }
+
+    if let Some(folder) = &filters.folder {
+        conditions.push(folder_condition(folder, &mut values));
+    }
+
+    for tag in &filters.tags {
+        conditions.push(tag_condition(tag, filters.include_tag_descendants, &mut values));
+    }
+
+    for person_id in &filters.person_ids {
+        conditions.push("id IN (SELECT media_id FROM face_regions WHERE person_id = ?)".to_string());
+        values.push(Value::from(*person_id));
+    }
+
+    if let Some(min_rating) = filters.min_rating {
+        conditions.push("rating >= ?".to_string());
+        values.push(Value::from(min_rating));
+    }
+
+    if filters.favorites_only {
+        conditions.push("favorite".to_string());
+    }
+
+    if let Some(pick) = filters.pick {
+        conditions.push("pick = ?".to_string());
+        values.push(Value::from(pick.to_db()));
+    }
+
+    match filters.has_gps {
+        Some(true) => conditions.push("latitude IS NOT NULL AND longitude IS NOT NULL".to_string()),
+        Some(false) => conditions.push("(latitude IS NULL OR longitude IS NULL)".to_string()),
+        None => {}
+    }
+
+    if let Some(camera) = &filters.camera {
+        conditions.push("camera_model = ? COLLATE NOCASE".to_string());
+        values.push(Value::from(camera.clone()));
+    }
+
+    if let Some(min_width) = filters.min_width {
+        conditions.push("width >= ?".to_string());
+        values.push(Value::from(min_width));
+    }
+    if let Some(min_height) = filters.min_height {
+        conditions.push("height >= ?".to_string());
+        values.push(Value::from(min_height));
+    }
+
+    if let Some(min_duration) = filters.min_duration {
+        conditions.push("duration >= ?".to_string());
+        values.push(Value::from(min_duration));
+    }
+    if let Some(max_duration) = filters.max_duration {
+        conditions.push("duration <= ?".to_string());
+        values.push(Value::from(max_duration));
+    }
+
+    if filters.collapse_stacks {
+        conditions.push("(stack_id IS NULL OR id IN (SELECT cover_id FROM stacks))".to_string());
+    }
+
+    let where_clause = format!("WHERE {}", conditions.join(" AND "));
+
+    Ok((where_clause, values))
+}
+
+/// Files inside `folder`, recursively. Written as a range of paths so the file_path
+/// index finds them: everything from "folder/" up to, not including, "folder0".
+pub fn folder_condition(folder: &str, values: &mut Vec<Value>) -> String {
+    let (lower, upper) = folder_range(folder);
+    values.push(Value::from(lower));
+    values.push(Value::from(upper));
+    "(file_path >= ? AND file_path < ?)".to_string()
+}
+
+/// Bounds of the paths below `folder`, for `path >= lower AND path < upper`
+pub fn folder_range(folder: &str) -> (String, String) {
+    let folder = folder.trim_end_matches(['/', '\\']);
+    let separator = std::path::MAIN_SEPARATOR;
+    let after_separator = char::from(separator as u8 + 1);
+    (format!("{}{}", folder, separator), format!("{}{}", folder, after_separator))
+}
+
+/// Files tagged with `tag`, or with one of its nested tags when `include_descendants` is set
+pub fn tag_condition(tag: &str, include_descendants: bool, values: &mut Vec<Value>) -> String {
+    values.push(Value::from(tag.to_string()));
+    if include_descendants {
+        values.push(Value::from(descendant_pattern(tag)));
+        "id IN (SELECT mt.media_id FROM media_tags mt JOIN tags t ON t.id = mt.tag_id
+                WHERE t.name = ? OR t.name LIKE ? ESCAPE '\\')"
+            .to_string()
+    } else {
+        "id IN (SELECT mt.media_id FROM media_tags mt JOIN tags t ON t.id = mt.tag_id WHERE t.name = ?)"
+            .to_string()
+    }
+}
+
+/// Escape LIKE wildcards so user text matches literally
+pub fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use rusqlite::{params_from_iter, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+
+use crate::error::PenglerError;
+use crate::models::MediaFile;
+use crate::utils::{blob_to_embedding, dot};
+use crate::commands::cache::{init_database, media_file_from_row, MEDIA_COLUMNS};
+use crate::commands::private::hidden_condition;
+
+/// Emitted while `index_embeddings` works through the library
+#[cfg(feature = "semantic-search")]
+pub const SEMANTIC_INDEX_PROGRESS_EVENT: &str = "semantic-index-progress";
+
+const DEFAULT_TOP_K: usize = 50;
+
+#[cfg(feature = "semantic-search")]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticIndexProgress {
+    pub processed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticIndexSummary {
+    pub processed: usize,
+    pub failed: usize,
+}
+
+/// A description to match, or a photo to find look-alikes of
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum SemanticQuery {
+    Text { text: String },
+    Image { media_id: i64 },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticMatch {
+    pub media: MediaFile,
+    /// Cosine similarity; higher is closer
+    pub score: f32,
+}
+
+/// Compute CLIP embeddings for photos that are new or changed since the last run.
+/// Needs the `semantic-search` build feature and the `semantic_search` config option.
+#[tauri::command]
+pub async fn index_embeddings(app: tauri::AppHandle) -> Result<SemanticIndexSummary, PenglerError> {
+    index_embeddings_internal(&app)
+        .map_err(|e| PenglerError::report("Failed to index photos", e))
+}
+
+#[cfg(feature = "semantic-search")]
+fn index_embeddings_internal(app: &tauri::AppHandle) -> Result<SemanticIndexSummary> {
+    use std::path::Path;
+    use rusqlite::params;
+    use tauri::Emitter;
+    use tracing::{error, info, warn};
+    use crate::config::{Config, get_models_folder};
+    use crate::models::MediaType;
+    use crate::utils::{embedding_to_blob, open_image, ClipImageEncoder};
+
+    if !Config::load()?.semantic_search {
+        return Err(anyhow::anyhow!("Semantic search is disabled in settings"));
+    }
+
+    let mut encoder = ClipImageEncoder::load(&get_models_folder()?)?;
+    let conn = init_database()?;
+
+    let pending: Vec<(i64, String, String)> = {
+        let mut stmt = conn.prepare(
+            "SELECT m.id, m.file_path, m.file_hash FROM media_files m
+             LEFT JOIN media_embeddings e ON e.media_id = m.id
+             WHERE m.media_type = ?1 AND NOT m.online_only AND (e.file_hash IS NULL OR e.file_hash != m.file_hash)",
+        )?;
+        let rows = stmt.query_map([serde_json::to_string(&MediaType::Image)?], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+
+    let total = pending.len();
+    let mut failed = 0;
+
+    for (index, (media_id, file_path, file_hash)) in pending.iter().enumerate() {
+        match open_image(Path::new(file_path)).and_then(|img| encoder.embed(&img)) {
+            Ok(embedding) => {
+                conn.execute(
+                    "INSERT OR REPLACE INTO media_embeddings (media_id, file_hash, embedding) VALUES (?1, ?2, ?3)",
+                    params![media_id, file_hash, embedding_to_blob(&embedding)],
+                )?;
+            }
+            Err(e) => {
+                error!("Failed to embed {}: {}", file_path, e);
+                failed += 1;
+            }
+        }
+
+        let progress = SemanticIndexProgress { processed: index + 1, total };
+        if let Err(e) = app.emit(SEMANTIC_INDEX_PROGRESS_EVENT, progress) {
+            warn!("Failed to emit {}: {}", SEMANTIC_INDEX_PROGRESS_EVENT, e);
+        }
+    }
+
+    info!("Indexed {} photos for semantic search", total - failed);
+
+    Ok(SemanticIndexSummary { processed: total, failed })
+}
+
+#[cfg(not(feature = "semantic-search"))]
+fn index_embeddings_internal(_app: &tauri::AppHandle) -> Result<SemanticIndexSummary> {
+    Err(anyhow::anyhow!("This build of Pengler does not include semantic search"))
+}
+
+/// Photos closest to a description ("dog on a beach") or to another photo, best first
+#[tauri::command]
+pub async fn semantic_search(query: SemanticQuery, top_k: Option<usize>) -> Result<Vec<SemanticMatch>, PenglerError> {
+    semantic_search_internal(&query, top_k.unwrap_or(DEFAULT_TOP_K))
+        .map_err(|e| PenglerError::report("Failed to search photos", e))
+}
+
+fn semantic_search_internal(query: &SemanticQuery, top_k: usize) -> Result<Vec<SemanticMatch>> {
+    let conn = init_database()?;
+
+    let (target, exclude) = match query {
+        SemanticQuery::Text { text } => (embed_text(text)?, None),
+        SemanticQuery::Image { media_id } => {
+            let blob: Vec<u8> = conn
+                .query_row(
+                    &format!(
+                        "SELECT embedding FROM media_embeddings
+                         WHERE media_id = ?1 AND media_id NOT IN (SELECT id FROM media_files WHERE {})",
+                        hidden_condition()
+                    ),
+                    [media_id],
+                    |row| row.get(0),
+                )
+                .optional()?
+                .ok_or_else(|| anyhow::anyhow!("Media {} has not been indexed yet", media_id))?;
+            (blob_to_embedding(&blob), Some(*media_id))
+        }
+    };
+
+    // Brute force is a few milliseconds per 10k photos, well below the cost of an ANN index
+    let mut scored: Vec<(i64, f32)> = {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT media_id, embedding FROM media_embeddings
+             WHERE media_id NOT IN (SELECT id FROM media_files WHERE {})",
+            hidden_condition()
+        ))?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?)))?;
+
+        let mut scored = Vec::new();
+        for row in rows {
+            let (media_id, blob) = row?;
+            if Some(media_id) != exclude {
+                scored.push((media_id, dot(&target, &blob_to_embedding(&blob))));
+            }
+        }
+        scored
+    };
+
+    scored.sort_unstable_by(|a, b| b.1.total_cmp(&a.1));
+    scored.truncate(top_k);
+
+    if scored.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let placeholders = vec!["?"; scored.len()].join(", ");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM media_files WHERE id IN ({})",
+        MEDIA_COLUMNS, placeholders
+    ))?;
+    let mut files: HashMap<i64, MediaFile> = stmt
+        .query_map(params_from_iter(scored.iter().map(|(id, _)| id)), media_file_from_row)?
+        .map(|file| file.map(|file| (file.id, file)))
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(scored
+        .into_iter()
+        .filter_map(|(id, score)| files.remove(&id).map(|media| SemanticMatch { media, score }))
+        .collect())
+}
+
+#[cfg(feature = "semantic-search")]
+fn embed_text(text: &str) -> Result<Vec<f32>> {
+    use std::sync::Mutex;
+    use crate::config::get_models_folder;
+    use crate::utils::ClipTextEncoder;
+
+    // Loading the text model takes a moment, so keep it around between queries
+    static TEXT_ENCODER: Mutex<Option<ClipTextEncoder>> = Mutex::new(None);
+
+    let mut encoder = TEXT_ENCODER.lock().unwrap_or_else(|e| e.into_inner());
+    let encoder = match encoder.as_mut() {
+        Some(encoder) => encoder,
+        None => encoder.insert(ClipTextEncoder::load(&get_models_folder()?)?),
+    };
+
+    encoder.embed(text)
+}
+
+#[cfg(not(feature = "semantic-search"))]
+fn embed_text(_text: &str) -> Result<Vec<f32>> {
+    Err(anyhow::anyhow!("This build of Pengler does not include semantic search"))
+}
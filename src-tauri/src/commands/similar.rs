@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use rayon::prelude::*;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use anyhow::Result;
+use tracing::{error, info, warn};
+
+use crate::error::PenglerError;
+use crate::models::{MediaFile, MediaType};
+use crate::utils::{acquire_slot, hamming_distance, open_image, parse_perceptual_hash, perceptual_hash, run_in_worker_pool, Priority, Workload};
+use crate::commands::cache::{init_database, media_file_from_row, MEDIA_COLUMNS};
+use crate::commands::private::visible_condition;
+
+/// Emitted while `group_similar_photos` hashes photos scanned before perceptual hashing existed
+pub const SIMILAR_PHOTOS_PROGRESS_EVENT: &str = "similar-photos-progress";
+
+/// Max differing hash bits (of 64) for two photos to count as near-duplicates
+pub const DEFAULT_SIMILARITY_THRESHOLD: u32 = 8;
+
+/// Files hashed between progress events
+const PROGRESS_INTERVAL: usize = 50;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarPhotosProgress {
+    pub processed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarMedia {
+    pub media: MediaFile,
+    /// Differing hash bits; 0 means visually identical
+    pub distance: u32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarGroup {
+    pub group_id: i64,
+    /// Largest file first
+    pub items: Vec<MediaFile>,
+}
+
+/// Photos that look like `media_id`, closest first
+#[tauri::command]
+pub async fn find_similar(media_id: i64, threshold: Option<u32>) -> Result<Vec<SimilarMedia>, PenglerError> {
+    find_similar_internal(media_id, threshold.unwrap_or(DEFAULT_SIMILARITY_THRESHOLD))
+        .map_err(|e| PenglerError::report("Failed to find similar photos", e))
+}
+
+fn find_similar_internal(media_id: i64, threshold: u32) -> Result<Vec<SimilarMedia>> {
+    let conn = init_database()?;
+
+    let hash: Option<String> = conn
+        .query_row(
+            &format!("SELECT perceptual_hash FROM media_files WHERE id = ?1 AND {}", visible_condition()),
+            [media_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| anyhow::anyhow!("Media {} not found", media_id))?;
+    let hash = hash
+        .as_deref()
+        .and_then(parse_perceptual_hash)
+        .ok_or_else(|| anyhow::anyhow!("Media {} has no perceptual hash", media_id))?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM media_files WHERE perceptual_hash IS NOT NULL AND id != ?1 AND {}",
+        MEDIA_COLUMNS, visible_condition()
+    ))?;
+    let files = stmt.query_map([media_id], media_file_from_row)?;
+
+    let mut similar = Vec::new();
+    for file in files {
+        let file = file?;
+        let Some(other) = file.perceptual_hash.as_deref().and_then(parse_perceptual_hash) else {
+            continue;
+        };
+        let distance = hamming_distance(hash, other);
+        if distance <= threshold {
+            similar.push(SimilarMedia { media: file, distance });
+        }
+    }
+    similar.sort_by_key(|s| s.distance);
+
+    Ok(similar)
+}
+
+/// Group near-duplicate photos across the library for review, hashing any photos
+/// that don't have a perceptual hash yet. Returns the number of groups.
+#[tauri::command]
+pub async fn group_similar_photos(app: AppHandle, threshold: Option<u32>) -> Result<usize, PenglerError> {
+    group_similar_photos_internal(&app, threshold.unwrap_or(DEFAULT_SIMILARITY_THRESHOLD))
+        .map_err(|e| PenglerError::report("Failed to group similar photos", e))
+}
+
+fn group_similar_photos_internal(app: &AppHandle, threshold: u32) -> Result<usize> {
+    let mut conn = init_database()?;
+
+    backfill_perceptual_hashes(&conn, |processed, total| {
+        let progress = SimilarPhotosProgress { processed, total };
+        if let Err(e) = app.emit(SIMILAR_PHOTOS_PROGRESS_EVENT, progress) {
+            warn!("Failed to emit {}: {}", SIMILAR_PHOTOS_PROGRESS_EVENT, e);
+        }
+    })?;
+
+    let hashes = load_perceptual_hashes(&conn)?;
+    let groups = group_by_similarity(&hashes, threshold);
+
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM similar_groups", [])?;
+    for (group_id, members) in groups.iter().enumerate() {
+        for media_id in members {
+            tx.execute(
+                "INSERT INTO similar_groups (group_id, media_id) VALUES (?1, ?2)",
+                params![group_id as i64 + 1, media_id],
+            )?;
+        }
+    }
+    tx.commit()?;
+
+    info!("Found {} groups of similar photos", groups.len());
+
+    Ok(groups.len())
+}
+
+/// Groups found by the last `group_similar_photos` run
+#[tauri::command]
+pub async fn get_similar_groups() -> Result<Vec<SimilarGroup>, PenglerError> {
+    get_similar_groups_internal()
+        .map_err(|e| PenglerError::report("Failed to load similar photos", e))
+}
+
+fn get_similar_groups_internal() -> Result<Vec<SimilarGroup>> {
+    let conn = init_database()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM media_files WHERE id IN (SELECT media_id FROM similar_groups) AND {}",
+        MEDIA_COLUMNS, visible_condition()
+    ))?;
+    let mut files: HashMap<i64, MediaFile> = stmt
+        .query_map([], media_file_from_row)?
+        .map(|file| file.map(|file| (file.id, file)))
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT g.group_id, g.media_id FROM similar_groups g
+         JOIN media_files m ON m.id = g.media_id
+         ORDER BY g.group_id, m.file_size DESC",
+    )?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))?;
+
+    let mut groups: Vec<SimilarGroup> = Vec::new();
+    for row in rows {
+        let (group_id, media_id) = row?;
+        let Some(media) = files.remove(&media_id) else {
+            continue;
+        };
+        match groups.last_mut() {
+            Some(group) if group.group_id == group_id => group.items.push(media),
+            _ => groups.push(SimilarGroup { group_id, items: vec![media] }),
+        }
+    }
+
+    // A group shrinks below two when its files were removed since the last run
+    groups.retain(|group| group.items.len() > 1);
+
+    Ok(groups)
+}
+
+/// Hash photos scanned before perceptual hashes were recorded, reporting
+/// `(processed, total)` every few files
+pub fn backfill_perceptual_hashes<F>(conn: &Connection, on_progress: F) -> Result<()>
+where
+    F: Fn(usize, usize) + Sync,
+{
+    let pending: Vec<(i64, String)> = {
+        let mut stmt = conn.prepare(
+            "SELECT id, file_path FROM media_files WHERE media_type = ?1 AND perceptual_hash IS NULL AND NOT online_only",
+        )?;
+        let rows = stmt.query_map([serde_json::to_string(&MediaType::Image)?], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let total = pending.len();
+    let processed = AtomicUsize::new(0);
+
+    let hashes: Vec<(i64, String)> = run_in_worker_pool(Workload::Scan, || {
+        pending
+            .par_iter()
+            .filter_map(|(id, file_path)| {
+                let _slot = acquire_slot(Priority::Batch);
+                let hash = match open_image(Path::new(file_path)) {
+                    Ok(img) => Some((*id, perceptual_hash(&img))),
+                    Err(e) => {
+                        error!("Failed to hash {}: {}", file_path, e);
+                        None
+                    }
+                };
+
+                let done = processed.fetch_add(1, Ordering::Relaxed) + 1;
+                if done.is_multiple_of(PROGRESS_INTERVAL) || done == total {
+                    on_progress(done, total);
+                }
+
+                hash
+            })
+            .collect()
+    })?;
+
+    let tx = conn.unchecked_transaction()?;
+    for (id, hash) in &hashes {
+        tx.execute("UPDATE media_files SET perceptual_hash = ?1 WHERE id = ?2", params![hash, id])?;
+    }
+    tx.commit()?;
+
+    Ok(())
+}
+
+/// `(media id, hash)` of every file with a perceptual hash
+pub fn load_perceptual_hashes(conn: &Connection) -> Result<Vec<(i64, u64)>> {
+    let mut stmt = conn.prepare("SELECT id, perceptual_hash FROM media_files WHERE perceptual_hash IS NOT NULL")?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+
+    let mut hashes = Vec::new();
+    for row in rows {
+        let (id, hash) = row?;
+        if let Some(hash) = parse_perceptual_hash(&hash) {
+            hashes.push((id, hash));
+        }
+    }
+
+    Ok(hashes)
+}
+
+/// Connected groups (two or more) of ids whose hashes are within `threshold` bits,
+/// found with a BK-tree instead of comparing every pair
+pub fn group_by_similarity(hashes: &[(i64, u64)], threshold: u32) -> Vec<Vec<i64>> {
+    let mut tree = BkTree::default();
+    for (index, (_, hash)) in hashes.iter().enumerate() {
+        tree.insert(*hash, index);
+    }
+
+    // Union-find over indices into `hashes`
+    let mut parent: Vec<usize> = (0..hashes.len()).collect();
+    fn root(parent: &mut [usize], mut index: usize) -> usize {
+        while parent[index] != index {
+            parent[index] = parent[parent[index]];
+            index = parent[index];
+        }
+        index
+    }
+
+    for (index, (_, hash)) in hashes.iter().enumerate() {
+        for other in tree.find(*hash, threshold) {
+            let (a, b) = (root(&mut parent, index), root(&mut parent, other));
+            if a != b {
+                parent[a] = b;
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<i64>> = HashMap::new();
+    for (index, (id, _)) in hashes.iter().enumerate() {
+        let group = root(&mut parent, index);
+        groups.entry(group).or_default().push(*id);
+    }
+
+    groups.into_values().filter(|group| group.len() > 1).collect()
+}
+
+/// Metric tree over Hamming distance
+#[derive(Default)]
+struct BkTree {
+    nodes: Vec<BkNode>,
+}
+
+struct BkNode {
+    hash: u64,
+    item: usize,
+    /// (distance to this node, child node index)
+    children: Vec<(u32, usize)>,
+}
+
+impl BkTree {
+    fn insert(&mut self, hash: u64, item: usize) {
+        let new_index = self.nodes.len();
+        self.nodes.push(BkNode { hash, item, children: Vec::new() });
+        if new_index == 0 {
+            return;
+        }
+
+        let mut current = 0;
+        loop {
+            let distance = hamming_distance(self.nodes[current].hash, hash);
+            match self.nodes[current].children.iter().find(|(d, _)| *d == distance) {
+                Some(&(_, child)) => current = child,
+                None => {
+                    self.nodes[current].children.push((distance, new_index));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Items within `threshold` of `hash`
+    fn find(&self, hash: u64, threshold: u32) -> Vec<usize> {
+        let mut found = Vec::new();
+        if self.nodes.is_empty() {
+            return found;
+        }
+
+        let mut pending = vec![0];
+        while let Some(index) = pending.pop() {
+            let node = &self.nodes[index];
+            let distance = hamming_distance(node.hash, hash);
+            if distance <= threshold {
+                found.push(node.item);
+            }
+            // Triangle inequality: only subtrees within threshold of `distance` can match
+            for &(child_distance, child) in &node.children {
+                if child_distance.abs_diff(distance) <= threshold {
+                    pending.push(child);
+                }
+            }
+        }
+
+        found
+    }
+}
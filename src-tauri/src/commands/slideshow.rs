@@ -0,0 +1,301 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use image::{imageops::FilterType, DynamicImage, Rgb, RgbImage};
+use rusqlite::params_from_iter;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use anyhow::Result;
+use tracing::{info, warn};
+
+use crate::error::PenglerError;
+use crate::models::MediaType;
+use crate::utils::{apply_edits, convert_to_srgb, open_upright_image_with_profile, write_atomically};
+use crate::commands::cache::init_database;
+use crate::commands::edits::current_recipe;
+use crate::commands::private::visible_condition;
+
+/// Emitted while slides are prepared and while ffmpeg encodes
+pub const SLIDESHOW_PROGRESS_EVENT: &str = "slideshow-progress";
+
+const FRAME_RATE: u32 = 30;
+/// Music fades out over this many seconds at the end
+const AUDIO_FADE_OUT: f64 = 2.0;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlideshowOptions {
+    /// Output MP4 file
+    pub dest: String,
+    /// Seconds each photo is shown, including its crossfade
+    #[serde(default = "default_slide_duration")]
+    pub slide_duration: f64,
+    /// Seconds of crossfade between photos; 0 for hard cuts
+    #[serde(default = "default_crossfade")]
+    pub crossfade: f64,
+    /// Audio file played under the slideshow, cut to its length
+    pub music: Option<String>,
+    #[serde(default = "default_width")]
+    pub width: u32,
+    #[serde(default = "default_height")]
+    pub height: u32,
+}
+
+fn default_slide_duration() -> f64 {
+    3.0
+}
+
+fn default_crossfade() -> f64 {
+    0.5
+}
+
+fn default_width() -> u32 {
+    1920
+}
+
+fn default_height() -> u32 {
+    1080
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlideshowProgress {
+    /// "preparing" while photos are scaled, "encoding" while ffmpeg runs
+    pub phase: String,
+    /// Slides prepared, or milliseconds of video encoded
+    pub processed: u64,
+    pub total: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlideshowResult {
+    pub path: String,
+    pub slides: usize,
+    /// Length of the video in seconds
+    pub duration: f64,
+}
+
+/// Render the selected photos into an MP4 slideshow with ffmpeg, in the given order.
+/// Videos in the selection are skipped; saved adjustments are applied to photos.
+#[tauri::command]
+pub async fn render_slideshow(
+    app: AppHandle,
+    media_ids: Vec<i64>,
+    options: SlideshowOptions,
+) -> Result<SlideshowResult, PenglerError> {
+    render_slideshow_internal(&app, &media_ids, &options)
+        .map_err(|e| PenglerError::report("Failed to render slideshow", e))
+}
+
+fn render_slideshow_internal(app: &AppHandle, media_ids: &[i64], options: &SlideshowOptions) -> Result<SlideshowResult> {
+    if options.slide_duration <= 0.0 {
+        return Err(anyhow::anyhow!("Slide duration must be greater than 0"));
+    }
+    if options.crossfade < 0.0 || options.crossfade >= options.slide_duration {
+        return Err(anyhow::anyhow!("Crossfade must be shorter than the slide duration"));
+    }
+    // H.264 with 4:2:0 chroma needs even dimensions
+    if options.width == 0 || options.height == 0 || !options.width.is_multiple_of(2) || !options.height.is_multiple_of(2) {
+        return Err(anyhow::anyhow!("Resolution must be even and greater than 0"));
+    }
+    if let Some(music) = &options.music {
+        if !Path::new(music).exists() {
+            return Err(anyhow::anyhow!("Music file not found: {}", music));
+        }
+    }
+
+    let photos = load_photos(media_ids)?;
+    if photos.is_empty() {
+        return Err(anyhow::anyhow!("No photos selected"));
+    }
+
+    let work_dir = std::env::temp_dir().join(format!("pengler-slideshow-{}", std::process::id()));
+    fs::create_dir_all(&work_dir)?;
+
+    let result = prepare_slides(app, &photos, &work_dir, options)
+        .and_then(|slides| encode_slideshow(app, &slides, options));
+
+    let _ = fs::remove_dir_all(&work_dir);
+
+    let duration = result?;
+    info!("Rendered slideshow of {} photos to {}", photos.len(), options.dest);
+
+    Ok(SlideshowResult { path: options.dest.clone(), slides: photos.len(), duration })
+}
+
+/// Paths and ids of the selected photos, keeping the selection order
+fn load_photos(media_ids: &[i64]) -> Result<Vec<(i64, String)>> {
+    if media_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let conn = init_database()?;
+    let placeholders = vec!["?"; media_ids.len()].join(", ");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, file_path, media_type FROM media_files WHERE id IN ({}) AND {}",
+        placeholders,
+        visible_condition()
+    ))?;
+    let rows: Vec<(i64, String, String)> = stmt
+        .query_map(params_from_iter(media_ids.iter()), |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let image_type = serde_json::to_string(&MediaType::Image)?;
+    Ok(media_ids
+        .iter()
+        .filter_map(|id| rows.iter().find(|(row_id, _, _)| row_id == id))
+        .filter(|(_, _, media_type)| *media_type == image_type)
+        .map(|(id, path, _)| (*id, path.clone()))
+        .collect())
+}
+
+/// Scale every photo to fit the frame on a black background, so ffmpeg gets
+/// uniform inputs whatever the source format, orientation or edits
+fn prepare_slides(
+    app: &AppHandle,
+    photos: &[(i64, String)],
+    work_dir: &Path,
+    options: &SlideshowOptions,
+) -> Result<Vec<PathBuf>> {
+    let conn = init_database()?;
+    let mut slides = Vec::with_capacity(photos.len());
+
+    for (index, (media_id, file_path)) in photos.iter().enumerate() {
+        let (mut img, icc) = open_upright_image_with_profile(Path::new(file_path))?;
+        if let Some(recipe) = current_recipe(&conn, *media_id)? {
+            img = apply_edits(img, &recipe);
+        }
+        if let Some(icc) = icc {
+            img = convert_to_srgb(img, &icc)?;
+        }
+
+        let fitted = img.resize(options.width, options.height, FilterType::Lanczos3).to_rgb8();
+        let mut frame = RgbImage::from_pixel(options.width, options.height, Rgb([0, 0, 0]));
+        image::imageops::overlay(
+            &mut frame,
+            &fitted,
+            ((options.width - fitted.width()) / 2) as i64,
+            ((options.height - fitted.height()) / 2) as i64,
+        );
+
+        let slide = work_dir.join(format!("slide-{:05}.png", index));
+        DynamicImage::ImageRgb8(frame).save(&slide)?;
+        slides.push(slide);
+
+        emit_progress(app, "preparing", index as u64 + 1, photos.len() as u64);
+    }
+
+    Ok(slides)
+}
+
+/// Run ffmpeg over the prepared slides; returns the video length in seconds
+fn encode_slideshow(app: &AppHandle, slides: &[PathBuf], options: &SlideshowOptions) -> Result<f64> {
+    let count = slides.len();
+    let duration = count as f64 * options.slide_duration - (count - 1) as f64 * options.crossfade;
+    let total_ms = (duration * 1000.0) as u64;
+
+    let mut command = Command::new("ffmpeg");
+    command.arg("-y").arg("-loglevel").arg("error").arg("-nostats");
+
+    for slide in slides {
+        command
+            .arg("-loop").arg("1")
+            .arg("-framerate").arg(FRAME_RATE.to_string())
+            .arg("-t").arg(options.slide_duration.to_string())
+            .arg("-i").arg(slide);
+    }
+    if let Some(music) = &options.music {
+        command.arg("-i").arg(music);
+    }
+
+    command
+        .arg("-filter_complex").arg(slideshow_filter(count, options))
+        .arg("-map").arg("[video]");
+
+    if options.music.is_some() {
+        let fade_start = (duration - AUDIO_FADE_OUT).max(0.0);
+        command
+            .arg("-map").arg(format!("{}:a:0", count))
+            .arg("-af").arg(format!("afade=t=out:st={}:d={}", fade_start, AUDIO_FADE_OUT))
+            .arg("-c:a").arg("aac");
+    }
+
+    command
+        .arg("-t").arg(duration.to_string())
+        .arg("-c:v").arg("libx264")
+        .arg("-pix_fmt").arg("yuv420p")
+        .arg("-r").arg(FRAME_RATE.to_string())
+        .arg("-movflags").arg("+faststart")
+        .arg("-progress").arg("pipe:1");
+
+    write_atomically(Path::new(&options.dest), |part| {
+        // The .part name hides the container from ffmpeg, so name it explicitly
+        let mut child = match command.arg("-f").arg("mp4").arg(part).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn() {
+            Ok(child) => child,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(PenglerError::FfmpegMissing("ffmpeg not found. Please install ffmpeg to render slideshows.".to_string()).into());
+            }
+            Err(e) => return Err(anyhow::anyhow!("Failed to run ffmpeg: {}", e)),
+        };
+
+        if let Some(stdout) = child.stdout.take() {
+            for line in BufReader::new(stdout).lines() {
+                let line = line?;
+                // Despite the name, out_time_ms is in microseconds
+                if let Some(micros) = line.strip_prefix("out_time_ms=").and_then(|v| v.trim().parse::<u64>().ok()) {
+                    emit_progress(app, "encoding", (micros / 1000).min(total_ms), total_ms);
+                }
+            }
+        }
+
+        let mut stderr = String::new();
+        if let Some(mut pipe) = child.stderr.take() {
+            pipe.read_to_string(&mut stderr)?;
+        }
+
+        if child.wait()?.success() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("ffmpeg failed: {}", stderr.trim()))
+        }
+    })?;
+
+    Ok(duration)
+}
+
+/// Filter graph joining the slide inputs into a `[video]` output
+fn slideshow_filter(count: usize, options: &SlideshowOptions) -> String {
+    // xfade needs every input in the same timebase and pixel format
+    let mut filter: Vec<String> = (0..count)
+        .map(|i| format!("[{}:v]settb=AVTB,fps={},format=yuv420p[s{}]", i, FRAME_RATE, i))
+        .collect();
+
+    if count == 1 {
+        filter.push("[s0]null[video]".to_string());
+    } else if options.crossfade == 0.0 {
+        let inputs: String = (0..count).map(|i| format!("[s{}]", i)).collect();
+        filter.push(format!("{}concat=n={}:v=1:a=0[video]", inputs, count));
+    } else {
+        let mut previous = "s0".to_string();
+        for i in 1..count {
+            let output = if i == count - 1 { "video".to_string() } else { format!("x{}", i) };
+            let offset = i as f64 * (options.slide_duration - options.crossfade);
+            filter.push(format!(
+                "[{}][s{}]xfade=transition=fade:duration={}:offset={}[{}]",
+                previous, i, options.crossfade, offset, output
+            ));
+            previous = output;
+        }
+    }
+
+    filter.join(";")
+}
+
+fn emit_progress(app: &AppHandle, phase: &str, processed: u64, total: u64) {
+    let progress = SlideshowProgress { phase: phase.to_string(), processed, total };
+    if let Err(e) = app.emit(SLIDESHOW_PROGRESS_EVENT, progress) {
+        warn!("Failed to emit {}: {}", SLIDESHOW_PROGRESS_EVENT, e);
+    }
+}
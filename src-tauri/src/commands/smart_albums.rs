@@ -0,0 +1,271 @@
+use std::path::Path;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use rusqlite::types::Value;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use anyhow::Result;
+use tracing::warn;
+
+use crate::error::PenglerError;
+use crate::models::{MediaFile, MediaType};
+use crate::commands::cache::{init_database, media_file_from_row, MEDIA_COLUMNS};
+use crate::commands::search::{folder_condition, tag_condition};
+use crate::commands::private::visible_condition;
+use crate::commands::relink::relinked_path;
+
+/// Emitted after changes that can alter which files a smart album contains
+pub const SMART_ALBUMS_CHANGED_EVENT: &str = "smart-albums-changed";
+
+/// A condition on media files; `all` / `any` combine nested rules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum SmartRule {
+    All { rules: Vec<SmartRule> },
+    Any { rules: Vec<SmartRule> },
+    TagIs {
+        tag: String,
+        #[serde(default)]
+        include_descendants: bool,
+    },
+    DateBetween {
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    },
+    CameraEquals { camera: String },
+    RatingAtLeast { rating: i32 },
+    TypeIs { media_type: MediaType },
+    FolderUnder { folder: String },
+    PersonIs { person_id: i64 },
+}
+
+impl SmartRule {
+    /// Compile into a SQL condition on media_files, appending its parameters to `values`
+    fn to_sql(&self, values: &mut Vec<Value>) -> Result<String> {
+        Ok(match self {
+            SmartRule::All { rules } => combine(rules, " AND ", "1", values)?,
+            SmartRule::Any { rules } => combine(rules, " OR ", "0", values)?,
+            SmartRule::TagIs { tag, include_descendants } => tag_condition(tag, *include_descendants, values),
+            SmartRule::DateBetween { from, to } => {
+                let mut parts = vec!["taken_at IS NOT NULL".to_string()];
+                if let Some(from) = from {
+                    parts.push("taken_at >= ?".to_string());
+                    values.push(Value::from(from.to_rfc3339()));
+                }
+                if let Some(to) = to {
+                    parts.push("taken_at <= ?".to_string());
+                    values.push(Value::from(to.to_rfc3339()));
+                }
+                format!("({})", parts.join(" AND "))
+            }
+            SmartRule::CameraEquals { camera } => {
+                values.push(Value::from(camera.clone()));
+                "camera_model = ? COLLATE NOCASE".to_string()
+            }
+            SmartRule::RatingAtLeast { rating } => {
+                values.push(Value::from(*rating));
+                "rating >= ?".to_string()
+            }
+            SmartRule::TypeIs { media_type } => {
+                values.push(Value::from(serde_json::to_string(media_type)?));
+                "media_type = ?".to_string()
+            }
+            SmartRule::FolderUnder { folder } => folder_condition(folder, values),
+            SmartRule::PersonIs { person_id } => {
+                values.push(Value::from(*person_id));
+                "id IN (SELECT media_id FROM face_regions WHERE person_id = ?)".to_string()
+            }
+        })
+    }
+
+    /// Point `folderUnder` conditions at or below `old` to the same place under `new`;
+    /// whether any changed
+    fn relink_folders(&mut self, old: &Path, new: &Path) -> bool {
+        match self {
+            SmartRule::All { rules } | SmartRule::Any { rules } => {
+                let mut changed = false;
+                for rule in rules {
+                    changed |= rule.relink_folders(old, new);
+                }
+                changed
+            }
+            SmartRule::FolderUnder { folder } => match relinked_path(folder, old, new) {
+                Some(moved) => {
+                    *folder = moved;
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+/// Join nested rules; an empty `all` matches everything and an empty `any` nothing
+fn combine(rules: &[SmartRule], operator: &str, empty: &str, values: &mut Vec<Value>) -> Result<String> {
+    if rules.is_empty() {
+        return Ok(empty.to_string());
+    }
+
+    let parts = rules
+        .iter()
+        .map(|rule| rule.to_sql(values))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(format!("({})", parts.join(operator)))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartAlbum {
+    pub id: i64,
+    pub name: String,
+    pub rule: SmartRule,
+}
+
+#[tauri::command]
+pub async fn list_smart_albums() -> Result<Vec<SmartAlbum>, PenglerError> {
+    list_smart_albums_internal()
+        .map_err(|e| PenglerError::report("Failed to load smart albums", e))
+}
+
+fn list_smart_albums_internal() -> Result<Vec<SmartAlbum>> {
+    let conn = init_database()?;
+
+    let mut stmt = conn.prepare("SELECT id, name, rule FROM smart_albums ORDER BY name COLLATE NOCASE")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+    })?;
+
+    let mut albums = Vec::new();
+    for row in rows {
+        let (id, name, rule) = row?;
+        albums.push(SmartAlbum { id, name, rule: serde_json::from_str(&rule)? });
+    }
+
+    Ok(albums)
+}
+
+#[tauri::command]
+pub async fn create_smart_album(name: String, rule: SmartRule) -> Result<SmartAlbum, PenglerError> {
+    create_smart_album_internal(name, rule)
+        .map_err(|e| PenglerError::report("Failed to create smart album", e))
+}
+
+fn create_smart_album_internal(name: String, rule: SmartRule) -> Result<SmartAlbum> {
+    // Compile once so an invalid rule is rejected up front
+    rule.to_sql(&mut Vec::new())?;
+
+    let conn = init_database()?;
+    conn.execute(
+        "INSERT INTO smart_albums (name, rule) VALUES (?1, ?2)",
+        params![name, serde_json::to_string(&rule)?],
+    )?;
+
+    Ok(SmartAlbum { id: conn.last_insert_rowid(), name, rule })
+}
+
+#[tauri::command]
+pub async fn update_smart_album(id: i64, name: String, rule: SmartRule) -> Result<(), PenglerError> {
+    update_smart_album_internal(id, name, rule)
+        .map_err(|e| PenglerError::report("Failed to update smart album", e))
+}
+
+fn update_smart_album_internal(id: i64, name: String, rule: SmartRule) -> Result<()> {
+    rule.to_sql(&mut Vec::new())?;
+
+    let conn = init_database()?;
+    let updated = conn.execute(
+        "UPDATE smart_albums SET name = ?1, rule = ?2 WHERE id = ?3",
+        params![name, serde_json::to_string(&rule)?, id],
+    )?;
+    if updated == 0 {
+        return Err(anyhow::anyhow!("Smart album {} not found", id));
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_smart_album(id: i64) -> Result<(), PenglerError> {
+    delete_smart_album_internal(id)
+        .map_err(|e| PenglerError::report("Failed to delete smart album", e))
+}
+
+fn delete_smart_album_internal(id: i64) -> Result<()> {
+    let conn = init_database()?;
+    conn.execute("DELETE FROM smart_albums WHERE id = ?1", [id])?;
+    Ok(())
+}
+
+/// Files currently matching a smart album's rule
+#[tauri::command]
+pub async fn evaluate_smart_album(id: i64) -> Result<Vec<MediaFile>, PenglerError> {
+    evaluate_smart_album_internal(id)
+        .map_err(|e| PenglerError::report("Failed to evaluate smart album", e))
+}
+
+pub fn evaluate_smart_album_internal(id: i64) -> Result<Vec<MediaFile>> {
+    let conn = init_database()?;
+    let rule = load_rule(&conn, id)?;
+
+    let mut values = Vec::new();
+    let condition = rule.to_sql(&mut values)?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM media_files WHERE {} AND {} ORDER BY taken_at DESC, modified_at DESC",
+        MEDIA_COLUMNS, visible_condition(), condition
+    ))?;
+    let files = stmt.query_map(params_from_iter(values.iter()), media_file_from_row)?;
+
+    Ok(files.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Number of files matching a rule
+pub fn count_matches(conn: &Connection, rule: &SmartRule) -> Result<i64> {
+    let mut values = Vec::new();
+    let condition = rule.to_sql(&mut values)?;
+
+    Ok(conn.query_row(
+        &format!("SELECT COUNT(*) FROM media_files WHERE {} AND {}", visible_condition(), condition),
+        params_from_iter(values.iter()),
+        |row| row.get(0),
+    )?)
+}
+
+fn load_rule(conn: &Connection, id: i64) -> Result<SmartRule> {
+    let rule: String = conn
+        .query_row("SELECT rule FROM smart_albums WHERE id = ?1", [id], |row| row.get(0))
+        .optional()?
+        .ok_or_else(|| anyhow::anyhow!("Smart album {} not found", id))?;
+
+    Ok(serde_json::from_str(&rule)?)
+}
+
+/// Rewrite folder conditions after a library folder moved from `old` to `new`; returns
+/// how many smart albums changed
+pub fn relink_smart_album_folders(conn: &Connection, old: &Path, new: &Path) -> Result<usize> {
+    let albums: Vec<(i64, String)> = conn
+        .prepare("SELECT id, rule FROM smart_albums")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut changed = 0;
+    for (id, rule) in albums {
+        let Ok(mut rule) = serde_json::from_str::<SmartRule>(&rule) else {
+            continue;
+        };
+        if rule.relink_folders(old, new) {
+            conn.execute("UPDATE smart_albums SET rule = ?1 WHERE id = ?2", params![serde_json::to_string(&rule)?, id])?;
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+/// Tell the frontend to re-evaluate open smart albums
+pub fn notify_smart_albums_changed(app: &AppHandle) {
+    if let Err(e) = app.emit(SMART_ALBUMS_CHANGED_EVENT, ()) {
+        warn!("Failed to emit {}: {}", SMART_ALBUMS_CHANGED_EVENT, e);
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::HashSet;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::params;
+use anyhow::Result;
+use tracing::info;
+
+use crate::error::PenglerError;
+use crate::config::Config;
+use crate::models::{MediaFile, MediaType};
+use crate::commands::cache::{init_database, media_file_from_row, MEDIA_COLUMNS};
+use crate::commands::private::visible_condition;
+
+struct BurstCandidate {
+    id: i64,
+    camera_model: String,
+    taken_at: DateTime<Utc>,
+    file_size: i64,
+}
+
+/// Group photos shot in quick succession by the same camera into burst stacks.
+/// Returns the number of stacks.
+#[tauri::command]
+pub async fn detect_bursts() -> Result<usize, PenglerError> {
+    detect_bursts_internal()
+        .map_err(|e| PenglerError::report("Failed to detect bursts", e))
+}
+
+fn detect_bursts_internal() -> Result<usize> {
+    let config = Config::load()?;
+    let window = Duration::milliseconds(config.burst_window_ms as i64);
+
+    let mut conn = init_database()?;
+    let tx = conn.transaction()?;
+
+    // Remember user-picked covers so regrouping doesn't undo them
+    let previous_covers: HashSet<i64> = {
+        let mut stmt = tx.prepare("SELECT cover_id FROM stacks")?;
+        let covers = stmt.query_map([], |row| row.get(0))?;
+        covers.collect::<rusqlite::Result<_>>()?
+    };
+
+    tx.execute("UPDATE media_files SET stack_id = NULL WHERE stack_id IS NOT NULL", [])?;
+    tx.execute("DELETE FROM stacks", [])?;
+
+    let candidates: Vec<BurstCandidate> = {
+        let mut stmt = tx.prepare(
+            "SELECT id, camera_model, taken_at, file_size FROM media_files
+             WHERE media_type = ?1 AND camera_model IS NOT NULL AND taken_at IS NOT NULL
+             ORDER BY camera_model, taken_at"
+        )?;
+        let rows = stmt.query_map([serde_json::to_string(&MediaType::Image)?], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, i64>(3)?))
+        })?;
+
+        let mut candidates = Vec::new();
+        for row in rows {
+            let (id, camera_model, taken_at, file_size) = row?;
+            if let Ok(taken_at) = DateTime::parse_from_rfc3339(&taken_at) {
+                candidates.push(BurstCandidate {
+                    id,
+                    camera_model,
+                    taken_at: taken_at.with_timezone(&Utc),
+                    file_size,
+                });
+            }
+        }
+        candidates
+    };
+
+    // Split into runs where each shot follows the previous one within the window
+    let mut groups: Vec<Vec<&BurstCandidate>> = Vec::new();
+    for candidate in &candidates {
+        match groups.last_mut() {
+            Some(group)
+                if group.last().is_some_and(|last| {
+                    last.camera_model == candidate.camera_model
+                        && candidate.taken_at - last.taken_at <= window
+                }) =>
+            {
+                group.push(candidate)
+            }
+            _ => groups.push(vec![candidate]),
+        }
+    }
+
+    let mut stack_count = 0;
+    let mut stacked_photos = 0;
+    for group in groups.iter().filter(|g| g.len() > 1) {
+        // Keep a previous cover, otherwise the largest file (usually the sharpest frame)
+        let cover = group
+            .iter()
+            .find(|m| previous_covers.contains(&m.id))
+            .or_else(|| group.iter().max_by_key(|m| m.file_size))
+            .map(|m| m.id)
+            .unwrap_or(group[0].id);
+
+        tx.execute("INSERT INTO stacks (cover_id) VALUES (?1)", [cover])?;
+        let stack_id = tx.last_insert_rowid();
+
+        for member in group {
+            tx.execute("UPDATE media_files SET stack_id = ?1 WHERE id = ?2", params![stack_id, member.id])?;
+        }
+        stack_count += 1;
+        stacked_photos += group.len();
+    }
+
+    tx.commit()?;
+
+    info!("Grouped {} photos into {} burst stacks", stacked_photos, stack_count);
+
+    Ok(stack_count)
+}
+
+#[tauri::command]
+pub async fn get_stack_members(stack_id: i64) -> Result<Vec<MediaFile>, PenglerError> {
+    get_stack_members_internal(stack_id)
+        .map_err(|e| PenglerError::report("Failed to load stack", e))
+}
+
+fn get_stack_members_internal(stack_id: i64) -> Result<Vec<MediaFile>> {
+    let conn = init_database()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM media_files WHERE stack_id = ?1 AND {} ORDER BY taken_at",
+        MEDIA_COLUMNS, visible_condition()
+    ))?;
+    let members = stmt.query_map([stack_id], media_file_from_row)?;
+
+    Ok(members.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Pick which photo represents the stack in the grid
+#[tauri::command]
+pub async fn set_stack_cover(stack_id: i64, media_id: i64) -> Result<(), PenglerError> {
+    set_stack_cover_internal(stack_id, media_id)
+        .map_err(|e| PenglerError::report("Failed to set stack cover", e))
+}
+
+fn set_stack_cover_internal(stack_id: i64, media_id: i64) -> Result<()> {
+    let conn = init_database()?;
+
+    let is_member = conn
+        .prepare(&format!("SELECT 1 FROM media_files WHERE id = ?1 AND stack_id = ?2 AND {}", visible_condition()))?
+        .exists(params![media_id, stack_id])?;
+    if !is_member {
+        return Err(anyhow::anyhow!("Media {} is not part of stack {}", media_id, stack_id));
+    }
+
+    conn.execute("UPDATE stacks SET cover_id = ?1 WHERE id = ?2", params![media_id, stack_id])?;
+
+    Ok(())
+}
@@ -0,0 +1,162 @@
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use anyhow::Result;
+
+use crate::error::PenglerError;
+use crate::commands::cache::init_database;
+use crate::commands::search::escape_like;
+use crate::commands::private::visible_condition;
+use crate::commands::smart_albums::{count_matches, SmartRule};
+
+/// Suggestions returned per kind
+const SUGGESTIONS_PER_KIND: usize = 5;
+
+/// SQLite expression for the folder part of file_path, including the trailing separator
+pub const FOLDER_EXPRESSION: &str = "rtrim(file_path, replace(replace(file_path, '/', ''), '\\', ''))";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuggestionKind {
+    Tag,
+    Folder,
+    Camera,
+    Album,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Suggestion {
+    pub kind: SuggestionKind,
+    /// Tag path, folder path, camera model or smart album id
+    pub value: String,
+    /// Text to show, e.g. the folder name instead of its full path
+    pub label: String,
+    pub count: i64,
+}
+
+/// Tags, folders, cameras and smart albums starting with `prefix`, for search-as-you-type
+#[tauri::command]
+pub async fn suggest(prefix: String) -> Result<Vec<Suggestion>, PenglerError> {
+    suggest_internal(prefix.trim())
+        .map_err(|e| PenglerError::report("Failed to load suggestions", e))
+}
+
+fn suggest_internal(prefix: &str) -> Result<Vec<Suggestion>> {
+    if prefix.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let conn = init_database()?;
+
+    let mut suggestions = Vec::new();
+    suggestions.extend(suggest_tags(&conn, prefix)?);
+    suggestions.extend(suggest_folders(&conn, prefix)?);
+    suggestions.extend(suggest_cameras(&conn, prefix)?);
+    suggestions.extend(suggest_albums(&conn, prefix)?);
+
+    Ok(suggestions)
+}
+
+/// Tags where the full path or any nested level starts with the prefix
+fn suggest_tags(conn: &Connection, prefix: &str) -> Result<Vec<Suggestion>> {
+    let escaped = escape_like(prefix);
+
+    let mut stmt = conn.prepare(
+        "SELECT t.name, COUNT(mt.media_id) AS uses FROM tags t
+         LEFT JOIN media_tags mt ON mt.tag_id = t.id
+         WHERE t.name LIKE ?1 ESCAPE '\\' OR t.name LIKE ?2 ESCAPE '\\'
+         GROUP BY t.id ORDER BY uses DESC LIMIT ?3",
+    )?;
+    let tags = stmt.query_map(
+        params![format!("{}%", escaped), format!("%/{}%", escaped), SUGGESTIONS_PER_KIND as i64],
+        |row| {
+            let name: String = row.get(0)?;
+            Ok(Suggestion {
+                kind: SuggestionKind::Tag,
+                label: name.clone(),
+                value: name,
+                count: row.get(1)?,
+            })
+        },
+    )?;
+
+    Ok(tags.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Folders whose own name starts with the prefix
+fn suggest_folders(conn: &Connection, prefix: &str) -> Result<Vec<Suggestion>> {
+    // Grouping walks idx_folder, so this is one pass over the folder list rather than the files
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {0}, COUNT(*) FROM media_files WHERE {1} GROUP BY {0}",
+        FOLDER_EXPRESSION,
+        visible_condition()
+    ))?;
+    let folders = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?;
+
+    let prefix = prefix.to_lowercase();
+    let mut matches = Vec::new();
+    for folder in folders {
+        let (folder, count) = folder?;
+        let folder = folder.trim_end_matches(['/', '\\']).to_string();
+        let name = folder.rsplit(['/', '\\']).next().unwrap_or(&folder).to_string();
+        if name.to_lowercase().starts_with(&prefix) {
+            matches.push(Suggestion { kind: SuggestionKind::Folder, value: folder, label: name, count });
+        }
+    }
+
+    matches.sort_by_key(|s| std::cmp::Reverse(s.count));
+    matches.truncate(SUGGESTIONS_PER_KIND);
+
+    Ok(matches)
+}
+
+/// Camera models where the make or model starts with the prefix
+fn suggest_cameras(conn: &Connection, prefix: &str) -> Result<Vec<Suggestion>> {
+    let escaped = escape_like(prefix);
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT camera_model, COUNT(*) AS uses FROM media_files
+         WHERE (camera_model LIKE ?1 ESCAPE '\\' OR camera_model LIKE ?2 ESCAPE '\\') AND {}
+         GROUP BY camera_model ORDER BY uses DESC LIMIT ?3",
+        visible_condition()
+    ))?;
+    let cameras = stmt.query_map(
+        params![format!("{}%", escaped), format!("% {}%", escaped), SUGGESTIONS_PER_KIND as i64],
+        |row| {
+            let camera: String = row.get(0)?;
+            Ok(Suggestion {
+                kind: SuggestionKind::Camera,
+                label: camera.clone(),
+                value: camera,
+                count: row.get(1)?,
+            })
+        },
+    )?;
+
+    Ok(cameras.collect::<rusqlite::Result<_>>()?)
+}
+
+fn suggest_albums(conn: &Connection, prefix: &str) -> Result<Vec<Suggestion>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, rule FROM smart_albums WHERE name LIKE ?1 ESCAPE '\\'
+         ORDER BY name COLLATE NOCASE LIMIT ?2",
+    )?;
+    let albums = stmt.query_map(
+        params![format!("{}%", escape_like(prefix)), SUGGESTIONS_PER_KIND as i64],
+        |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)),
+    )?;
+
+    let mut suggestions = Vec::new();
+    for album in albums {
+        let (id, name, rule) = album?;
+        let rule: SmartRule = serde_json::from_str(&rule)?;
+        suggestions.push(Suggestion {
+            kind: SuggestionKind::Album,
+            value: id.to_string(),
+            label: name,
+            count: count_matches(conn, &rule)?,
+        });
+    }
+
+    Ok(suggestions)
+}
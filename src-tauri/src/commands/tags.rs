@@ -0,0 +1,205 @@
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::Serialize;
+use anyhow::Result;
+
+use crate::error::PenglerError;
+use crate::commands::cache::init_database;
+use crate::commands::search::escape_like;
+use crate::commands::private::hidden_condition;
+use crate::commands::smart_albums::notify_smart_albums_changed;
+
+/// Separator between levels of a hierarchical tag, e.g. "Travel/Japan/Tokyo"
+pub const TAG_PATH_SEPARATOR: char = '/';
+
+/// A tag; `name` is the full path and `parent_id` points at the tag one level up
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+    pub parent_id: Option<i64>,
+    /// Files tagged with exactly this tag, not counting descendants
+    pub media_count: i64,
+}
+
+/// Find or create a tag by its full path, creating missing ancestors along the way.
+/// Returns `None` for an empty path.
+pub fn ensure_tag(conn: &Connection, path: &str) -> Result<Option<i64>> {
+    let mut parent_id: Option<i64> = None;
+    let mut name = String::new();
+
+    for part in path.split(TAG_PATH_SEPARATOR).map(str::trim).filter(|p| !p.is_empty()) {
+        if !name.is_empty() {
+            name.push(TAG_PATH_SEPARATOR);
+        }
+        name.push_str(part);
+
+        conn.execute("INSERT OR IGNORE INTO tags (name, parent_id) VALUES (?1, ?2)", params![name, parent_id])?;
+
+        // Names are case-insensitive; continue from the stored spelling so paths stay consistent
+        let (id, stored_name) = conn.query_row("SELECT id, name FROM tags WHERE name = ?1", [&name], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?;
+        parent_id = Some(id);
+        name = stored_name;
+    }
+
+    Ok(parent_id)
+}
+
+/// Delete tags that have neither files nor child tags
+pub fn remove_unused_tags(conn: &Connection) -> Result<()> {
+    // Removing a leaf can leave its parent unused, so repeat until nothing changes
+    while conn.execute(
+        "DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM media_tags)
+         AND id NOT IN (SELECT parent_id FROM tags WHERE parent_id IS NOT NULL)",
+        [],
+    )? > 0 {}
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_tags() -> Result<Vec<Tag>, PenglerError> {
+    get_tags_internal()
+        .map_err(|e| PenglerError::report("Failed to load tags", e))
+}
+
+fn get_tags_internal() -> Result<Vec<Tag>> {
+    let conn = init_database()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT t.id, t.name, t.parent_id, (SELECT COUNT(*) FROM media_tags mt WHERE mt.tag_id = t.id
+            AND mt.media_id NOT IN (SELECT id FROM media_files WHERE {}))
+         FROM tags t ORDER BY t.name COLLATE NOCASE",
+        hidden_condition()
+    ))?;
+    let tags = stmt.query_map([], |row| {
+        Ok(Tag {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            parent_id: row.get(2)?,
+            media_count: row.get(3)?,
+        })
+    })?;
+
+    Ok(tags.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Move a tag (with its descendants) under another tag, or to the top level with `None`.
+/// If a tag with the resulting name already exists the two are merged.
+#[tauri::command]
+pub async fn move_tag(app: tauri::AppHandle, tag_id: i64, new_parent_id: Option<i64>) -> Result<(), PenglerError> {
+    move_tag_internal(tag_id, new_parent_id)
+        .map_err(|e| PenglerError::report("Failed to move tag", e))?;
+    notify_smart_albums_changed(&app);
+    Ok(())
+}
+
+fn move_tag_internal(tag_id: i64, new_parent_id: Option<i64>) -> Result<()> {
+    let mut conn = init_database()?;
+    let tx = conn.transaction()?;
+    move_tag_in(&tx, tag_id, new_parent_id)?;
+    remove_unused_tags(&tx)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Merge `source_id` into `target_id`: its files and child tags move over and it is deleted
+#[tauri::command]
+pub async fn merge_tags(app: tauri::AppHandle, source_id: i64, target_id: i64) -> Result<(), PenglerError> {
+    merge_tags_internal(source_id, target_id)
+        .map_err(|e| PenglerError::report("Failed to merge tags", e))?;
+    notify_smart_albums_changed(&app);
+    Ok(())
+}
+
+fn merge_tags_internal(source_id: i64, target_id: i64) -> Result<()> {
+    let mut conn = init_database()?;
+    let tx = conn.transaction()?;
+    merge_tags_in(&tx, source_id, target_id)?;
+    remove_unused_tags(&tx)?;
+    tx.commit()?;
+    Ok(())
+}
+
+fn move_tag_in(conn: &Connection, tag_id: i64, new_parent_id: Option<i64>) -> Result<()> {
+    let name = tag_name(conn, tag_id)?;
+    let leaf = name.rsplit(TAG_PATH_SEPARATOR).next().unwrap_or(&name).to_string();
+
+    let new_name = match new_parent_id {
+        Some(parent_id) => {
+            let parent_name = tag_name(conn, parent_id)?;
+            if is_same_or_descendant(&parent_name, &name) {
+                return Err(anyhow::anyhow!("Cannot move \"{}\" into itself", name));
+            }
+            format!("{}{}{}", parent_name, TAG_PATH_SEPARATOR, leaf)
+        }
+        None => leaf,
+    };
+
+    if new_name == name {
+        return Ok(());
+    }
+
+    let existing: Option<i64> = conn
+        .query_row("SELECT id FROM tags WHERE name = ?1 AND id != ?2", params![new_name, tag_id], |row| row.get(0))
+        .optional()?;
+    if let Some(existing) = existing {
+        return merge_tags_in(conn, tag_id, existing);
+    }
+
+    // Rename the tag and every descendant by swapping the path prefix
+    conn.execute(
+        "UPDATE tags SET name = ?1 || substr(name, length(?2) + 1)
+         WHERE id = ?3 OR name LIKE ?4 ESCAPE '\\'",
+        params![new_name, name, tag_id, descendant_pattern(&name)],
+    )?;
+    conn.execute("UPDATE tags SET parent_id = ?1 WHERE id = ?2", params![new_parent_id, tag_id])?;
+
+    Ok(())
+}
+
+fn merge_tags_in(conn: &Connection, source_id: i64, target_id: i64) -> Result<()> {
+    let source_name = tag_name(conn, source_id)?;
+    let target_name = tag_name(conn, target_id)?;
+    if is_same_or_descendant(&target_name, &source_name) {
+        return Err(anyhow::anyhow!("Cannot merge \"{}\" into itself", source_name));
+    }
+
+    conn.execute(
+        "INSERT OR IGNORE INTO media_tags (media_id, tag_id) SELECT media_id, ?1 FROM media_tags WHERE tag_id = ?2",
+        params![target_id, source_id],
+    )?;
+    conn.execute("DELETE FROM media_tags WHERE tag_id = ?1", [source_id])?;
+
+    let children: Vec<i64> = {
+        let mut stmt = conn.prepare("SELECT id FROM tags WHERE parent_id = ?1")?;
+        let children = stmt.query_map([source_id], |row| row.get(0))?;
+        children.collect::<rusqlite::Result<_>>()?
+    };
+    for child in children {
+        move_tag_in(conn, child, Some(target_id))?;
+    }
+
+    conn.execute("DELETE FROM tags WHERE id = ?1", [source_id])?;
+
+    Ok(())
+}
+
+fn tag_name(conn: &Connection, tag_id: i64) -> Result<String> {
+    conn.query_row("SELECT name FROM tags WHERE id = ?1", [tag_id], |row| row.get(0))
+        .optional()?
+        .ok_or_else(|| anyhow::anyhow!("Tag {} not found", tag_id))
+}
+
+fn is_same_or_descendant(name: &str, ancestor: &str) -> bool {
+    let name = name.to_lowercase();
+    let ancestor = ancestor.to_lowercase();
+    name == ancestor || name.starts_with(&format!("{}{}", ancestor, TAG_PATH_SEPARATOR))
+}
+
+/// LIKE pattern matching every descendant of a tag path
+pub fn descendant_pattern(name: &str) -> String {
+    format!("{}{}%", escape_like(name), TAG_PATH_SEPARATOR)
+}
@@ -0,0 +1,501 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+use tauri::{AppHandle, Emitter};
+use anyhow::Result;
+use tracing::{error, info, warn};
+
+use crate::error::PenglerError;
+use crate::models::{is_media_file, MediaFile, MediaType};
+use crate::utils::{ensure_free_space, hash_file, run_in_worker_pool, wall_clock, write_atomically, DuplicateScreen, OperationTimer, Workload};
+use crate::commands::cache::{init_database, save_media_files_internal};
+use crate::commands::exif_edit::run_exiftool;
+use crate::commands::ingest::{process_file, IngestContext};
+use crate::commands::smart_albums::notify_smart_albums_changed;
+
+/// Emitted while an import copies a file, every few megabytes and when it's done
+pub const IMPORT_PROGRESS_EVENT: &str = "import-progress";
+
+/// Large reads keep big videos from crawling through the copy loop
+const COPY_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Bytes copied between progress events
+const PROGRESS_INTERVAL_BYTES: u64 = 8 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportProgress {
+    /// Files finished before the current one
+    pub processed: usize,
+    pub total: usize,
+    pub current_file: String,
+    pub bytes_copied: u64,
+    pub file_size: u64,
+}
+
+pub fn emit_import_progress(app: &AppHandle, progress: ImportProgress) {
+    if let Err(e) = app.emit(IMPORT_PROGRESS_EVENT, progress) {
+        warn!("Failed to emit {}: {}", IMPORT_PROGRESS_EVENT, e);
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TakeoutImportResult {
+    pub imported: usize,
+    pub duplicates_skipped: usize,
+    pub metadata_restored: usize,
+    pub failed: usize,
+}
+
+/// The parts of a Takeout `*.json` sidecar we care about
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TakeoutMetadata {
+    photo_taken_time: Option<TakeoutTimestamp>,
+    geo_data: Option<TakeoutGeoData>,
+    geo_data_exif: Option<TakeoutGeoData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TakeoutTimestamp {
+    timestamp: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TakeoutGeoData {
+    latitude: f64,
+    longitude: f64,
+}
+
+impl TakeoutMetadata {
+    fn taken_at(&self) -> Option<DateTime<Utc>> {
+        let seconds = self.photo_taken_time.as_ref()?.timestamp.parse::<i64>().ok()?;
+        DateTime::from_timestamp(seconds, 0)
+    }
+
+    fn location(&self) -> Option<(f64, f64)> {
+        // Takeout writes 0.0/0.0 when there is no location
+        [&self.geo_data_exif, &self.geo_data]
+            .into_iter()
+            .flatten()
+            .find(|geo| geo.latitude != 0.0 || geo.longitude != 0.0)
+            .map(|geo| (geo.latitude, geo.longitude))
+    }
+}
+
+/// A Takeout export, either extracted to a folder or still a zip archive
+enum TakeoutSource {
+    Folder(PathBuf),
+    Archive(zip::ZipArchive<File>),
+}
+
+struct TakeoutEntry {
+    path: PathBuf,
+    index: usize,
+    size: u64,
+}
+
+impl TakeoutSource {
+    fn open(path: &Path) -> Result<Self> {
+        if path.is_dir() {
+            Ok(TakeoutSource::Folder(path.to_path_buf()))
+        } else if path.is_file() {
+            Ok(TakeoutSource::Archive(zip::ZipArchive::new(File::open(path)?)?))
+        } else {
+            Err(anyhow::anyhow!("Takeout source not found: {}", path.display()))
+        }
+    }
+
+    /// List every file in the export with its path relative to the root
+    fn entries(&mut self) -> Result<Vec<TakeoutEntry>> {
+        match self {
+            TakeoutSource::Folder(root) => Ok(WalkDir::new(&*root)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+                .filter_map(|e| {
+                    let size = e.metadata().ok()?.len();
+                    let path = e.path().strip_prefix(&*root).ok()?.to_path_buf();
+                    Some((path, size))
+                })
+                .enumerate()
+                .map(|(index, (path, size))| TakeoutEntry { path, index, size })
+                .collect()),
+            TakeoutSource::Archive(archive) => {
+                let mut entries = Vec::new();
+                for index in 0..archive.len() {
+                    let file = archive.by_index(index)?;
+                    // enclosed_name rejects entries that would escape the destination
+                    if let (false, Some(path)) = (file.is_dir(), file.enclosed_name()) {
+                        entries.push(TakeoutEntry { path, index, size: file.size() });
+                    }
+                }
+                Ok(entries)
+            }
+        }
+    }
+
+    fn open_entry(&mut self, entry: &TakeoutEntry) -> Result<Box<dyn Read + '_>> {
+        match self {
+            TakeoutSource::Folder(root) => Ok(Box::new(File::open(root.join(&entry.path))?)),
+            TakeoutSource::Archive(archive) => Ok(Box::new(archive.by_index(entry.index)?)),
+        }
+    }
+}
+
+/// Import a Google Takeout export, restoring taken-at time and GPS from its JSON sidecars.
+/// Runs on the blocking pool so a long import doesn't hold up other commands.
+#[tauri::command]
+pub async fn import_google_takeout(
+    app: AppHandle,
+    archive_or_folder: String,
+    destination: String,
+) -> Result<TakeoutImportResult, PenglerError> {
+    let handle = app.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        run_in_worker_pool(Workload::Import, || {
+            import_google_takeout_internal(&handle, Path::new(&archive_or_folder), Path::new(&destination))
+        })
+        .and_then(|result| result)
+    })
+    .await
+    .map_err(|e| PenglerError::report("Google Takeout import stopped", e))?
+    .map_err(|e| PenglerError::report("Failed to import Google Takeout", e))?;
+    notify_smart_albums_changed(&app);
+    Ok(result)
+}
+
+fn import_google_takeout_internal(app: &AppHandle, source_path: &Path, destination: &Path) -> Result<TakeoutImportResult> {
+    info!("Importing Google Takeout: {}", source_path.display());
+
+    let timer = OperationTimer::start("import");
+    let mut source = TakeoutSource::open(source_path)?;
+    let entries = source.entries()?;
+
+    // Read all sidecars up front; album metadata.json files simply have no timestamp
+    let mut sidecars: HashMap<PathBuf, TakeoutMetadata> = HashMap::new();
+    for entry in entries.iter().filter(|e| e.path.extension().is_some_and(|ext| ext == "json")) {
+        let reader = source.open_entry(entry)?;
+        if let Ok(metadata) = serde_json::from_reader::<_, TakeoutMetadata>(reader) {
+            sidecars.insert(entry.path.clone(), metadata);
+        }
+    }
+
+    let media_entries: Vec<&TakeoutEntry> = entries
+        .iter()
+        .filter(|e| e.path.to_str().and_then(is_media_file).is_some())
+        .collect();
+
+    info!("Found {} media files and {} sidecars", media_entries.len(), sidecars.len());
+
+    // Check before writing anything rather than failing halfway through
+    let required_bytes: u64 = media_entries.iter().map(|e| e.size).sum();
+    ensure_free_space(destination, required_bytes)?;
+    fs::create_dir_all(destination)?;
+
+    let conn = init_database()?;
+    let mut existing_hash = conn.prepare(
+        "SELECT 1 FROM media_files
+         WHERE file_hash = ?1 OR file_hash IN (SELECT file_hash FROM import_sources WHERE source_hash = ?1)
+         LIMIT 1",
+    )?;
+    let mut screen = DuplicateScreen::load(&conn)?;
+    let mut ctx = IngestContext::local(&conn)?;
+
+    let mut result = TakeoutImportResult::default();
+    let mut seen_hashes: HashSet<String> = HashSet::new();
+    let mut imported = Vec::new();
+    let mut rewritten = Vec::new();
+    let total = media_entries.len();
+
+    for (index, entry) in media_entries.into_iter().enumerate() {
+        // Keep the album / "Photos from YYYY" folder name
+        let target_dir = match entry.path.parent().and_then(|p| p.file_name()) {
+            Some(folder) => destination.join(folder),
+            None => destination.to_path_buf(),
+        };
+        let file_name = entry.path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+
+        // Takeout repeats files across albums and as "(1)" copies; skip those without copying
+        let known = if let TakeoutSource::Folder(root) = &source {
+            screen.find_file(&root.join(&entry.path))
+        } else {
+            screen.find_stream(entry.size, || source.open_entry(entry))
+        };
+        match known {
+            Ok(Some(_)) => {
+                result.duplicates_skipped += 1;
+                continue;
+            }
+            Ok(None) => {}
+            Err(e) => error!("Failed to check {} for duplicates: {}", entry.path.display(), e),
+        }
+
+        let current_file = entry.path.to_string_lossy().to_string();
+        let mut on_progress = |bytes_copied| {
+            let progress = ImportProgress {
+                processed: index,
+                total,
+                current_file: current_file.clone(),
+                bytes_copied,
+                file_size: entry.size,
+            };
+            emit_import_progress(app, progress);
+        };
+        let (target, hash) = match copy_entry(&mut source, entry, &target_dir, file_name, &mut on_progress) {
+            Ok(copied) => copied,
+            Err(e) => {
+                error!("Failed to copy {}: {}", entry.path.display(), e);
+                result.failed += 1;
+                continue;
+            }
+        };
+
+        // Library files that are offline or changed since their scan aren't screened
+        if seen_hashes.contains(&hash) || existing_hash.exists([&hash])? {
+            match fs::remove_file(&target) {
+                Ok(()) => result.duplicates_skipped += 1,
+                Err(e) => {
+                    error!("Failed to remove duplicate copy {}: {}", target.display(), e);
+                    result.failed += 1;
+                }
+            }
+            continue;
+        }
+        // The copy was hashed on the way, so it needn't be read again
+        ctx.record_hash(&target, &hash);
+
+        let metadata = sidecar_candidates(&entry.path)
+            .iter()
+            .find_map(|candidate| sidecars.get(candidate));
+
+        let media = process_file(&target, &ctx).and_then(|mut media| {
+            if let Some(metadata) = metadata {
+                restore_metadata(&target, &mut media, metadata, &mut ctx)?;
+            }
+            Ok(media)
+        });
+        let media = match media {
+            Ok(media) => media,
+            Err(e) => {
+                error!("Failed to import {}: {}", target.display(), e);
+                // Leave no copy behind that the library doesn't know about
+                if let Err(e) = fs::remove_file(&target) {
+                    error!("Failed to remove {}: {}", target.display(), e);
+                }
+                result.failed += 1;
+                continue;
+            }
+        };
+        if metadata.is_some() {
+            result.metadata_restored += 1;
+        }
+        if media.file_hash != hash {
+            rewritten.push((hash.clone(), media.file_hash.clone()));
+        }
+        screen.add(target.clone(), entry.size, hash.clone());
+        seen_hashes.insert(hash);
+
+        imported.push(media);
+        result.imported += 1;
+    }
+
+    drop(existing_hash);
+    let bytes = imported.iter().map(|media| media.file_size.max(0) as u64).sum();
+    timer.finish(imported.len(), bytes);
+    ctx.save(&conn)?;
+    save_media_files_internal(imported)?;
+
+    let mut remember_source = conn.prepare("INSERT OR REPLACE INTO import_sources (source_hash, file_hash) VALUES (?1, ?2)")?;
+    for (source_hash, file_hash) in &rewritten {
+        remember_source.execute([source_hash, file_hash])?;
+    }
+
+    info!(
+        "Takeout import finished: {} imported, {} duplicates skipped, {} failed",
+        result.imported, result.duplicates_skipped, result.failed
+    );
+
+    Ok(result)
+}
+
+/// Give an imported copy the date and place from its Takeout sidecar. Photos missing
+/// them get them written in, so they travel with the file; otherwise they are only
+/// kept in the library.
+fn restore_metadata(
+    target: &Path,
+    media: &mut MediaFile,
+    metadata: &TakeoutMetadata,
+    ctx: &mut IngestContext,
+) -> Result<()> {
+    let taken_at = metadata.taken_at();
+    let location = metadata.location();
+
+    let mut args = Vec::new();
+    if media.media_type == MediaType::Image {
+        if let (None, Some(taken_at)) = (media.taken_at, taken_at) {
+            // Takeout has the instant; dates taken are kept as wall-clock time
+            let (wall_clock, offset) = wall_clock(taken_at);
+            args.push(format!("-DateTimeOriginal={}", wall_clock.format("%Y:%m:%d %H:%M:%S")));
+            args.push(format!(
+                "-OffsetTimeOriginal={}{:02}:{:02}",
+                if offset < 0 { "-" } else { "+" },
+                offset.abs() / 60,
+                offset.abs() % 60
+            ));
+        }
+        if let (None, Some((latitude, longitude))) = (media.latitude, location) {
+            args.push(format!("-GPSLatitude={}", latitude.abs()));
+            args.push(format!("-GPSLatitudeRef={}", if latitude < 0.0 { "S" } else { "N" }));
+            args.push(format!("-GPSLongitude={}", longitude.abs()));
+            args.push(format!("-GPSLongitudeRef={}", if longitude < 0.0 { "W" } else { "E" }));
+        }
+    }
+
+    let mut rewritten = false;
+    if !args.is_empty() {
+        let mut exiftool_args: Vec<&std::ffi::OsStr> = vec!["-overwrite_original".as_ref()];
+        exiftool_args.extend(args.iter().map(std::ffi::OsStr::new));
+        exiftool_args.push(target.as_os_str());
+        match run_exiftool(&exiftool_args) {
+            Ok(()) => rewritten = true,
+            // The library still gets them below
+            Err(e) => warn!("Failed to write Takeout metadata into {}: {}", target.display(), e),
+        }
+    }
+
+    if let Some(taken_at) = taken_at {
+        // Downloaded files carry the export time as mtime
+        let mtime = filetime::FileTime::from_unix_time(taken_at.timestamp(), 0);
+        filetime::set_file_mtime(target, mtime)?;
+    }
+    if rewritten {
+        ctx.record_hash(target, &hash_file(target)?);
+        *media = process_file(target, ctx)?;
+    } else if taken_at.is_some() {
+        ctx.record_hash(target, &media.file_hash);
+    }
+
+    if let Some(taken_at) = taken_at {
+        media.modified_at = taken_at;
+        if media.taken_at.is_none() {
+            let (wall_clock, offset) = wall_clock(taken_at);
+            media.taken_at = Some(wall_clock);
+            media.taken_offset = Some(offset);
+        }
+    }
+    if let (None, Some((latitude, longitude))) = (media.latitude, location) {
+        media.latitude = Some(latitude);
+        media.longitude = Some(longitude);
+    }
+
+    Ok(())
+}
+
+fn copy_entry(
+    source: &mut TakeoutSource,
+    entry: &TakeoutEntry,
+    target_dir: &Path,
+    file_name: &str,
+    on_progress: &mut dyn FnMut(u64),
+) -> Result<(PathBuf, String)> {
+    fs::create_dir_all(target_dir)?;
+    let target = unique_destination(target_dir, file_name);
+
+    let reader = source.open_entry(entry)?;
+    let hash = write_atomically(&target, |part_path| copy_and_hash(reader, part_path, on_progress))?;
+
+    Ok((target, hash))
+}
+
+/// Sidecar names Takeout may have used for a media file
+fn sidecar_candidates(media_path: &Path) -> Vec<PathBuf> {
+    let dir = media_path.parent().unwrap_or(Path::new(""));
+    let file_name = media_path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+
+    let mut names = vec![
+        format!("{}.json", file_name),
+        format!("{}.supplemental-metadata.json", file_name),
+    ];
+
+    if let Some((stem, ext)) = file_name.rsplit_once('.') {
+        // "IMG(1).jpg" pairs with "IMG.jpg(1).json"
+        if let (true, Some(open)) = (stem.ends_with(')'), stem.rfind('(')) {
+            let (base, counter) = stem.split_at(open);
+            names.push(format!("{}.{}{}.json", base, ext, counter));
+        }
+
+        // Edited copies share the original's sidecar
+        if let Some(base) = stem.strip_suffix("-edited") {
+            names.push(format!("{}.{}.json", base, ext));
+        }
+
+        names.push(format!("{}.json", stem));
+    }
+
+    // Long sidecar names are truncated to 46 characters before ".json"
+    let truncated: String = file_name.chars().take(46).collect();
+    names.push(format!("{}.json", truncated));
+
+    names.into_iter().map(|name| dir.join(name)).collect()
+}
+
+/// Pick a free file name in `dir`, appending " (n)" on conflicts
+pub fn unique_destination(dir: &Path, file_name: &str) -> PathBuf {
+    unique_destination_by(dir, file_name, |candidate| candidate.exists())
+}
+
+/// `unique_destination` with another test of whether a name is taken
+pub fn unique_destination_by(dir: &Path, file_name: &str, taken: impl Fn(&Path) -> bool) -> PathBuf {
+    let candidate = dir.join(file_name);
+    if !taken(&candidate) {
+        return candidate;
+    }
+
+    let (stem, ext) = match file_name.rsplit_once('.') {
+        Some((stem, ext)) => (stem, format!(".{}", ext)),
+        None => (file_name, String::new()),
+    };
+
+    let mut counter = 1;
+    loop {
+        let candidate = dir.join(format!("{} ({}){}", stem, counter, ext));
+        if !taken(&candidate) {
+            return candidate;
+        }
+        counter += 1;
+    }
+}
+
+/// Copy a stream to `dest` while computing its BLAKE3 hash in the same pass, telling
+/// `on_progress` the bytes copied so far every few megabytes and at the end
+pub fn copy_and_hash(mut reader: impl Read, dest: &Path, on_progress: &mut dyn FnMut(u64)) -> Result<String> {
+    let mut output = File::create(dest)?;
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0; COPY_BUFFER_SIZE];
+    let mut copied = 0u64;
+    let mut reported = 0u64;
+
+    loop {
+        let count = reader.read(&mut buffer)?;
+        if count == 0 {
+            break;
+        }
+        hasher.update(&buffer[..count]);
+        output.write_all(&buffer[..count])?;
+
+        copied += count as u64;
+        if copied - reported >= PROGRESS_INTERVAL_BYTES {
+            on_progress(copied);
+            reported = copied;
+        }
+    }
+    on_progress(copied);
+
+    Ok(hasher.finalize().to_hex().to_string())
+}
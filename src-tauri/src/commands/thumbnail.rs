@@ -1,56 +1,183 @@
 use std::path::{Path, PathBuf};
 use std::fs;
 use std::process::Command;
-use image::{imageops::FilterType, ImageFormat};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
+use image::{imageops::FilterType, DynamicImage, ImageFormat};
+use rusqlite::OptionalExtension;
 use anyhow::Result;
+use tracing::warn;
 
-use crate::utils::short_hash;
-use crate::models::{MediaType, is_media_file};
+use crate::config::get_data_folder;
+use crate::error::PenglerError;
+use crate::utils::{
+    apply_edits, convert_to_srgb, is_hdr, is_online_only, open_image_with_profile, open_upright_image_with_profile, probe_video, short_hash,
+    run_interactive, write_atomically, EditRecipe, EncryptionKey, OperationTimer,
+};
+use crate::models::{MediaType, detect_media_type};
+use crate::commands::cache::init_database;
+use crate::commands::cache_janitor::{cache_budget, record_view};
+use crate::commands::edits::current_recipe;
+use crate::commands::private::{private_key, thumbnail_key, ENCRYPTED_THUMBNAIL_EXTENSION};
 
 const THUMBNAIL_SIZE: u32 = 300;
 
+/// Seal every thumbnail, not just those of private files; see `encrypt_cache`
+static ENCRYPT_THUMBNAILS: AtomicBool = AtomicBool::new(false);
+
+/// Set from the config
+pub fn set_thumbnail_encryption(enabled: bool) {
+    ENCRYPT_THUMBNAILS.store(enabled, Ordering::Relaxed);
+}
+
+pub fn thumbnails_encrypted() -> bool {
+    ENCRYPT_THUMBNAILS.load(Ordering::Relaxed)
+}
+
+/// Map HDR (PQ/HLG, BT.2020) frames to SDR BT.709
+const HDR_TONEMAP_FILTER: &str = "zscale=t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,\
+    tonemap=tonemap=hable:desat=0,zscale=t=bt709:m=bt709:r=tv,format=yuv420p";
+
+/// Thumbnail of a media file; with `with_edits` photos are rendered with their saved adjustments.
+/// The grid is waiting on it, so it goes ahead of queued batch work. Files in private
+/// folders, and all files with `encrypt_cache`, get an encrypted thumbnail, shown through
+/// the `private` URI scheme; it fails with `Locked` until the passphrase was entered.
 #[tauri::command]
 pub async fn generate_thumbnail(
     file_path: String,
     file_hash: String,
-) -> Result<String, String> {
-    generate_thumbnail_internal(&file_path, &file_hash)
-        .map_err(|e| format!("Failed to generate thumbnail: {}", e))
+    with_edits: Option<bool>,
+) -> Result<String, PenglerError> {
+    record_view(&file_hash);
+    // Waiting for a slot and rendering both block, so neither may hold up the async runtime
+    tauri::async_runtime::spawn_blocking(move || {
+        run_interactive(|| generate_thumbnail_internal(&file_path, &file_hash, with_edits.unwrap_or(false)))
+    })
+    .await
+    .map_err(|e| PenglerError::report("Thumbnail generation stopped", e))?
+    .map_err(|e| PenglerError::report("Failed to generate thumbnail", e))
 }
 
-fn generate_thumbnail_internal(file_path: &str, file_hash: &str) -> Result<String> {
+pub fn generate_thumbnail_internal(file_path: &str, file_hash: &str, with_edits: bool) -> Result<String> {
     let source_path = Path::new(file_path);
+    // Adjustments only apply to photos
+    let recipe = if with_edits && detect_media_type(source_path) == Some(MediaType::Image) {
+        edit_recipe_for(file_path)?
+    } else {
+        None
+    };
+
+    // Sealed with the session key for private files or an encrypted cache
+    let key = thumbnail_key(file_path)?;
 
     // Get cache directory
     let cache_dir = get_cache_directory()?;
     let thumbnail_dir = cache_dir.join("thumbnails");
     fs::create_dir_all(&thumbnail_dir)?;
 
-    // Generate thumbnail filename
-    let short_name = short_hash(file_hash);
-    let thumbnail_path = thumbnail_dir.join(format!("{}.webp", short_name));
+    // Generate thumbnail filename; edited renders are keyed by the recipe too
+    let short_name = match &recipe {
+        Some(recipe) => format!(
+            "{}-{}",
+            short_hash(file_hash),
+            short_hash(blake3::hash(serde_json::to_string(recipe)?.as_bytes()).to_hex().as_str())
+        ),
+        None => short_hash(file_hash),
+    };
+    let thumbnail_path = thumbnail_file(&thumbnail_dir, &short_name, key.is_some());
 
     // Check if thumbnail already exists
     if thumbnail_path.exists() {
         return Ok(thumbnail_path.to_string_lossy().to_string());
     }
 
+    // Rendering would download the whole file; the UI offers to hydrate it instead
+    if is_online_only(source_path) {
+        return Err(anyhow::anyhow!("The file is online-only"));
+    }
+
     // Determine media type
-    let media_type = is_media_file(file_path)
+    let media_type = detect_media_type(source_path)
         .ok_or_else(|| anyhow::anyhow!("Not a supported media file"))?;
 
-    match media_type {
-        MediaType::Image => generate_image_thumbnail(source_path, &thumbnail_path)?,
-        MediaType::Video => generate_video_thumbnail(source_path, &thumbnail_path)?,
-    }
+    // Write to a .part file so an interrupted run can't leave a broken thumbnail behind
+    let timer = OperationTimer::start("thumbnail");
+    write_atomically(&thumbnail_path, |part_path| {
+        match media_type {
+            MediaType::Image => generate_image_thumbnail(source_path, part_path, recipe.as_ref())?,
+            MediaType::Video => generate_video_thumbnail(source_path, part_path)?,
+        }
+        seal_in_place(key.as_deref(), part_path)
+    })?;
+    timer.finish(1, fs::metadata(source_path).map(|m| m.len()).unwrap_or(0));
 
     Ok(thumbnail_path.to_string_lossy().to_string())
 }
 
-fn generate_image_thumbnail(source_path: &Path, thumbnail_path: &Path) -> Result<()> {
+fn generate_image_thumbnail(source_path: &Path, thumbnail_path: &Path, recipe: Option<&EditRecipe>) -> Result<()> {
     // Open and resize image
-    let img = image::open(source_path)?;
-    let thumbnail = img.resize(THUMBNAIL_SIZE, THUMBNAIL_SIZE, FilterType::Lanczos3);
+    let (img, icc) = match recipe {
+        // Recipes are relative to the photo as displayed, so orient it first
+        Some(recipe) => {
+            let (img, icc) = open_upright_image_with_profile(source_path)?;
+            (apply_edits(img, recipe), icc)
+        }
+        None => open_image_with_profile(source_path)?,
+    };
+    write_image_thumbnail(&img, icc.as_deref(), thumbnail_path)
+}
+
+/// Store the unedited thumbnail of a photo that was decoded anyway, e.g. while scanning,
+/// so it doesn't have to be decoded again when the grid asks for it. Thumbnails that
+/// must be sealed are left to `generate_thumbnail` while the key isn't there.
+pub fn cache_image_thumbnail(file_path: &str, file_hash: &str, img: &DynamicImage, icc: Option<&[u8]>) -> Result<()> {
+    let Ok(key) = thumbnail_key(file_path) else { return Ok(()) };
+    let thumbnail_dir = get_cache_directory()?.join("thumbnails");
+    fs::create_dir_all(&thumbnail_dir)?;
+
+    let thumbnail_path = thumbnail_file(&thumbnail_dir, &short_hash(file_hash), key.is_some());
+    if thumbnail_path.exists() {
+        return Ok(());
+    }
+    write_atomically(&thumbnail_path, |part_path| {
+        write_image_thumbnail(img, icc, part_path)?;
+        seal_in_place(key.as_deref(), part_path)
+    })
+}
+
+fn thumbnail_file(thumbnail_dir: &Path, name: &str, encrypted: bool) -> PathBuf {
+    if encrypted {
+        thumbnail_dir.join(format!("{}.webp.{}", name, ENCRYPTED_THUMBNAIL_EXTENSION))
+    } else {
+        thumbnail_dir.join(format!("{}.webp", name))
+    }
+}
+
+/// Replace a freshly written thumbnail with its sealed form. It's plain on disk only
+/// as a `.part` file, for the moment between the two writes.
+fn seal_in_place(key: Option<&EncryptionKey>, path: &Path) -> Result<()> {
+    match key {
+        Some(key) => Ok(fs::write(path, key.seal(&fs::read(path)?)?)?),
+        None => Ok(()),
+    }
+}
+
+/// Contents of a thumbnail made by `generate_thumbnail`, decrypted if it's sealed
+pub fn read_thumbnail(path: &Path) -> Result<Vec<u8>> {
+    let bytes = fs::read(path)?;
+    if path.extension().is_some_and(|ext| ext == ENCRYPTED_THUMBNAIL_EXTENSION) {
+        return private_key()?.open(&bytes);
+    }
+    Ok(bytes)
+}
+
+fn write_image_thumbnail(img: &DynamicImage, icc: Option<&[u8]>, thumbnail_path: &Path) -> Result<()> {
+    let mut thumbnail = img.resize(THUMBNAIL_SIZE, THUMBNAIL_SIZE, FilterType::Lanczos3);
+
+    // Wide-gamut photos (Display P3, Adobe RGB) look washed out unless converted to sRGB
+    if let Some(icc) = icc {
+        thumbnail = convert_to_srgb(thumbnail, icc)?;
+    }
 
     // Save as WebP
     thumbnail.save_with_format(thumbnail_path, ImageFormat::WebP)?;
@@ -62,15 +189,24 @@ fn generate_video_thumbnail(source_path: &Path, thumbnail_path: &Path) -> Result
     // Create a temporary PNG file first
     let temp_png = thumbnail_path.with_extension("png");
 
-    // Try to use ffmpeg to extract frame at 1 second
-    let output = Command::new("ffmpeg")
-        .arg("-ss").arg("1") // Seek to 1 second
-        .arg("-i").arg(source_path)
-        .arg("-vframes").arg("1") // Extract one frame
-        .arg("-vf").arg(format!("scale={}:{}:force_original_aspect_ratio=decrease", THUMBNAIL_SIZE, THUMBNAIL_SIZE))
-        .arg("-y") // Overwrite output file
-        .arg(&temp_png)
-        .output();
+    let scale = format!("scale={}:{}:force_original_aspect_ratio=decrease", THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+
+    // HDR frames have blown-out highlights unless tone mapped to SDR first
+    let hdr = probe_video(source_path)
+        .ok()
+        .and_then(|probe| probe.color_space)
+        .is_some_and(|color_space| is_hdr(&color_space));
+
+    let mut output = extract_video_frame(source_path, &temp_png, &if hdr {
+        format!("{},{}", HDR_TONEMAP_FILTER, scale)
+    } else {
+        scale.clone()
+    });
+
+    // The tone mapping filters need an ffmpeg built with zimg; fall back to the plain frame
+    if hdr && !matches!(&output, Ok(result) if result.status.success()) {
+        output = extract_video_frame(source_path, &temp_png, &scale);
+    }
 
     match output {
         Ok(result) if result.status.success() => {
@@ -91,7 +227,7 @@ fn generate_video_thumbnail(source_path: &Path, thumbnail_path: &Path) -> Result
         Err(e) => {
             // If ffmpeg is not installed, return a more helpful error
             if e.kind() == std::io::ErrorKind::NotFound {
-                Err(anyhow::anyhow!("ffmpeg not found. Please install ffmpeg to generate video thumbnails."))
+                Err(PenglerError::FfmpegMissing("ffmpeg not found. Please install ffmpeg to generate video thumbnails.".to_string()).into())
             } else {
                 Err(anyhow::anyhow!("Failed to run ffmpeg: {}", e))
             }
@@ -99,16 +235,82 @@ fn generate_video_thumbnail(source_path: &Path, thumbnail_path: &Path) -> Result
     }
 }
 
+fn extract_video_frame(source_path: &Path, frame_path: &Path, filter: &str) -> std::io::Result<std::process::Output> {
+    // Try to use ffmpeg to extract frame at 1 second
+    Command::new("ffmpeg")
+        .arg("-ss").arg("1") // Seek to 1 second
+        .arg("-i").arg(source_path)
+        .arg("-vframes").arg("1") // Extract one frame
+        .arg("-vf").arg(filter)
+        .arg("-y") // Overwrite output file
+        .arg(frame_path)
+        .output()
+}
+
+/// Saved adjustments of the cataloged file at `file_path`, unless they change nothing
+fn edit_recipe_for(file_path: &str) -> Result<Option<EditRecipe>> {
+    let conn = init_database()?;
+    let media_id: Option<i64> = conn
+        .query_row("SELECT id FROM media_files WHERE file_path = ?1", [file_path], |row| row.get(0))
+        .optional()?;
+
+    Ok(match media_id {
+        Some(media_id) => current_recipe(&conn, media_id)?.filter(|recipe| !recipe.is_identity()),
+        None => None,
+    })
+}
+
 pub fn get_cache_directory() -> Result<PathBuf> {
-    let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Cannot find home directory"))?;
-    let cache_dir = home.join(".pengler").join("cache");
-    Ok(cache_dir)
+    Ok(get_data_folder()?.join("cache"))
+}
+
+/// Remove every cached thumbnail of a file, plain and edited renders alike
+pub fn remove_thumbnails(file_hash: &str) -> Result<()> {
+    remove_thumbnail_files(file_hash, true)
+}
+
+/// Remove every encrypted thumbnail, or every plain one, e.g. when the cache is switched
+/// to the other
+pub fn remove_cached_thumbnails(encrypted: bool) -> Result<usize> {
+    let mut removed = 0;
+    for thumbnail in cached_thumbnails()?.into_iter().filter(|thumbnail| thumbnail.encrypted == encrypted) {
+        match fs::remove_file(&thumbnail.path) {
+            Ok(()) => removed += 1,
+            Err(e) => warn!("Failed to remove {}: {}", thumbnail.path.display(), e),
+        }
+    }
+    Ok(removed)
+}
+
+/// Remove the unencrypted thumbnails of a file, e.g. once it became private
+pub fn remove_plain_thumbnails(file_hash: &str) -> Result<()> {
+    remove_thumbnail_files(file_hash, false)
+}
+
+fn remove_thumbnail_files(file_hash: &str, encrypted_too: bool) -> Result<()> {
+    let thumbnail_dir = get_cache_directory()?.join("thumbnails");
+    let key = short_hash(file_hash);
+    let edited_prefix = format!("{}-", key);
+
+    let entries = match fs::read_dir(&thumbnail_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some((stem, encrypted)) = thumbnail_stem(&name) else { continue };
+        if (stem == key || stem.starts_with(&edited_prefix)) && (encrypted_too || !encrypted) {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
 }
 
 #[tauri::command]
-pub async fn get_cache_stats() -> Result<CacheStats, String> {
+pub async fn get_cache_stats() -> Result<CacheStats, PenglerError> {
     get_cache_stats_internal()
-        .map_err(|e| format!("Failed to get cache stats: {}", e))
+        .map_err(|e| PenglerError::report("Failed to get cache stats", e))
 }
 
 #[derive(serde::Serialize)]
@@ -116,46 +318,74 @@ pub async fn get_cache_stats() -> Result<CacheStats, String> {
 pub struct CacheStats {
     pub total_size: u64,
     pub file_count: usize,
+    /// `cache_max_bytes`; 0 when unlimited
     pub max_size: u64,
 }
 
 fn get_cache_stats_internal() -> Result<CacheStats> {
-    let cache_dir = get_cache_directory()?;
-    let thumbnail_dir = cache_dir.join("thumbnails");
+    let thumbnails = cached_thumbnails()?;
 
-    if !thumbnail_dir.exists() {
-        return Ok(CacheStats {
-            total_size: 0,
-            file_count: 0,
-            max_size: 500 * 1024 * 1024, // 500MB
-        });
+    Ok(CacheStats {
+        total_size: thumbnails.iter().map(|thumbnail| thumbnail.size).sum(),
+        file_count: thumbnails.len(),
+        max_size: cache_budget(),
+    })
+}
+
+/// A finished thumbnail in the cache
+pub struct CachedThumbnail {
+    pub path: PathBuf,
+    /// `short_hash` of the file it shows; edited renders share it
+    pub key: String,
+    pub size: u64,
+    pub modified: SystemTime,
+    /// Of a private file, sealed with the private passphrase's key
+    pub encrypted: bool,
+}
+
+/// Name of a thumbnail file without its extensions, and whether it's encrypted; `None`
+/// for anything else in the folder
+fn thumbnail_stem(name: &str) -> Option<(&str, bool)> {
+    match name.strip_suffix(ENCRYPTED_THUMBNAIL_EXTENSION).and_then(|name| name.strip_suffix('.')) {
+        Some(name) => name.strip_suffix(".webp").map(|stem| (stem, true)),
+        None => name.strip_suffix(".webp").map(|stem| (stem, false)),
     }
+}
 
-    let mut total_size = 0u64;
-    let mut file_count = 0usize;
+/// Every thumbnail in the cache, plain and edited renders alike; renders still being
+/// written are left out
+pub fn cached_thumbnails() -> Result<Vec<CachedThumbnail>> {
+    let thumbnail_dir = get_cache_directory()?.join("thumbnails");
+    let entries = match fs::read_dir(&thumbnail_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
 
-    for entry in fs::read_dir(&thumbnail_dir)? {
-        if let Ok(entry) = entry {
-            if let Ok(metadata) = entry.metadata() {
-                if metadata.is_file() {
-                    total_size += metadata.len();
-                    file_count += 1;
-                }
-            }
+    let mut thumbnails = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some((stem, encrypted)) = thumbnail_stem(&name) else { continue };
+        let Ok(metadata) = entry.metadata() else { continue };
+        if !metadata.is_file() {
+            continue;
         }
-    }
 
-    Ok(CacheStats {
-        total_size,
-        file_count,
-        max_size: 500 * 1024 * 1024,
-    })
+        thumbnails.push(CachedThumbnail {
+            path: entry.path(),
+            key: stem.split('-').next().unwrap_or(stem).to_string(),
+            size: metadata.len(),
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            encrypted,
+        });
+    }
+    Ok(thumbnails)
 }
 
 #[tauri::command]
-pub async fn clear_cache() -> Result<(), String> {
+pub async fn clear_cache() -> Result<(), PenglerError> {
     clear_cache_internal()
-        .map_err(|e| format!("Failed to clear cache: {}", e))
+        .map_err(|e| PenglerError::report("Failed to clear cache", e))
 }
 
 fn clear_cache_internal() -> Result<()> {
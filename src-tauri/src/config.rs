@@ -1,16 +1,209 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 use anyhow::Result;
+use tracing::info;
+
+use crate::error::PenglerError;
+use crate::models::set_media_extensions;
+use crate::utils::{
+    is_read_only_instance, set_date_folder_template, set_date_timezone, set_worker_limits, validate_date_folder_template,
+    Watermark, DEFAULT_DATE_FOLDER_TEMPLATE,
+};
+use crate::models::media::{DEFAULT_IMAGE_EXTENSIONS, DEFAULT_VIDEO_EXTENSIONS};
+use crate::commands::cache::{init_database, refresh_modified_days};
+use crate::commands::cache_janitor::{set_cache_budget, DEFAULT_CACHE_MAX_BYTES};
+use crate::commands::private::has_private_passphrase;
+use crate::commands::thumbnail::{remove_cached_thumbnails, set_thumbnail_encryption};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
-    pub library_folders: Vec<String>,
+    /// Library folders with their settings
+    #[serde(default)]
+    pub folders: Vec<FolderSettings>,
+    /// Library folders as stored before per-folder settings; moved into `folders` on load
+    #[serde(default, skip_serializing)]
+    library_folders: Vec<String>,
+    #[serde(default, skip_serializing)]
+    network_folders: Vec<String>,
+    /// Threads each worker pool (scanning, importing, thumbnails, exports) may use; 0 leaves
+    /// one core free
+    #[serde(default)]
+    pub max_worker_threads: usize,
+    /// Run those threads at low priority so other apps stay responsive
+    #[serde(default)]
+    pub low_priority_workers: bool,
     pub cache_folder: String,
+    /// Keep every thumbnail encrypted on disk with the private passphrase's key; they show
+    /// once `unlock_cache` or `unlock_private` was called
+    #[serde(default)]
+    pub encrypt_cache: bool,
+    /// Size the thumbnail cache is trimmed back to, least recently viewed first; 0 for no limit
+    #[serde(default = "default_cache_max_bytes")]
+    pub cache_max_bytes: u64,
     #[serde(default = "default_quality")]
     pub optimization_quality: u8,
     #[serde(default = "default_max_resolution")]
     pub max_resolution: u32,
+    /// Max gap between shots from the same camera to stack them as a burst
+    #[serde(default = "default_burst_window_ms")]
+    pub burst_window_ms: u32,
+    /// Zone for dates that carry no offset of their own, like file modification times
+    /// and imported timestamps: "local" (this computer's), "utc" or e.g. "+09:00"
+    #[serde(default = "default_date_timezone")]
+    pub date_timezone: String,
+    /// Folders that imports and date-arranged copies sort files into, in strftime codes;
+    /// `/` starts a subfolder, e.g. "%Y/%m - %B" for "2024/03 - März"
+    #[serde(default = "default_date_folder_template")]
+    pub date_folder_template: String,
+    /// Language of month and day names in date folders, e.g. "de_DE"; empty for the system's
+    #[serde(default)]
+    pub locale: String,
+    /// File extensions (without the dot) treated as photos
+    #[serde(default = "default_image_extensions")]
+    pub image_extensions: Vec<String>,
+    /// File extensions (without the dot) treated as videos
+    #[serde(default = "default_video_extensions")]
+    pub video_extensions: Vec<String>,
+    /// Mirror ratings, tags and favorites into XMP sidecars next to the originals
+    #[serde(default)]
+    pub write_xmp_sidecars: bool,
+    /// Allow the on-device face detector (builds with the `face-detection` feature only)
+    #[serde(default)]
+    pub face_detection: bool,
+    /// Recognize text in screenshots and scans with tesseract so it can be searched
+    #[serde(default)]
+    pub ocr: bool,
+    /// Tesseract languages, e.g. "eng+jpn"
+    #[serde(default = "default_ocr_languages")]
+    pub ocr_languages: String,
+    /// Compute CLIP embeddings so photos can be searched by description
+    /// (builds with the `semantic-search` feature only)
+    #[serde(default)]
+    pub semantic_search: bool,
+    /// Stamped on exports that ask for it
+    #[serde(default)]
+    pub watermark: Option<Watermark>,
+    /// Port of the LAN gallery server (builds with the `lan-server` feature only)
+    #[serde(default = "default_lan_server_port")]
+    pub lan_server_port: u16,
+    /// Access token for the LAN gallery server; generated on first start
+    #[serde(default)]
+    pub lan_server_token: Option<String>,
+    /// Name TVs show for the DLNA server (builds with the `dlna` feature only)
+    #[serde(default = "default_dlna_name")]
+    pub dlna_name: String,
+    #[serde(default = "default_dlna_port")]
+    pub dlna_port: u16,
+    /// Where `run_backup` mirrors the library folders
+    #[serde(default)]
+    pub backup: Option<BackupConfig>,
+    /// Off-site encrypted backup (builds with the `cloud-backup` feature only)
+    #[serde(default)]
+    pub cloud_backup: Option<CloudBackupConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderSettings {
+    pub path: String,
+    /// Pick up new and changed files without a manual rescan
+    #[serde(default)]
+    pub watch: bool,
+    /// Make optimized copies of new files as they arrive
+    #[serde(default)]
+    pub auto_optimize: bool,
+    /// Name of the optimization settings to use; the global ones when unset
+    #[serde(default)]
+    pub optimization_profile: Option<String>,
+    /// File and folder names to leave out of scans; `*` and `?` are wildcards,
+    /// e.g. "*.tmp" or "@eaDir"
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// On a NAS or other network share; scanned with quick hashes, fewer parallel
+    /// reads and retries on dropped connections
+    #[serde(default)]
+    pub network: bool,
+}
+
+impl FolderSettings {
+    pub fn new(path: String) -> Self {
+        Self {
+            path,
+            watch: false,
+            auto_optimize: false,
+            optimization_profile: None,
+            exclude: Vec::new(),
+            network: false,
+        }
+    }
+
+    /// Whether a file or folder named `name` matches one of the exclude patterns
+    pub fn is_excluded(&self, name: &str) -> bool {
+        self.exclude.iter().any(|pattern| wildcard_match(pattern, name))
+    }
+}
+
+/// Case-insensitive match of `name` against a pattern where `*` stands for any run of
+/// characters and `?` for a single one
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((after_star, tried)) => {
+                    p = after_star;
+                    n = tried + 1;
+                    backtrack = Some((after_star, tried + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupConfig {
+    /// Folder that receives the copies, usually on an external drive
+    pub target: String,
+    /// Also delete copies whose original is gone from the library
+    #[serde(default)]
+    pub mirror_deletions: bool,
+}
+
+/// An S3-compatible bucket (AWS S3, Backblaze B2, Cloudflare R2, MinIO, ...)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloudBackupConfig {
+    /// e.g. https://s3.eu-central-1.amazonaws.com or https://s3.us-west-004.backblazeb2.com
+    pub endpoint: String,
+    #[serde(default = "default_cloud_region")]
+    pub region: String,
+    pub bucket: String,
+    /// Key prefix inside the bucket, e.g. "pengler"
+    #[serde(default)]
+    pub prefix: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Files are encrypted with a key derived from this before they leave the machine;
+    /// without it the backup cannot be restored
+    pub passphrase: String,
+    /// Upload limit in KiB/s, 0 for unlimited
+    #[serde(default)]
+    pub bandwidth_limit_kbps: u32,
 }
 
 fn default_quality() -> u8 {
@@ -21,16 +214,84 @@ fn default_max_resolution() -> u32 {
     1920
 }
 
+fn default_date_timezone() -> String {
+    String::from("local")
+}
+
+fn default_date_folder_template() -> String {
+    String::from(DEFAULT_DATE_FOLDER_TEMPLATE)
+}
+
+fn default_burst_window_ms() -> u32 {
+    2000
+}
+
+fn default_ocr_languages() -> String {
+    String::from("eng")
+}
+
+fn default_lan_server_port() -> u16 {
+    8787
+}
+
+fn default_dlna_name() -> String {
+    String::from("Pengler")
+}
+
+fn default_dlna_port() -> u16 {
+    8788
+}
+
+fn default_cloud_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_image_extensions() -> Vec<String> {
+    DEFAULT_IMAGE_EXTENSIONS.iter().map(|ext| ext.to_string()).collect()
+}
+
+fn default_cache_max_bytes() -> u64 {
+    DEFAULT_CACHE_MAX_BYTES
+}
+
+fn default_video_extensions() -> Vec<String> {
+    DEFAULT_VIDEO_EXTENSIONS.iter().map(|ext| ext.to_string()).collect()
+}
+
 impl Default for Config {
     fn default() -> Self {
         let cache_folder = get_default_cache_folder()
             .unwrap_or_else(|_| String::from("~/.pengler/cache"));
 
         Self {
+            folders: Vec::new(),
             library_folders: Vec::new(),
+            network_folders: Vec::new(),
+            max_worker_threads: 0,
+            low_priority_workers: false,
             cache_folder,
+            encrypt_cache: false,
+            cache_max_bytes: default_cache_max_bytes(),
             optimization_quality: 85,
             max_resolution: 1920,
+            burst_window_ms: default_burst_window_ms(),
+            date_timezone: default_date_timezone(),
+            date_folder_template: default_date_folder_template(),
+            locale: String::new(),
+            image_extensions: default_image_extensions(),
+            video_extensions: default_video_extensions(),
+            write_xmp_sidecars: false,
+            face_detection: false,
+            ocr: false,
+            ocr_languages: default_ocr_languages(),
+            semantic_search: false,
+            watermark: None,
+            lan_server_port: default_lan_server_port(),
+            lan_server_token: None,
+            dlna_name: default_dlna_name(),
+            dlna_port: default_dlna_port(),
+            backup: None,
+            cloud_backup: None,
         }
     }
 }
@@ -41,16 +302,22 @@ impl Config {
 
         if config_path.exists() {
             let contents = fs::read_to_string(&config_path)?;
-            let config: Config = toml::from_str(&contents)?;
+            let mut config: Config = toml::from_str(&contents)?;
+            config.migrate_folders();
             Ok(config)
         } else {
             let config = Config::default();
-            config.save()?;
+            if !is_read_only_instance() {
+                config.save()?;
+            }
             Ok(config)
         }
     }
 
     pub fn save(&self) -> Result<()> {
+        if is_read_only_instance() {
+            return Err(PenglerError::ReadOnly("Settings can only be changed in the Pengler window opened first".to_string()).into());
+        }
         let config_path = get_config_path()?;
 
         // Create parent directory if it doesn't exist
@@ -61,23 +328,82 @@ impl Config {
         let toml_string = toml::to_string_pretty(self)?;
         fs::write(&config_path, toml_string)?;
 
+        self.apply();
+
         Ok(())
     }
 
+    /// Push settings that are read outside of commands into their process-wide homes
+    pub fn apply(&self) {
+        set_media_extensions(&self.image_extensions, &self.video_extensions);
+        set_worker_limits(self.max_worker_threads, self.low_priority_workers);
+        set_cache_budget(self.cache_max_bytes);
+        set_thumbnail_encryption(self.encrypt_cache);
+        set_date_timezone(&self.date_timezone);
+        set_date_folder_template(&self.date_folder_template, &self.locale);
+    }
+
+    /// Turn the flat folder lists of older configs into `folders`
+    fn migrate_folders(&mut self) {
+        for path in std::mem::take(&mut self.library_folders) {
+            if !self.folders.iter().any(|folder| folder.path == path) {
+                let network = self.network_folders.contains(&path);
+                self.folders.push(FolderSettings { network, ..FolderSettings::new(path) });
+            }
+        }
+        self.network_folders.clear();
+    }
+
+    /// Paths of the library folders
+    pub fn library_folders(&self) -> Vec<String> {
+        self.folders.iter().map(|folder| folder.path.clone()).collect()
+    }
+
     pub fn add_library_folder(&mut self, folder: String) -> Result<()> {
-        if !self.library_folders.contains(&folder) {
-            self.library_folders.push(folder);
+        if !self.folders.iter().any(|f| f.path == folder) {
+            self.folders.push(FolderSettings::new(folder));
             self.save()?;
         }
         Ok(())
     }
 
     pub fn remove_library_folder(&mut self, folder: &str) -> Result<()> {
-        self.library_folders.retain(|f| f != folder);
+        self.folders.retain(|f| f.path != folder);
+        self.save()?;
+        Ok(())
+    }
+
+    /// Replace the settings of the library folder at `settings.path`
+    pub fn set_folder_settings(&mut self, settings: FolderSettings) -> Result<()> {
+        let folder = self
+            .folders
+            .iter_mut()
+            .find(|f| f.path == settings.path)
+            .ok_or_else(|| anyhow::anyhow!("{} is not a library folder", settings.path))?;
+        *folder = settings;
         self.save()?;
         Ok(())
     }
 
+    pub fn set_network_folder(&mut self, folder: &str, network: bool) -> Result<()> {
+        let settings = self
+            .folders
+            .iter_mut()
+            .find(|f| f.path == folder)
+            .ok_or_else(|| anyhow::anyhow!("{} is not a library folder", folder))?;
+        settings.network = network;
+        self.save()?;
+        Ok(())
+    }
+
+    /// Settings of the library folder `path` is in; the innermost one for nested folders
+    pub fn folder_settings(&self, path: &Path) -> Option<&FolderSettings> {
+        self.folders
+            .iter()
+            .filter(|folder| path.starts_with(&folder.path))
+            .max_by_key(|folder| folder.path.len())
+    }
+
     pub fn set_cache_folder(&mut self, folder: String) -> Result<()> {
         self.cache_folder = folder;
         self.save()?;
@@ -85,45 +411,177 @@ impl Config {
     }
 }
 
-pub fn get_config_path() -> Result<PathBuf> {
+/// File beside the app that switches on portable mode, like starting it with `--portable`
+const PORTABLE_MARKER: &str = "pengler.portable";
+pub const PORTABLE_FLAG: &str = "--portable";
+
+static PORTABLE_DATA_FOLDER: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// `data/` beside the app in portable mode, so config, database and cache travel
+/// with it on an external drive. `None` when data lives in the home folder.
+pub fn portable_data_folder() -> Option<&'static Path> {
+    PORTABLE_DATA_FOLDER
+        .get_or_init(|| {
+            let app_folder = app_folder()?;
+            let flagged = std::env::args().skip(1).any(|arg| arg == PORTABLE_FLAG);
+            (flagged || app_folder.join(PORTABLE_MARKER).exists()).then(|| app_folder.join("data"))
+        })
+        .as_deref()
+}
+
+/// Folder the user sees the app in: beside the executable, the .app bundle on macOS
+/// or the AppImage on Linux
+fn app_folder() -> Option<PathBuf> {
+    if let Some(appimage) = std::env::var_os("APPIMAGE") {
+        return Path::new(&appimage).parent().map(Path::to_path_buf);
+    }
+
+    let exe = std::env::current_exe().ok()?;
+    let exe_folder = exe.parent()?;
+    // Pengler.app/Contents/MacOS/pengler
+    match exe_folder.ancestors().nth(2) {
+        Some(bundle) if bundle.extension().is_some_and(|ext| ext == "app") => bundle.parent().map(Path::to_path_buf),
+        _ => Some(exe_folder.to_path_buf()),
+    }
+}
+
+/// Where config, database, cache and logs live: ~/.pengler, or `data/` beside the app
+/// in portable mode
+pub fn get_data_folder() -> Result<PathBuf> {
+    if let Some(folder) = portable_data_folder() {
+        return Ok(folder.to_path_buf());
+    }
+
     let home = dirs::home_dir()
         .ok_or_else(|| anyhow::anyhow!("Cannot find home directory"))?;
-    Ok(home.join(".pengler").join("config.toml"))
+    Ok(home.join(".pengler"))
+}
+
+pub fn get_config_path() -> Result<PathBuf> {
+    Ok(get_data_folder()?.join("config.toml"))
+}
+
+/// Where optional ML models (face detector, CLIP) are looked up
+#[cfg(any(feature = "face-detection", feature = "semantic-search"))]
+pub fn get_models_folder() -> Result<PathBuf> {
+    Ok(get_data_folder()?.join("models"))
+}
+
+/// Where the rotating log files are written
+pub fn get_logs_folder() -> Result<PathBuf> {
+    Ok(get_data_folder()?.join("logs"))
 }
 
 pub fn get_default_cache_folder() -> Result<String> {
-    let home = dirs::home_dir()
-        .ok_or_else(|| anyhow::anyhow!("Cannot find home directory"))?;
-    Ok(home.join(".pengler").join("cache").to_string_lossy().to_string())
+    Ok(get_data_folder()?.join("cache").to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub async fn get_config() -> Result<Config, PenglerError> {
+    Config::load().map_err(|e| PenglerError::report("Failed to load config", e))
 }
 
+/// Whether another Pengler window owns the library, leaving this one to browse it
+/// without changing anything
 #[tauri::command]
-pub async fn get_config() -> Result<Config, String> {
-    Config::load().map_err(|e| e.to_string())
+pub async fn get_read_only() -> Result<bool, PenglerError> {
+    Ok(is_read_only_instance())
 }
 
 #[tauri::command]
-pub async fn update_config(config: Config) -> Result<(), String> {
-    config.save().map_err(|e| e.to_string())
+pub async fn update_config(mut config: Config) -> Result<(), PenglerError> {
+    update_config_internal(&mut config).map_err(|e| PenglerError::report("Failed to save config", e))
+}
+
+fn update_config_internal(config: &mut Config) -> Result<()> {
+    let previous = Config::load()?;
+    validate_date_folder_template(&config.date_folder_template)
+        .map_err(|e| PenglerError::InvalidInput(e.to_string()))?;
+    if config.encrypt_cache && !previous.encrypt_cache && !has_private_passphrase(&init_database()?)? {
+        return Err(PenglerError::InvalidInput("Set a private passphrase before encrypting the cache".to_string()).into());
+    }
+    config.migrate_folders();
+    config.save()?;
+
+    // Thumbnails are made again in the other form as they're viewed
+    if config.encrypt_cache != previous.encrypt_cache {
+        let removed = remove_cached_thumbnails(!config.encrypt_cache)?;
+        info!("Removed {} cached thumbnails after switching cache encryption", removed);
+    }
+
+    // Files without a date taken are grouped by their modification day in that zone
+    if config.date_timezone != previous.date_timezone {
+        refresh_modified_days(&init_database()?, false)?;
+    }
+    Ok(())
 }
 
 #[tauri::command]
-pub async fn add_library_folder(folder: String) -> Result<Config, String> {
-    let mut config = Config::load().map_err(|e| e.to_string())?;
-    config.add_library_folder(folder).map_err(|e| e.to_string())?;
+pub async fn add_library_folder(folder: String) -> Result<Config, PenglerError> {
+    let mut config = Config::load().map_err(|e| PenglerError::report("Failed to load config", e))?;
+    config.add_library_folder(folder).map_err(|e| PenglerError::report("Failed to add library folder", e))?;
     Ok(config)
 }
 
 #[tauri::command]
-pub async fn remove_library_folder(folder: String) -> Result<Config, String> {
-    let mut config = Config::load().map_err(|e| e.to_string())?;
-    config.remove_library_folder(&folder).map_err(|e| e.to_string())?;
+pub async fn remove_library_folder(folder: String) -> Result<Config, PenglerError> {
+    let mut config = Config::load().map_err(|e| PenglerError::report("Failed to load config", e))?;
+    config.remove_library_folder(&folder).map_err(|e| PenglerError::report("Failed to remove library folder", e))?;
     Ok(config)
 }
 
+/// Change what a library folder is watched, optimized and scanned with
 #[tauri::command]
-pub async fn set_cache_folder(folder: String) -> Result<Config, String> {
-    let mut config = Config::load().map_err(|e| e.to_string())?;
-    config.set_cache_folder(folder).map_err(|e| e.to_string())?;
+pub async fn set_folder_settings(settings: FolderSettings) -> Result<Config, PenglerError> {
+    let mut config = Config::load().map_err(|e| PenglerError::report("Failed to load config", e))?;
+    config.set_folder_settings(settings).map_err(|e| PenglerError::report("Failed to set folder settings", e))?;
     Ok(config)
 }
+
+/// Mark a library folder as being on a network share (SMB/NFS) or not
+#[tauri::command]
+pub async fn set_network_folder(folder: String, network: bool) -> Result<Config, PenglerError> {
+    let mut config = Config::load().map_err(|e| PenglerError::report("Failed to load config", e))?;
+    config.set_network_folder(&folder, network).map_err(|e| PenglerError::report("Failed to set network folder", e))?;
+    Ok(config)
+}
+
+#[tauri::command]
+pub async fn set_cache_folder(folder: String) -> Result<Config, PenglerError> {
+    let mut config = Config::load().map_err(|e| PenglerError::report("Failed to load config", e))?;
+    config.set_cache_folder(folder).map_err(|e| PenglerError::report("Failed to set cache folder", e))?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_literal_names_ignoring_case() {
+        assert!(wildcard_match("Thumbs.db", "thumbs.DB"));
+        assert!(!wildcard_match("Thumbs.db", "Thumbs.db2"));
+        assert!(!wildcard_match("Thumbs.db", "Thumbs.d"));
+    }
+
+    #[test]
+    fn star_matches_any_run() {
+        assert!(wildcard_match("*.tmp", "photo.tmp"));
+        assert!(wildcard_match("*.tmp", ".tmp"));
+        assert!(!wildcard_match("*.tmp", "photo.tmp.jpg"));
+        assert!(wildcard_match("IMG_*_edit*", "IMG_0001_edited.jpg"));
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("a**b", "ab"));
+        // Needs backtracking past an early partial match
+        assert!(wildcard_match("*ab", "aab"));
+        assert!(!wildcard_match("*ab*c", "abab"));
+    }
+
+    #[test]
+    fn question_mark_matches_one_character() {
+        assert!(wildcard_match("IMG_????.JPG", "img_1234.jpg"));
+        assert!(!wildcard_match("IMG_????.JPG", "IMG_123.JPG"));
+        assert!(wildcard_match("?", "é"));
+        assert!(!wildcard_match("?", ""));
+    }
+}
@@ -0,0 +1,146 @@
+use std::fmt;
+use std::io;
+use serde::Serialize;
+use tauri::Emitter;
+use tracing::error;
+
+use crate::utils::is_read_only_instance;
+use crate::utils::logging::app_handle;
+
+/// Emitted with the `PenglerError` of every failed command
+pub const ERROR_EVENT: &str = "error";
+
+/// What commands fail with. Serialized as `{ code, message }`, so the frontend can
+/// pick an actionable message or retry by code and still show the details.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "code", content = "message")]
+pub enum PenglerError {
+    /// ffmpeg or ffprobe isn't installed
+    FfmpegMissing(String),
+    ExiftoolMissing(String),
+    TesseractMissing(String),
+    PermissionDenied(String),
+    NotFound(String),
+    DiskFull(String),
+    /// Another connection holds the database; worth retrying
+    DbLocked(String),
+    UnsupportedFormat(String),
+    /// A network share or server dropped out or timed out; worth retrying
+    NetworkUnavailable(String),
+    InvalidInput(String),
+    /// Another instance owns the library, so this one can't change it
+    ReadOnly(String),
+    /// Private items were asked for while they are locked
+    Locked(String),
+    Other(String),
+}
+
+impl PenglerError {
+    pub fn message(&self) -> &str {
+        match self {
+            Self::FfmpegMissing(message)
+            | Self::ExiftoolMissing(message)
+            | Self::TesseractMissing(message)
+            | Self::PermissionDenied(message)
+            | Self::NotFound(message)
+            | Self::DiskFull(message)
+            | Self::DbLocked(message)
+            | Self::UnsupportedFormat(message)
+            | Self::NetworkUnavailable(message)
+            | Self::InvalidInput(message)
+            | Self::ReadOnly(message)
+            | Self::Locked(message)
+            | Self::Other(message) => message,
+        }
+    }
+
+    /// The same kind of error with another message
+    fn with_message(&self, message: String) -> Self {
+        match self {
+            Self::FfmpegMissing(_) => Self::FfmpegMissing(message),
+            Self::ExiftoolMissing(_) => Self::ExiftoolMissing(message),
+            Self::TesseractMissing(_) => Self::TesseractMissing(message),
+            Self::PermissionDenied(_) => Self::PermissionDenied(message),
+            Self::NotFound(_) => Self::NotFound(message),
+            Self::DiskFull(_) => Self::DiskFull(message),
+            Self::DbLocked(_) => Self::DbLocked(message),
+            Self::UnsupportedFormat(_) => Self::UnsupportedFormat(message),
+            Self::NetworkUnavailable(_) => Self::NetworkUnavailable(message),
+            Self::InvalidInput(_) => Self::InvalidInput(message),
+            Self::ReadOnly(_) => Self::ReadOnly(message),
+            Self::Locked(_) => Self::Locked(message),
+            Self::Other(_) => Self::Other(message),
+        }
+    }
+
+    /// Classify `e` by the first cause that says what went wrong, with `context`
+    /// ("Failed to export") in front of its message
+    pub fn from_error(context: &str, e: impl Into<anyhow::Error>) -> Self {
+        let e = e.into();
+        let kind = e.chain().find_map(classify).unwrap_or(Self::Other(String::new()));
+        kind.with_message(format!("{}: {}", context, e))
+    }
+
+    /// `from_error`, also logged and emitted as an `ERROR_EVENT`. For a command's
+    /// failure: `.map_err(|e| PenglerError::report("Failed to export", e))`
+    pub fn report(context: &str, e: impl Into<anyhow::Error>) -> Self {
+        let error = Self::from_error(context, e);
+        error!("{}", error);
+        if let Some(app) = app_handle() {
+            // Already logged above
+            let _ = app.emit(ERROR_EVENT, &error);
+        }
+        error
+    }
+}
+
+impl fmt::Display for PenglerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for PenglerError {}
+
+fn classify(cause: &(dyn std::error::Error + 'static)) -> Option<PenglerError> {
+    let empty = String::new;
+
+    if let Some(error) = cause.downcast_ref::<PenglerError>() {
+        return Some(error.clone());
+    }
+
+    if let Some(error) = cause.downcast_ref::<io::Error>() {
+        return match error.kind() {
+            io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem => Some(PenglerError::PermissionDenied(empty())),
+            io::ErrorKind::NotFound => Some(PenglerError::NotFound(empty())),
+            io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded => Some(PenglerError::DiskFull(empty())),
+            io::ErrorKind::TimedOut
+            | io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::HostUnreachable
+            | io::ErrorKind::NetworkUnreachable
+            | io::ErrorKind::NetworkDown
+            | io::ErrorKind::StaleNetworkFileHandle => Some(PenglerError::NetworkUnavailable(empty())),
+            io::ErrorKind::Unsupported => Some(PenglerError::UnsupportedFormat(empty())),
+            _ => None,
+        };
+    }
+
+    if let Some(rusqlite::Error::SqliteFailure(error, _)) = cause.downcast_ref::<rusqlite::Error>() {
+        return match error.code {
+            rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked => Some(PenglerError::DbLocked(empty())),
+            rusqlite::ErrorCode::DiskFull => Some(PenglerError::DiskFull(empty())),
+            rusqlite::ErrorCode::ReadOnly if is_read_only_instance() => Some(PenglerError::ReadOnly(empty())),
+            rusqlite::ErrorCode::PermissionDenied | rusqlite::ErrorCode::ReadOnly => Some(PenglerError::PermissionDenied(empty())),
+            _ => None,
+        };
+    }
+
+    if let Some(image::ImageError::Unsupported(_)) = cause.downcast_ref::<image::ImageError>() {
+        return Some(PenglerError::UnsupportedFormat(empty()));
+    }
+
+    None
+}
@@ -5,6 +5,8 @@ mod models;
 mod commands;
 mod utils;
 mod config;
+mod error;
+mod cli;
 
 use commands::{
     scan_folder,
@@ -13,20 +15,145 @@ use commands::{
     clear_cache,
     save_media_files,
     load_media_files,
+    eject_drive,
+    import_google_takeout,
+    import_apple_photos,
+    import_lightroom_catalog,
+    hydrate_files,
+    delete_media,
+    move_media,
+    copy_media,
+    detect_bursts,
+    get_stack_members,
+    set_stack_cover,
+    set_rating,
+    set_color_label,
+    set_favorite,
+    set_tags,
+    write_xmp_sidecars,
+    set_taken_at,
+    shift_taken_at,
+    normalize_orientation,
+    sync_mtime_from_exif,
+    search_media,
+    list_media,
+    get_tags,
+    move_tag,
+    merge_tags,
+    list_smart_albums,
+    create_smart_album,
+    update_smart_album,
+    delete_smart_album,
+    evaluate_smart_album,
+    get_geo_clusters,
+    set_location,
+    apply_gpx_track,
+    suggest,
+    list_persons,
+    create_person,
+    rename_person,
+    delete_person,
+    get_face_regions,
+    add_face_region,
+    update_face_region,
+    delete_face_region,
+    detect_faces,
+    get_face_clusters,
+    recluster_faces,
+    name_face_cluster,
+    find_similar,
+    group_similar_photos,
+    get_similar_groups,
+    find_library_duplicates,
+    get_duplicate_groups,
+    resolve_duplicate_groups,
+    extract_text,
+    get_media_text,
+    index_embeddings,
+    semantic_search,
+    get_memories,
+    get_random_sample,
+    set_pick,
+    delete_all_rejected,
+    save_edits,
+    load_edits,
+    get_edit_history,
+    clear_edits,
+    export_media,
+    convert_to_jpeg,
+    render_slideshow,
+    export_html_gallery,
+    generate_contact_sheet,
+    start_lan_server,
+    stop_lan_server,
+    get_lan_server_status,
+    start_dlna_server,
+    stop_dlna_server,
+    get_dlna_status,
+    run_backup,
+    get_backup_history,
+    verify_backup,
+    restore_files,
+    start_cloud_backup,
+    cancel_cloud_backup,
+    get_cloud_backup_status,
+    get_performance_metrics,
+    get_recent_logs,
+    detect_candidate_folders,
+    check_library_health,
+    get_changes_since,
+    get_private_status,
+    set_private_passphrase,
+    unlock_private,
+    lock_private,
+    unlock_cache,
+    lock_cache,
+    set_folder_private,
+    relink_folder,
+    suggest_relink_locations,
 };
 use config::{
     get_config,
     update_config,
+    get_read_only,
     add_library_folder,
     remove_library_folder,
+    set_folder_settings,
+    set_network_folder,
     set_cache_folder,
 };
 
 fn main() {
+    utils::init_logging(config::get_logs_folder().ok().as_deref());
+    if let Some(folder) = config::portable_data_folder() {
+        tracing::info!("Portable mode: keeping data in {}", folder.display());
+    }
+    // A second instance only browses, so two never write the catalog at once
+    let owns_library = config::get_data_folder().map_or(true, |folder| utils::claim_instance_lock(&folder));
+
+    // Apply settings that take effect outside of command calls
+    if let Ok(config) = config::Config::load() {
+        config.apply();
+    }
+    if let Some(code) = cli::run_from_args(owns_library) {
+        std::process::exit(code);
+    }
+    if owns_library {
+        commands::cache_janitor::start_cache_janitor();
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_shell::init())
+        .register_uri_scheme_protocol(commands::private::PRIVATE_THUMBNAIL_SCHEME, |_ctx, request| {
+            commands::private::serve_private_thumbnail(&request)
+        })
+        .setup(|app| {
+            utils::set_log_app_handle(app.handle().clone());
+            commands::changes::start_change_feed(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             scan_folder,
             generate_thumbnail,
@@ -34,10 +161,109 @@ fn main() {
             clear_cache,
             save_media_files,
             load_media_files,
+            eject_drive,
+            import_google_takeout,
+            import_apple_photos,
+            import_lightroom_catalog,
+            hydrate_files,
+            delete_media,
+            move_media,
+            copy_media,
+            detect_bursts,
+            get_stack_members,
+            set_stack_cover,
+            set_rating,
+            set_color_label,
+            set_favorite,
+            set_tags,
+            write_xmp_sidecars,
+            set_taken_at,
+            shift_taken_at,
+            normalize_orientation,
+            sync_mtime_from_exif,
+            search_media,
+            list_media,
+            get_tags,
+            move_tag,
+            merge_tags,
+            list_smart_albums,
+            create_smart_album,
+            update_smart_album,
+            delete_smart_album,
+            evaluate_smart_album,
+            get_geo_clusters,
+            set_location,
+            apply_gpx_track,
+            suggest,
+            list_persons,
+            create_person,
+            rename_person,
+            delete_person,
+            get_face_regions,
+            add_face_region,
+            update_face_region,
+            delete_face_region,
+            detect_faces,
+            get_face_clusters,
+            recluster_faces,
+            name_face_cluster,
+            find_similar,
+            group_similar_photos,
+            get_similar_groups,
+            find_library_duplicates,
+            get_duplicate_groups,
+            resolve_duplicate_groups,
+            extract_text,
+            get_media_text,
+            index_embeddings,
+            semantic_search,
+            get_memories,
+            get_random_sample,
+            set_pick,
+            delete_all_rejected,
+            save_edits,
+            load_edits,
+            get_edit_history,
+            clear_edits,
+            export_media,
+            convert_to_jpeg,
+            render_slideshow,
+            export_html_gallery,
+            generate_contact_sheet,
+            start_lan_server,
+            stop_lan_server,
+            get_lan_server_status,
+            start_dlna_server,
+            stop_dlna_server,
+            get_dlna_status,
+            run_backup,
+            get_backup_history,
+            verify_backup,
+            restore_files,
+            start_cloud_backup,
+            cancel_cloud_backup,
+            get_cloud_backup_status,
+            get_performance_metrics,
+            get_recent_logs,
+            detect_candidate_folders,
+            check_library_health,
+            get_changes_since,
+            get_private_status,
+            set_private_passphrase,
+            unlock_private,
+            lock_private,
+            unlock_cache,
+            lock_cache,
+            set_folder_private,
+            relink_folder,
+            suggest_relink_locations,
             get_config,
             update_config,
+            get_read_only,
             add_library_folder,
             remove_library_folder,
+            set_folder_settings,
+            set_network_folder,
             set_cache_folder,
         ])
         .run(tauri::generate_context!())
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
 use chrono::{DateTime, Utc};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,13 +11,86 @@ pub struct MediaFile {
     pub file_size: i64,
     pub width: i32,
     pub height: i32,
+    /// Wall-clock time where it was taken, stored with a UTC marker
     pub taken_at: Option<DateTime<Utc>>,
+    /// Minutes east of UTC at `taken_at`, when the file or import recorded it
+    pub taken_offset: Option<i32>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub camera_model: Option<String>,
     pub modified_at: DateTime<Utc>,
     pub thumbnail_path: Option<String>,
     pub media_type: MediaType,
+    pub video_info: Option<VideoInfo>,
+    /// Embedded ICC profile name for photos, primaries/transfer for videos; `None` is sRGB
+    pub color_space: Option<String>,
+    /// dHash of the pixels for finding resized or re-exported copies; photos only
+    pub perceptual_hash: Option<String>,
+    /// Burst stack this photo belongs to, if any
+    pub stack_id: Option<i64>,
+    /// 0-5 stars, -1 for rejected
+    pub rating: Option<i32>,
+    pub color_label: Option<String>,
+    pub favorite: bool,
+    /// Culling flag, independent of the rating
+    pub pick: PickState,
+    /// Motion clip of a Live Photo, if the pair was imported together
+    pub live_video_id: Option<i64>,
+    /// Cloud placeholder not downloaded to this machine; see `hydrate_files`
+    pub online_only: bool,
+    pub tags: Vec<String>,
     pub created_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoInfo {
+    /// Duration in seconds
+    pub duration: f64,
+    pub fps: Option<f64>,
+    pub video_codec: Option<String>,
+    /// Overall bitrate in bits per second
+    pub bitrate: Option<i64>,
+    pub audio_tracks: Vec<AudioTrack>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioTrack {
+    pub codec: Option<String>,
+    pub channels: Option<i32>,
+    pub sample_rate: Option<i32>,
+    pub language: Option<String>,
+}
+
+/// Culling flag, stored as 1 / 0 / -1
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PickState {
+    Pick,
+    #[default]
+    Unflagged,
+    Reject,
+}
+
+impl PickState {
+    pub fn to_db(self) -> i32 {
+        match self {
+            PickState::Pick => 1,
+            PickState::Unflagged => 0,
+            PickState::Reject => -1,
+        }
+    }
+
+    pub fn from_db(value: i32) -> Self {
+        match value.signum() {
+            1 => PickState::Pick,
+            -1 => PickState::Reject,
+            _ => PickState::Unflagged,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum MediaType {
@@ -41,16 +115,55 @@ impl MediaFile {
             width,
             height,
             taken_at: None,
+            taken_offset: None,
+            latitude: None,
+            longitude: None,
+            camera_model: None,
             modified_at: Utc::now(),
             thumbnail_path: None,
             media_type,
+            video_info: None,
+            color_space: None,
+            perceptual_hash: None,
+            stack_id: None,
+            rating: None,
+            color_label: None,
+            favorite: false,
+            pick: PickState::Unflagged,
+            live_video_id: None,
+            online_only: false,
+            tags: Vec::new(),
             created_at: Utc::now(),
         }
     }
 }
 
-pub const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp", "heic", "heif"];
-pub const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mov", "avi", "mkv", "webm", "m4v"];
+pub const DEFAULT_IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp", "heic", "heif"];
+pub const DEFAULT_VIDEO_EXTENSIONS: &[&str] = &["mp4", "mov", "avi", "mkv", "webm", "m4v"];
+
+struct MediaExtensions {
+    image: Vec<String>,
+    video: Vec<String>,
+}
+
+/// Extension lists from config; the defaults apply until they are set
+static MEDIA_EXTENSIONS: RwLock<Option<MediaExtensions>> = RwLock::new(None);
+
+/// Make `is_media_file` use the user-configured extension lists
+pub fn set_media_extensions(image: &[String], video: &[String]) {
+    let normalize = |list: &[String]| -> Vec<String> {
+        list.iter()
+            .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect()
+    };
+
+    let mut extensions = MEDIA_EXTENSIONS.write().unwrap_or_else(|e| e.into_inner());
+    *extensions = Some(MediaExtensions {
+        image: normalize(image),
+        video: normalize(video),
+    });
+}
 
 pub fn is_media_file(path: &str) -> Option<MediaType> {
     let ext = std::path::Path::new(path)
@@ -58,11 +171,42 @@ pub fn is_media_file(path: &str) -> Option<MediaType> {
         .to_str()?
         .to_lowercase();
 
-    if IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+    let extensions = MEDIA_EXTENSIONS.read().unwrap_or_else(|e| e.into_inner());
+    let (is_image, is_video) = match extensions.as_ref() {
+        Some(configured) => (configured.image.contains(&ext), configured.video.contains(&ext)),
+        None => (
+            DEFAULT_IMAGE_EXTENSIONS.contains(&ext.as_str()),
+            DEFAULT_VIDEO_EXTENSIONS.contains(&ext.as_str()),
+        ),
+    };
+
+    if is_image {
         Some(MediaType::Image)
-    } else if VIDEO_EXTENSIONS.contains(&ext.as_str()) {
+    } else if is_video {
         Some(MediaType::Video)
     } else {
         None
     }
 }
+
+/// Media type of a file on disk: the extension decides which files are considered,
+/// the leading bytes decide what they actually are. Extensionless files are sniffed.
+pub fn detect_media_type(path: &std::path::Path) -> Option<MediaType> {
+    let by_extension = path.to_str().and_then(is_media_file);
+
+    // Unknown extensions are skipped without reading the file
+    if by_extension.is_none() && path.extension().is_some() {
+        return None;
+    }
+
+    let by_content = infer::get_from_path(path).ok().flatten().map(|kind| kind.matcher_type());
+
+    match (by_extension, by_content) {
+        (_, Some(infer::MatcherType::Image)) => Some(MediaType::Image),
+        (_, Some(infer::MatcherType::Video)) => Some(MediaType::Video),
+        // Content recognized as something else, e.g. audio or an archive renamed to .jpg
+        (_, Some(_)) => None,
+        // Formats infer doesn't know keep the extension's verdict
+        (by_extension, None) => by_extension,
+    }
+}
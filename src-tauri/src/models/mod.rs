@@ -1,3 +1,3 @@
 pub mod media;
 
-pub use media::{MediaFile, MediaType, is_media_file};
+pub use media::{MediaFile, MediaType, PickState, VideoInfo, AudioTrack, is_media_file, detect_media_type, set_media_extensions};
@@ -0,0 +1,30 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+
+/// Temporary sibling of `path` used while it is being written
+pub fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    path.with_file_name(name)
+}
+
+/// Let `write` produce the file at a `.part` path and rename it into place on success,
+/// so a crash never leaves a truncated file under the final name
+pub fn write_atomically<T, F>(path: &Path, write: F) -> Result<T>
+where
+    F: FnOnce(&Path) -> Result<T>,
+{
+    let part = part_path(path);
+
+    match write(&part) {
+        Ok(value) => {
+            fs::rename(&part, path)?;
+            Ok(value)
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&part);
+            Err(e)
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::borrow::Cow;
+use std::path::Path;
+use image::{imageops::FilterType, DynamicImage};
+use ort::session::Session;
+use ort::value::{DynValue, Tensor};
+use tokenizers::Tokenizer;
+use anyhow::Result;
+
+use crate::utils::l2_normalize;
+
+/// CLIP vision tower (e.g. clip-vit-base-patch32 `vision_model.onnx`), expected at `<models>/clip_image.onnx`
+pub const IMAGE_MODEL: &str = "clip_image.onnx";
+
+/// Matching text tower, expected at `<models>/clip_text.onnx`
+pub const TEXT_MODEL: &str = "clip_text.onnx";
+
+/// The model's `tokenizer.json`, expected at `<models>/clip_tokenizer.json`
+pub const TOKENIZER: &str = "clip_tokenizer.json";
+
+const INPUT_SIZE: u32 = 224;
+const CONTEXT_LENGTH: usize = 77;
+const MEAN: [f32; 3] = [0.481_454_66, 0.457_827_5, 0.408_210_73];
+const STD: [f32; 3] = [0.268_629_54, 0.261_302_6, 0.275_777_1];
+
+pub struct ClipImageEncoder {
+    session: Session,
+}
+
+impl ClipImageEncoder {
+    pub fn load(models_dir: &Path) -> Result<Self> {
+        Ok(Self { session: load_session(models_dir, IMAGE_MODEL)? })
+    }
+
+    /// L2-normalized embedding of an image
+    pub fn embed(&mut self, img: &DynamicImage) -> Result<Vec<f32>> {
+        // Scale the short side to the input size and center-crop, as CLIP was trained
+        let rgb = img.resize_to_fill(INPUT_SIZE, INPUT_SIZE, FilterType::CatmullRom).to_rgb8();
+
+        let size = INPUT_SIZE as usize;
+        let mut data = vec![0f32; 3 * size * size];
+        for (x, y, pixel) in rgb.enumerate_pixels() {
+            let offset = y as usize * size + x as usize;
+            for channel in 0..3 {
+                data[channel * size * size + offset] = (pixel[channel] as f32 / 255.0 - MEAN[channel]) / STD[channel];
+            }
+        }
+
+        let input = Tensor::from_array(([1usize, 3, size, size], data))?;
+        let outputs = self.session.run(ort::inputs![input])?;
+        let embeds = outputs.get("image_embeds").unwrap_or(&outputs[0]);
+        let (_, values) = embeds.try_extract_tensor::<f32>()?;
+
+        Ok(l2_normalize(values))
+    }
+}
+
+pub struct ClipTextEncoder {
+    session: Session,
+    tokenizer: Tokenizer,
+}
+
+impl ClipTextEncoder {
+    pub fn load(models_dir: &Path) -> Result<Self> {
+        let tokenizer_path = models_dir.join(TOKENIZER);
+        let tokenizer = Tokenizer::from_file(&tokenizer_path)
+            .map_err(|e| anyhow::anyhow!("Failed to load {}: {}", tokenizer_path.display(), e))?;
+
+        Ok(Self {
+            session: load_session(models_dir, TEXT_MODEL)?,
+            tokenizer,
+        })
+    }
+
+    /// L2-normalized embedding of a text query, comparable with image embeddings
+    pub fn embed(&mut self, text: &str) -> Result<Vec<f32>> {
+        let encoding = self.tokenizer
+            .encode(text, true)
+            .map_err(|e| anyhow::anyhow!("Failed to tokenize query: {}", e))?;
+
+        let mut ids: Vec<i64> = encoding.get_ids().iter().map(|&id| id as i64).collect();
+        if ids.len() > CONTEXT_LENGTH {
+            // Keep the end-of-text token, which the model pools on
+            let end = ids[ids.len() - 1];
+            ids.truncate(CONTEXT_LENGTH);
+            ids[CONTEXT_LENGTH - 1] = end;
+        }
+        let mut mask = vec![1i64; ids.len()];
+        ids.resize(CONTEXT_LENGTH, 0);
+        mask.resize(CONTEXT_LENGTH, 0);
+
+        // Exports differ in whether they take an attention mask
+        let mut inputs: Vec<(Cow<str>, DynValue)> = Vec::new();
+        for input in &self.session.inputs {
+            let values = if input.name.contains("mask") { mask.clone() } else { ids.clone() };
+            inputs.push((
+                Cow::Owned(input.name.clone()),
+                Tensor::from_array(([1usize, CONTEXT_LENGTH], values))?.into_dyn(),
+            ));
+        }
+
+        let outputs = self.session.run(inputs)?;
+        let embeds = outputs.get("text_embeds").unwrap_or(&outputs[0]);
+        let (_, values) = embeds.try_extract_tensor::<f32>()?;
+
+        Ok(l2_normalize(values))
+    }
+}
+
+/// The ONNX Runtime library is loaded at runtime; set ORT_DYLIB_PATH if it is not
+/// on the library search path
+fn load_session(models_dir: &Path, name: &str) -> Result<Session> {
+    let path = models_dir.join(name);
+    if !path.exists() {
+        return Err(anyhow::anyhow!("CLIP model not found: {}", path.display()));
+    }
+    Ok(Session::builder()?.commit_from_file(&path)?)
+}
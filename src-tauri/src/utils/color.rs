@@ -0,0 +1,69 @@
+use std::path::Path;
+use image::{DynamicImage, ImageDecoder, ImageReader, RgbaImage};
+use moxcms::{ColorProfile, Layout, ProfileText, TransformOptions};
+use anyhow::Result;
+
+/// Decode an image together with its embedded ICC profile, if any
+pub fn open_image_with_profile(path: &Path) -> Result<(DynamicImage, Option<Vec<u8>>)> {
+    let mut decoder = ImageReader::open(path)?.with_guessed_format()?.into_decoder()?;
+    let icc = decoder.icc_profile().ok().flatten();
+    let img = DynamicImage::from_decoder(decoder)?;
+    Ok((img, icc))
+}
+
+/// Like `open_image_with_profile`, with the EXIF orientation applied to the pixels
+pub fn open_upright_image_with_profile(path: &Path) -> Result<(DynamicImage, Option<Vec<u8>>)> {
+    let mut decoder = ImageReader::open(path)?.with_guessed_format()?.into_decoder()?;
+    let icc = decoder.icc_profile().ok().flatten();
+    let orientation = decoder.orientation()?;
+    let mut img = DynamicImage::from_decoder(decoder)?;
+    img.apply_orientation(orientation);
+    Ok((img, icc))
+}
+
+/// Name of the color space an image is encoded in ("Display P3", "Adobe RGB (1998)", ...).
+/// Reads only the header; `None` means no embedded profile, i.e. sRGB.
+pub fn image_color_space(path: &Path) -> Option<String> {
+    let mut decoder = ImageReader::open(path).ok()?.with_guessed_format().ok()?.into_decoder().ok()?;
+    let icc = decoder.icc_profile().ok()??;
+    profile_color_space(&icc)
+}
+
+/// Name of the color space an ICC profile describes
+pub fn profile_color_space(icc: &[u8]) -> Option<String> {
+    profile_name(&ColorProfile::new_from_slice(icc).ok()?)
+}
+
+/// Convert pixels from the given ICC profile to sRGB so they display correctly everywhere.
+/// Images that are already sRGB, or whose profile can't be parsed, are returned as is.
+pub fn convert_to_srgb(img: DynamicImage, icc: &[u8]) -> Result<DynamicImage> {
+    let source = match ColorProfile::new_from_slice(icc) {
+        Ok(profile) => profile,
+        Err(_) => return Ok(img),
+    };
+    if profile_name(&source).is_some_and(|name| name.starts_with("sRGB")) {
+        return Ok(img);
+    }
+
+    let transform = source
+        .create_transform_8bit(Layout::Rgba, &ColorProfile::new_srgb(), Layout::Rgba, TransformOptions::default())
+        .map_err(|e| anyhow::anyhow!("Unsupported color profile: {:?}", e))?;
+
+    let pixels = img.to_rgba8();
+    let mut converted = RgbaImage::new(pixels.width(), pixels.height());
+    transform
+        .transform(pixels.as_raw(), &mut converted)
+        .map_err(|e| anyhow::anyhow!("Color conversion failed: {:?}", e))?;
+
+    Ok(DynamicImage::ImageRgba8(converted))
+}
+
+fn profile_name(profile: &ColorProfile) -> Option<String> {
+    let name = match profile.description.as_ref()? {
+        ProfileText::PlainString(text) => text.clone(),
+        ProfileText::Localizable(texts) => texts.first()?.value.clone(),
+        ProfileText::Description(text) => text.ascii_string.clone(),
+    };
+    let name = name.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    (!name.is_empty()).then(|| name.to_string())
+}
@@ -0,0 +1,84 @@
+use std::path::Path;
+use anyhow::Result;
+
+use crate::error::PenglerError;
+
+/// Headroom kept free on top of the estimated write size
+const SPACE_MARGIN: u64 = 64 * 1024 * 1024;
+
+/// Fail early if the volume holding `path` cannot fit `required_bytes`
+pub fn ensure_free_space(path: &Path, required_bytes: u64) -> Result<()> {
+    // The destination may not exist yet, so check the nearest existing ancestor
+    let existing = path
+        .ancestors()
+        .find(|p| p.exists())
+        .ok_or_else(|| anyhow::anyhow!("Invalid destination path: {}", path.display()))?;
+
+    let available = fs4::available_space(existing)?;
+    let required = required_bytes.saturating_add(SPACE_MARGIN);
+
+    if available < required {
+        return Err(PenglerError::DiskFull(format!(
+            "Not enough free space on {}: {} required, {} available",
+            existing.display(),
+            format_bytes(required),
+            format_bytes(available)
+        ))
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Format a byte count for messages, e.g. "1.5 GB"
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Whether two paths are hard links to the same file
+pub fn same_file(a: &Path, b: &Path) -> Result<bool> {
+    same_file_platform(a, b)
+}
+
+#[cfg(unix)]
+fn same_file_platform(a: &Path, b: &Path) -> Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+
+    let (a, b) = (std::fs::metadata(a)?, std::fs::metadata(b)?);
+    Ok(a.dev() == b.dev() && a.ino() == b.ino())
+}
+
+#[cfg(windows)]
+fn same_file_platform(a: &Path, b: &Path) -> Result<bool> {
+    use std::fs::File;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::{GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION};
+
+    let identity = |path: &Path| -> Result<(u32, u32, u32)> {
+        let file = File::open(path)?;
+        let mut info: BY_HANDLE_FILE_INFORMATION = unsafe { std::mem::zeroed() };
+        if unsafe { GetFileInformationByHandle(file.as_raw_handle(), &mut info) } == 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok((info.dwVolumeSerialNumber, info.nFileIndexHigh, info.nFileIndexLow))
+    };
+    Ok(identity(a)? == identity(b)?)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn same_file_platform(_a: &Path, _b: &Path) -> Result<bool> {
+    Ok(false)
+}
@@ -0,0 +1,102 @@
+use image::{DynamicImage, Rgba32FImage};
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+
+/// Crop rectangle relative to the rotated image (0-1)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CropRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Non-destructive adjustments to the upright (EXIF-oriented) photo, applied in order:
+/// rotation, crop, exposure.
+/// Missing fields mean "unchanged", so recipes saved by older versions keep loading.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EditRecipe {
+    /// Clockwise, in multiples of 90 degrees
+    pub rotation: i32,
+    pub crop: Option<CropRect>,
+    /// Exposure compensation in stops
+    pub exposure: f32,
+}
+
+impl EditRecipe {
+    pub fn validate(&self) -> Result<()> {
+        if self.rotation % 90 != 0 {
+            return Err(anyhow::anyhow!("Rotation must be a multiple of 90 degrees"));
+        }
+        if let Some(crop) = &self.crop {
+            let in_range = |v: f32| (0.0..=1.0).contains(&v);
+            if !(in_range(crop.x) && in_range(crop.y) && crop.width > 0.0 && crop.height > 0.0
+                && crop.x + crop.width <= 1.0 && crop.y + crop.height <= 1.0)
+            {
+                return Err(anyhow::anyhow!("Crop must lie within the image"));
+            }
+        }
+        if !(-5.0..=5.0).contains(&self.exposure) {
+            return Err(anyhow::anyhow!("Exposure must be between -5 and 5 stops"));
+        }
+        Ok(())
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Render the recipe onto a decoded image; the original file is never touched
+pub fn apply_edits(img: DynamicImage, recipe: &EditRecipe) -> DynamicImage {
+    let mut img = match recipe.rotation.rem_euclid(360) {
+        90 => img.rotate90(),
+        180 => img.rotate180(),
+        270 => img.rotate270(),
+        _ => img,
+    };
+
+    if let Some(crop) = &recipe.crop {
+        let (w, h) = (img.width() as f32, img.height() as f32);
+        let x = (crop.x * w).round() as u32;
+        let y = (crop.y * h).round() as u32;
+        let width = ((crop.width * w).round() as u32).clamp(1, img.width() - x.min(img.width() - 1));
+        let height = ((crop.height * h).round() as u32).clamp(1, img.height() - y.min(img.height() - 1));
+        img = img.crop_imm(x, y, width, height);
+    }
+
+    if recipe.exposure != 0.0 {
+        img = adjust_exposure(img, recipe.exposure);
+    }
+
+    img
+}
+
+/// Scale linear light by 2^stops, working on sRGB-decoded values
+fn adjust_exposure(img: DynamicImage, stops: f32) -> DynamicImage {
+    let gain = 2f32.powf(stops);
+    let mut pixels: Rgba32FImage = img.to_rgba32f();
+
+    for pixel in pixels.pixels_mut() {
+        for channel in &mut pixel.0[..3] {
+            *channel = linear_to_srgb(srgb_to_linear(*channel) * gain).clamp(0.0, 1.0);
+        }
+    }
+
+    let adjusted = DynamicImage::ImageRgba32F(pixels);
+    if img.color().has_alpha() {
+        DynamicImage::ImageRgba8(adjusted.to_rgba8())
+    } else {
+        DynamicImage::ImageRgb8(adjusted.to_rgb8())
+    }
+}
+
+fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.003_130_8 { v * 12.92 } else { 1.055 * v.powf(1.0 / 2.4) - 0.055 }
+}
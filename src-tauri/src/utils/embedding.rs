@@ -0,0 +1,23 @@
+/// Store an embedding as little-endian f32 bytes
+#[cfg(any(feature = "face-detection", feature = "semantic-search"))]
+pub fn embedding_to_blob(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+/// Read an embedding written by `embedding_to_blob`
+pub fn blob_to_embedding(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect()
+}
+
+/// Scale to unit length, so cosine similarity becomes a dot product
+#[cfg(any(feature = "face-detection", feature = "semantic-search"))]
+pub fn l2_normalize(values: &[f32]) -> Vec<f32> {
+    let norm = values.iter().map(|v| v * v).sum::<f32>().sqrt().max(f32::EPSILON);
+    values.iter().map(|v| v / norm).collect()
+}
+
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
@@ -0,0 +1,200 @@
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use anyhow::Result;
+
+/// Plaintext bytes per sealed chunk
+pub const CHUNK_SIZE: usize = 1024 * 1024;
+const MAGIC: &[u8; 8] = b"PNGLENC1";
+const NONCE_PREFIX_SIZE: usize = 16;
+pub const TAG_SIZE: usize = 16;
+/// Magic followed by the file's nonce prefix
+pub const HEADER_SIZE: usize = MAGIC.len() + NONCE_PREFIX_SIZE;
+
+/// Key for encrypting backups and private thumbnails, derived from the user's passphrase
+pub struct EncryptionKey {
+    cipher: XChaCha20Poly1305,
+}
+
+impl EncryptionKey {
+    /// Argon2id over the passphrase; `context` (e.g. bucket and prefix) fixes the salt, so
+    /// the same passphrase always gives the same key for the same backup
+    pub fn derive(passphrase: &str, context: &str) -> Result<Self> {
+        if passphrase.is_empty() {
+            return Err(anyhow::anyhow!("An encryption passphrase is required"));
+        }
+        let salt = blake3::hash(format!("pengler-backup:{}", context).as_bytes());
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt.as_bytes()[..16], &mut key)
+            .map_err(|e| anyhow::anyhow!("Failed to derive encryption key: {}", e))?;
+        Ok(Self { cipher: XChaCha20Poly1305::new(&key.into()) })
+    }
+
+    /// Encrypt a whole small file, like a thumbnail, in the same format as `FileEncryptor`
+    pub fn seal(&self, plain: &[u8]) -> Result<Vec<u8>> {
+        let encryptor = FileEncryptor::new(self, FileEncryptor::random_nonce_prefix()?, plain.len() as u64);
+        let mut sealed = encryptor.header();
+        for index in 0..encryptor.chunk_count() {
+            let start = (index as usize * CHUNK_SIZE).min(plain.len());
+            let end = (start + CHUNK_SIZE).min(plain.len());
+            sealed.extend(encryptor.seal_chunk(index, &plain[start..end])?);
+        }
+        Ok(sealed)
+    }
+
+    /// Decrypt a file made by `seal` or `FileEncryptor`; fails if it was altered or cut short
+    pub fn open(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < HEADER_SIZE || &sealed[..MAGIC.len()] != MAGIC {
+            return Err(anyhow::anyhow!("Not an encrypted Pengler file"));
+        }
+        let mut nonce = [0u8; 24];
+        nonce[..NONCE_PREFIX_SIZE].copy_from_slice(&sealed[MAGIC.len()..HEADER_SIZE]);
+
+        let chunks: Vec<&[u8]> = sealed[HEADER_SIZE..].chunks(CHUNK_SIZE + TAG_SIZE).collect();
+        let mut plain = Vec::with_capacity(sealed.len() - HEADER_SIZE);
+        for (index, chunk) in chunks.iter().enumerate() {
+            nonce[NONCE_PREFIX_SIZE..].copy_from_slice(&(index as u64).to_le_bytes());
+            let last = [u8::from(index + 1 == chunks.len())];
+            plain.extend(
+                self.cipher
+                    .decrypt(XNonce::from_slice(&nonce), Payload { msg: chunk, aad: &last })
+                    .map_err(|_| anyhow::anyhow!("Failed to decrypt: wrong key or damaged file"))?,
+            );
+        }
+        if chunks.is_empty() {
+            return Err(anyhow::anyhow!("Failed to decrypt: the file is cut short"));
+        }
+        Ok(plain)
+    }
+}
+
+/// Argon2id hash of a passphrase with a random salt, for checking it later with
+/// `verify_passphrase`
+pub fn hash_passphrase(passphrase: &str) -> Result<String> {
+    let mut salt = [0u8; 16];
+    getrandom::fill(&mut salt).map_err(|e| anyhow::anyhow!("Failed to generate salt: {}", e))?;
+    let salt = SaltString::encode_b64(&salt).map_err(|e| anyhow::anyhow!("Failed to encode salt: {}", e))?;
+    let hash = Argon2::default()
+        .hash_password(passphrase.as_bytes(), &salt)
+        .map_err(|e| anyhow::anyhow!("Failed to hash passphrase: {}", e))?;
+    Ok(hash.to_string())
+}
+
+/// Whether `passphrase` is the one `hash` was made from
+pub fn verify_passphrase(passphrase: &str, hash: &str) -> Result<bool> {
+    let hash = PasswordHash::new(hash).map_err(|e| anyhow::anyhow!("Invalid passphrase hash: {}", e))?;
+    Ok(Argon2::default().verify_password(passphrase.as_bytes(), &hash).is_ok())
+}
+
+/// Encrypts one file as a header followed by independently sealed chunks, so any range
+/// of chunks can be produced again (e.g. to resume an upload) without the ones before it
+pub struct FileEncryptor<'a> {
+    key: &'a EncryptionKey,
+    nonce_prefix: [u8; NONCE_PREFIX_SIZE],
+    chunk_count: u64,
+}
+
+impl<'a> FileEncryptor<'a> {
+    /// `nonce_prefix` must be unique per encrypted file; see `random_nonce_prefix`
+    pub fn new(key: &'a EncryptionKey, nonce_prefix: [u8; NONCE_PREFIX_SIZE], plain_size: u64) -> Self {
+        Self { key, nonce_prefix, chunk_count: chunk_count(plain_size) }
+    }
+
+    pub fn random_nonce_prefix() -> Result<[u8; NONCE_PREFIX_SIZE]> {
+        let mut prefix = [0u8; NONCE_PREFIX_SIZE];
+        getrandom::fill(&mut prefix).map_err(|e| anyhow::anyhow!("Failed to generate nonce: {}", e))?;
+        Ok(prefix)
+    }
+
+    pub fn chunk_count(&self) -> u64 {
+        self.chunk_count
+    }
+
+    pub fn header(&self) -> Vec<u8> {
+        let mut header = Vec::with_capacity(HEADER_SIZE);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&self.nonce_prefix);
+        header
+    }
+
+    /// Seal chunk `index`; the last chunk is marked so a truncated file fails to decrypt
+    pub fn seal_chunk(&self, index: u64, plain: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; 24];
+        nonce[..NONCE_PREFIX_SIZE].copy_from_slice(&self.nonce_prefix);
+        nonce[NONCE_PREFIX_SIZE..].copy_from_slice(&index.to_le_bytes());
+        let last = [u8::from(index + 1 == self.chunk_count)];
+
+        self.key
+            .cipher
+            .encrypt(XNonce::from_slice(&nonce), Payload { msg: plain, aad: &last })
+            .map_err(|e| anyhow::anyhow!("Failed to encrypt: {}", e))
+    }
+}
+
+/// Chunks in a file of `plain_size` bytes; an empty file still gets one (empty) chunk
+pub fn chunk_count(plain_size: u64) -> u64 {
+    plain_size.div_ceil(CHUNK_SIZE as u64).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> EncryptionKey {
+        EncryptionKey::derive("correct horse battery staple", "test-bucket/photos").unwrap()
+    }
+
+    /// Two full chunks and a partial one
+    fn plain() -> Vec<u8> {
+        (0..2 * CHUNK_SIZE + 1000).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn seal_then_open_round_trips() {
+        let key = key();
+        let plain = plain();
+        let sealed = key.seal(&plain).unwrap();
+        assert_eq!(sealed.len(), HEADER_SIZE + plain.len() + 3 * TAG_SIZE);
+        assert_eq!(key.open(&sealed).unwrap(), plain);
+    }
+
+    #[test]
+    fn empty_file_round_trips() {
+        let key = key();
+        let sealed = key.seal(&[]).unwrap();
+        assert_eq!(sealed.len(), HEADER_SIZE + TAG_SIZE);
+        assert!(key.open(&sealed).unwrap().is_empty());
+        // Without its one chunk the file is cut short
+        assert!(key.open(&sealed[..HEADER_SIZE]).is_err());
+    }
+
+    #[test]
+    fn truncated_file_is_rejected() {
+        let key = key();
+        let sealed = key.seal(&plain()).unwrap();
+        // Cut at a chunk boundary: every remaining chunk is intact
+        assert!(key.open(&sealed[..HEADER_SIZE + 2 * (CHUNK_SIZE + TAG_SIZE)]).is_err());
+        assert!(key.open(&sealed[..sealed.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn reordered_chunks_are_rejected() {
+        let key = key();
+        let sealed = key.seal(&plain()).unwrap();
+        let sealed_chunk = CHUNK_SIZE + TAG_SIZE;
+        let mut swapped = sealed[..HEADER_SIZE].to_vec();
+        swapped.extend_from_slice(&sealed[HEADER_SIZE + sealed_chunk..HEADER_SIZE + 2 * sealed_chunk]);
+        swapped.extend_from_slice(&sealed[HEADER_SIZE..HEADER_SIZE + sealed_chunk]);
+        swapped.extend_from_slice(&sealed[HEADER_SIZE + 2 * sealed_chunk..]);
+        assert!(key.open(&swapped).is_err());
+    }
+
+    #[test]
+    fn wrong_key_is_rejected() {
+        let sealed = key().seal(b"thumbnail").unwrap();
+        let other = EncryptionKey::derive("another passphrase", "test-bucket/photos").unwrap();
+        assert!(other.open(&sealed).is_err());
+    }
+}
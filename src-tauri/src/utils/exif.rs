@@ -1,52 +1,213 @@
 use std::fs::File;
 use std::path::Path;
-use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use anyhow::Result;
 
-/// Extract EXIF date taken from image
-pub fn extract_date_taken(path: &Path) -> Option<DateTime<Utc>> {
+use crate::utils::parse_utc_offset;
+
+/// Metadata read from an image's EXIF block
+#[derive(Debug, Default)]
+pub struct ExifMetadata {
+    /// Camera's wall-clock time, stored with a UTC marker
+    pub taken_at: Option<DateTime<Utc>>,
+    /// Minutes east of UTC the camera was set to, when it recorded that
+    pub taken_offset: Option<i32>,
+    pub gps: Option<(f64, f64)>,
+    pub camera_model: Option<String>,
+}
+
+/// Read date taken, GPS location and camera from image EXIF in a single pass
+pub fn extract_exif_metadata(path: &Path) -> ExifMetadata {
+    let Some(exif) = read_exif(path) else {
+        return ExifMetadata::default();
+    };
+
+    ExifMetadata {
+        taken_at: extract_date_taken(&exif),
+        taken_offset: extract_taken_offset(&exif),
+        gps: extract_gps(&exif),
+        camera_model: extract_camera_model(&exif),
+    }
+}
+
+/// Exposure settings for captions, e.g. "1/250 s · f/2.8 · ISO 200 · 35 mm"
+pub fn exposure_summary(path: &Path) -> Option<String> {
+    let exif = read_exif(path)?;
+    let value = |tag| exif.get_field(tag, exif::In::PRIMARY).map(|f| f.display_value().to_string());
+
+    let parts: Vec<String> = [
+        value(exif::Tag::ExposureTime).map(|v| format!("{} s", v)),
+        value(exif::Tag::FNumber).map(|v| format!("f/{}", v)),
+        value(exif::Tag::PhotographicSensitivity).map(|v| format!("ISO {}", v)),
+        value(exif::Tag::FocalLength).map(|v| format!("{} mm", v)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    (!parts.is_empty()).then(|| parts.join(" · "))
+}
+
+/// EXIF of a photo rebuilt without anything that identifies where it was taken or
+/// which device took it: GPS, serial numbers, owner name, unique ids and maker notes
+/// (where vendors keep serials). The embedded preview, orientation and pixel size go
+/// too, since they describe the original rather than the exported pixels.
+/// Returns raw TIFF data for `ImageEncoder::set_exif_metadata`.
+pub fn privacy_filtered_exif(path: &Path) -> Option<Vec<u8>> {
+    use exif::{Context, In, Tag};
+
+    const REMOVED_TAGS: &[Tag] = &[
+        Tag::BodySerialNumber,
+        Tag::LensSerialNumber,
+        Tag::CameraOwnerName,
+        Tag::ImageUniqueID,
+        Tag::MakerNote,
+        Tag::Orientation,
+        Tag::PixelXDimension,
+        Tag::PixelYDimension,
+    ];
+
+    let exif = read_exif(path)?;
+
+    let mut writer = exif::experimental::Writer::new();
+    let mut kept = 0;
+    for field in exif.fields() {
+        if field.ifd_num != In::PRIMARY
+            || field.tag.context() == Context::Gps
+            || REMOVED_TAGS.contains(&field.tag)
+        {
+            continue;
+        }
+        writer.push_field(field);
+        kept += 1;
+    }
+    if kept == 0 {
+        return None;
+    }
+
+    let mut data = std::io::Cursor::new(Vec::new());
+    writer.write(&mut data, exif.little_endian()).ok()?;
+    Some(data.into_inner())
+}
+
+fn read_exif(path: &Path) -> Option<exif::Exif> {
     let file = File::open(path).ok()?;
     let mut bufreader = std::io::BufReader::new(&file);
     let exifreader = exif::Reader::new();
-    let exif = exifreader.read_from_container(&mut bufreader).ok()?;
+    exifreader.read_from_container(&mut bufreader).ok()
+}
 
-    // Try DateTimeOriginal first (when photo was taken)
-    if let Some(field) = exif.get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY) {
-        if let Some(datetime_str) = field.display_value().to_string().split_whitespace().next() {
-            return parse_exif_datetime(datetime_str);
+fn extract_date_taken(exif: &exif::Exif) -> Option<DateTime<Utc>> {
+    // Try DateTimeOriginal first (when photo was taken), then fall back to DateTime
+    exif_datetime(exif, exif::Tag::DateTimeOriginal, exif::Tag::SubSecTimeOriginal)
+        .or_else(|| exif_datetime(exif, exif::Tag::DateTime, exif::Tag::SubSecTime))
+}
+
+/// OffsetTimeOriginal (EXIF 2.31), or OffsetTime for the fallback date
+fn extract_taken_offset(exif: &exif::Exif) -> Option<i32> {
+    [exif::Tag::OffsetTimeOriginal, exif::Tag::OffsetTime].into_iter().find_map(|tag| {
+        match &exif.get_field(tag, exif::In::PRIMARY)?.value {
+            exif::Value::Ascii(values) => {
+                let offset = parse_utc_offset(std::str::from_utf8(values.first()?).ok()?)?;
+                Some(offset.local_minus_utc() / 60)
+            }
+            _ => None,
         }
-    }
+    })
+}
+
+fn exif_datetime(exif: &exif::Exif, tag: exif::Tag, subsec_tag: exif::Tag) -> Option<DateTime<Utc>> {
+    // EXIF format: "YYYY:MM:DD HH:MM:SS"
+    let mut datetime = match &exif.get_field(tag, exif::In::PRIMARY)?.value {
+        exif::Value::Ascii(values) => exif::DateTime::from_ascii(values.first()?).ok()?,
+        _ => return None,
+    };
 
-    // Fallback to DateTime
-    if let Some(field) = exif.get_field(exif::Tag::DateTime, exif::In::PRIMARY) {
-        if let Some(datetime_str) = field.display_value().to_string().split_whitespace().next() {
-            return parse_exif_datetime(datetime_str);
+    // Sub-second precision orders shots taken within the same second
+    if let Some(exif::Value::Ascii(values)) = exif.get_field(subsec_tag, exif::In::PRIMARY).map(|f| &f.value) {
+        if let Some(subsec) = values.first() {
+            let _ = datetime.parse_subsec(subsec);
         }
     }
 
-    None
+    let date = NaiveDate::from_ymd_opt(datetime.year as i32, datetime.month as u32, datetime.day as u32)?;
+    let time = NaiveTime::from_hms_nano_opt(
+        datetime.hour as u32,
+        datetime.minute as u32,
+        datetime.second as u32,
+        datetime.nanosecond.unwrap_or(0),
+    )?;
+
+    Some(DateTime::<Utc>::from_naive_utc_and_offset(date.and_time(time), Utc))
 }
 
-fn parse_exif_datetime(datetime_str: &str) -> Option<DateTime<Utc>> {
-    // EXIF format: "YYYY:MM:DD HH:MM:SS"
-    let normalized = datetime_str.replace(':', "-");
-    let parts: Vec<&str> = normalized.split_whitespace().collect();
+fn extract_gps(exif: &exif::Exif) -> Option<(f64, f64)> {
+    let latitude = gps_coordinate(exif, exif::Tag::GPSLatitude, exif::Tag::GPSLatitudeRef, b'S')?;
+    let longitude = gps_coordinate(exif, exif::Tag::GPSLongitude, exif::Tag::GPSLongitudeRef, b'W')?;
 
-    if parts.len() != 2 {
-        return None;
+    Some((latitude, longitude))
+}
+
+fn gps_coordinate(exif: &exif::Exif, tag: exif::Tag, ref_tag: exif::Tag, negative_ref: u8) -> Option<f64> {
+    // Coordinates are stored as degrees, minutes, seconds rationals
+    let field = exif.get_field(tag, exif::In::PRIMARY)?;
+    let degrees = match &field.value {
+        exif::Value::Rational(parts) if parts.len() >= 3 => {
+            parts[0].to_f64() + parts[1].to_f64() / 60.0 + parts[2].to_f64() / 3600.0
+        }
+        _ => return None,
+    };
+
+    let negative = match exif.get_field(ref_tag, exif::In::PRIMARY).map(|f| &f.value) {
+        Some(exif::Value::Ascii(values)) => values.first().and_then(|v| v.first()) == Some(&negative_ref),
+        _ => false,
+    };
+
+    Some(if negative { -degrees } else { degrees })
+}
+
+/// Camera name as "Make Model", without repeating the make ("Canon Canon EOS R5")
+fn extract_camera_model(exif: &exif::Exif) -> Option<String> {
+    let make = ascii_field(exif, exif::Tag::Make);
+    let model = ascii_field(exif, exif::Tag::Model);
+
+    match (make, model) {
+        (Some(make), Some(model)) => {
+            // "NIKON CORPORATION" + "NIKON D750" -> "NIKON D750"
+            let brand = make.split_whitespace().next().unwrap_or(&make).to_lowercase();
+            if model.to_lowercase().starts_with(&brand) {
+                Some(model)
+            } else {
+                Some(format!("{} {}", make, model))
+            }
+        }
+        (make, model) => model.or(make),
     }
+}
 
-    let date_part = parts[0];
-    let time_part = parts.get(1).unwrap_or(&"00:00:00");
+fn ascii_field(exif: &exif::Exif, tag: exif::Tag) -> Option<String> {
+    match &exif.get_field(tag, exif::In::PRIMARY)?.value {
+        exif::Value::Ascii(values) => {
+            let text = String::from_utf8_lossy(values.first()?);
+            let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+            (!text.is_empty()).then(|| text.to_string())
+        }
+        _ => None,
+    }
+}
 
-    let datetime_combined = format!("{} {}", date_part, time_part);
-    NaiveDateTime::parse_from_str(&datetime_combined, "%Y-%m-%d %H:%M:%S")
-        .ok()
-        .map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc))
+/// Decode an image, detecting the format from its content rather than the extension
+pub fn open_image(path: &Path) -> Result<image::DynamicImage> {
+    let img = image::ImageReader::open(path)?
+        .with_guessed_format()?
+        .decode()?;
+    Ok(img)
 }
 
-/// Get image dimensions
-pub fn get_image_dimensions(path: &Path) -> Result<(u32, u32)> {
-    let img = image::open(path)?;
-    Ok((img.width(), img.height()))
+/// Width and height from the image header, without decoding the pixels
+pub fn image_dimensions(path: &Path) -> Result<(u32, u32)> {
+    let dimensions = image::ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_dimensions()?;
+    Ok(dimensions)
 }
@@ -0,0 +1,154 @@
+use std::path::Path;
+use image::{imageops::FilterType, DynamicImage, RgbImage};
+use ort::session::Session;
+use ort::value::Tensor;
+use anyhow::Result;
+
+use crate::utils::l2_normalize;
+
+/// UltraFace (version-RFB-320) detector, expected at `<models>/face_detector.onnx`
+pub const DETECTOR_MODEL: &str = "face_detector.onnx";
+
+/// ArcFace-style 112x112 embedder (e.g. InsightFace w600k_mbf), expected at `<models>/face_embedder.onnx`
+pub const EMBEDDER_MODEL: &str = "face_embedder.onnx";
+
+const DETECTOR_WIDTH: u32 = 320;
+const DETECTOR_HEIGHT: u32 = 240;
+const EMBEDDER_SIZE: u32 = 112;
+
+const MIN_CONFIDENCE: f32 = 0.7;
+const NMS_IOU: f32 = 0.3;
+/// Faces narrower than this fraction of the image are too small to recognize
+const MIN_FACE_SIZE: f32 = 0.02;
+/// Extra context around the detected box before embedding
+const CROP_MARGIN: f32 = 0.15;
+
+/// A face found by the detector; coordinates are relative to the image (0-1)
+#[derive(Debug, Clone)]
+pub struct DetectedFace {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub confidence: f32,
+    /// L2-normalized, so cosine similarity is a dot product
+    pub embedding: Vec<f32>,
+}
+
+pub struct FaceDetector {
+    detector: Session,
+    embedder: Session,
+}
+
+impl FaceDetector {
+    /// Load both models from `models_dir`. The ONNX Runtime library is loaded at runtime;
+    /// set ORT_DYLIB_PATH if it is not on the library search path.
+    pub fn load(models_dir: &Path) -> Result<Self> {
+        let load = |name: &str| -> Result<Session> {
+            let path = models_dir.join(name);
+            if !path.exists() {
+                return Err(anyhow::anyhow!("Face model not found: {}", path.display()));
+            }
+            Ok(Session::builder()?.commit_from_file(&path)?)
+        };
+
+        Ok(Self {
+            detector: load(DETECTOR_MODEL)?,
+            embedder: load(EMBEDDER_MODEL)?,
+        })
+    }
+
+    pub fn detect(&mut self, img: &DynamicImage) -> Result<Vec<DetectedFace>> {
+        let rgb = img.to_rgb8();
+
+        let input = rgb_tensor(
+            &image::imageops::resize(&rgb, DETECTOR_WIDTH, DETECTOR_HEIGHT, FilterType::Triangle),
+            127.0,
+            128.0,
+        )?;
+        let outputs = self.detector.run(ort::inputs![input])?;
+        let (_, scores) = outputs[0].try_extract_tensor::<f32>()?;
+        let (_, boxes) = outputs[1].try_extract_tensor::<f32>()?;
+
+        // scores: [1, N, 2] (background, face), boxes: [1, N, 4] as relative x1, y1, x2, y2
+        let mut candidates: Vec<([f32; 4], f32)> = scores
+            .chunks_exact(2)
+            .zip(boxes.chunks_exact(4))
+            .filter(|(score, _)| score[1] >= MIN_CONFIDENCE)
+            .map(|(score, b)| ([b[0].max(0.0), b[1].max(0.0), b[2].min(1.0), b[3].min(1.0)], score[1]))
+            .filter(|(b, _)| b[2] - b[0] >= MIN_FACE_SIZE && b[3] - b[1] > 0.0)
+            .collect();
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let mut kept: Vec<([f32; 4], f32)> = Vec::new();
+        for candidate in candidates {
+            if kept.iter().all(|k| iou(&k.0, &candidate.0) < NMS_IOU) {
+                kept.push(candidate);
+            }
+        }
+        drop(outputs);
+
+        let mut faces = Vec::with_capacity(kept.len());
+        for (b, confidence) in kept {
+            let embedding = self.embed(&rgb, &b)?;
+            faces.push(DetectedFace {
+                x: b[0],
+                y: b[1],
+                width: b[2] - b[0],
+                height: b[3] - b[1],
+                confidence,
+                embedding,
+            });
+        }
+
+        Ok(faces)
+    }
+
+    /// Crop a square around the face and run it through the embedder
+    fn embed(&mut self, rgb: &RgbImage, b: &[f32; 4]) -> Result<Vec<f32>> {
+        let (w, h) = (rgb.width() as f32, rgb.height() as f32);
+        let center_x = (b[0] + b[2]) / 2.0 * w;
+        let center_y = (b[1] + b[3]) / 2.0 * h;
+        let side = ((b[2] - b[0]) * w).max((b[3] - b[1]) * h) * (1.0 + CROP_MARGIN * 2.0);
+
+        let left = (center_x - side / 2.0).clamp(0.0, w - 1.0) as u32;
+        let top = (center_y - side / 2.0).clamp(0.0, h - 1.0) as u32;
+        let crop_w = (side as u32).clamp(1, rgb.width() - left);
+        let crop_h = (side as u32).clamp(1, rgb.height() - top);
+
+        let crop = image::imageops::crop_imm(rgb, left, top, crop_w, crop_h).to_image();
+        let input = rgb_tensor(
+            &image::imageops::resize(&crop, EMBEDDER_SIZE, EMBEDDER_SIZE, FilterType::Triangle),
+            127.5,
+            127.5,
+        )?;
+
+        let outputs = self.embedder.run(ort::inputs![input])?;
+        let (_, values) = outputs[0].try_extract_tensor::<f32>()?;
+
+        Ok(l2_normalize(values))
+    }
+}
+
+/// NCHW float tensor with `(pixel - mean) / scale`
+fn rgb_tensor(img: &RgbImage, mean: f32, scale: f32) -> Result<Tensor<f32>> {
+    let (w, h) = (img.width() as usize, img.height() as usize);
+    let mut data = vec![0f32; 3 * w * h];
+
+    for (x, y, pixel) in img.enumerate_pixels() {
+        let offset = y as usize * w + x as usize;
+        for channel in 0..3 {
+            data[channel * w * h + offset] = (pixel[channel] as f32 - mean) / scale;
+        }
+    }
+
+    Ok(Tensor::from_array(([1usize, 3, h, w], data))?)
+}
+
+fn iou(a: &[f32; 4], b: &[f32; 4]) -> f32 {
+    let overlap_w = (a[2].min(b[2]) - a[0].max(b[0])).max(0.0);
+    let overlap_h = (a[3].min(b[3]) - a[1].max(b[1])).max(0.0);
+    let overlap = overlap_w * overlap_h;
+    let union = (a[2] - a[0]) * (a[3] - a[1]) + (b[2] - b[0]) * (b[3] - b[1]) - overlap;
+    if union <= 0.0 { 0.0 } else { overlap / union }
+}
@@ -0,0 +1,94 @@
+use std::path::PathBuf;
+use std::sync::RwLock;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Locale, Utc};
+use anyhow::Result;
+use tracing::warn;
+
+/// Folders made for files by date: year, then day
+pub const DEFAULT_DATE_FOLDER_TEMPLATE: &str = "%Y/%Y-%m-%d";
+
+struct DateFolderTemplate {
+    template: String,
+    locale: Locale,
+}
+
+static TEMPLATE: RwLock<Option<DateFolderTemplate>> = RwLock::new(None);
+
+/// Set the template from the config, e.g. "%Y/%m - %B" for "2024/03 - März" in German.
+/// Month and day names and `%x` dates follow `locale` ("de_DE"), or the system's when
+/// empty. An invalid template falls back to the default.
+pub fn set_date_folder_template(template: &str, locale: &str) {
+    let template = match validate_date_folder_template(template) {
+        Ok(()) => template.to_string(),
+        Err(e) => {
+            warn!("{}; using {:?}", e, DEFAULT_DATE_FOLDER_TEMPLATE);
+            DEFAULT_DATE_FOLDER_TEMPLATE.to_string()
+        }
+    };
+    let locale = parse_locale(locale).unwrap_or_else(|| {
+        warn!("Unknown locale {:?}; using en_US", locale);
+        Locale::en_US
+    });
+    *TEMPLATE.write().unwrap_or_else(|e| e.into_inner()) = Some(DateFolderTemplate { template, locale });
+}
+
+/// A template must render to a relative path; each `/` starts a subfolder
+pub fn validate_date_folder_template(template: &str) -> Result<()> {
+    if template.trim().is_empty() || template.starts_with('/') || template.starts_with('\\') {
+        return Err(anyhow::anyhow!("Date folder template {:?} must be a relative path", template));
+    }
+    if template.split(['/', '\\']).any(|part| part.trim() == "..") {
+        return Err(anyhow::anyhow!("Date folder template {:?} can't contain \"..\"", template));
+    }
+    if StrftimeItems::new(template).any(|item| item == Item::Error) {
+        return Err(anyhow::anyhow!("Date folder template {:?} has an unknown % code", template));
+    }
+    Ok(())
+}
+
+/// "de_DE", "de-DE" and "de_DE.UTF-8" alike; empty for the system's locale
+fn parse_locale(setting: &str) -> Option<Locale> {
+    let setting = setting.trim();
+    let setting = if setting.is_empty() { system_locale()? } else { setting.to_string() };
+    let name = setting.split(['.', '@']).next().unwrap_or_default().replace('-', "_");
+    match name.as_str() {
+        "" | "C" | "POSIX" => Some(Locale::en_US),
+        name => Locale::try_from(name).ok(),
+    }
+}
+
+/// From the environment on Unix; other systems get en_US
+fn system_locale() -> Option<String> {
+    ["LC_ALL", "LC_TIME", "LANG"]
+        .iter()
+        .filter_map(|variable| std::env::var(variable).ok())
+        .find(|value| !value.is_empty())
+        .or_else(|| Some("en_US".to_string()))
+}
+
+/// Folder for a file dated `date` (wall-clock time), relative to the destination
+pub fn date_folder(date: DateTime<Utc>) -> PathBuf {
+    let template = TEMPLATE.read().unwrap_or_else(|e| e.into_inner());
+    let (template, locale) = match template.as_ref() {
+        Some(template) => (template.template.as_str(), template.locale),
+        None => (DEFAULT_DATE_FOLDER_TEMPLATE, Locale::en_US),
+    };
+
+    // Rendered part by part, so a `/` in a rendered date (`%x` in en_US) can't nest folders
+    template
+        .split(['/', '\\'])
+        .map(|part| file_name_safe(&date.format_localized(part, locale).to_string()))
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+/// Without characters that aren't allowed in file names on some system, and without
+/// the trailing dots and spaces Windows drops
+fn file_name_safe(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control() { '-' } else { c })
+        .collect();
+    name.trim().trim_end_matches(['.', ' ']).to_string()
+}
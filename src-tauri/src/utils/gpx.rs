@@ -0,0 +1,106 @@
+use std::fs;
+use std::path::Path;
+use chrono::{DateTime, Duration, Utc};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use anyhow::Result;
+
+/// Longest gap between two track points that is bridged by interpolation
+const MAX_INTERPOLATION_GAP: i64 = 10 * 60;
+
+/// How far from the nearest track point a photo may be when it can't be interpolated,
+/// e.g. just before the logger was started
+const MAX_NEAREST_DISTANCE: i64 = 5 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackPoint {
+    pub time: DateTime<Utc>,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Timed points of every track in a GPX file, oldest first
+pub fn read_gpx(path: &Path) -> Result<Vec<TrackPoint>> {
+    let points = parse_gpx(&fs::read_to_string(path)?)?;
+    if points.is_empty() {
+        return Err(anyhow::anyhow!("{} has no timed track points", path.display()));
+    }
+    Ok(points)
+}
+
+fn parse_gpx(xml: &str) -> Result<Vec<TrackPoint>> {
+    let mut reader = Reader::from_str(xml);
+    let mut points = Vec::new();
+    // Position of the open <trkpt>, and whether we're inside its <time>
+    let mut current: Option<(f64, f64)> = None;
+    let mut in_time = false;
+    let mut text = String::new();
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) => match element.local_name().as_ref() {
+                b"trkpt" => {
+                    let mut latitude = None;
+                    let mut longitude = None;
+                    for attribute in element.attributes().flatten() {
+                        let value = attribute.unescape_value()?;
+                        match attribute.key.local_name().as_ref() {
+                            b"lat" => latitude = value.trim().parse::<f64>().ok(),
+                            b"lon" => longitude = value.trim().parse::<f64>().ok(),
+                            _ => {}
+                        }
+                    }
+                    current = latitude.zip(longitude);
+                }
+                b"time" if current.is_some() => {
+                    in_time = true;
+                    text.clear();
+                }
+                _ => {}
+            },
+            Event::Text(content) if in_time => text.push_str(&content.decode()?),
+            Event::End(element) => match element.local_name().as_ref() {
+                b"time" => in_time = false,
+                b"trkpt" => {
+                    let time = DateTime::parse_from_rfc3339(text.trim()).ok();
+                    if let (Some((latitude, longitude)), Some(time)) = (current.take(), time) {
+                        points.push(TrackPoint { time: time.with_timezone(&Utc), latitude, longitude });
+                    }
+                    text.clear();
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    points.sort_by_key(|point| point.time);
+    Ok(points)
+}
+
+/// Where the track was at `time`: interpolated between the surrounding points, or the
+/// nearest point if it is close enough. `points` must be sorted by time.
+pub fn locate(points: &[TrackPoint], time: DateTime<Utc>) -> Option<(f64, f64)> {
+    let index = points.partition_point(|point| point.time <= time);
+    let before = index.checked_sub(1).map(|i| points[i]);
+    let after = points.get(index).copied();
+
+    if let (Some(before), Some(after)) = (before, after) {
+        let span = (after.time - before.time).num_milliseconds();
+        if span > 0 && span <= MAX_INTERPOLATION_GAP * 1000 {
+            let fraction = (time - before.time).num_milliseconds() as f64 / span as f64;
+            return Some((
+                before.latitude + (after.latitude - before.latitude) * fraction,
+                before.longitude + (after.longitude - before.longitude) * fraction,
+            ));
+        }
+    }
+
+    [before, after]
+        .into_iter()
+        .flatten()
+        .filter(|point| (point.time - time).abs() <= Duration::seconds(MAX_NEAREST_DISTANCE))
+        .min_by_key(|point| (point.time - time).abs())
+        .map(|point| (point.latitude, point.longitude))
+}
@@ -1,14 +1,43 @@
-use std::fs::File;
-use std::io::{BufReader, Read};
-use std::path::Path;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use image::{imageops::FilterType, DynamicImage};
+use rusqlite::{params, Connection};
 use anyhow::Result;
 
+use crate::utils::{run_in_worker_pool, OperationTimer, Workload};
+
+/// Files at least this big are memory-mapped and hashed on all worker threads
+const PARALLEL_HASH_THRESHOLD: u64 = 16 * 1024 * 1024;
+
 /// Generate BLAKE3 hash for a file (fast and secure)
 pub fn hash_file(path: &Path) -> Result<String> {
-    let file = File::open(path)?;
-    let mut reader = BufReader::new(file);
+    let timer = OperationTimer::start("hash");
+    let size = fs::metadata(path)?.len();
+    let hash = if size >= PARALLEL_HASH_THRESHOLD {
+        let mut hasher = blake3::Hasher::new();
+        if rayon::current_thread_index().is_some() {
+            // Already on a worker, e.g. importing; hash on the same pool
+            hasher.update_mmap_rayon(path)?;
+        } else {
+            run_in_worker_pool(Workload::Scan, || hasher.update_mmap_rayon(path).map(drop))??;
+        }
+        hasher.finalize().to_hex().to_string()
+    } else {
+        hash_reader(File::open(path)?)?
+    };
+    timer.finish(1, size);
+    Ok(hash)
+}
+
+/// `hash_file` of a stream, e.g. a zip entry
+pub fn hash_reader(reader: impl Read) -> Result<String> {
+    let mut reader = BufReader::new(reader);
     let mut hasher = blake3::Hasher::new();
-    let mut buffer = [0; 8192];
+    // BLAKE3 only uses its SIMD paths on reads of 16 KiB and up
+    let mut buffer = vec![0; 64 * 1024];
 
     loop {
         let count = reader.read(&mut buffer)?;
@@ -21,7 +50,223 @@ pub fn hash_file(path: &Path) -> Result<String> {
     Ok(hasher.finalize().to_hex().to_string())
 }
 
+/// `hash_file` results from the `file_hashes` table, reused while a file's size and
+/// modification time are unchanged. Safe to share between threads; call `save` to
+/// remember the files hashed since `load`.
+pub struct HashCache {
+    known: HashMap<String, (i64, i64, String)>,
+    hashed: Mutex<Vec<(String, i64, i64, String)>>,
+}
+
+impl HashCache {
+    pub fn load(conn: &Connection) -> Result<Self> {
+        let mut stmt = conn.prepare("SELECT file_path, file_size, modified_ns, file_hash FROM file_hashes")?;
+        let known = stmt
+            .query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?, row.get(3)?))))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(HashCache { known, hashed: Mutex::new(Vec::new()) })
+    }
+
+    /// Same result as `hash_file`, without reading the file if it's known
+    pub fn hash(&self, path: &Path) -> Result<String> {
+        let (size, modified_ns) = file_stamp(path)?;
+        let key = path.to_string_lossy().to_string();
+
+        if let Some((known_size, known_modified_ns, hash)) = self.known.get(&key) {
+            if *known_size == size && *known_modified_ns == modified_ns {
+                return Ok(hash.clone());
+            }
+        }
+
+        let hash = hash_file(path)?;
+        if let Ok(mut hashed) = self.hashed.lock() {
+            hashed.push((key, size, modified_ns, hash.clone()));
+        }
+        Ok(hash)
+    }
+
+    /// Take `hash` as the hash of `path` as it is now
+    pub fn record(&mut self, path: &Path, hash: &str) -> Result<()> {
+        let (size, modified_ns) = file_stamp(path)?;
+        let key = path.to_string_lossy().to_string();
+        self.known.insert(key.clone(), (size, modified_ns, hash.to_string()));
+        if let Ok(hashed) = self.hashed.get_mut() {
+            hashed.push((key, size, modified_ns, hash.to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, conn: &Connection) -> Result<()> {
+        let hashed = std::mem::take(&mut *self.hashed.lock().map_err(|_| anyhow::anyhow!("Hash cache lock poisoned"))?);
+        let tx = conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO file_hashes (file_path, file_size, modified_ns, file_hash) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for (path, size, modified_ns, hash) in &hashed {
+                stmt.execute(params![path, size, modified_ns, hash])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+/// `HashCache::hash` for a single file
+pub fn cached_hash_file(conn: &Connection, path: &Path) -> Result<String> {
+    let (size, modified_ns) = file_stamp(path)?;
+    let key = path.to_string_lossy().to_string();
+
+    let known: Option<String> = conn
+        .query_row(
+            "SELECT file_hash FROM file_hashes WHERE file_path = ?1 AND file_size = ?2 AND modified_ns = ?3",
+            params![key, size, modified_ns],
+            |row| row.get(0),
+        )
+        .ok();
+    if let Some(hash) = known {
+        return Ok(hash);
+    }
+
+    let hash = hash_file(path)?;
+    conn.execute(
+        "INSERT OR REPLACE INTO file_hashes (file_path, file_size, modified_ns, file_hash) VALUES (?1, ?2, ?3, ?4)",
+        params![key, size, modified_ns, hash],
+    )?;
+    Ok(hash)
+}
+
+/// Spots import sources the library (or the import so far) already holds while reading
+/// as little of them as possible: a source is only hashed when a known file has its
+/// size, and only in full when their first and last megabytes match too.
+/// Network files carry quick hashes in the catalog and are never matched.
+pub struct DuplicateScreen {
+    /// Known files by size, with their full hashes
+    by_size: HashMap<u64, Vec<(PathBuf, String)>>,
+    /// `quick_hash_file` of known files, `None` if unreadable
+    quick_hashes: HashMap<PathBuf, Option<String>>,
+}
+
+impl DuplicateScreen {
+    pub fn load(conn: &Connection) -> Result<Self> {
+        let mut screen = DuplicateScreen { by_size: HashMap::new(), quick_hashes: HashMap::new() };
+        let mut stmt = conn.prepare("SELECT file_path, file_size, file_hash FROM media_files WHERE online_only = 0")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get(2)?)))?;
+        for row in rows {
+            let (path, size, hash) = row?;
+            screen.add(PathBuf::from(path), size.max(0) as u64, hash);
+        }
+        Ok(screen)
+    }
+
+    /// Remember an imported file so later sources are checked against it too
+    pub fn add(&mut self, path: PathBuf, size: u64, hash: String) {
+        self.by_size.entry(size).or_default().push((path, hash));
+    }
+
+    /// Full hash of `source` if a known file has the same content
+    pub fn find_file(&mut self, source: &Path) -> Result<Option<String>> {
+        let size = fs::metadata(source)?.len();
+        if !self.by_size.contains_key(&size) {
+            return Ok(None);
+        }
+
+        let quick = quick_hash_file(source)?;
+        let candidates: Vec<PathBuf> = self.by_size[&size].iter().map(|(path, _)| path.clone()).collect();
+        let quick_match = candidates.into_iter().any(|path| {
+            self.quick_hashes
+                .entry(path)
+                .or_insert_with_key(|path| quick_hash_file(path).ok())
+                .as_deref()
+                == Some(quick.as_str())
+        });
+        if !quick_match {
+            return Ok(None);
+        }
+
+        Ok(self.find_hash(size, hash_file(source)?))
+    }
+
+    /// Like `find_file` for a source that can only be read start to finish, such as a
+    /// compressed zip entry: hashed in full, but only if its size matches a known file
+    pub fn find_stream<R: Read>(&mut self, size: u64, reader: impl FnOnce() -> Result<R>) -> Result<Option<String>> {
+        if !self.by_size.contains_key(&size) {
+            return Ok(None);
+        }
+        Ok(self.find_hash(size, hash_reader(reader()?)?))
+    }
+
+    fn find_hash(&self, size: u64, hash: String) -> Option<String> {
+        let known = self.by_size.get(&size).is_some_and(|files| files.iter().any(|(_, known)| *known == hash));
+        known.then_some(hash)
+    }
+}
+
+/// Size and modification time in nanoseconds, which change whenever a file is rewritten
+fn file_stamp(path: &Path) -> Result<(i64, i64)> {
+    let metadata = fs::metadata(path)?;
+    let modified = filetime::FileTime::from_last_modification_time(&metadata);
+    Ok((
+        metadata.len() as i64,
+        modified.unix_seconds() * 1_000_000_000 + modified.nanoseconds() as i64,
+    ))
+}
+
+/// Bytes read from each end of a file by `quick_hash_file`
+const QUICK_HASH_SAMPLE: u64 = 1024 * 1024;
+
+/// Hash of a file's size and its first and last megabyte, for network shares where
+/// reading every file in full is too slow. Never equal to a `hash_file` result, so
+/// quick-hashed files aren't matched as duplicates of fully hashed ones.
+pub fn quick_hash_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut hasher = blake3::Hasher::new_derive_key("pengler quick hash v1");
+    hasher.update(&size.to_le_bytes());
+
+    let mut buffer = Vec::with_capacity(QUICK_HASH_SAMPLE as usize);
+    (&mut file).take(QUICK_HASH_SAMPLE).read_to_end(&mut buffer)?;
+    hasher.update(&buffer);
+
+    if size > QUICK_HASH_SAMPLE * 2 {
+        buffer.clear();
+        file.seek(SeekFrom::End(-(QUICK_HASH_SAMPLE as i64)))?;
+        file.read_to_end(&mut buffer)?;
+        hasher.update(&buffer);
+    }
+
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
 /// Generate a shorter hash for cache keys (first 16 chars)
 pub fn short_hash(full_hash: &str) -> String {
     full_hash.chars().take(16).collect()
 }
+
+/// Difference hash (dHash) of an image as 16 hex chars. Resized or re-encoded copies
+/// stay within a few bits of the original; compare with `hamming_distance`.
+pub fn perceptual_hash(img: &DynamicImage) -> String {
+    let small = img.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] < small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+
+    format!("{:016x}", hash)
+}
+
+/// Parse a hash produced by `perceptual_hash`
+pub fn parse_perceptual_hash(hash: &str) -> Option<u64> {
+    u64::from_str_radix(hash, 16).ok()
+}
+
+/// Number of differing bits between two perceptual hashes
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
@@ -0,0 +1,53 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use tracing::warn;
+
+/// Held locked by the instance that owns the catalog; the lock goes with the process,
+/// so a crash never leaves a stale one behind
+const LOCK_FILE: &str = "pengler.lock";
+
+static LOCK: OnceLock<File> = OnceLock::new();
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Claim the catalog in `data_folder` for this process. Returns false if another
+/// instance already has it, in which case this one is read-only from now on.
+pub fn claim_instance_lock(data_folder: &Path) -> bool {
+    let path = data_folder.join(LOCK_FILE);
+    let file = fs::create_dir_all(data_folder).and_then(|_| {
+        OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path)
+    });
+    let mut file = match file {
+        Ok(file) => file,
+        Err(e) => {
+            // Better to risk a second writer than to lock the user out of their library
+            warn!("Failed to open {}: {}", path.display(), e);
+            return true;
+        }
+    };
+
+    match file.try_lock() {
+        Ok(()) => {
+            let _ = file.set_len(0).and_then(|_| write!(file, "{}", std::process::id()));
+            let _ = LOCK.set(file);
+            true
+        }
+        Err(TryLockError::WouldBlock) => {
+            let owner = fs::read_to_string(&path).unwrap_or_default();
+            warn!("Pengler is already running (process {}); opening the library read-only", owner.trim());
+            READ_ONLY.store(true, Ordering::Relaxed);
+            false
+        }
+        Err(TryLockError::Error(e)) => {
+            warn!("Failed to lock {}: {}", path.display(), e);
+            true
+        }
+    }
+}
+
+/// Another instance owns the catalog; this one must not write to it or the config
+pub fn is_read_only_instance() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
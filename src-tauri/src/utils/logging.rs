@@ -0,0 +1,167 @@
+use std::collections::VecDeque;
+use std::fmt::{self, Write as _};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Emitted with a `LogEntry` whenever an error is logged
+pub const LOG_EVENT: &str = "log-event";
+
+/// Entries kept in memory for `recent_logs`
+const RECENT_LOGS: usize = 2000;
+
+/// Daily log files kept before the oldest is deleted
+const LOG_FILES_KEPT: usize = 14;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    pub time: DateTime<Utc>,
+    /// "error", "warn", "info", "debug" or "trace"
+    pub level: String,
+    /// Module that logged it, e.g. "pengler::commands::scanner"
+    pub target: String,
+    pub message: String,
+    #[serde(skip)]
+    severity: Level,
+}
+
+static RECENT: Mutex<VecDeque<LogEntry>> = Mutex::new(VecDeque::new());
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+/// Flushes the file writer's buffer when the app exits
+static FILE_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+
+/// Send logs to stderr, a daily file in `log_folder` and the in-memory buffer.
+/// Pengler's own modules log from debug level in debug builds and info otherwise;
+/// dependencies only log warnings and errors.
+pub fn init_logging(log_folder: Option<&Path>) {
+    let level = if cfg!(debug_assertions) { LevelFilter::DEBUG } else { LevelFilter::INFO };
+    let filter = Targets::new()
+        .with_target(env!("CARGO_CRATE_NAME"), level)
+        .with_default(LevelFilter::WARN);
+
+    let file_layer = log_folder.and_then(|folder| match log_file(folder) {
+        Ok(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let _ = FILE_GUARD.set(guard);
+            Some(tracing_subscriber::fmt::layer().with_ansi(false).with_writer(writer))
+        }
+        Err(e) => {
+            eprintln!("Failed to open log file in {}: {}", folder.display(), e);
+            None
+        }
+    });
+
+    let result = tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(file_layer)
+        .with(RecentLogs)
+        .with(filter)
+        .try_init();
+    if let Err(e) = result {
+        eprintln!("Failed to set up logging: {}", e);
+    }
+}
+
+fn log_file(folder: &Path) -> Result<RollingFileAppender> {
+    Ok(RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("pengler")
+        .filename_suffix("log")
+        .max_log_files(LOG_FILES_KEPT)
+        .build(folder)?)
+}
+
+/// Lets logged errors reach the frontend as `LOG_EVENT`s; errors logged before
+/// this only go to the buffer and the log file
+pub fn set_log_app_handle(app: AppHandle) {
+    let _ = APP_HANDLE.set(app);
+}
+
+/// The handle given to `set_log_app_handle`, for emitting events outside of commands
+pub fn app_handle() -> Option<&'static AppHandle> {
+    APP_HANDLE.get()
+}
+
+/// The last `limit` entries at `min_level` or more severe, oldest first
+pub fn recent_logs(min_level: Option<&str>, limit: usize) -> Result<Vec<LogEntry>> {
+    let min_level = match min_level {
+        Some(level) => Level::from_str(level).map_err(|_| anyhow::anyhow!("Unknown log level: {}", level))?,
+        None => Level::TRACE,
+    };
+
+    let recent = RECENT.lock().unwrap_or_else(|e| e.into_inner());
+    let mut entries: Vec<LogEntry> = recent
+        .iter()
+        .rev()
+        .filter(|entry| entry.severity <= min_level)
+        .take(limit)
+        .cloned()
+        .collect();
+    entries.reverse();
+    Ok(entries)
+}
+
+/// Keeps recent entries in memory and forwards errors to the frontend
+struct RecentLogs;
+
+impl<S: Subscriber> Layer<S> for RecentLogs {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut message = MessageVisitor::default();
+        event.record(&mut message);
+
+        let entry = LogEntry {
+            time: Utc::now(),
+            level: metadata.level().as_str().to_lowercase(),
+            target: metadata.target().to_string(),
+            message: message.0,
+            severity: *metadata.level(),
+        };
+
+        if entry.severity == Level::ERROR {
+            if let Some(app) = APP_HANDLE.get() {
+                // Not logged on failure, which would recurse
+                let _ = app.emit(LOG_EVENT, &entry);
+            }
+        }
+
+        let mut recent = RECENT.lock().unwrap_or_else(|e| e.into_inner());
+        if recent.len() == RECENT_LOGS {
+            recent.pop_front();
+        }
+        recent.push_back(entry);
+    }
+}
+
+/// Formats the message followed by any other fields as `name=value`
+#[derive(Default)]
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record_debug(field, &format_args!("{}", value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        if field.name() == "message" {
+            let _ = write!(self.0, "{:?}", value);
+        } else {
+            let _ = write!(self.0, "{}={:?}", field.name(), value);
+        }
+    }
+}
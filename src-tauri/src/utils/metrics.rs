@@ -0,0 +1,107 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Finished runs kept per operation
+const RECENT_RUNS: usize = 200;
+
+#[derive(Debug, Clone, Copy)]
+struct Run {
+    files: usize,
+    bytes: u64,
+    elapsed: Duration,
+    finished_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+struct OperationLog {
+    runs: VecDeque<Run>,
+    active: usize,
+}
+
+static OPERATIONS: Mutex<Option<HashMap<&'static str, OperationLog>>> = Mutex::new(None);
+
+fn with_log<T>(operation: &'static str, f: impl FnOnce(&mut OperationLog) -> T) -> T {
+    let mut operations = OPERATIONS.lock().unwrap_or_else(|e| e.into_inner());
+    f(operations.get_or_insert_with(HashMap::new).entry(operation).or_default())
+}
+
+/// Times one run of an operation ("scan", "hash", ...) while counting it as active.
+/// Runs dropped without `finish`, e.g. on an error, aren't recorded.
+pub struct OperationTimer {
+    operation: &'static str,
+    started: Instant,
+}
+
+impl OperationTimer {
+    pub fn start(operation: &'static str) -> Self {
+        with_log(operation, |log| log.active += 1);
+        Self { operation, started: Instant::now() }
+    }
+
+    /// Record the run as having processed `files` files totalling `bytes`
+    pub fn finish(self, files: usize, bytes: u64) {
+        let run = Run { files, bytes, elapsed: self.started.elapsed(), finished_at: Utc::now() };
+        with_log(self.operation, |log| {
+            if log.runs.len() == RECENT_RUNS {
+                log.runs.pop_front();
+            }
+            log.runs.push_back(run);
+        });
+    }
+}
+
+impl Drop for OperationTimer {
+    fn drop(&mut self) {
+        with_log(self.operation, |log| log.active = log.active.saturating_sub(1));
+    }
+}
+
+/// Stats over the recent runs of one operation
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationMetrics {
+    pub operation: String,
+    pub runs: usize,
+    /// Runs in progress right now
+    pub active: usize,
+    pub files: usize,
+    pub bytes: u64,
+    pub total_ms: f64,
+    pub avg_ms_per_file: Option<f64>,
+    pub megabytes_per_second: Option<f64>,
+    pub last_run_at: Option<DateTime<Utc>>,
+}
+
+/// Recent stats of every operation that has run since startup, by name
+pub fn operation_metrics() -> Vec<OperationMetrics> {
+    let operations = OPERATIONS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut metrics: Vec<OperationMetrics> = operations
+        .iter()
+        .flatten()
+        .map(|(operation, log)| {
+            let files: usize = log.runs.iter().map(|run| run.files).sum();
+            let bytes: u64 = log.runs.iter().map(|run| run.bytes).sum();
+            let elapsed: Duration = log.runs.iter().map(|run| run.elapsed).sum();
+            let total_ms = elapsed.as_secs_f64() * 1000.0;
+
+            OperationMetrics {
+                operation: operation.to_string(),
+                runs: log.runs.len(),
+                active: log.active,
+                files,
+                bytes,
+                total_ms,
+                avg_ms_per_file: (files > 0).then(|| total_ms / files as f64),
+                megabytes_per_second: (!elapsed.is_zero() && bytes > 0)
+                    .then(|| bytes as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64()),
+                last_run_at: log.runs.back().map(|run| run.finished_at),
+            }
+        })
+        .collect();
+
+    metrics.sort_by(|a, b| a.operation.cmp(&b.operation));
+    metrics
+}
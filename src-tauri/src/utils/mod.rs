@@ -1,5 +1,61 @@
 pub mod hash;
 pub mod exif;
+pub mod disk;
+pub mod atomic;
+pub mod video;
+pub mod xmp;
+pub mod color;
+pub mod ocr;
+pub mod embedding;
+pub mod edits;
+pub mod text;
+pub mod watermark;
+pub mod placeholder;
+pub mod network;
+pub mod gpx;
+pub mod workers;
+pub mod metrics;
+pub mod logging;
+pub mod timezone;
+pub mod instance;
+pub mod folder_template;
+#[cfg(feature = "face-detection")]
+pub mod faces;
+#[cfg(feature = "semantic-search")]
+pub mod clip;
+pub mod encryption;
+#[cfg(feature = "cloud-backup")]
+pub mod s3;
 
-pub use hash::{hash_file, short_hash};
-pub use exif::{extract_date_taken, get_image_dimensions};
+pub use hash::{cached_hash_file, hash_file, quick_hash_file, short_hash, DuplicateScreen, HashCache, perceptual_hash, parse_perceptual_hash, hamming_distance};
+pub use exif::{extract_exif_metadata, exposure_summary, image_dimensions, open_image, privacy_filtered_exif};
+pub use disk::{ensure_free_space, same_file};
+pub use atomic::{part_path, write_atomically};
+pub use video::{probe_video, is_hdr};
+pub use xmp::{find_sidecar, read_xmp_metadata, write_xmp_sidecar, XmpMetadata};
+pub use color::{open_image_with_profile, open_upright_image_with_profile, image_color_space, profile_color_space, convert_to_srgb};
+pub use ocr::recognize_text;
+pub use embedding::{blob_to_embedding, dot};
+pub use edits::{apply_edits, EditRecipe};
+pub use text::{draw_text, line_height, load_font, text_width};
+pub use watermark::Watermark;
+pub use placeholder::{hydrate, is_online_only};
+pub use network::retry_network_io;
+pub use gpx::{locate, read_gpx};
+pub use logging::{init_logging, recent_logs, set_log_app_handle, LogEntry};
+pub use metrics::{operation_metrics, OperationMetrics, OperationTimer};
+pub use timezone::{parse_utc_offset, set_date_timezone, wall_clock, wall_clock_to_utc};
+pub use folder_template::{date_folder, set_date_folder_template, validate_date_folder_template, DEFAULT_DATE_FOLDER_TEMPLATE};
+pub use instance::{claim_instance_lock, is_read_only_instance};
+pub use encryption::{hash_passphrase, verify_passphrase, EncryptionKey};
+pub use workers::{acquire_slot, build_worker_pool, run_in_worker_pool, run_interactive, set_worker_limits, Priority, Workload};
+#[cfg(any(feature = "face-detection", feature = "semantic-search"))]
+pub use embedding::{embedding_to_blob, l2_normalize};
+#[cfg(feature = "face-detection")]
+pub use faces::FaceDetector;
+#[cfg(feature = "semantic-search")]
+pub use clip::{ClipImageEncoder, ClipTextEncoder};
+#[cfg(feature = "cloud-backup")]
+pub use encryption::{chunk_count, FileEncryptor, CHUNK_SIZE, HEADER_SIZE, TAG_SIZE};
+#[cfg(feature = "cloud-backup")]
+pub use s3::S3Client;
@@ -0,0 +1,57 @@
+use std::io;
+use std::thread;
+use std::time::Duration;
+use anyhow::Result;
+use tracing::warn;
+
+/// Attempts made by `retry_network_io` before giving up
+const NETWORK_ATTEMPTS: u32 = 4;
+
+/// Run `op`, retrying with a growing pause when it fails the way network shares do
+/// now and then: stale NFS handles, dropped SMB sessions, timeouts
+pub fn retry_network_io<T>(mut op: impl FnMut() -> Result<T>) -> Result<T> {
+    let mut attempt = 1;
+    loop {
+        match op() {
+            Err(e) if attempt < NETWORK_ATTEMPTS && is_transient(&e) => {
+                warn!("Network I/O failed (attempt {} of {}): {}", attempt, NETWORK_ATTEMPTS, e);
+                thread::sleep(Duration::from_millis(500 * attempt as u64));
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn is_transient(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<io::Error>())
+        .any(|e| {
+            matches!(e.kind(), io::ErrorKind::TimedOut | io::ErrorKind::Interrupted | io::ErrorKind::ConnectionReset)
+                || e.raw_os_error().is_some_and(is_transient_os_error)
+        })
+}
+
+#[cfg(target_os = "linux")]
+fn is_transient_os_error(code: i32) -> bool {
+    // ESTALE, EHOSTDOWN, EHOSTUNREACH, EREMOTEIO
+    matches!(code, 116 | 112 | 113 | 121)
+}
+
+#[cfg(target_os = "macos")]
+fn is_transient_os_error(code: i32) -> bool {
+    // ESTALE, EHOSTDOWN, EHOSTUNREACH
+    matches!(code, 70 | 64 | 65)
+}
+
+#[cfg(windows)]
+fn is_transient_os_error(code: i32) -> bool {
+    // ERROR_BAD_NETPATH, ERROR_NETWORK_BUSY, ERROR_UNEXP_NET_ERR, ERROR_NETNAME_DELETED, ERROR_SEM_TIMEOUT
+    matches!(code, 53 | 54 | 59 | 64 | 121)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn is_transient_os_error(_code: i32) -> bool {
+    false
+}
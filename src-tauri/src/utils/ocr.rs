@@ -0,0 +1,52 @@
+use std::io::{Cursor, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use image::ImageFormat;
+use anyhow::Result;
+
+use crate::error::PenglerError;
+use crate::utils::open_image;
+
+/// Recognize text in an image with tesseract. `languages` is a tesseract language list
+/// such as "eng" or "eng+jpn".
+pub fn recognize_text(path: &Path, languages: &str) -> Result<String> {
+    // Decode ourselves and hand tesseract a PNG, so every format we can read works
+    let img = open_image(path)?;
+    let mut png = Vec::new();
+    img.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+
+    let child = Command::new("tesseract")
+        .arg("stdin")
+        .arg("stdout")
+        .arg("-l").arg(languages)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            return if e.kind() == std::io::ErrorKind::NotFound {
+                Err(PenglerError::TesseractMissing("tesseract not found. Please install tesseract to recognize text in photos.".to_string()).into())
+            } else {
+                Err(anyhow::anyhow!("Failed to run tesseract: {}", e))
+            };
+        }
+    };
+
+    // tesseract reads the whole image before writing anything, so this can't deadlock
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(&png)?;
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow::anyhow!("tesseract failed: {}", stderr.trim()));
+    }
+
+    // Collapse the page layout into plain words
+    let text = String::from_utf8_lossy(&output.stdout);
+    Ok(text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
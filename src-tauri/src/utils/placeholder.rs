@@ -0,0 +1,43 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use anyhow::Result;
+
+/// Whether `path` is a cloud placeholder whose content isn't on this machine
+/// (OneDrive/Dropbox "online-only" files on Windows, evicted iCloud Drive files on macOS).
+/// Reading such a file makes the sync client download it, so only metadata is checked.
+pub fn is_online_only(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| placeholder_metadata(&metadata))
+}
+
+#[cfg(windows)]
+fn placeholder_metadata(metadata: &fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    use windows_sys::Win32::Storage::FileSystem::{
+        FILE_ATTRIBUTE_OFFLINE, FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS, FILE_ATTRIBUTE_RECALL_ON_OPEN,
+    };
+
+    metadata.file_attributes()
+        & (FILE_ATTRIBUTE_OFFLINE | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS | FILE_ATTRIBUTE_RECALL_ON_OPEN)
+        != 0
+}
+
+#[cfg(target_os = "macos")]
+fn placeholder_metadata(metadata: &fs::Metadata) -> bool {
+    use std::os::macos::fs::MetadataExt;
+
+    /// `SF_DATALESS` from <sys/stat.h>: the file's data lives in the cloud
+    const SF_DATALESS: u32 = 0x4000_0000;
+    metadata.st_flags() & SF_DATALESS != 0
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn placeholder_metadata(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// Make the sync client download a placeholder by reading it through
+pub fn hydrate(path: &Path) -> Result<()> {
+    io::copy(&mut File::open(path)?, &mut io::sink())?;
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use std::path::Path;
+use ab_glyph::{point, Font, FontVec, PxScale, ScaleFont};
+use image::{Rgba, RgbaImage};
+use anyhow::Result;
+
+/// Fonts tried when no font file is configured, in order
+const SYSTEM_FONTS: &[&str] = &[
+    // Linux
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+    "/usr/share/fonts/noto/NotoSans-Regular.ttf",
+    "/usr/share/fonts/truetype/noto/NotoSans-Regular.ttf",
+    // macOS
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "/Library/Fonts/Arial.ttf",
+    // Windows
+    "C:\\Windows\\Fonts\\arial.ttf",
+    "C:\\Windows\\Fonts\\segoeui.ttf",
+];
+
+/// Load a TrueType/OpenType font from `path`, or the first common system font found
+pub fn load_font(path: Option<&str>) -> Result<FontVec> {
+    let path = match path {
+        Some(path) => Path::new(path),
+        None => SYSTEM_FONTS
+            .iter()
+            .map(Path::new)
+            .find(|path| path.exists())
+            .ok_or_else(|| anyhow::anyhow!("No font found; set a font file in settings"))?,
+    };
+
+    let data = std::fs::read(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read font {}: {}", path.display(), e))?;
+    FontVec::try_from_vec(data).map_err(|_| anyhow::anyhow!("Not a valid font: {}", path.display()))
+}
+
+/// Width in pixels of `text` set at `size` pixels
+pub fn text_width(font: &FontVec, text: &str, size: f32) -> f32 {
+    let font = font.as_scaled(PxScale::from(size));
+    let mut width = 0.0;
+    let mut previous = None;
+    for c in text.chars() {
+        let id = font.glyph_id(c);
+        if let Some(previous) = previous {
+            width += font.kern(previous, id);
+        }
+        width += font.h_advance(id);
+        previous = Some(id);
+    }
+    width
+}
+
+/// Height of a line of text at `size` pixels, from ascender to descender
+pub fn line_height(font: &FontVec, size: f32) -> f32 {
+    let font = font.as_scaled(PxScale::from(size));
+    font.ascent() - font.descent()
+}
+
+/// Draw a single line of text with its top-left corner at (`x`, `y`), blending onto `canvas`
+pub fn draw_text(canvas: &mut RgbaImage, font: &FontVec, text: &str, x: f32, y: f32, size: f32, color: Rgba<u8>) {
+    let scaled = font.as_scaled(PxScale::from(size));
+    let baseline = y + scaled.ascent();
+
+    let mut caret = x;
+    let mut previous = None;
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(previous) = previous {
+            caret += scaled.kern(previous, id);
+        }
+        let glyph = id.with_scale_and_position(PxScale::from(size), point(caret, baseline));
+        caret += scaled.h_advance(id);
+        previous = Some(id);
+
+        let Some(outlined) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outlined.px_bounds();
+        outlined.draw(|gx, gy, coverage| {
+            let px = bounds.min.x as i64 + gx as i64;
+            let py = bounds.min.y as i64 + gy as i64;
+            if px < 0 || py < 0 || px >= canvas.width() as i64 || py >= canvas.height() as i64 {
+                return;
+            }
+            blend(canvas.get_pixel_mut(px as u32, py as u32), color, coverage);
+        });
+    }
+}
+
+/// Source-over blend of `color` at `coverage` onto `pixel`
+fn blend(pixel: &mut Rgba<u8>, color: Rgba<u8>, coverage: f32) {
+    let src_alpha = coverage.clamp(0.0, 1.0) * color[3] as f32 / 255.0;
+    let dst_alpha = pixel[3] as f32 / 255.0;
+    let out_alpha = src_alpha + dst_alpha * (1.0 - src_alpha);
+    if out_alpha <= 0.0 {
+        return;
+    }
+
+    for channel in 0..3 {
+        let value = (color[channel] as f32 * src_alpha + pixel[channel] as f32 * dst_alpha * (1.0 - src_alpha)) / out_alpha;
+        pixel[channel] = value.round() as u8;
+    }
+    pixel[3] = (out_alpha * 255.0).round() as u8;
+}
@@ -0,0 +1,106 @@
+use std::sync::RwLock;
+use chrono::{DateTime, FixedOffset, Local, Offset, TimeZone, Utc};
+use tracing::warn;
+
+/// Zone that dates without an offset of their own are read in
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DateTimezone {
+    /// The computer's zone, daylight saving time included
+    Local,
+    Fixed(FixedOffset),
+}
+
+static DATE_TIMEZONE: RwLock<DateTimezone> = RwLock::new(DateTimezone::Local);
+
+/// Set the zone from the config: "local", "utc" or an offset like "+09:00".
+/// Anything else falls back to "local".
+pub fn set_date_timezone(setting: &str) {
+    let timezone = parse_date_timezone(setting).unwrap_or_else(|| {
+        warn!("Unknown date timezone {:?}; using the local timezone", setting);
+        DateTimezone::Local
+    });
+    *DATE_TIMEZONE.write().unwrap_or_else(|e| e.into_inner()) = timezone;
+}
+
+fn parse_date_timezone(setting: &str) -> Option<DateTimezone> {
+    match setting.trim().to_ascii_lowercase().as_str() {
+        "" | "local" => Some(DateTimezone::Local),
+        "utc" | "z" => Some(DateTimezone::Fixed(Utc.fix())),
+        offset => parse_utc_offset(offset).map(DateTimezone::Fixed),
+    }
+}
+
+/// Parse "+09:00", "-0530" or "+09" as written in EXIF OffsetTime tags and the config
+pub fn parse_utc_offset(value: &str) -> Option<FixedOffset> {
+    let value = value.trim();
+    let (sign, rest) = match value.as_bytes().first()? {
+        b'+' => (1, &value[1..]),
+        b'-' => (-1, &value[1..]),
+        _ => return None,
+    };
+    let digits: String = rest.chars().filter(|c| *c != ':').collect();
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = match digits.len() {
+        2 => (digits.parse::<i32>().ok()?, 0),
+        4 => (digits[..2].parse::<i32>().ok()?, digits[2..].parse::<i32>().ok()?),
+        _ => return None,
+    };
+    if minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+fn date_timezone() -> DateTimezone {
+    *DATE_TIMEZONE.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Wall-clock time of `instant` in the configured zone, stored like `taken_at`
+/// (the local time with a UTC marker), and that zone's offset then in minutes
+pub fn wall_clock(instant: DateTime<Utc>) -> (DateTime<Utc>, i32) {
+    let offset = match date_timezone() {
+        DateTimezone::Local => Local.offset_from_utc_datetime(&instant.naive_utc()).fix(),
+        DateTimezone::Fixed(offset) => offset,
+    };
+    let wall = instant.with_timezone(&offset).naive_local().and_utc();
+    (wall, offset.local_minus_utc() / 60)
+}
+
+/// The instant a wall-clock `taken_at` stands for: shifted by its own offset in
+/// minutes when known, otherwise read in the configured zone
+pub fn wall_clock_to_utc(taken_at: DateTime<Utc>, offset_minutes: Option<i32>) -> Option<DateTime<Utc>> {
+    let naive = taken_at.naive_utc();
+    match (offset_minutes, date_timezone()) {
+        (Some(minutes), _) => FixedOffset::east_opt(minutes * 60)?.from_local_datetime(&naive).single(),
+        (None, DateTimezone::Fixed(offset)) => offset.from_local_datetime(&naive).single(),
+        (None, DateTimezone::Local) => Local.from_local_datetime(&naive).earliest().map(|time| time.fixed_offset()),
+    }
+    .map(|time| time.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minutes(value: &str) -> Option<i32> {
+        parse_utc_offset(value).map(|offset| offset.local_minus_utc() / 60)
+    }
+
+    #[test]
+    fn parses_offset_forms() {
+        assert_eq!(minutes("+09:00"), Some(9 * 60));
+        assert_eq!(minutes("-0530"), Some(-(5 * 60 + 30)));
+        assert_eq!(minutes("+09"), Some(9 * 60));
+        assert_eq!(minutes(" +05:45 "), Some(5 * 60 + 45));
+        assert_eq!(minutes("-00:00"), Some(0));
+    }
+
+    #[test]
+    fn rejects_malformed_offsets() {
+        for value in ["", "09:00", "+", "+9", "+9:00", "+09:0", "+09:60", "+0a:00", "+09:00:00", "UTC", "+25:00"] {
+            assert_eq!(minutes(value), None, "{}", value);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+use serde::Deserialize;
+use anyhow::Result;
+
+use crate::error::PenglerError;
+use crate::models::{AudioTrack, VideoInfo};
+
+/// Dimensions and stream details of a video as reported by ffprobe
+pub struct VideoProbe {
+    pub width: u32,
+    pub height: u32,
+    pub info: VideoInfo,
+    /// e.g. "BT.709", or "BT.2020 PQ" / "BT.2020 HLG" for HDR
+    pub color_space: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: Option<ProbeFormat>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    avg_frame_rate: Option<String>,
+    color_transfer: Option<String>,
+    color_primaries: Option<String>,
+    channels: Option<i32>,
+    sample_rate: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+    #[serde(default)]
+    side_data_list: Vec<ProbeSideData>,
+}
+
+#[derive(Deserialize)]
+struct ProbeSideData {
+    rotation: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    duration: Option<String>,
+    bit_rate: Option<String>,
+}
+
+/// Read duration, frame rate, codecs and audio tracks of a video with ffprobe
+pub fn probe_video(path: &Path) -> Result<VideoProbe> {
+    let output = Command::new("ffprobe")
+        .arg("-v").arg("error")
+        .arg("-print_format").arg("json")
+        .arg("-show_format")
+        .arg("-show_streams")
+        .arg(path)
+        .output();
+
+    let output = match output {
+        Ok(result) if result.status.success() => result,
+        Ok(result) => {
+            let stderr = String::from_utf8_lossy(&result.stderr);
+            return Err(anyhow::anyhow!("ffprobe failed: {}", stderr));
+        },
+        Err(e) => {
+            return if e.kind() == std::io::ErrorKind::NotFound {
+                Err(PenglerError::FfmpegMissing("ffprobe not found. Please install ffmpeg to read video metadata.".to_string()).into())
+            } else {
+                Err(anyhow::anyhow!("Failed to run ffprobe: {}", e))
+            };
+        }
+    };
+
+    let probe: ProbeOutput = serde_json::from_slice(&output.stdout)?;
+
+    let video = probe
+        .streams
+        .iter()
+        .find(|s| s.codec_type.as_deref() == Some("video"))
+        .ok_or_else(|| anyhow::anyhow!("No video stream found"))?;
+
+    let (mut width, mut height) = (video.width.unwrap_or(0), video.height.unwrap_or(0));
+
+    // Phones record portrait video as landscape frames plus a rotation flag
+    let rotation = video
+        .side_data_list
+        .iter()
+        .find_map(|d| d.rotation)
+        .or_else(|| video.tags.get("rotate").and_then(|r| r.parse().ok()))
+        .unwrap_or(0.0);
+    if (rotation.abs() as i64) % 180 == 90 {
+        std::mem::swap(&mut width, &mut height);
+    }
+
+    let audio_tracks = probe
+        .streams
+        .iter()
+        .filter(|s| s.codec_type.as_deref() == Some("audio"))
+        .map(|s| AudioTrack {
+            codec: s.codec_name.clone(),
+            channels: s.channels,
+            sample_rate: s.sample_rate.as_deref().and_then(|r| r.parse().ok()),
+            language: s.tags.get("language").cloned(),
+        })
+        .collect();
+
+    let format = probe.format.as_ref();
+    let info = VideoInfo {
+        duration: format
+            .and_then(|f| f.duration.as_deref())
+            .and_then(|d| d.parse().ok())
+            .unwrap_or(0.0),
+        fps: video.avg_frame_rate.as_deref().and_then(parse_frame_rate),
+        video_codec: video.codec_name.clone(),
+        bitrate: format
+            .and_then(|f| f.bit_rate.as_deref())
+            .and_then(|b| b.parse().ok()),
+        audio_tracks,
+    };
+
+    let color_space = describe_color_space(video);
+
+    Ok(VideoProbe { width, height, info, color_space })
+}
+
+/// Whether a color space from `VideoProbe` needs tone mapping for SDR display
+pub fn is_hdr(color_space: &str) -> bool {
+    color_space.ends_with(" PQ") || color_space.ends_with(" HLG")
+}
+
+fn describe_color_space(video: &ProbeStream) -> Option<String> {
+    let transfer = match video.color_transfer.as_deref() {
+        Some("smpte2084") => Some("PQ"),
+        Some("arib-std-b67") => Some("HLG"),
+        _ => None,
+    };
+
+    let primaries = match video.color_primaries.as_deref() {
+        Some("bt2020") => "BT.2020",
+        Some("bt709") => "BT.709",
+        Some("smpte432") => "Display P3",
+        Some("unknown") | None if transfer.is_some() => "BT.2020",
+        Some("unknown") | None => return None,
+        Some(other) => other,
+    };
+
+    Some(match transfer {
+        Some(transfer) => format!("{} {}", primaries, transfer),
+        None => primaries.to_string(),
+    })
+}
+
+/// Parse ffprobe's rational frame rate, e.g. "30000/1001"
+fn parse_frame_rate(rate: &str) -> Option<f64> {
+    let (num, den) = rate.split_once('/')?;
+    let (num, den): (f64, f64) = (num.parse().ok()?, den.parse().ok()?);
+
+    if den == 0.0 || num == 0.0 {
+        None
+    } else {
+        Some(num / den)
+    }
+}
@@ -0,0 +1,144 @@
+use std::path::Path;
+use image::{imageops::FilterType, DynamicImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+
+use crate::utils::{draw_text, line_height, load_font, open_image, text_width};
+
+/// Watermark stamped on exported photos and videos
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Watermark {
+    pub content: WatermarkContent,
+    #[serde(default)]
+    pub position: WatermarkPosition,
+    /// 0 (invisible) to 1 (opaque)
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+    /// Width of the watermark relative to the photo's width
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum WatermarkContent {
+    /// White text with a soft shadow; uses a system font unless `font_path` is set
+    Text { text: String, font_path: Option<String> },
+    /// A logo, typically a PNG with transparency
+    Image { path: String },
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+}
+
+fn default_opacity() -> f64 {
+    0.5
+}
+
+fn default_scale() -> f64 {
+    0.2
+}
+
+/// Distance from the edges, relative to the photo's shorter side
+const MARGIN: f32 = 0.03;
+
+impl Watermark {
+    pub fn validate(&self) -> Result<()> {
+        if !(0.0..=1.0).contains(&self.opacity) {
+            return Err(anyhow::anyhow!("Watermark opacity must be between 0 and 1"));
+        }
+        if !(self.scale > 0.0 && self.scale <= 1.0) {
+            return Err(anyhow::anyhow!("Watermark scale must be between 0 and 1"));
+        }
+        match &self.content {
+            WatermarkContent::Text { text, .. } if text.trim().is_empty() => {
+                Err(anyhow::anyhow!("Watermark text is empty"))
+            }
+            WatermarkContent::Image { path } if !Path::new(path).exists() => {
+                Err(anyhow::anyhow!("Watermark image not found: {}", path))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The watermark as an image sized for a photo `target_width` pixels wide,
+    /// with the opacity applied to its alpha channel
+    pub fn render(&self, target_width: u32) -> Result<RgbaImage> {
+        let width = ((target_width as f64 * self.scale).round() as u32).max(1);
+
+        let mut mark = match &self.content {
+            WatermarkContent::Image { path } => {
+                open_image(Path::new(path))?.resize(width, u32::MAX, FilterType::Lanczos3).to_rgba8()
+            }
+            WatermarkContent::Text { text, font_path } => {
+                let font = load_font(font_path.as_deref())?;
+                // Fit the text to the requested width
+                let size = 100.0 * width as f32 / text_width(&font, text, 100.0).max(1.0);
+                let shadow = (size / 24.0).max(1.0);
+
+                let mut mark = RgbaImage::new(
+                    (width as f32 + shadow).ceil() as u32,
+                    (line_height(&font, size) + shadow).ceil() as u32,
+                );
+                draw_text(&mut mark, &font, text, shadow, shadow, size, Rgba([0, 0, 0, 128]));
+                draw_text(&mut mark, &font, text, 0.0, 0.0, size, Rgba([255, 255, 255, 255]));
+                mark
+            }
+        };
+
+        for pixel in mark.pixels_mut() {
+            pixel[3] = (pixel[3] as f64 * self.opacity).round() as u8;
+        }
+
+        Ok(mark)
+    }
+
+    /// Stamp the watermark onto a photo
+    pub fn apply(&self, img: DynamicImage) -> Result<DynamicImage> {
+        let mark = self.render(img.width())?;
+        let (x, y) = self.offset(img.width(), img.height(), mark.width(), mark.height());
+
+        let mut canvas = img.to_rgba8();
+        image::imageops::overlay(&mut canvas, &mark, x, y);
+
+        Ok(DynamicImage::ImageRgba8(canvas))
+    }
+
+    /// Top-left corner of a `mark_width` x `mark_height` watermark on a `width` x `height` frame
+    fn offset(&self, width: u32, height: u32, mark_width: u32, mark_height: u32) -> (i64, i64) {
+        let margin = (width.min(height) as f32 * MARGIN).round() as i64;
+        let right = width as i64 - mark_width as i64 - margin;
+        let bottom = height as i64 - mark_height as i64 - margin;
+
+        match self.position {
+            WatermarkPosition::TopLeft => (margin, margin),
+            WatermarkPosition::TopRight => (right, margin),
+            WatermarkPosition::BottomLeft => (margin, bottom),
+            WatermarkPosition::BottomRight => (right, bottom),
+            WatermarkPosition::Center => (
+                (width as i64 - mark_width as i64) / 2,
+                (height as i64 - mark_height as i64) / 2,
+            ),
+        }
+    }
+
+    /// Position for ffmpeg's overlay filter, where W/H is the video and w/h the watermark
+    pub fn overlay_expression(&self) -> String {
+        let margin = format!("min(W,H)*{}", MARGIN);
+        match self.position {
+            WatermarkPosition::TopLeft => format!("{m}:{m}", m = margin),
+            WatermarkPosition::TopRight => format!("W-w-{m}:{m}", m = margin),
+            WatermarkPosition::BottomLeft => format!("{m}:H-h-{m}", m = margin),
+            WatermarkPosition::BottomRight => format!("W-w-{m}:H-h-{m}", m = margin),
+            WatermarkPosition::Center => "(W-w)/2:(H-h)/2".to_string(),
+        }
+    }
+}
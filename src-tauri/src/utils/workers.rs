@@ -0,0 +1,234 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use anyhow::Result;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use tracing::warn;
+
+/// Nice value given to low-priority workers on Linux
+#[cfg(target_os = "linux")]
+const LOW_PRIORITY_NICE: i32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct WorkerLimits {
+    threads: usize,
+    low_priority: bool,
+}
+
+static WORKER_LIMITS: RwLock<Option<WorkerLimits>> = RwLock::new(None);
+/// Kinds of parallel work, each with a pool of its own so a long import or export
+/// doesn't hold up scanning or thumbnails
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Workload {
+    /// Reading the library: scans, hashes, OCR, similarity
+    Scan,
+    /// Copying files in from other apps and devices
+    Import,
+    /// Generating thumbnails ahead of time
+    Thumbnail,
+    /// Exports, galleries and contact sheets
+    Export,
+}
+
+impl Workload {
+    fn name(self) -> &'static str {
+        match self {
+            Workload::Scan => "scan",
+            Workload::Import => "import",
+            Workload::Thumbnail => "thumbnail",
+            Workload::Export => "export",
+        }
+    }
+}
+
+/// A pool and the limits it was built with; rebuilt when they change
+type CachedPool = Option<(WorkerLimits, Arc<ThreadPool>)>;
+
+/// One pool per `Workload`, indexed by it
+static WORKER_POOLS: Mutex<[CachedPool; 4]> = Mutex::new([None, None, None, None]);
+
+/// Who is waiting on a piece of work
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    /// The user is looking at the result: visible thumbnails, the open photo
+    Interactive,
+    /// Scans, exports, OCR and other jobs nobody watches item by item
+    Batch,
+}
+
+/// Work of one priority that holds a slot or waits for one, first come first served
+#[derive(Debug)]
+struct WorkQueue {
+    running: usize,
+    waiting: VecDeque<u64>,
+}
+
+impl WorkQueue {
+    const fn new() -> Self {
+        Self { running: 0, waiting: VecDeque::new() }
+    }
+}
+
+#[derive(Debug)]
+struct Scheduler {
+    next_ticket: u64,
+    interactive: WorkQueue,
+    batch: WorkQueue,
+}
+
+impl Scheduler {
+    fn queue(&mut self, priority: Priority) -> &mut WorkQueue {
+        match priority {
+            Priority::Interactive => &mut self.interactive,
+            Priority::Batch => &mut self.batch,
+        }
+    }
+
+    /// Slots a priority may hold at once. Interactive work may use every worker thread;
+    /// batch work gets what it leaves and none while interactive work is queued.
+    fn budget(&self, priority: Priority, threads: usize) -> usize {
+        match priority {
+            Priority::Interactive => threads,
+            Priority::Batch if !self.interactive.waiting.is_empty() => 0,
+            Priority::Batch => threads.saturating_sub(self.interactive.running),
+        }
+    }
+}
+
+static SCHEDULER: Mutex<Scheduler> = Mutex::new(Scheduler {
+    next_ticket: 0,
+    interactive: WorkQueue::new(),
+    batch: WorkQueue::new(),
+});
+static SCHEDULER_CHANGED: Condvar = Condvar::new();
+
+/// A slot from the scheduler; given back when dropped, even if the work panics
+pub struct WorkSlot {
+    priority: Priority,
+}
+
+impl Drop for WorkSlot {
+    fn drop(&mut self) {
+        let mut scheduler = SCHEDULER.lock().unwrap_or_else(|e| e.into_inner());
+        scheduler.queue(self.priority).running -= 1;
+        SCHEDULER_CHANGED.notify_all();
+    }
+}
+
+/// Cap the threads of each worker pool (scanning, importing, thumbnails, exports). 0 leaves one core
+/// free for the UI; `low_priority` lets other apps win when they need the CPU.
+pub fn set_worker_limits(max_threads: usize, low_priority: bool) {
+    let threads = if max_threads == 0 { default_worker_threads() } else { max_threads };
+
+    let mut limits = WORKER_LIMITS.write().unwrap_or_else(|e| e.into_inner());
+    *limits = Some(WorkerLimits { threads, low_priority });
+}
+
+fn worker_limits() -> WorkerLimits {
+    let limits = WORKER_LIMITS.read().unwrap_or_else(|e| e.into_inner());
+    limits.unwrap_or(WorkerLimits { threads: default_worker_threads(), low_priority: false })
+}
+
+fn default_worker_threads() -> usize {
+    std::thread::available_parallelism()
+        .map(|cores| cores.get().saturating_sub(1).max(1))
+        .unwrap_or(1)
+}
+
+/// Run `work` on the pool of `workload`, so its `par_iter`s stay within the configured
+/// limits instead of spreading over every core like rayon's global pool. Each pool
+/// may use the whole limit; the scheduler's batch budget keeps their items in check.
+pub fn run_in_worker_pool<T: Send>(workload: Workload, work: impl FnOnce() -> T + Send) -> Result<T> {
+    let pool = worker_pool(workload)?;
+    Ok(pool.install(work))
+}
+
+fn worker_pool(workload: Workload) -> Result<Arc<ThreadPool>> {
+    let limits = worker_limits();
+    let index = workload as usize;
+    let mut pools = WORKER_POOLS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((built_with, pool)) = pools[index].as_ref() {
+        if *built_with == limits {
+            return Ok(pool.clone());
+        }
+    }
+
+    let pool = Arc::new(build_pool(workload.name(), limits.threads)?);
+    pools[index] = Some((limits, pool.clone()));
+    Ok(pool)
+}
+
+/// Wait in the queue of `priority` until its budget has a free slot. Batch work takes
+/// one per item, so interactive work overtakes it at the next item.
+pub fn acquire_slot(priority: Priority) -> WorkSlot {
+    let threads = worker_limits().threads;
+    let mut scheduler = SCHEDULER.lock().unwrap_or_else(|e| e.into_inner());
+    let ticket = scheduler.next_ticket;
+    scheduler.next_ticket += 1;
+    scheduler.queue(priority).waiting.push_back(ticket);
+
+    loop {
+        let budget = scheduler.budget(priority, threads);
+        let queue = scheduler.queue(priority);
+        if queue.waiting.front() == Some(&ticket) && queue.running < budget {
+            queue.waiting.pop_front();
+            queue.running += 1;
+            break;
+        }
+        scheduler = SCHEDULER_CHANGED.wait(scheduler).unwrap_or_else(|e| e.into_inner());
+    }
+    // The next in line may fit as well
+    SCHEDULER_CHANGED.notify_all();
+    WorkSlot { priority }
+}
+
+/// Run work the user is waiting on, like rendering a thumbnail that is on screen.
+/// Blocks until a slot is free, so call it off the async runtime.
+pub fn run_interactive<T>(work: impl FnOnce() -> T) -> T {
+    let _slot = acquire_slot(Priority::Interactive);
+    work()
+}
+
+/// A pool of at most `threads` workers (fewer if the limit is lower), at the
+/// configured priority. For work that needs its own concurrency, like network scans.
+pub fn build_worker_pool(threads: usize) -> Result<ThreadPool> {
+    build_pool("worker", threads)
+}
+
+fn build_pool(name: &'static str, threads: usize) -> Result<ThreadPool> {
+    let limits = worker_limits();
+    let mut builder = ThreadPoolBuilder::new()
+        .num_threads(threads.min(limits.threads).max(1))
+        .thread_name(move |index| format!("pengler-{}-{}", name, index));
+    if limits.low_priority {
+        builder = builder.start_handler(|_| lower_thread_priority());
+    }
+    Ok(builder.build()?)
+}
+
+#[cfg(target_os = "linux")]
+fn lower_thread_priority() {
+    // Linux keeps a nice value per thread; 0 means the calling one
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, LOW_PRIORITY_NICE) } != 0 {
+        warn!("Failed to lower worker priority: {}", std::io::Error::last_os_error());
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn lower_thread_priority() {
+    // Background band: lower CPU priority and throttled disk I/O
+    if unsafe { libc::setpriority(libc::PRIO_DARWIN_THREAD, 0, libc::PRIO_DARWIN_BG) } != 0 {
+        warn!("Failed to lower worker priority: {}", std::io::Error::last_os_error());
+    }
+}
+
+#[cfg(windows)]
+fn lower_thread_priority() {
+    use windows_sys::Win32::System::Threading::{GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_BELOW_NORMAL};
+
+    if unsafe { SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_BELOW_NORMAL) } == 0 {
+        warn!("Failed to lower worker priority: {}", std::io::Error::last_os_error());
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn lower_thread_priority() {}
@@ -0,0 +1,325 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::{Reader, Writer};
+use anyhow::Result;
+
+use crate::utils::write_atomically;
+
+/// How far into an image to look for an embedded XMP packet
+const EMBEDDED_SCAN_LIMIT: u64 = 1024 * 1024;
+
+/// Namespace for properties XMP has no standard term for
+const PENGLER_NAMESPACE: &str = "https://github.com/konoe-akitoshi/pengler/xmp/1.0/";
+
+/// Starting point for sidecars written from scratch
+const EMPTY_SIDECAR: &str = r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""/>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>
+"#;
+
+/// Attributes and elements owned by pengler when writing a sidecar
+const MANAGED_PROPERTIES: [&[u8]; 4] = [b"xmp:Rating", b"xmp:Label", b"dc:subject", b"pengler:Favorite"];
+
+/// Ratings, labels and keywords as written by Lightroom, Darktable, Bridge, etc.
+#[derive(Debug, Default)]
+pub struct XmpMetadata {
+    /// 0-5 stars, -1 for rejected
+    pub rating: Option<i32>,
+    pub color_label: Option<String>,
+    pub favorite: bool,
+    pub keywords: Vec<String>,
+}
+
+impl XmpMetadata {
+    /// Overlay `other` on top of this metadata; keywords are combined
+    fn merge(&mut self, other: XmpMetadata) {
+        if other.rating.is_some() {
+            self.rating = other.rating;
+        }
+        if other.color_label.is_some() {
+            self.color_label = other.color_label;
+        }
+        self.favorite |= other.favorite;
+        for keyword in other.keywords {
+            if !self.keywords.contains(&keyword) {
+                self.keywords.push(keyword);
+            }
+        }
+    }
+}
+
+/// Read XMP metadata for a media file from its embedded packet and its sidecar.
+/// Sidecar values win, since that is where editors write for formats they can't modify.
+pub fn read_xmp_metadata(path: &Path, scan_embedded: bool) -> XmpMetadata {
+    let mut metadata = if scan_embedded {
+        read_embedded_xmp(path).map(|xml| parse_xmp(&xml)).unwrap_or_default()
+    } else {
+        XmpMetadata::default()
+    };
+
+    if let Some(xml) = find_sidecar(path).and_then(|sidecar| fs::read_to_string(sidecar).ok()) {
+        metadata.merge(parse_xmp(&xml));
+    }
+
+    metadata
+}
+
+/// Find the `.xmp` sidecar of a media file: "IMG_0001.xmp" (Lightroom) or "IMG_0001.CR2.xmp" (Darktable)
+pub fn find_sidecar(path: &Path) -> Option<PathBuf> {
+    let appended = |ext: &str| {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(ext);
+        path.with_file_name(name)
+    };
+
+    [
+        path.with_extension("xmp"),
+        path.with_extension("XMP"),
+        appended(".xmp"),
+        appended(".XMP"),
+    ]
+    .into_iter()
+    .find(|candidate| candidate.is_file())
+}
+
+/// Locate an XMP packet in the file header (JPEG APP1, PNG iTXt, TIFF, ...)
+fn read_embedded_xmp(path: &Path) -> Option<String> {
+    let mut buffer = Vec::new();
+    File::open(path).ok()?.take(EMBEDDED_SCAN_LIMIT).read_to_end(&mut buffer).ok()?;
+
+    let start = find_bytes(&buffer, b"<x:xmpmeta")?;
+    let closing = b"</x:xmpmeta>";
+    let end = start + find_bytes(&buffer[start..], closing)? + closing.len();
+
+    String::from_utf8(buffer[start..end].to_vec()).ok()
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// Parse the properties we import from an XMP document.
+/// Matches the conventional prefixes (xmp:, dc:), which every common tool uses.
+fn parse_xmp(xml: &str) -> XmpMetadata {
+    let mut reader = Reader::from_str(xml);
+    let mut metadata = XmpMetadata::default();
+    let mut open_elements: Vec<Vec<u8>> = Vec::new();
+    let mut text = String::new();
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(element)) => {
+                read_attributes(&element, &mut metadata);
+                open_elements.push(element.name().as_ref().to_vec());
+                text.clear();
+            }
+            Ok(Event::Empty(element)) => read_attributes(&element, &mut metadata),
+            Ok(Event::Text(content)) => {
+                if let Ok(content) = content.decode() {
+                    text.push_str(&content);
+                }
+            }
+            Ok(Event::GeneralRef(reference)) => {
+                if let Ok(Some(c)) = reference.resolve_char_ref() {
+                    text.push(c);
+                } else if let Some(c) = predefined_entity(reference.as_ref()) {
+                    text.push(c);
+                }
+            }
+            Ok(Event::End(_)) => {
+                let name = open_elements.pop();
+                let in_subject = open_elements.iter().any(|n| n == b"dc:subject");
+
+                match name.as_deref() {
+                    Some(b"rdf:li") if in_subject => {
+                        let keyword = text.trim();
+                        if !keyword.is_empty() && !metadata.keywords.iter().any(|k| k == keyword) {
+                            metadata.keywords.push(keyword.to_string());
+                        }
+                    }
+                    Some(b"xmp:Rating") => metadata.rating = parse_rating(&text),
+                    Some(b"xmp:Label") => metadata.color_label = parse_label(&text),
+                    Some(b"pengler:Favorite") => metadata.favorite = parse_bool(&text),
+                    _ => {}
+                }
+                text.clear();
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+
+    metadata
+}
+
+/// Rating and label are usually attributes of rdf:Description
+fn read_attributes(element: &BytesStart, metadata: &mut XmpMetadata) {
+    for attribute in element.attributes().flatten() {
+        let Ok(value) = attribute.unescape_value() else {
+            continue;
+        };
+        match attribute.key.as_ref() {
+            b"xmp:Rating" => metadata.rating = parse_rating(&value),
+            b"xmp:Label" => metadata.color_label = parse_label(&value),
+            b"pengler:Favorite" => metadata.favorite = parse_bool(&value),
+            _ => {}
+        }
+    }
+}
+
+fn parse_rating(value: &str) -> Option<i32> {
+    value.trim().parse::<f64>().ok().map(|rating| rating.round().clamp(-1.0, 5.0) as i32)
+}
+
+fn parse_label(value: &str) -> Option<String> {
+    let label = value.trim();
+    (!label.is_empty()).then(|| label.to_string())
+}
+
+fn parse_bool(value: &str) -> bool {
+    value.trim().eq_ignore_ascii_case("true")
+}
+
+fn predefined_entity(name: &[u8]) -> Option<char> {
+    match name {
+        b"amp" => Some('&'),
+        b"lt" => Some('<'),
+        b"gt" => Some('>'),
+        b"quot" => Some('"'),
+        b"apos" => Some('\''),
+        _ => None,
+    }
+}
+
+/// Write rating, label, favorite and keywords to the media file's sidecar.
+/// The original file is never modified; an existing sidecar keeps everything else it contains.
+pub fn write_xmp_sidecar(media_path: &Path, metadata: &XmpMetadata) -> Result<PathBuf> {
+    // Append ".xmp" for new sidecars so RAW+JPEG pairs don't share one
+    let (sidecar, existing) = match find_sidecar(media_path) {
+        Some(sidecar) => {
+            let xml = fs::read_to_string(&sidecar)?;
+            (sidecar, xml)
+        }
+        None => {
+            let mut name = media_path.file_name().unwrap_or_default().to_os_string();
+            name.push(".xmp");
+            (media_path.with_file_name(name), EMPTY_SIDECAR.to_string())
+        }
+    };
+
+    let xml = update_sidecar(&existing, metadata)?;
+    write_atomically(&sidecar, |part_path| Ok(fs::write(part_path, &xml)?))?;
+
+    Ok(sidecar)
+}
+
+/// Rewrite the managed properties on the first rdf:Description, copying everything else as is
+fn update_sidecar(xml: &str, metadata: &XmpMetadata) -> Result<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut writer = Writer::new(Vec::new());
+    let mut skip_depth = 0usize;
+    let mut written = false;
+
+    loop {
+        let event = reader.read_event()?;
+
+        // Drop the old value of a managed element, including its children
+        if skip_depth > 0 {
+            match event {
+                Event::Start(_) => skip_depth += 1,
+                Event::End(_) => skip_depth -= 1,
+                Event::Eof => break,
+                _ => {}
+            }
+            continue;
+        }
+
+        match event {
+            Event::Start(element) if is_managed(element.name().as_ref()) => skip_depth = 1,
+            Event::Empty(element) if is_managed(element.name().as_ref()) => {}
+            Event::Start(element) if !written && element.name().as_ref() == b"rdf:Description" => {
+                writer.write_event(Event::Start(description_with_metadata(&element, metadata)))?;
+                write_keywords(&mut writer, &metadata.keywords)?;
+                written = true;
+            }
+            Event::Empty(element) if !written && element.name().as_ref() == b"rdf:Description" => {
+                writer.write_event(Event::Start(description_with_metadata(&element, metadata)))?;
+                write_keywords(&mut writer, &metadata.keywords)?;
+                writer.write_event(Event::End(BytesEnd::new("rdf:Description")))?;
+                written = true;
+            }
+            Event::Eof => break,
+            event => writer.write_event(event)?,
+        }
+    }
+
+    if !written {
+        return Err(anyhow::anyhow!("Sidecar has no rdf:Description"));
+    }
+
+    Ok(String::from_utf8(writer.into_inner())?)
+}
+
+fn is_managed(name: &[u8]) -> bool {
+    MANAGED_PROPERTIES.contains(&name)
+}
+
+/// Copy an rdf:Description start tag, replacing the managed attributes
+fn description_with_metadata(element: &BytesStart, metadata: &XmpMetadata) -> BytesStart<'static> {
+    let mut description = BytesStart::new("rdf:Description");
+    let mut declared = Vec::new();
+
+    for attribute in element.attributes().flatten() {
+        if !is_managed(attribute.key.as_ref()) {
+            declared.push(attribute.key.as_ref().to_vec());
+            description.push_attribute(attribute);
+        }
+    }
+
+    let namespaces = [
+        ("xmlns:xmp", "http://ns.adobe.com/xap/1.0/"),
+        ("xmlns:dc", "http://purl.org/dc/elements/1.1/"),
+        ("xmlns:pengler", PENGLER_NAMESPACE),
+    ];
+    for (key, uri) in namespaces {
+        if !declared.iter().any(|k| k == key.as_bytes()) {
+            description.push_attribute((key, uri));
+        }
+    }
+
+    if let Some(rating) = metadata.rating {
+        description.push_attribute(("xmp:Rating", rating.to_string().as_str()));
+    }
+    if let Some(label) = &metadata.color_label {
+        description.push_attribute(("xmp:Label", label.as_str()));
+    }
+    if metadata.favorite {
+        description.push_attribute(("pengler:Favorite", "True"));
+    }
+
+    description
+}
+
+fn write_keywords(writer: &mut Writer<Vec<u8>>, keywords: &[String]) -> Result<()> {
+    if keywords.is_empty() {
+        return Ok(());
+    }
+
+    writer.write_event(Event::Start(BytesStart::new("dc:subject")))?;
+    writer.write_event(Event::Start(BytesStart::new("rdf:Bag")))?;
+    for keyword in keywords {
+        writer.write_event(Event::Start(BytesStart::new("rdf:li")))?;
+        writer.write_event(Event::Text(BytesText::new(keyword)))?;
+        writer.write_event(Event::End(BytesEnd::new("rdf:li")))?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("rdf:Bag")))?;
+    writer.write_event(Event::End(BytesEnd::new("dc:subject")))?;
+
+    Ok(())
+}
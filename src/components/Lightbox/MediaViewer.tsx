@@ -2,6 +2,30 @@ import { useEffect, useCallback } from 'react';
 import { convertFileSrc } from '@tauri-apps/api/core';
 import { useMediaStore } from '../../stores/mediaStore';
 import { motion } from 'framer-motion';
+import { MediaFile } from '../../types/media';
+
+function formatDuration(seconds: number): string {
+  const total = Math.round(seconds);
+  const h = Math.floor(total / 3600);
+  const m = Math.floor((total % 3600) / 60);
+  const s = (total % 60).toString().padStart(2, '0');
+  return h > 0 ? `${h}:${m.toString().padStart(2, '0')}:${s}` : `${m}:${s}`;
+}
+
+function videoDetails(media: MediaFile): string[] {
+  const info = media.videoInfo;
+  if (!info) return [];
+
+  const details = [formatDuration(info.duration)];
+  if (media.width > 0 && media.height > 0) details.push(`${media.width}×${media.height}`);
+  if (info.fps) details.push(`${Math.round(info.fps * 100) / 100} fps`);
+  if (info.videoCodec) details.push(info.videoCodec.toUpperCase());
+  if (info.bitrate) details.push(`${(info.bitrate / 1_000_000).toFixed(1)} Mbps`);
+  if (info.audioTracks.length > 0) {
+    details.push(info.audioTracks.length === 1 ? '1 audio track' : `${info.audioTracks.length} audio tracks`);
+  }
+  return details;
+}
 
 function MediaViewer() {
   const { selectedMedia, setSelectedMedia, mediaFiles } = useMediaStore();
@@ -46,6 +70,7 @@ function MediaViewer() {
   const currentIndex = mediaFiles.findIndex((m) => m.id === selectedMedia.id);
   const hasPrevious = currentIndex > 0;
   const hasNext = currentIndex < mediaFiles.length - 1;
+  const details = videoDetails(selectedMedia);
 
   return (
     <motion.div
@@ -94,6 +119,13 @@ function MediaViewer() {
         )}
       </div>
 
+      {/* Video details - bottom center */}
+      {details.length > 0 && (
+        <div className="absolute bottom-6 left-1/2 -translate-x-1/2 z-10 bg-black bg-opacity-60 backdrop-blur-sm px-4 py-2 rounded-full text-white text-xs font-medium">
+          {details.join(' · ')}
+        </div>
+      )}
+
       {/* Navigation buttons */}
       {hasPrevious && (
         <button
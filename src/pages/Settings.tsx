@@ -25,8 +25,8 @@ function Settings() {
     try {
       const cfg = await invoke<Config>('get_config');
       setConfig(cfg);
-      if (cfg.library_folders.length > 0) {
-        setSelectedFolder(cfg.library_folders[0]);
+      if (cfg.folders.length > 0) {
+        setSelectedFolder(cfg.folders[0].path);
       }
     } catch (error) {
       console.error('Failed to load config:', error);
@@ -1,6 +1,101 @@
+export interface FolderSettings {
+  path: string;
+  /** Pick up new and changed files without a manual rescan */
+  watch: boolean;
+  /** Make optimized copies of new files as they arrive */
+  auto_optimize: boolean;
+  /** Name of the optimization settings to use; the global ones when unset */
+  optimization_profile: string | null;
+  /** File and folder names left out of scans; `*` and `?` are wildcards */
+  exclude: string[];
+  /** On a network share; scanned with quick hashes and retries */
+  network: boolean;
+}
+
 export interface Config {
-  library_folders: string[];
+  /** Library folders with their settings */
+  folders: FolderSettings[];
+  /** Threads per worker pool (scanning, importing, thumbnails, exports); 0 leaves one core free */
+  max_worker_threads: number;
+  /** Run those threads at low priority */
+  low_priority_workers: boolean;
   cache_folder: string;
+  /** Keep all thumbnails encrypted with the private passphrase; shown after unlock_cache */
+  encrypt_cache: boolean;
+  /** Thumbnail cache is trimmed to this, least recently viewed first; 0 for no limit */
+  cache_max_bytes: number;
   optimization_quality: number;
   max_resolution: number;
+  burst_window_ms: number;
+  /** Zone for dates without their own offset: "local", "utc" or e.g. "+09:00" */
+  date_timezone: string;
+  /** strftime codes; "/" starts a subfolder, e.g. "%Y/%m - %B" for "2024/03 - März" */
+  date_folder_template: string;
+  /** Language of month and day names in date folders, e.g. "de_DE"; empty for the system's */
+  locale: string;
+  image_extensions: string[];
+  video_extensions: string[];
+  write_xmp_sidecars: boolean;
+  face_detection: boolean;
+  ocr: boolean;
+  ocr_languages: string;
+  semantic_search: boolean;
+  watermark?: Watermark | null;
+  lan_server_port: number;
+  lan_server_token?: string | null;
+  dlna_name: string;
+  dlna_port: number;
+  backup?: BackupConfig | null;
+  cloud_backup?: CloudBackupConfig | null;
+}
+
+export interface BackupConfig {
+  /** Folder that receives the copies, usually on an external drive */
+  target: string;
+  /** Also delete copies whose original is gone from the library */
+  mirror_deletions: boolean;
+}
+
+/** An S3-compatible bucket (AWS S3, Backblaze B2, Cloudflare R2, MinIO, ...) */
+export interface CloudBackupConfig {
+  endpoint: string;
+  region: string;
+  bucket: string;
+  prefix: string;
+  access_key_id: string;
+  secret_access_key: string;
+  /** Files are encrypted with a key derived from this; without it the backup cannot be restored */
+  passphrase: string;
+  /** Upload limit in KiB/s, 0 for unlimited */
+  bandwidth_limit_kbps: number;
+}
+
+export interface DlnaStatus {
+  running: boolean;
+  name: string | null;
+  port: number | null;
+  /** Photos and videos streamed since the server started */
+  served: number;
+  /** Why the server stopped, if it failed */
+  error: string | null;
+}
+
+export interface LanServerStatus {
+  running: boolean;
+  port: number | null;
+  /** Addresses to open on other devices, token included */
+  urls: string[];
+}
+
+export type WatermarkContent =
+  | { type: 'text'; text: string; font_path?: string | null }
+  | { type: 'image'; path: string };
+
+export type WatermarkPosition = 'top_left' | 'top_right' | 'bottom_left' | 'bottom_right' | 'center';
+
+export interface Watermark {
+  content: WatermarkContent;
+  position?: WatermarkPosition;
+  opacity?: number;
+  scale?: number;
 }
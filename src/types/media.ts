@@ -1,5 +1,7 @@
 export type MediaType = 'image' | 'video';
 
+export type PickState = 'pick' | 'unflagged' | 'reject';
+
 export interface MediaFile {
   id: number;
   filePath: string;
@@ -7,13 +9,470 @@ export interface MediaFile {
   fileSize: number;
   width: number;
   height: number;
+  /** Wall-clock time where it was taken */
   takenAt: string | null;
+  /** Minutes east of UTC at takenAt, when known */
+  takenOffset: number | null;
+  latitude: number | null;
+  longitude: number | null;
+  cameraModel: string | null;
   modifiedAt: string;
   thumbnailPath: string | null;
   mediaType: MediaType;
+  videoInfo: VideoInfo | null;
+  colorSpace: string | null;
+  perceptualHash: string | null;
+  stackId: number | null;
+  rating: number | null;
+  colorLabel: string | null;
+  favorite: boolean;
+  pick: PickState;
+  /** Motion clip of a Live Photo, if the pair was imported together */
+  liveVideoId: number | null;
+  /** Cloud placeholder not downloaded to this machine; see hydrate_files */
+  onlineOnly: boolean;
+  tags: string[];
+  createdAt: string;
+}
+
+export interface VideoInfo {
+  duration: number;
+  fps: number | null;
+  videoCodec: string | null;
+  bitrate: number | null;
+  audioTracks: AudioTrack[];
+}
+
+export interface AudioTrack {
+  codec: string | null;
+  channels: number | null;
+  sampleRate: number | null;
+  language: string | null;
+}
+
+export interface MediaFilters {
+  text?: string;
+  takenAfter?: string;
+  takenBefore?: string;
+  mediaType?: MediaType;
+  folder?: string;
+  tags?: string[];
+  includeTagDescendants?: boolean;
+  personIds?: number[];
+  minRating?: number;
+  favoritesOnly?: boolean;
+  pick?: PickState;
+  hasGps?: boolean;
+  camera?: string;
+  minWidth?: number;
+  minHeight?: number;
+  minDuration?: number;
+  maxDuration?: number;
+  collapseStacks?: boolean;
+  sort?: MediaSort;
+  offset?: number;
+  limit?: number;
+}
+
+export type SortKey = 'takenAt' | 'modifiedAt' | 'name' | 'size' | 'rating';
+
+export interface MediaSort {
+  key: SortKey;
+  descending: boolean;
+}
+
+export type GroupBy = 'day' | 'folder';
+
+export interface MediaGroupHint {
+  key: string;
+  start: number;
+  count: number;
+}
+
+export interface MediaListing {
+  items: MediaFile[];
+  groups: MediaGroupHint[];
+  total: number;
+}
+
+export interface Tag {
+  id: number;
+  name: string;
+  parentId: number | null;
+  mediaCount: number;
+}
+
+export type SmartRule =
+  | { type: 'all'; rules: SmartRule[] }
+  | { type: 'any'; rules: SmartRule[] }
+  | { type: 'tagIs'; tag: string; includeDescendants?: boolean }
+  | { type: 'dateBetween'; from: string | null; to: string | null }
+  | { type: 'cameraEquals'; camera: string }
+  | { type: 'ratingAtLeast'; rating: number }
+  | { type: 'typeIs'; mediaType: MediaType }
+  | { type: 'folderUnder'; folder: string }
+  | { type: 'personIs'; personId: number };
+
+export interface SmartAlbum {
+  id: number;
+  name: string;
+  rule: SmartRule;
+}
+
+export interface GeoBounds {
+  north: number;
+  south: number;
+  east: number;
+  west: number;
+}
+
+export interface GeoCluster {
+  latitude: number;
+  longitude: number;
+  count: number;
+  coverId: number;
+  coverThumbnail: string | null;
+}
+
+export interface Suggestion {
+  kind: 'tag' | 'folder' | 'camera' | 'album';
+  value: string;
+  label: string;
+  count: number;
+}
+
+export interface SearchResult {
+  items: MediaFile[];
+  total: number;
+}
+
+export interface Person {
+  id: number;
+  name: string;
+  faceCount: number;
+}
+
+/** Face rectangle relative to the image size (0-1) */
+export interface FaceBox {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+export interface FaceRegion extends FaceBox {
+  id: number;
+  mediaId: number;
+  personId: number | null;
+  personName: string | null;
+  source: string;
+  confidence: number | null;
+}
+
+/** Unnamed faces the detector grouped as one person */
+export interface FaceCluster {
+  clusterId: number;
+  faceCount: number;
+  coverRegionId: number;
+  coverMediaId: number;
+  coverThumbnail: string | null;
+  suggestedPersonId: number | null;
+  suggestedPersonName: string | null;
+}
+
+export interface FaceDetectionProgress {
+  processed: number;
+  total: number;
+}
+
+export interface FaceDetectionSummary {
+  processed: number;
+  facesFound: number;
+  clusters: number;
+}
+
+export interface SimilarMedia {
+  media: MediaFile;
+  distance: number;
+}
+
+export interface SimilarGroup {
+  groupId: number;
+  items: MediaFile[];
+}
+
+export interface SimilarPhotosProgress {
+  processed: number;
+  total: number;
+}
+
+export type DuplicateKind = 'exact' | 'similar';
+
+export interface DuplicateGroup {
+  groupId: number;
+  kind: DuplicateKind;
+  items: MediaFile[];
+}
+
+export interface DuplicateScanSummary {
+  exactGroups: number;
+  similarGroups: number;
+  reclaimableBytes: number;
+}
+
+export interface DuplicatesProgress {
+  phase: 'hashing' | 'grouping' | 'resolving';
+  processed: number;
+  total: number;
+}
+
+export type KeepStrategy = 'largest' | 'original';
+export type DuplicateAction = 'delete' | 'hardlink';
+
+export interface ResolveResult {
+  resolvedGroups: number;
+  removedFiles: number;
+  freedBytes: number;
+  skippedFiles: number;
+  failed: number;
+}
+
+export interface OcrProgress {
+  processed: number;
+  total: number;
+}
+
+export interface OcrSummary {
+  processed: number;
+  withText: number;
+  failed: number;
+}
+
+export type SemanticQuery =
+  | { type: 'text'; text: string }
+  | { type: 'image'; mediaId: number };
+
+export interface SemanticMatch {
+  media: MediaFile;
+  score: number;
+}
+
+export interface SemanticIndexProgress {
+  processed: number;
+  total: number;
+}
+
+export interface SemanticIndexSummary {
+  processed: number;
+  failed: number;
+}
+
+export interface CropRect {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+export interface EditRecipe {
+  rotation?: number;
+  crop?: CropRect | null;
+  exposure?: number;
+}
+
+export interface EditRevision {
+  id: number;
+  recipe: EditRecipe;
   createdAt: string;
 }
 
+export type ExportFormat = 'jpeg' | 'webp' | 'original';
+
+export type MetadataMode = 'strip' | 'keep' | 'private';
+
+export interface ExportOptions {
+  maxDimension?: number;
+  format?: ExportFormat;
+  quality?: number;
+  metadata?: MetadataMode;
+  withEdits?: boolean;
+  watermark?: boolean;
+}
+
+/** Copy progress of a Google Takeout or Apple Photos import */
+export interface ImportProgress {
+  /** Files finished before the current one */
+  processed: number;
+  total: number;
+  currentFile: string;
+  bytesCopied: number;
+  fileSize: number;
+}
+
+export interface ExportProgress {
+  processed: number;
+  total: number;
+  currentFile: string;
+}
+
+export interface ExportResult {
+  exported: number;
+  failed: number;
+  files: string[];
+}
+
+export interface SlideshowOptions {
+  dest: string;
+  slideDuration?: number;
+  crossfade?: number;
+  music?: string;
+  width?: number;
+  height?: number;
+}
+
+export interface SlideshowProgress {
+  phase: 'preparing' | 'encoding';
+  processed: number;
+  total: number;
+}
+
+export interface SlideshowResult {
+  path: string;
+  slides: number;
+  duration: number;
+}
+
+export interface GalleryProgress {
+  processed: number;
+  total: number;
+}
+
+export interface GalleryResult {
+  indexPath: string;
+  photos: number;
+  failed: number;
+}
+
+export type ContactSheetFormat = 'pdf' | 'jpeg';
+
+export type PageSize = 'a4' | 'letter';
+
+export interface ContactSheetOptions {
+  dest: string;
+  format?: ContactSheetFormat;
+  columns?: number;
+  /** Rows per page; ignored for JPEG sheets */
+  rows?: number;
+  pageSize?: PageSize;
+  title?: string;
+}
+
+export interface ContactSheetProgress {
+  processed: number;
+  total: number;
+}
+
+export interface ContactSheetResult {
+  path: string;
+  pages: number;
+  photos: number;
+  failed: number;
+}
+
+export interface BackupProgress {
+  processed: number;
+  total: number;
+  currentFile: string;
+}
+
+export interface BackupRun {
+  id: number;
+  target: string;
+  startedAt: string;
+  /** null while running, or if the run was interrupted */
+  finishedAt: string | null;
+  copied: number;
+  unchanged: number;
+  deleted: number;
+  failed: number;
+  bytesCopied: number;
+}
+
+/** Paths are those of the originals */
+export interface BackupVerification {
+  checked: number;
+  verified: number;
+  missing: string[];
+  corrupted: string[];
+}
+
+export interface RestoreResult {
+  /** Originals copied back from the backup */
+  restored: number;
+  /** Originals that were already intact */
+  intact: number;
+  failed: number;
+  /** Media whose path in the library changed because its folder moved */
+  relinked: number;
+}
+
+export interface HydrateResult {
+  hydrated: number;
+  failed: number;
+}
+
+export interface CloudBackupStatus {
+  running: boolean;
+  processed: number;
+  total: number;
+  uploaded: number;
+  unchanged: number;
+  failed: number;
+  bytesUploaded: number;
+  currentFile: string | null;
+  startedAt: string | null;
+  finishedAt: string | null;
+  cancelled: boolean;
+  /** Why the run stopped, if it failed as a whole */
+  error: string | null;
+}
+
+export interface DeleteRejectedResult {
+  deleted: number;
+  failed: number;
+}
+
+export interface DeleteMediaResult {
+  deleted: number;
+  failed: number;
+}
+
+export interface MoveMediaResult {
+  moved: number;
+  skipped: number;
+  failed: number;
+}
+
+export type CopyLayout = 'preserveStructure' | 'flatten' | 'dateFolders';
+
+export interface CopyProgress {
+  processed: number;
+  total: number;
+  currentFile: string;
+}
+
+export interface CopyMediaResult {
+  copied: number;
+  failed: number;
+  bytesCopied: number;
+}
+
+export interface Memory {
+  year: number;
+  yearsAgo: number;
+  items: MediaFile[];
+}
+
 export interface ScanProgress {
   current: number;
   total: number;
@@ -25,3 +484,156 @@ export interface CacheStats {
   fileCount: number;
   maxSize: number;
 }
+
+/** Stats over the recent runs of one operation ("scan", "hash", "thumbnail", "export", "import") */
+export interface OperationMetrics {
+  operation: string;
+  runs: number;
+  /** Runs in progress right now */
+  active: number;
+  files: number;
+  bytes: number;
+  totalMs: number;
+  avgMsPerFile: number | null;
+  megabytesPerSecond: number | null;
+  lastRunAt: string | null;
+}
+
+export type LogLevel = 'error' | 'warn' | 'info' | 'debug' | 'trace';
+
+/** Returned by get_recent_logs; errors are also emitted as "log-event" */
+export interface LogEntry {
+  time: string;
+  level: LogLevel;
+  /** Module that logged it, e.g. "pengler::commands::scanner" */
+  target: string;
+  message: string;
+}
+
+export type ErrorCode =
+  | 'FfmpegMissing'
+  | 'ExiftoolMissing'
+  | 'TesseractMissing'
+  | 'PermissionDenied'
+  | 'NotFound'
+  | 'DiskFull'
+  | 'DbLocked'
+  | 'UnsupportedFormat'
+  | 'NetworkUnavailable'
+  | 'InvalidInput'
+  | 'ReadOnly'
+  | 'Locked'
+  | 'Other';
+
+/** What failed commands reject with; also emitted as "error" */
+export interface PenglerError {
+  code: ErrorCode;
+  message: string;
+}
+
+export type CandidateKind = 'pictures' | 'videos' | 'cameraUploads' | 'dcim';
+
+export interface CandidateFolder {
+  path: string;
+  kind: CandidateKind;
+  /** Name to show, e.g. "Dropbox Camera Uploads" or "DCIM on E:\" */
+  label: string;
+  /** Photos and videos found, up to the counting limit */
+  mediaCount: number;
+  /** Counting stopped at the limit; there are more */
+  more: boolean;
+  /** Already a library folder or inside one */
+  inLibrary: boolean;
+}
+
+export type HealthRepair = 'forgetMissing' | 'removeStaleEntries' | 'removeOrphanedThumbnails' | 'importUntracked';
+
+export interface HealthIssue {
+  count: number;
+  /** The first 200 paths */
+  examples: string[];
+  /** How many were fixed by the requested repairs */
+  repaired: number;
+}
+
+export interface LibraryHealth {
+  /** Cataloged files no longer on disk */
+  missingOriginals: HealthIssue;
+  /** Remembered hashes and per-file data without a file behind them */
+  staleEntries: HealthIssue;
+  /** Cached thumbnails of files not in the catalog */
+  orphanedThumbnails: HealthIssue;
+  orphanedThumbnailBytes: number;
+  /** Media files in library folders that aren't cataloged */
+  untrackedFiles: HealthIssue;
+  /** Files and folders that can't be read, and the cache folder if it can't be written */
+  permissionProblems: HealthIssue;
+  /** Library folders that aren't there; files in them aren't counted as missing */
+  offlineFolders: string[];
+}
+
+export type ChangeEntity = 'media' | 'tag' | 'album';
+
+export type ChangeOp = 'insert' | 'update' | 'delete';
+
+export interface Change {
+  seq: number;
+  entity: ChangeEntity;
+  /** Id of the media file, tag or smart album */
+  id: number;
+  /** The last of the entity's changes */
+  op: ChangeOp;
+}
+
+/** From get_changes_since; "library-changed" carries the latest seq */
+export interface ChangeSet {
+  /** One entry per changed entity, in the order of their last change */
+  changes: Change[];
+  /** Where to continue from next time */
+  seq: number;
+  /** Changes were cut off at the limit; ask again from seq */
+  more: boolean;
+  /** The given seq is older than the log reaches back; reload everything */
+  reset: boolean;
+}
+
+/**
+ * From get_private_status and the commands that change it. Thumbnails of private
+ * items, and all of them with encrypt_cache, are encrypted (paths ending in ".enc");
+ * show them with convertFileSrc(path, 'private').
+ */
+export interface PrivateStatus {
+  /** A passphrase was set, so folders can be made private */
+  hasPassphrase: boolean;
+  unlocked: boolean;
+  /** Encrypted thumbnails can be shown, after unlock_private or unlock_cache */
+  cacheUnlocked: boolean;
+  /** Private folders; only listed while unlocked */
+  folders: string[];
+}
+
+/** From relink_folder */
+export interface RelinkResult {
+  /** Catalog entries now pointing into the new folder */
+  relinked: number;
+  /** Files hashed at the new location, and how many of them matched */
+  sampled: number;
+  matched: number;
+  /** Entries a scan of the new folder had already added, replaced by the relinked ones */
+  replaced: number;
+}
+
+/** A folder that may be where an offline library folder went */
+export interface RelinkCandidate {
+  path: string;
+  /** Sampled files found there with their cataloged size */
+  matched: number;
+  sampled: number;
+}
+
+/** From suggest_relink_locations, one per library folder that can't be found */
+export interface RelinkSuggestion {
+  folder: string;
+  /** Best match first */
+  candidates: RelinkCandidate[];
+}